@@ -0,0 +1,285 @@
+// GhostBay web console. Talks only to /console/api; see crates/admin-ui/src/lib.rs for the endpoints.
+"use strict";
+
+const api = "/console/api";
+let session = null;
+let listing = { bucket: null, prefix: "", after: null };
+let selected = null;
+
+const $ = (id) => document.getElementById(id);
+
+function encodeKey(key) {
+  return key.split("/").map(encodeURIComponent).join("/");
+}
+
+function formatSize(bytes) {
+  const units = ["B", "KiB", "MiB", "GiB", "TiB"];
+  let size = bytes;
+  let unit = 0;
+  while (size >= 1024 && unit < units.length - 1) {
+    size /= 1024;
+    unit += 1;
+  }
+  return unit === 0 ? `${size} B` : `${size.toFixed(1)} ${units[unit]}`;
+}
+
+function cell(row, content) {
+  const td = row.insertCell();
+  if (content instanceof Node) {
+    td.appendChild(content);
+  } else {
+    td.textContent = content ?? "";
+  }
+  return td;
+}
+
+function link(text, onClick) {
+  const button = document.createElement("button");
+  button.className = "link";
+  button.textContent = text;
+  button.addEventListener("click", onClick);
+  return button;
+}
+
+function showError(message) {
+  $("error").textContent = message;
+  $("error").hidden = !message;
+}
+
+// Errors come back as S3 XML documents; show their Message
+async function request(method, path, body, headers = {}) {
+  const options = { method, headers: { ...headers }, credentials: "same-origin" };
+  if (method !== "GET" && session) {
+    options.headers["x-ghostbay-csrf"] = session.CsrfToken;
+  }
+  if (body !== undefined) {
+    options.body = body;
+  }
+
+  const response = await fetch(api + path, options);
+  if (response.status === 403 && path !== "/login") {
+    session = null;
+    show("login");
+  }
+  if (!response.ok) {
+    const text = await response.text();
+    const document = new DOMParser().parseFromString(text, "application/xml");
+    const message = document.querySelector("Message")?.textContent || response.statusText;
+    throw new Error(`${message} (${response.status})`);
+  }
+  return response.status === 204 ? null : response.json();
+}
+
+function show(view) {
+  for (const name of ["login", "buckets", "objects", "keys"]) {
+    $(`${name}-view`).hidden = name !== view;
+  }
+  $("nav").hidden = view === "login";
+  if (session) {
+    $("whoami").textContent = session.AccessKeyId;
+  }
+}
+
+async function loadBuckets() {
+  const buckets = await request("GET", "/buckets");
+  const rows = $("bucket-rows");
+  rows.replaceChildren();
+  for (const bucket of buckets) {
+    const row = rows.insertRow();
+    cell(row, link(bucket.Name, () => openFolder(bucket.Name, "").catch((e) => showError(e.message))));
+    cell(row, bucket.Region);
+    cell(row, new Date(bucket.CreatedAt).toLocaleString());
+  }
+  show("buckets");
+}
+
+function renderBreadcrumbs() {
+  const crumbs = $("breadcrumbs");
+  crumbs.replaceChildren(link("Buckets", () => loadBuckets().catch((e) => showError(e.message))));
+  crumbs.append(" / ", link(listing.bucket, () => openFolder(listing.bucket, "").catch((e) => showError(e.message))));
+  let path = "";
+  for (const part of listing.prefix.split("/").filter(Boolean)) {
+    path += `${part}/`;
+    const prefix = path;
+    crumbs.append(" / ", link(part, () => openFolder(listing.bucket, prefix).catch((e) => showError(e.message))));
+  }
+}
+
+async function openFolder(bucket, prefix) {
+  listing = { bucket, prefix, after: null };
+  $("object-rows").replaceChildren();
+  $("object-details").hidden = true;
+  renderBreadcrumbs();
+  await loadMoreObjects();
+  show("objects");
+}
+
+async function loadMoreObjects() {
+  const query = new URLSearchParams({ prefix: listing.prefix });
+  if (listing.after) {
+    query.set("after", listing.after);
+  }
+  const page = await request("GET", `/buckets/${encodeURIComponent(listing.bucket)}/objects?${query}`);
+  const rows = $("object-rows");
+  for (const folder of page.Folders) {
+    const row = rows.insertRow();
+    const name = folder.slice(listing.prefix.length);
+    cell(row, link(name, () => openFolder(listing.bucket, folder).catch((e) => showError(e.message))));
+    cell(row, "");
+    cell(row, "");
+    cell(row, "");
+  }
+  for (const object of page.Objects) {
+    const row = rows.insertRow();
+    cell(row, link(object.Key.slice(listing.prefix.length), () => showObject(object.Key).catch((e) => showError(e.message))));
+    cell(row, formatSize(object.Size)).className = "size";
+    cell(row, new Date(object.LastModified).toLocaleString());
+    const download = document.createElement("a");
+    download.textContent = "Download";
+    download.href = `${api}/buckets/${encodeURIComponent(listing.bucket)}/download/${encodeKey(object.Key)}`;
+    cell(row, download);
+  }
+  listing.after = page.NextAfter;
+  $("more-objects").hidden = !page.NextAfter;
+}
+
+async function showObject(key) {
+  const path = `/buckets/${encodeURIComponent(listing.bucket)}/objects/${encodeKey(key)}`;
+  const object = await request("GET", path);
+  const list = $("object-metadata");
+  list.replaceChildren();
+  const add = (name, value) => {
+    const dt = document.createElement("dt");
+    dt.textContent = name;
+    const dd = document.createElement("dd");
+    dd.textContent = value;
+    list.append(dt, dd);
+  };
+  add("Key", object.Key);
+  add("Size", `${formatSize(object.Size)} (${object.Size} bytes)`);
+  add("ETag", object.ETag);
+  add("Content-Type", object.ContentType);
+  if (object.ContentEncoding) {
+    add("Content-Encoding", object.ContentEncoding);
+  }
+  add("Created", new Date(object.CreatedAt).toLocaleString());
+  add("Last modified", new Date(object.LastModified).toLocaleString());
+  for (const [name, value] of Object.entries(object.Metadata)) {
+    add(`x-amz-meta-${name}`, value);
+  }
+  $("object-download").href = `${api}/buckets/${encodeURIComponent(listing.bucket)}/download/${encodeKey(key)}`;
+  selected = key;
+  $("object-details").hidden = false;
+}
+
+async function loadKeys() {
+  const keys = await request("GET", "/access-keys");
+  const rows = $("key-rows");
+  rows.replaceChildren();
+  for (const key of keys) {
+    const row = rows.insertRow();
+    cell(row, key.AccessKeyId);
+    cell(row, key.Description);
+    cell(row, key.Policies.join(", "));
+    cell(row, key.IsActive ? "yes" : "no");
+    cell(row, new Date(key.CreatedAt).toLocaleString());
+    if (key.AccessKeyId === session.AccessKeyId) {
+      cell(row, "current session");
+    } else {
+      cell(row, link("Delete", async () => {
+        if (!confirm(`Delete access key ${key.AccessKeyId}?`)) {
+          return;
+        }
+        try {
+          await request("DELETE", `/access-keys/${encodeURIComponent(key.AccessKeyId)}`);
+          await loadKeys();
+        } catch (e) {
+          showError(e.message);
+        }
+      }));
+    }
+  }
+  show("keys");
+}
+
+function handle(form, submit) {
+  form.addEventListener("submit", async (event) => {
+    event.preventDefault();
+    showError("");
+    try {
+      await submit(new FormData(form));
+    } catch (e) {
+      showError(e.message);
+    }
+  });
+}
+
+handle($("login-form"), async (data) => {
+  session = await request("POST", "/login", JSON.stringify(Object.fromEntries(data)), {
+    "content-type": "application/json",
+  });
+  $("login-form").reset();
+  await loadBuckets();
+});
+
+handle($("upload-form"), async (data) => {
+  const file = data.get("file");
+  const key = listing.prefix + file.name;
+  await request("PUT", `/buckets/${encodeURIComponent(listing.bucket)}/objects/${encodeKey(key)}`, file, {
+    "content-type": file.type || "application/octet-stream",
+  });
+  $("upload-form").reset();
+  await openFolder(listing.bucket, listing.prefix);
+});
+
+handle($("key-form"), async (data) => {
+  const policies = (data.get("Policies") || "").split(",").map((p) => p.trim()).filter(Boolean);
+  const key = await request("POST", "/access-keys", JSON.stringify({
+    Description: data.get("Description") || null,
+    Policies: policies,
+  }), { "content-type": "application/json" });
+  $("key-form").reset();
+  $("new-key").textContent = `Created ${key.AccessKeyId}, secret ${key.SecretAccessKey} (shown only once)`;
+  $("new-key").hidden = false;
+  await loadKeys();
+});
+
+$("object-delete").addEventListener("click", async () => {
+  if (!selected || !confirm(`Delete ${selected}?`)) {
+    return;
+  }
+  try {
+    await request("DELETE", `/buckets/${encodeURIComponent(listing.bucket)}/objects/${encodeKey(selected)}`);
+    await openFolder(listing.bucket, listing.prefix);
+  } catch (e) {
+    showError(e.message);
+  }
+});
+
+$("more-objects").addEventListener("click", () => loadMoreObjects().catch((e) => showError(e.message)));
+
+$("logout").addEventListener("click", async () => {
+  try {
+    await request("POST", "/logout");
+  } finally {
+    session = null;
+    show("login");
+  }
+});
+
+for (const button of document.querySelectorAll("nav [data-view]")) {
+  button.addEventListener("click", () => {
+    showError("");
+    $("new-key").hidden = true;
+    const load = button.dataset.view === "keys" ? loadKeys : loadBuckets;
+    load().catch((e) => showError(e.message));
+  });
+}
+
+// Resume an existing session, if the cookie is still valid
+request("GET", "/session")
+  .then((current) => {
+    session = current;
+    return loadBuckets();
+  })
+  .catch(() => show("login"));
@@ -1 +1,556 @@
-// Admin UI placeholder - will be implemented as a web interface later
\ No newline at end of file
+use axum::{
+    async_trait,
+    extract::{Extension, FromRequestParts, Path, Query, Request, State},
+    handler::Handler,
+    http::{header, request::Parts, HeaderValue, Method, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Duration, Utc};
+use ghostbay_auth::{
+    policy::{validate_key_policies, ADMIN_POLICY},
+    AccessKeyRepository, AuthContext, CreateAccessKeyRequest,
+};
+use ghostbay_catalog::{BucketRepository, ObjectRepository};
+use rust_embed::RustEmbed;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+
+use ghostbay_api::{
+    handlers::{self, list_page, resolve_bucket},
+    middleware::AuditAction,
+    ApiError, ApiResult, AppState,
+};
+
+// Built-in web console under /console, enabled with console_enabled. It talks
+// to its own JSON endpoints under /console/api rather than the S3 routes, so
+// browser sessions never mix with SDK traffic:
+//
+//   POST   /console/api/login                       access key id and secret in, session cookie out
+//   POST   /console/api/logout
+//   GET    /console/api/session                     signed-in key and the CSRF token
+//   GET    /console/api/buckets
+//   GET    /console/api/buckets/:bucket/objects     one folder level: ?prefix=&after=
+//   GET    /console/api/buckets/:bucket/objects/*key   object metadata
+//   PUT    /console/api/buckets/:bucket/objects/*key   upload (small objects only)
+//   DELETE /console/api/buckets/:bucket/objects/*key
+//   GET    /console/api/buckets/:bucket/download/*key
+//   GET    /console/api/access-keys, POST to create, DELETE /console/api/access-keys/:id
+//
+// Only keys with the admin policy can sign in. Every request other than a
+// GET must echo the session's CSRF token in x-ghostbay-csrf.
+
+#[derive(RustEmbed)]
+#[folder = "console/"]
+struct ConsoleAssets;
+
+const SESSION_COOKIE: &str = "ghostbay_console";
+const CSRF_HEADER: &str = "x-ghostbay-csrf";
+const SESSION_LIFETIME_HOURS: i64 = 12;
+const LIST_PAGE_SIZE: usize = 200;
+
+// Console uploads are buffered in memory, so they are capped well below the
+// S3 limits; larger objects go through an SDK
+pub const CONSOLE_MAX_UPLOAD_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Default)]
+pub struct ConsoleSessions {
+    sessions: RwLock<HashMap<String, SessionEntry>>,
+}
+
+#[derive(Debug, Clone)]
+struct SessionEntry {
+    access_key_id: String,
+    csrf_token: String,
+    expires_at: DateTime<Utc>,
+}
+
+impl ConsoleSessions {
+    fn create(&self, access_key_id: &str) -> (String, SessionEntry) {
+        let token = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+        let entry = SessionEntry {
+            access_key_id: access_key_id.to_string(),
+            csrf_token: uuid::Uuid::new_v4().simple().to_string(),
+            expires_at: Utc::now() + Duration::hours(SESSION_LIFETIME_HOURS),
+        };
+
+        let mut sessions = self.sessions.write().unwrap_or_else(|e| e.into_inner());
+        let now = Utc::now();
+        sessions.retain(|_, session| session.expires_at > now);
+        sessions.insert(token.clone(), entry.clone());
+        (token, entry)
+    }
+
+    fn get(&self, token: &str) -> Option<SessionEntry> {
+        let sessions = self.sessions.read().unwrap_or_else(|e| e.into_inner());
+        sessions.get(token).filter(|session| session.expires_at > Utc::now()).cloned()
+    }
+
+    fn remove(&self, token: &str) {
+        self.sessions.write().unwrap_or_else(|e| e.into_inner()).remove(token);
+    }
+
+    // Signs out every session of a key, once the key is deleted
+    fn remove_key(&self, access_key_id: &str) {
+        self.sessions
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|_, session| session.access_key_id != access_key_id);
+    }
+}
+
+// A signed-in console session. The access key is reloaded on every request,
+// so deactivating it or dropping its admin policy ends the session at once.
+pub struct ConsoleSession {
+    token: String,
+    entry: SessionEntry,
+    auth: AuthContext,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for ConsoleSession {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let token = session_cookie(parts)
+            .ok_or_else(|| ApiError::AuthorizationFailed("no console session".to_string()))?;
+        let Extension(sessions) = Extension::<Arc<ConsoleSessions>>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| ApiError::Internal(anyhow::anyhow!("console sessions unavailable: {}", e)))?;
+        let entry = sessions
+            .get(&token)
+            .ok_or_else(|| ApiError::AuthorizationFailed("console session expired".to_string()))?;
+
+        // SameSite=Strict keeps the cookie off cross-site requests; the token
+        // also covers browsers that do not honour it
+        if parts.method != Method::GET && parts.method != Method::HEAD {
+            let csrf_token = parts.headers.get(CSRF_HEADER).and_then(|v| v.to_str().ok()).unwrap_or("");
+            if !constant_time_eq(csrf_token.as_bytes(), entry.csrf_token.as_bytes()) {
+                return Err(ApiError::AuthorizationFailed("missing or invalid CSRF token".to_string()));
+            }
+        }
+
+        let auth = admin_context(state, &entry.access_key_id).await?;
+        Ok(ConsoleSession { token, entry, auth })
+    }
+}
+
+impl ConsoleSession {
+    // Response extension naming the signed-in key in the audit log; the audit
+    // middleware runs before this extractor, so it cannot see it on the request
+    fn principal(&self) -> Extension<AuthContext> {
+        Extension(self.auth.clone())
+    }
+}
+
+fn session_cookie(parts: &Parts) -> Option<String> {
+    parts
+        .headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value.to_string())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+// Loads the key and checks it may use the console
+async fn admin_context(state: &AppState, access_key_id: &str) -> ApiResult<AuthContext> {
+    let denied = || ApiError::AuthorizationFailed(format!("{} may not use the console", access_key_id));
+    let key = state.auth.get_access_key(access_key_id).await?.ok_or_else(denied)?;
+    let expired = key.expires_at.is_some_and(|expires_at| Utc::now() > expires_at);
+    if !key.is_active || expired || !key.policies.iter().any(|policy| policy == ADMIN_POLICY) {
+        return Err(denied());
+    }
+
+    Ok(AuthContext {
+        access_key_id: key.access_key_id,
+        authenticated: true,
+        policies: key.policies,
+        session_token: None,
+        quota: key.quota,
+    })
+}
+
+// Tags a response for the audit log with the operation and the signed-in key
+fn audited(mut response: Response, session: &ConsoleSession, action: &'static str) -> Response {
+    response.extensions_mut().insert(AuditAction(action));
+    response.extensions_mut().insert(session.principal().0);
+    response
+}
+
+pub fn console_router(sessions: Arc<ConsoleSessions>) -> Router<AppState> {
+    Router::new()
+        .route("/console", get(|| async { Redirect::permanent("/console/") }))
+        .route("/console/", get(index))
+        .route("/console/assets/*path", get(asset))
+        .route("/console/api/login", post(login))
+        .route("/console/api/logout", post(logout))
+        .route("/console/api/session", get(session))
+        .route("/console/api/buckets", get(list_buckets))
+        .route("/console/api/buckets/:bucket/objects", get(list_objects))
+        .route(
+            "/console/api/buckets/:bucket/objects/*key",
+            get(object_metadata).put(upload_object).delete(delete_object),
+        )
+        .route("/console/api/buckets/:bucket/download/*key", get(download_object))
+        .route("/console/api/access-keys", get(list_access_keys).post(create_access_key))
+        .route("/console/api/access-keys/:access_key_id", delete(delete_access_key))
+        .layer(Extension(sessions))
+}
+
+async fn index() -> Response {
+    asset(Path("index.html".to_string())).await
+}
+
+async fn asset(Path(path): Path<String>) -> Response {
+    let Some(file) = ConsoleAssets::get(&path) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let content_type = match path.rsplit_once('.').map(|(_, extension)| extension) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        _ => "application/octet-stream",
+    };
+    ([(header::CONTENT_TYPE, content_type), (header::CACHE_CONTROL, "no-cache")], file.data.into_owned()).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LoginRequest {
+    access_key_id: String,
+    secret_access_key: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SessionResponse {
+    access_key_id: String,
+    csrf_token: String,
+    expires_at: DateTime<Utc>,
+}
+
+impl SessionResponse {
+    fn new(entry: &SessionEntry) -> Self {
+        Self {
+            access_key_id: entry.access_key_id.clone(),
+            csrf_token: entry.csrf_token.clone(),
+            expires_at: entry.expires_at,
+        }
+    }
+}
+
+async fn login(
+    Extension(sessions): Extension<Arc<ConsoleSessions>>,
+    State(state): State<AppState>,
+    Json(request): Json<LoginRequest>,
+) -> ApiResult<Response> {
+    let key = state.auth.get_access_key(&request.access_key_id).await?;
+    let secret_matches = key.as_ref().is_some_and(|key| {
+        constant_time_eq(key.secret_access_key.as_bytes(), request.secret_access_key.as_bytes())
+    });
+    if !secret_matches {
+        tracing::warn!("Failed console sign-in for {}", request.access_key_id);
+        return Err(ApiError::AuthorizationFailed("invalid console credentials".to_string()));
+    }
+    let auth = admin_context(&state, &request.access_key_id).await?;
+
+    let (token, entry) = sessions.create(&request.access_key_id);
+    tracing::info!("Console sign-in for {}", entry.access_key_id);
+
+    let cookie = format!(
+        "{}={}; Path=/console; HttpOnly; SameSite=Strict; Max-Age={}",
+        SESSION_COOKIE,
+        token,
+        SESSION_LIFETIME_HOURS * 3600
+    );
+    let mut response = (Extension(auth), Json(SessionResponse::new(&entry))).into_response();
+    response
+        .headers_mut()
+        .insert(header::SET_COOKIE, HeaderValue::from_str(&cookie).map_err(anyhow::Error::from)?);
+    Ok(response)
+}
+
+async fn logout(Extension(sessions): Extension<Arc<ConsoleSessions>>, session: ConsoleSession) -> Response {
+    sessions.remove(&session.token);
+    let cookie = format!("{}=; Path=/console; HttpOnly; SameSite=Strict; Max-Age=0", SESSION_COOKIE);
+    (session.principal(), [(header::SET_COOKIE, cookie)], StatusCode::NO_CONTENT).into_response()
+}
+
+async fn session(session: ConsoleSession) -> (Extension<AuthContext>, Json<SessionResponse>) {
+    (session.principal(), Json(SessionResponse::new(&session.entry)))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ConsoleBucket {
+    name: String,
+    region: String,
+    created_at: DateTime<Utc>,
+}
+
+async fn list_buckets(
+    session: ConsoleSession,
+    State(state): State<AppState>,
+) -> ApiResult<(Extension<AuthContext>, Json<Vec<ConsoleBucket>>)> {
+    let buckets = BucketRepository::new(state.catalog.pool().clone()).list().await?;
+    Ok((session.principal(), Json(
+        buckets
+            .into_iter()
+            .map(|bucket| ConsoleBucket {
+                name: bucket.name,
+                region: bucket.region,
+                created_at: bucket.created_at,
+            })
+            .collect(),
+    )))
+}
+
+#[derive(Debug, Deserialize)]
+struct ConsoleListQuery {
+    prefix: Option<String>,
+    after: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ConsoleListing {
+    prefix: String,
+    folders: Vec<String>,
+    objects: Vec<ConsoleObject>,
+    next_after: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ConsoleObject {
+    key: String,
+    size: u64,
+    last_modified: DateTime<Utc>,
+    #[serde(rename = "ETag")]
+    etag: String,
+}
+
+// One folder level of a bucket, with "/" as the delimiter
+async fn list_objects(
+    session: ConsoleSession,
+    Path(bucket_name): Path<String>,
+    Query(query): Query<ConsoleListQuery>,
+    State(state): State<AppState>,
+) -> ApiResult<(Extension<AuthContext>, Json<ConsoleListing>)> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let prefix = query.prefix.unwrap_or_default();
+    let resume_prefix = query.after.clone().filter(|after| after.ends_with('/'));
+
+    let repo = ObjectRepository::new(state.catalog.pool().clone());
+    let page = list_page(
+        &repo,
+        bucket.id,
+        Some(&prefix).filter(|prefix| !prefix.is_empty()).map(|prefix| prefix.as_str()),
+        Some("/"),
+        query.after,
+        resume_prefix,
+        LIST_PAGE_SIZE,
+    )
+    .await?;
+
+    Ok((session.principal(), Json(ConsoleListing {
+        prefix,
+        folders: page.common_prefixes,
+        objects: page
+            .objects
+            .into_iter()
+            .map(|object| ConsoleObject {
+                key: object.key,
+                size: object.size as u64,
+                last_modified: object.updated_at,
+                etag: object.etag,
+            })
+            .collect(),
+        next_after: if page.is_truncated { page.last_entry } else { None },
+    })))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ConsoleObjectMetadata {
+    bucket: String,
+    key: String,
+    size: u64,
+    #[serde(rename = "ETag")]
+    etag: String,
+    content_type: String,
+    content_encoding: Option<String>,
+    created_at: DateTime<Utc>,
+    last_modified: DateTime<Utc>,
+    metadata: BTreeMap<String, String>,
+}
+
+async fn object_metadata(
+    session: ConsoleSession,
+    Path((bucket_name, key)): Path<(String, String)>,
+    State(state): State<AppState>,
+) -> ApiResult<(Extension<AuthContext>, Json<ConsoleObjectMetadata>)> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let object = ObjectRepository::new(state.catalog.pool().clone())
+        .find_by_bucket_and_key(bucket.id, &key)
+        .await?
+        .ok_or_else(|| ApiError::ObjectNotFound(key.clone()))?;
+
+    let metadata = object
+        .metadata
+        .as_deref()
+        .and_then(|metadata| serde_json::from_str(metadata).ok())
+        .unwrap_or_default();
+
+    Ok((session.principal(), Json(ConsoleObjectMetadata {
+        bucket: bucket_name,
+        key,
+        size: object.size as u64,
+        etag: object.etag,
+        content_type: object.content_type,
+        content_encoding: object.content_encoding,
+        created_at: object.created_at,
+        last_modified: object.updated_at,
+        metadata,
+    })))
+}
+
+// Object transfers run the S3 handlers themselves, so the console stores and
+// serves objects exactly as an SDK would
+async fn download_object(session: ConsoleSession, State(state): State<AppState>, request: Request) -> Response {
+    let file_name = request
+        .uri()
+        .path()
+        .rsplit('/')
+        .next()
+        .map(|name| urlencoding::decode(name).map(|name| name.into_owned()).unwrap_or_else(|_| name.to_string()))
+        .unwrap_or_default();
+
+    let mut response = handlers::get_object.call(request, state).await;
+    if response.status().is_success() {
+        let disposition = format!("attachment; filename*=UTF-8''{}", urlencoding::encode(&file_name));
+        if let Ok(value) = HeaderValue::from_str(&disposition) {
+            response.headers_mut().insert(header::CONTENT_DISPOSITION, value);
+        }
+    }
+    audited(response, &session, "GetObject")
+}
+
+async fn upload_object(session: ConsoleSession, State(state): State<AppState>, request: Request) -> Response {
+    let content_length = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    let response = match content_length {
+        Some(size) if size > CONSOLE_MAX_UPLOAD_BYTES => {
+            ApiError::EntityTooLarge { size, max_size: CONSOLE_MAX_UPLOAD_BYTES }.into_response()
+        }
+        Some(_) => handlers::put_object.call(request, state).await,
+        None => ApiError::BadRequest("Console uploads must carry a Content-Length.".to_string()).into_response(),
+    };
+    audited(response, &session, "PutObject")
+}
+
+async fn delete_object(session: ConsoleSession, State(state): State<AppState>, request: Request) -> Response {
+    let response = handlers::delete_object.call(request, state).await;
+    audited(response, &session, "DeleteObject")
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ConsoleAccessKey {
+    access_key_id: String,
+    // Only returned when the key is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret_access_key: Option<String>,
+    description: Option<String>,
+    policies: Vec<String>,
+    is_active: bool,
+    created_at: DateTime<Utc>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl ConsoleAccessKey {
+    fn new(key: ghostbay_auth::AccessKey, with_secret: bool) -> Self {
+        Self {
+            secret_access_key: with_secret.then_some(key.secret_access_key),
+            access_key_id: key.access_key_id,
+            description: key.description,
+            policies: key.policies,
+            is_active: key.is_active,
+            created_at: key.created_at,
+            expires_at: key.expires_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CreateConsoleAccessKey {
+    description: Option<String>,
+    #[serde(default)]
+    policies: Vec<String>,
+}
+
+async fn list_access_keys(
+    session: ConsoleSession,
+    State(state): State<AppState>,
+) -> ApiResult<(Extension<AuthContext>, Json<Vec<ConsoleAccessKey>>)> {
+    let keys = AccessKeyRepository::new(state.catalog.pool().clone()).list(true).await?;
+    Ok((session.principal(), Json(keys.into_iter().map(|key| ConsoleAccessKey::new(key, false)).collect())))
+}
+
+async fn create_access_key(
+    session: ConsoleSession,
+    State(state): State<AppState>,
+    Json(request): Json<CreateConsoleAccessKey>,
+) -> ApiResult<(StatusCode, Extension<AuthContext>, Json<ConsoleAccessKey>)> {
+    validate_key_policies(&request.policies).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let key = state
+        .auth
+        .create_access_key(CreateAccessKeyRequest {
+            policies: request.policies,
+            description: request.description,
+            expires_at: None,
+            quota: Default::default(),
+        })
+        .await?;
+    tracing::info!("Console: {} created access key {}", session.entry.access_key_id, key.access_key_id);
+
+    Ok((StatusCode::CREATED, session.principal(), Json(ConsoleAccessKey::new(key, true))))
+}
+
+async fn delete_access_key(
+    Extension(sessions): Extension<Arc<ConsoleSessions>>,
+    session: ConsoleSession,
+    Path(access_key_id): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<(Extension<AuthContext>, StatusCode)> {
+    // Deleting the key in use would lock the operator out mid-session
+    if access_key_id == session.entry.access_key_id {
+        return Err(ApiError::BadRequest("The access key of the current console session cannot be deleted.".to_string()));
+    }
+
+    if !AccessKeyRepository::new(state.catalog.pool().clone()).delete(&access_key_id).await? {
+        return Err(ApiError::InvalidArgument {
+            name: "AccessKeyId".to_string(),
+            value: Some(access_key_id),
+            message: "The access key does not exist.",
+        });
+    }
+    sessions.remove_key(&access_key_id);
+    tracing::info!("Console: {} deleted access key {}", session.entry.access_key_id, access_key_id);
+
+    Ok((session.principal(), StatusCode::NO_CONTENT))
+}
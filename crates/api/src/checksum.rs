@@ -0,0 +1,177 @@
+use axum::http::HeaderMap;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use crc::Crc;
+use sha1::Digest;
+
+use crate::error::ApiError;
+
+// Additional checksums newer SDKs send as x-amz-checksum-<algorithm>. An
+// upload carrying one is verified against it, and the checksum is kept with
+// the object and returned on later reads of the whole object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Crc32,
+    Crc32c,
+    Crc64Nvme,
+    Sha1,
+    Sha256,
+}
+
+const ALGORITHMS: [ChecksumAlgorithm; 5] = [
+    ChecksumAlgorithm::Crc32,
+    ChecksumAlgorithm::Crc32c,
+    ChecksumAlgorithm::Crc64Nvme,
+    ChecksumAlgorithm::Sha1,
+    ChecksumAlgorithm::Sha256,
+];
+
+static CRC32: Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+static CRC32C: Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISCSI);
+static CRC64NVME: Crc<u64> = Crc::<u64>::new(&crc::CRC_64_NVME);
+
+impl ChecksumAlgorithm {
+    // As S3 names them in x-amz-checksum-algorithm and the catalog stores them
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Crc32 => "CRC32",
+            Self::Crc32c => "CRC32C",
+            Self::Crc64Nvme => "CRC64NVME",
+            Self::Sha1 => "SHA1",
+            Self::Sha256 => "SHA256",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        ALGORITHMS.into_iter().find(|algorithm| algorithm.as_str().eq_ignore_ascii_case(name))
+    }
+
+    pub fn header_name(&self) -> &'static str {
+        match self {
+            Self::Crc32 => "x-amz-checksum-crc32",
+            Self::Crc32c => "x-amz-checksum-crc32c",
+            Self::Crc64Nvme => "x-amz-checksum-crc64nvme",
+            Self::Sha1 => "x-amz-checksum-sha1",
+            Self::Sha256 => "x-amz-checksum-sha256",
+        }
+    }
+
+    pub fn mismatch_message(&self) -> &'static str {
+        match self {
+            Self::Crc32 => "The CRC32 you specified did not match the calculated checksum.",
+            Self::Crc32c => "The CRC32C you specified did not match the calculated checksum.",
+            Self::Crc64Nvme => "The CRC64NVME you specified did not match the calculated checksum.",
+            Self::Sha1 => "The SHA1 you specified did not match the calculated checksum.",
+            Self::Sha256 => "The SHA256 you specified did not match the calculated checksum.",
+        }
+    }
+
+    pub fn hasher(&self) -> ChecksumHasher {
+        match self {
+            Self::Crc32 => ChecksumHasher::Crc32(CRC32.digest()),
+            Self::Crc32c => ChecksumHasher::Crc32(CRC32C.digest()),
+            Self::Crc64Nvme => ChecksumHasher::Crc64(CRC64NVME.digest()),
+            Self::Sha1 => ChecksumHasher::Sha1(sha1::Sha1::new()),
+            Self::Sha256 => ChecksumHasher::Sha256(sha2::Sha256::new()),
+        }
+    }
+
+    fn digest_len(&self) -> usize {
+        match self {
+            Self::Crc32 | Self::Crc32c => 4,
+            Self::Crc64Nvme => 8,
+            Self::Sha1 => 20,
+            Self::Sha256 => 32,
+        }
+    }
+}
+
+// Computes a checksum over a body as it arrives
+pub enum ChecksumHasher {
+    Crc32(crc::Digest<'static, u32>),
+    Crc64(crc::Digest<'static, u64>),
+    Sha1(sha1::Sha1),
+    Sha256(sha2::Sha256),
+}
+
+impl ChecksumHasher {
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Crc32(digest) => digest.update(data),
+            Self::Crc64(digest) => digest.update(data),
+            Self::Sha1(hasher) => hasher.update(data),
+            Self::Sha256(hasher) => hasher.update(data),
+        }
+    }
+
+    // Base64 of the digest; CRCs are big-endian
+    pub fn finalize(self) -> String {
+        match self {
+            Self::Crc32(digest) => BASE64.encode(digest.finalize().to_be_bytes()),
+            Self::Crc64(digest) => BASE64.encode(digest.finalize().to_be_bytes()),
+            Self::Sha1(hasher) => BASE64.encode(hasher.finalize()),
+            Self::Sha256(hasher) => BASE64.encode(hasher.finalize()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checksum {
+    pub algorithm: ChecksumAlgorithm,
+    pub value: String,
+}
+
+impl Checksum {
+    // The checksum a catalog row was stored with, if any
+    pub fn stored(algorithm: Option<&str>, value: Option<&str>) -> Option<Self> {
+        Some(Self {
+            algorithm: ChecksumAlgorithm::parse(algorithm?)?,
+            value: value?.to_string(),
+        })
+    }
+}
+
+// The algorithm of a checksum sent in the trailer of an aws-chunked body,
+// which x-amz-trailer names
+pub fn trailing_checksum_algorithm(headers: &HeaderMap) -> Result<Option<ChecksumAlgorithm>, ApiError> {
+    let Some(trailer) = headers.get("x-amz-trailer") else {
+        return Ok(None);
+    };
+    let name = trailer.to_str().unwrap_or_default().trim();
+    ALGORITHMS
+        .into_iter()
+        .find(|algorithm| algorithm.header_name().eq_ignore_ascii_case(name))
+        .map(Some)
+        .ok_or_else(|| ApiError::InvalidArgument {
+            name: "x-amz-trailer".to_string(),
+            value: Some(name.to_string()),
+            message: "The trailer must name a supported x-amz-checksum- header.",
+        })
+}
+
+// The checksum a request declares in an x-amz-checksum-* header, for the body
+// to be verified against as it arrives. At most one may be sent; requests
+// without one have no checksum.
+pub fn request_checksum(headers: &HeaderMap) -> Result<Option<Checksum>, ApiError> {
+    let mut declared = ALGORITHMS
+        .into_iter()
+        .filter_map(|algorithm| headers.get(algorithm.header_name()).map(|value| (algorithm, value)));
+    let Some((algorithm, value)) = declared.next() else {
+        return Ok(None);
+    };
+    if declared.next().is_some() {
+        return Err(ApiError::BadRequest(
+            "Expecting a single x-amz-checksum- header. Multiple checksum Types are not allowed.".to_string(),
+        ));
+    }
+
+    let expected = value.to_str().unwrap_or_default().to_string();
+    let well_formed = BASE64.decode(&expected).is_ok_and(|digest| digest.len() == algorithm.digest_len());
+    if !well_formed {
+        return Err(ApiError::InvalidArgument {
+            name: algorithm.header_name().to_string(),
+            value: Some(expected),
+            message: "The checksum is not a base64-encoded digest of its algorithm's length.",
+        });
+    }
+    Ok(Some(Checksum { algorithm, value: expected }))
+}
@@ -0,0 +1,224 @@
+use axum::http::HeaderMap;
+use bytes::{Buf, Bytes, BytesMut};
+use futures::StreamExt;
+use ghostbay_auth::ChunkSigner;
+use ghostbay_engine::ByteStream;
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, OnceLock};
+
+use crate::upload::UploadError;
+
+// x-amz-content-sha256 of a body in signed chunks, with or without a trailer.
+// STREAMING-UNSIGNED-PAYLOAD-TRAILER frames the body the same way, unsigned.
+pub const STREAMING_SIGNED_PAYLOAD: &str = "STREAMING-AWS4-HMAC-SHA256-PAYLOAD";
+
+// Chunk headers and trailer lines are short; anything longer is not aws-chunked
+const MAX_LINE_LENGTH: usize = 4096;
+
+// Trailing headers of the body, such as x-amz-checksum-crc32, set once the
+// last of them has arrived
+pub type Trailers = Arc<OnceLock<Vec<(String, String)>>>;
+
+// Whether the body is framed as aws-chunked: SDKs streaming a signed or
+// trailing-checksum upload say so in x-amz-content-sha256, and usually in
+// Content-Encoding as well
+pub fn is_aws_chunked(headers: &HeaderMap) -> bool {
+    let streaming = headers
+        .get("x-amz-content-sha256")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("STREAMING-"));
+    let encoded = headers
+        .get("content-encoding")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').any(|encoding| encoding.trim() == "aws-chunked"));
+    streaming || encoded
+}
+
+#[derive(Clone, Copy)]
+enum Frame {
+    Header,
+    // Bytes of the current chunk's data still to come
+    Data(u64),
+    DataEnd,
+    Trailer,
+    Done,
+}
+
+struct Decoder {
+    input: ByteStream,
+    buffer: BytesMut,
+    frame: Frame,
+    signer: Option<ChunkSigner>,
+    chunk_signature: Option<String>,
+    chunk_hasher: Sha256,
+    trailers: Vec<(String, String)>,
+    // The trailers as they are signed, one "name:value\n" each
+    signed_trailers: String,
+    trailer_signed: bool,
+    slot: Trailers,
+}
+
+// Strips the aws-chunked framing from a body:
+//
+//   <hex size>[;chunk-signature=<signature>]\r\n<data>\r\n
+//   ...
+//   0[;chunk-signature=<signature>]\r\n
+//   [<trailer>:<value>\r\n ...]\r\n
+//
+// With a signer every chunk's signature, and the trailer signature when there
+// are trailers, must match; without one the signatures are ignored, as for
+// STREAMING-UNSIGNED-PAYLOAD-TRAILER. Failures surface as an UploadError in
+// the stream's anyhow::Error.
+pub fn decode_aws_chunked(input: ByteStream, signer: Option<ChunkSigner>) -> (ByteStream, Trailers) {
+    let slot = Trailers::default();
+    let decoder = Decoder {
+        input,
+        buffer: BytesMut::new(),
+        frame: Frame::Header,
+        signer,
+        chunk_signature: None,
+        chunk_hasher: Sha256::new(),
+        trailers: Vec::new(),
+        signed_trailers: String::new(),
+        trailer_signed: false,
+        slot: slot.clone(),
+    };
+    let stream = futures::stream::try_unfold(decoder, |mut decoder| async move {
+        Ok(decoder.next().await?.map(|data| (data, decoder)))
+    });
+    (Box::pin(stream), slot)
+}
+
+impl Decoder {
+    async fn next(&mut self) -> anyhow::Result<Option<Bytes>> {
+        loop {
+            match self.frame {
+                Frame::Data(remaining) if remaining > 0 => {
+                    if !self.buffer.is_empty() {
+                        let take = remaining.min(self.buffer.len() as u64) as usize;
+                        let data = self.buffer.split_to(take).freeze();
+                        self.frame = Frame::Data(remaining - take as u64);
+                        if self.signer.is_some() {
+                            self.chunk_hasher.update(&data);
+                        }
+                        return Ok(Some(data));
+                    }
+                }
+                Frame::Data(_) => {
+                    self.verify_chunk()?;
+                    self.frame = Frame::DataEnd;
+                    continue;
+                }
+                Frame::DataEnd => {
+                    if self.buffer.len() >= 2 {
+                        if !self.buffer.starts_with(b"\r\n") {
+                            return Err(UploadError::MalformedChunk("chunk data is longer than its declared size").into());
+                        }
+                        self.buffer.advance(2);
+                        self.frame = Frame::Header;
+                        continue;
+                    }
+                }
+                Frame::Header => {
+                    if let Some(line) = self.take_line()? {
+                        self.chunk_header(&line)?;
+                        continue;
+                    }
+                }
+                Frame::Trailer => {
+                    if let Some(line) = self.take_line()? {
+                        self.trailer_line(&line)?;
+                        continue;
+                    }
+                }
+                Frame::Done => return Ok(None),
+            }
+
+            match self.input.next().await {
+                Some(chunk) => self.buffer.extend_from_slice(&chunk?),
+                // Some clients end the body right after the final chunk,
+                // without the empty line closing the trailers
+                None if matches!(self.frame, Frame::Trailer) && self.buffer.is_empty() => {
+                    self.finish_trailers()?;
+                    return Ok(None);
+                }
+                None => return Err(UploadError::MalformedChunk("the body ended inside a chunk").into()),
+            }
+        }
+    }
+
+    fn take_line(&mut self) -> Result<Option<String>, UploadError> {
+        // Checked whether or not the line end has arrived yet, so the limit
+        // does not depend on how the body was split into reads
+        let end = self.buffer.windows(2).position(|window| window == b"\r\n");
+        if end.unwrap_or(self.buffer.len()) > MAX_LINE_LENGTH {
+            return Err(UploadError::MalformedChunk("chunk header is too long"));
+        }
+        let Some(end) = end else {
+            return Ok(None);
+        };
+        let line = self.buffer.split_to(end + 2);
+        let line = std::str::from_utf8(&line[..end]).map_err(|_| UploadError::MalformedChunk("chunk header is not UTF-8"))?;
+        Ok(Some(line.to_string()))
+    }
+
+    fn chunk_header(&mut self, line: &str) -> Result<(), UploadError> {
+        let (size, extensions) = line.split_once(';').unwrap_or((line, ""));
+        let size = u64::from_str_radix(size.trim(), 16).map_err(|_| UploadError::MalformedChunk("chunk size is not hexadecimal"))?;
+        self.chunk_signature = extensions
+            .split(';')
+            .find_map(|extension| extension.trim().strip_prefix("chunk-signature="))
+            .map(str::to_string);
+        if size == 0 {
+            self.verify_chunk()?;
+            self.frame = Frame::Trailer;
+        } else {
+            self.frame = Frame::Data(size);
+        }
+        Ok(())
+    }
+
+    fn verify_chunk(&mut self) -> Result<(), UploadError> {
+        let signature = self.chunk_signature.take();
+        let Some(signer) = self.signer.as_mut() else {
+            return Ok(());
+        };
+        let chunk_hash = format!("{:x}", self.chunk_hasher.finalize_reset());
+        if !signature.is_some_and(|signature| signer.verify_chunk(&chunk_hash, &signature)) {
+            return Err(UploadError::ChunkSignatureMismatch);
+        }
+        Ok(())
+    }
+
+    fn trailer_line(&mut self, line: &str) -> Result<(), UploadError> {
+        if line.is_empty() {
+            self.finish_trailers()?;
+            self.frame = Frame::Done;
+            return Ok(());
+        }
+        let (name, value) = line.split_once(':').ok_or(UploadError::MalformedChunk("trailer is not a header"))?;
+        let name = name.trim().to_ascii_lowercase();
+        let value = value.trim();
+        if name == "x-amz-trailer-signature" {
+            if let Some(signer) = self.signer.as_mut() {
+                let trailer_hash = format!("{:x}", Sha256::digest(self.signed_trailers.as_bytes()));
+                if !signer.verify_trailer(&trailer_hash, value) {
+                    return Err(UploadError::ChunkSignatureMismatch);
+                }
+            }
+            self.trailer_signed = true;
+        } else {
+            self.signed_trailers.push_str(&format!("{}:{}\n", name, value));
+            self.trailers.push((name, value.to_string()));
+        }
+        Ok(())
+    }
+
+    fn finish_trailers(&mut self) -> Result<(), UploadError> {
+        if self.signer.is_some() && !self.trailers.is_empty() && !self.trailer_signed {
+            return Err(UploadError::ChunkSignatureMismatch);
+        }
+        let _ = self.slot.set(std::mem::take(&mut self.trailers));
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use ghostbay_catalog::BucketCorsConfig;
+
+// Methods a CORS rule can allow, and how many rules a bucket can have, as in S3
+const CORS_METHODS: &[&str] = &["GET", "PUT", "POST", "DELETE", "HEAD"];
+const MAX_CORS_RULES: usize = 100;
+
+// Checks a configuration before it is stored. The error is the reason given
+// to the client.
+pub fn validate_rules(rules: &[BucketCorsConfig]) -> Result<(), String> {
+    if rules.is_empty() || rules.len() > MAX_CORS_RULES {
+        return Err(format!("A CORS configuration must have between 1 and {} rules.", MAX_CORS_RULES));
+    }
+    for rule in rules {
+        if rule.allowed_origins.is_empty() || rule.allowed_methods.is_empty() {
+            return Err("Each CORSRule must have at least one AllowedOrigin and one AllowedMethod.".to_string());
+        }
+        if let Some(method) = rule.allowed_methods.iter().find(|method| !CORS_METHODS.contains(&method.as_str())) {
+            return Err(format!("Found unsupported HTTP method in CORS config. Unsupported method is {}", method));
+        }
+        let patterns = rule.allowed_origins.iter().chain(&rule.allowed_headers);
+        if let Some(pattern) = patterns.into_iter().find(|pattern| pattern.matches('*').count() > 1) {
+            return Err(format!("{} can not have more than one wildcard.", pattern));
+        }
+    }
+    Ok(())
+}
+
+// Origins and allowed headers may hold one `*` standing for any run of characters
+fn wildcard_match(pattern: &str, value: &str) -> bool {
+    match pattern.split_once('*') {
+        Some((prefix, suffix)) => {
+            value.len() >= prefix.len() + suffix.len() && value.starts_with(prefix) && value.ends_with(suffix)
+        }
+        None => pattern == value,
+    }
+}
+
+// The first rule that allows a request from `origin` using `method` and
+// sending `request_headers` (lowercase), the order S3 evaluates them in
+pub fn find_rule<'a>(
+    rules: &'a [BucketCorsConfig],
+    origin: &str,
+    method: &str,
+    request_headers: &[String],
+) -> Option<&'a BucketCorsConfig> {
+    rules.iter().find(|rule| {
+        rule.allowed_origins.iter().any(|pattern| wildcard_match(pattern, origin))
+            && rule.allowed_methods.iter().any(|allowed| allowed == method)
+            && request_headers.iter().all(|header| {
+                rule.allowed_headers
+                    .iter()
+                    .any(|pattern| wildcard_match(&pattern.to_ascii_lowercase(), header))
+            })
+    })
+}
+
+// A rule open to every origin answers `*`; otherwise the origin is echoed
+pub fn allowed_origin<'a>(rule: &BucketCorsConfig, origin: &'a str) -> &'a str {
+    if rule.allowed_origins.iter().any(|pattern| pattern == "*") { "*" } else { origin }
+}
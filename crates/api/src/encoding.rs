@@ -0,0 +1,130 @@
+use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
+use axum::http::HeaderMap;
+use futures::StreamExt;
+use ghostbay_engine::ByteStream;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::io::AsyncRead;
+use tokio_util::io::{ReaderStream, StreamReader};
+
+use crate::{error::ApiError, upload::UploadError};
+
+// Decompression-bomb guard for buckets that inflate uploads: the decoded body
+// may be at most MAX_EXPANSION_RATIO times the encoded one (but always at
+// least MIN_DECODED_LIMIT), and never more than MAX_DECODED_SIZE.
+pub const MAX_EXPANSION_RATIO: u64 = 100;
+pub const MIN_DECODED_LIMIT: u64 = 1024 * 1024;
+pub const MAX_DECODED_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+
+// Encodings a bucket with decompress_on_upload inflates before storing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadEncoding {
+    Gzip,
+    Zstd,
+}
+
+impl UploadEncoding {
+    pub fn parse(content_encoding: &str) -> Option<Self> {
+        match content_encoding {
+            "gzip" | "x-gzip" => Some(Self::Gzip),
+            "zstd" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DecodeError {
+    #[error("decoded body exceeds {limit} bytes")]
+    TooLarge { decoded: u64, limit: u64 },
+    #[error("body is not valid {encoding} data: {message}")]
+    Corrupt { encoding: &'static str, message: String },
+}
+
+impl From<DecodeError> for ApiError {
+    fn from(error: DecodeError) -> Self {
+        match error {
+            DecodeError::TooLarge { decoded, limit } => ApiError::EntityTooLarge { size: decoded, max_size: limit },
+            DecodeError::Corrupt { encoding, .. } => {
+                ApiError::BadRequest(format!("The request body is not valid {} data.", encoding))
+            }
+        }
+    }
+}
+
+// Content-Encoding to store with an object, from the request. aws-chunked
+// only frames the upload itself and is never stored, as in S3.
+pub fn stored_content_encoding(headers: &HeaderMap) -> Option<String> {
+    let header = headers.get("content-encoding")?.to_str().ok()?;
+    let encodings: Vec<&str> = header
+        .split(',')
+        .map(str::trim)
+        .filter(|encoding| !encoding.is_empty() && *encoding != "aws-chunked" && *encoding != "identity")
+        .collect();
+    if encodings.is_empty() { None } else { Some(encodings.join(", ")) }
+}
+
+pub struct DecodedBody {
+    pub stream: ByteStream,
+    // Bytes produced so far; the object's size once the stream is drained
+    pub decoded: Arc<AtomicU64>,
+}
+
+// Inflates an encoded upload as it is written. Failures surface as a
+// DecodeError inside the stream's anyhow::Error, see upload_error; errors of
+// the encoded body itself pass through unchanged. Without a Content-Length
+// the expansion ratio cannot be applied and only MAX_DECODED_SIZE holds.
+pub fn decode_body(encoding: UploadEncoding, body: ByteStream, content_length: Option<u64>) -> DecodedBody {
+    let limit = content_length
+        .map(|length| length.saturating_mul(MAX_EXPANSION_RATIO))
+        .unwrap_or(MAX_DECODED_SIZE)
+        .clamp(MIN_DECODED_LIMIT, MAX_DECODED_SIZE);
+
+    // The decoders read through std::io, which carries an UploadError boxed
+    // as is so it can be told apart from corrupt data below
+    let reader = StreamReader::new(body.map(|chunk| {
+        chunk.map_err(|e| match e.downcast::<UploadError>() {
+            Ok(upload_error) => std::io::Error::other(upload_error),
+            Err(e) => std::io::Error::other(e),
+        })
+    }));
+    let decoder: Pin<Box<dyn AsyncRead + Send>> = match encoding {
+        UploadEncoding::Gzip => {
+            let mut decoder = GzipDecoder::new(reader);
+            // Concatenated gzip files are one valid stream
+            decoder.multiple_members(true);
+            Box::pin(decoder)
+        }
+        UploadEncoding::Zstd => Box::pin(ZstdDecoder::new(reader)),
+    };
+
+    let decoded = Arc::new(AtomicU64::new(0));
+    let counter = decoded.clone();
+    let stream = ReaderStream::new(decoder).map(move |chunk| {
+        let chunk = chunk.map_err(|e| {
+            let message = e.to_string();
+            match e.into_inner().map(|inner| inner.downcast::<UploadError>()) {
+                Some(Ok(upload_error)) => anyhow::Error::from(*upload_error),
+                _ => DecodeError::Corrupt { encoding: encoding.as_str(), message }.into(),
+            }
+        })?;
+        let total = counter.fetch_add(chunk.len() as u64, Ordering::Relaxed) + chunk.len() as u64;
+        if total > limit {
+            return Err(DecodeError::TooLarge { decoded: total, limit }.into());
+        }
+        Ok(chunk)
+    });
+
+    DecodedBody {
+        stream: Box::pin(stream),
+        decoded,
+    }
+}
@@ -1,73 +1,477 @@
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
-    Json,
 };
-use serde_json::json;
 use thiserror::Error;
 
+use crate::checksum::ChecksumAlgorithm;
+
+// The Display impl carries dynamic context for logs. Clients only ever see the
+// stable `code()` and `message()` templates plus the structured `details()`,
+// so they can match and localize errors without parsing free-form text.
 #[derive(Error, Debug)]
 pub enum ApiError {
     #[error("Bucket not found: {0}")]
     BucketNotFound(String),
-    
+
     #[error("Object not found: {0}")]
     ObjectNotFound(String),
-    
+
     #[error("Bucket already exists: {0}")]
     BucketAlreadyExists(String),
-    
-    #[error("Invalid bucket name: {0}")]
-    InvalidBucketName(String),
-    
+
+    #[error("Bucket already owned by the caller: {0}")]
+    BucketAlreadyOwnedByYou(String),
+
+    #[error("Invalid bucket name {bucket}: {reason}")]
+    InvalidBucketName { bucket: String, reason: &'static str },
+
     #[error("Invalid object key: {0}")]
     InvalidObjectKey(String),
-    
+
+    #[error("Version {version_id} of {key} not found")]
+    NoSuchVersion { key: String, version_id: String },
+
+    #[error("Bucket {0} has no CORS configuration")]
+    NoSuchCorsConfiguration(String),
+
+    #[error("Bucket {0} has no policy")]
+    NoSuchBucketPolicy(String),
+
+    #[error("Malformed bucket policy: {0}")]
+    MalformedPolicy(String),
+
+    #[error("Malformed bucket ACL: {0}")]
+    MalformedAcl(String),
+
+    #[error("Malformed POST form: {0}")]
+    MalformedPostRequest(&'static str),
+
+    #[error("Invalid POST policy document: {0}")]
+    InvalidPolicyDocument(String),
+
+    // A POST upload its signed policy does not allow; the message says why
+    #[error("POST upload denied: {0}")]
+    PostPolicyDenied(String),
+
+    #[error("Bucket {0} has no lifecycle configuration")]
+    NoSuchLifecycleConfiguration(String),
+
+    #[error("Bucket {0} has no tags")]
+    NoSuchTagSet(String),
+
+    #[error("Cross-origin {method} request is not allowed")]
+    CorsRequestNotAllowed { method: String },
+
+    #[error("Multipart upload not found: {0}")]
+    NoSuchUpload(String),
+
+    #[error("Invalid argument {name}: {message}")]
+    InvalidArgument { name: String, value: Option<String>, message: &'static str },
+
+    #[error("Part {part_number} of upload {upload_id} exceeds the {max_part_count} part limit")]
+    PartCountExhausted { upload_id: String, part_number: i32, max_part_count: i32 },
+
+    #[error("Invalid versioning status: {0}")]
+    IllegalVersioningConfiguration(String),
+
+    #[error("Invalid object tags: {0}")]
+    InvalidTag(&'static str),
+
+    #[error("{count} tags exceed the limit of {limit}")]
+    TooManyTags { count: usize, limit: usize },
+
+    #[error("Content-MD5 is not a base64-encoded 128-bit digest")]
+    InvalidDigest,
+
+    #[error("Content-MD5 {expected} does not match the body's digest {calculated}")]
+    BadDigest { expected: String, calculated: String },
+
+    #[error("{} checksum {expected} does not match the body's checksum {calculated}", algorithm.as_str())]
+    BadChecksum { algorithm: ChecksumAlgorithm, expected: String, calculated: String },
+
+    #[error("x-amz-content-sha256 {client_computed} does not match the body's hash {server_computed}")]
+    XAmzContentSHA256Mismatch { client_computed: String, server_computed: String },
+
+    #[error("Precondition {condition} failed")]
+    PreconditionFailed { condition: &'static str },
+
+    #[error("Range {range} is not satisfiable for an object of {size} bytes")]
+    InvalidRange { range: String, size: u64 },
+
+    #[error("Part {part_number} is not one of the object's {parts_count} parts")]
+    InvalidPartNumber { part_number: i32, parts_count: usize },
+
+    #[error("User metadata of {size} bytes exceeds the {max_size} byte limit")]
+    MetadataTooLarge { size: usize, max_size: usize },
+
+    #[error("Request has a body but no Content-Length")]
+    MissingContentLength,
+
+    #[error("Body of {received} bytes is shorter or longer than the declared {expected}")]
+    IncompleteBody { expected: u64, received: u64 },
+
+    #[error("Entity of {size} bytes exceeds the {max_size} byte limit")]
+    EntityTooLarge { size: u64, max_size: u64 },
+
+    // part_number is None for whole objects, such as POST uploads
+    #[error("Entity of {size} bytes is below the {min_size} byte minimum")]
+    EntityTooSmall { part_number: Option<i32>, size: u64, min_size: u64 },
+
     #[error("Authentication failed: {0}")]
     AuthenticationFailed(String),
-    
+
     #[error("Authorization failed: {0}")]
     AuthorizationFailed(String),
-    
+
+    #[error("Presigned request expired at {expires_at}")]
+    RequestExpired { expires_in_seconds: u64, expires_at: String, server_time: String },
+
     #[error("Internal server error: {0}")]
     Internal(#[from] anyhow::Error),
-    
+
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
-    
+
     #[error("Storage error: {0}")]
     Storage(String),
-    
+
     #[error("Invalid request: {0}")]
     BadRequest(String),
+
+    #[error("Bucket {bucket} is homed in region {region} at {endpoint}")]
+    PermanentRedirect { bucket: String, region: String, endpoint: String },
+
+    #[error("Upload of {size} bytes would take bucket {bucket} past its {quota_bytes} byte quota")]
+    BucketQuotaExceeded { bucket: String, quota_bytes: u64, usage: u64, size: u64 },
+
+    #[error("Quota {quota} exceeded for access key {access_key_id}")]
+    QuotaExceeded { access_key_id: String, quota: &'static str, resets_at: String, retry_after_seconds: u64 },
+
+    #[error("Request rate limit exceeded")]
+    SlowDown { retry_after_seconds: u64 },
+}
+
+impl ApiError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::BucketNotFound(_)
+            | ApiError::ObjectNotFound(_)
+            | ApiError::NoSuchVersion { .. }
+            | ApiError::NoSuchCorsConfiguration(_)
+            | ApiError::NoSuchTagSet(_)
+            | ApiError::NoSuchBucketPolicy(_)
+            | ApiError::NoSuchLifecycleConfiguration(_)
+            | ApiError::NoSuchUpload(_) => StatusCode::NOT_FOUND,
+            ApiError::BucketAlreadyExists(_) | ApiError::BucketAlreadyOwnedByYou(_) => StatusCode::CONFLICT,
+            ApiError::PermanentRedirect { .. } => StatusCode::MOVED_PERMANENTLY,
+            ApiError::InvalidBucketName { .. }
+            | ApiError::InvalidObjectKey(_)
+            | ApiError::InvalidArgument { .. }
+            | ApiError::PartCountExhausted { .. }
+            | ApiError::EntityTooLarge { .. }
+            | ApiError::EntityTooSmall { .. }
+            | ApiError::IllegalVersioningConfiguration(_)
+            | ApiError::InvalidTag(_)
+            | ApiError::TooManyTags { .. }
+            | ApiError::MetadataTooLarge { .. }
+            | ApiError::MalformedPolicy(_)
+            | ApiError::MalformedAcl(_)
+            | ApiError::MalformedPostRequest(_)
+            | ApiError::InvalidPolicyDocument(_)
+            | ApiError::InvalidDigest
+            | ApiError::BadDigest { .. }
+            | ApiError::XAmzContentSHA256Mismatch { .. }
+            | ApiError::BadChecksum { .. }
+            | ApiError::IncompleteBody { .. }
+            | ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::InvalidRange { .. } | ApiError::InvalidPartNumber { .. } => StatusCode::RANGE_NOT_SATISFIABLE,
+            ApiError::PreconditionFailed { .. } => StatusCode::PRECONDITION_FAILED,
+            ApiError::MissingContentLength => StatusCode::LENGTH_REQUIRED,
+            ApiError::AuthenticationFailed(_) => StatusCode::UNAUTHORIZED,
+            ApiError::AuthorizationFailed(_)
+            | ApiError::PostPolicyDenied(_)
+            | ApiError::RequestExpired { .. }
+            | ApiError::QuotaExceeded { .. }
+            | ApiError::CorsRequestNotAllowed { .. } => StatusCode::FORBIDDEN,
+            ApiError::BucketQuotaExceeded { .. } => StatusCode::INSUFFICIENT_STORAGE,
+            ApiError::SlowDown { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::Internal(_) | ApiError::Database(_) | ApiError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BucketNotFound(_) => "NoSuchBucket",
+            ApiError::ObjectNotFound(_) => "NoSuchKey",
+            ApiError::BucketAlreadyExists(_) => "BucketAlreadyExists",
+            ApiError::BucketAlreadyOwnedByYou(_) => "BucketAlreadyOwnedByYou",
+            ApiError::InvalidBucketName { .. } => "InvalidBucketName",
+            ApiError::InvalidObjectKey(_) => "InvalidObjectKey",
+            ApiError::NoSuchVersion { .. } => "NoSuchVersion",
+            ApiError::NoSuchCorsConfiguration(_) => "NoSuchCORSConfiguration",
+            ApiError::NoSuchTagSet(_) => "NoSuchTagSet",
+            ApiError::NoSuchBucketPolicy(_) => "NoSuchBucketPolicy",
+            ApiError::NoSuchLifecycleConfiguration(_) => "NoSuchLifecycleConfiguration",
+            ApiError::MalformedPolicy(_) => "MalformedPolicy",
+            ApiError::MalformedAcl(_) => "MalformedACLError",
+            ApiError::MalformedPostRequest(_) => "MalformedPOSTRequest",
+            ApiError::InvalidPolicyDocument(_) => "InvalidPolicyDocument",
+            ApiError::CorsRequestNotAllowed { .. } => "AccessForbidden",
+            ApiError::NoSuchUpload(_) => "NoSuchUpload",
+            ApiError::InvalidArgument { .. } | ApiError::PartCountExhausted { .. } => "InvalidArgument",
+            ApiError::EntityTooLarge { .. } => "EntityTooLarge",
+            ApiError::EntityTooSmall { .. } => "EntityTooSmall",
+            ApiError::MissingContentLength => "MissingContentLength",
+            ApiError::IncompleteBody { .. } => "IncompleteBody",
+            ApiError::IllegalVersioningConfiguration(_) => "IllegalVersioningConfigurationException",
+            ApiError::InvalidTag(_) => "InvalidTag",
+            ApiError::TooManyTags { .. } => "TooManyTags",
+            ApiError::MetadataTooLarge { .. } => "MetadataTooLarge",
+            ApiError::InvalidDigest => "InvalidDigest",
+            ApiError::BadDigest { .. } => "BadDigest",
+            ApiError::XAmzContentSHA256Mismatch { .. } => "XAmzContentSHA256Mismatch",
+            ApiError::BadChecksum { .. } => "BadDigest",
+            ApiError::InvalidRange { .. } => "InvalidRange",
+            ApiError::InvalidPartNumber { .. } => "InvalidPartNumber",
+            ApiError::PreconditionFailed { .. } => "PreconditionFailed",
+            ApiError::PermanentRedirect { .. } => "PermanentRedirect",
+            ApiError::AuthenticationFailed(_)
+            | ApiError::AuthorizationFailed(_)
+            | ApiError::PostPolicyDenied(_)
+            | ApiError::RequestExpired { .. }
+            | ApiError::QuotaExceeded { .. } => "AccessDenied",
+            ApiError::BucketQuotaExceeded { .. } => "QuotaExceeded",
+            ApiError::SlowDown { .. } => "SlowDown",
+            ApiError::BadRequest(_) => "InvalidRequest",
+            ApiError::Internal(_) | ApiError::Database(_) | ApiError::Storage(_) => "InternalError",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ApiError::BucketNotFound(_) => "The specified bucket does not exist",
+            ApiError::ObjectNotFound(_) => "The specified key does not exist.",
+            ApiError::BucketAlreadyExists(_) => {
+                "The requested bucket name is not available. The bucket namespace is shared by all users of the system. Please select a different name and try again."
+            }
+            ApiError::BucketAlreadyOwnedByYou(_) => {
+                "Your previous request to create the named bucket succeeded and you already own it."
+            }
+            ApiError::InvalidBucketName { reason, .. } => reason,
+            ApiError::InvalidObjectKey(_) => "The specified key is not valid.",
+            ApiError::NoSuchVersion { .. } => "The specified version does not exist.",
+            ApiError::NoSuchCorsConfiguration(_) => "The CORS configuration does not exist",
+            ApiError::NoSuchTagSet(_) => "The TagSet does not exist",
+            ApiError::NoSuchBucketPolicy(_) => "The bucket policy does not exist",
+            ApiError::NoSuchLifecycleConfiguration(_) => "The lifecycle configuration does not exist",
+            ApiError::MalformedPolicy(message) => message,
+            ApiError::MalformedAcl(_) => "The XML you provided was not well-formed or did not validate against our published schema",
+            ApiError::MalformedPostRequest(_) => "The body of your POST request is not well-formed multipart/form-data.",
+            ApiError::InvalidPolicyDocument(message) | ApiError::PostPolicyDenied(message) => message,
+            ApiError::CorsRequestNotAllowed { .. } => "CORSResponse: This CORS request is not allowed.",
+            ApiError::NoSuchUpload(_) => {
+                "The specified upload does not exist. The upload ID may be invalid, or the upload may have been aborted or completed."
+            }
+            ApiError::InvalidArgument { message, .. } => message,
+            ApiError::PartCountExhausted { .. } => {
+                "This upload has reached the maximum number of parts. Complete it with the parts already uploaded, or abort it and retry with larger parts."
+            }
+            ApiError::EntityTooLarge { .. } => "Your proposed upload exceeds the maximum allowed size",
+            ApiError::EntityTooSmall { .. } => "Your proposed upload is smaller than the minimum allowed object size.",
+            ApiError::MissingContentLength => "You must provide the Content-Length HTTP header.",
+            ApiError::IncompleteBody { .. } => "You did not provide the number of bytes specified by the Content-Length HTTP header",
+            ApiError::IllegalVersioningConfiguration(_) => {
+                "The versioning configuration specified in the request is invalid."
+            }
+            ApiError::InvalidTag(message) => message,
+            ApiError::TooManyTags { .. } => "The number of tags exceeds the limit allowed for this resource",
+            ApiError::MetadataTooLarge { .. } => "Your metadata headers exceed the maximum allowed metadata size.",
+            ApiError::InvalidDigest => "The Content-MD5 you specified was invalid.",
+            ApiError::BadDigest { .. } => "The Content-MD5 you specified did not match what we received.",
+            ApiError::BadChecksum { algorithm, .. } => algorithm.mismatch_message(),
+            ApiError::XAmzContentSHA256Mismatch { .. } => "The provided 'x-amz-content-sha256' header does not match what was computed.",
+            ApiError::InvalidRange { .. } => "The requested range is not satisfiable",
+            ApiError::InvalidPartNumber { .. } => "The requested partnumber is not satisfiable",
+            ApiError::PreconditionFailed { .. } => "At least one of the pre-conditions you specified did not hold",
+            ApiError::PermanentRedirect { .. } => {
+                "The bucket you are attempting to access must be addressed using the specified endpoint. Please send all future requests to this endpoint."
+            }
+            ApiError::AuthenticationFailed(_) | ApiError::AuthorizationFailed(_) => "Access Denied",
+            ApiError::RequestExpired { .. } => "Request has expired",
+            ApiError::BadRequest(message) => message,
+            ApiError::QuotaExceeded { .. } => "The access key has exceeded its usage quota for the current period.",
+            ApiError::BucketQuotaExceeded { .. } => "The upload would exceed the bucket's storage quota.",
+            ApiError::SlowDown { .. } => "Please reduce your request rate.",
+            ApiError::Internal(_) | ApiError::Database(_) | ApiError::Storage(_) => {
+                "We encountered an internal error. Please try again."
+            }
+        }
+    }
+
+    // Structured fields emitted as dedicated elements next to Code and Message
+    pub fn details(&self) -> Vec<(&'static str, String)> {
+        match self {
+            ApiError::BucketNotFound(bucket)
+            | ApiError::BucketAlreadyExists(bucket)
+            | ApiError::BucketAlreadyOwnedByYou(bucket)
+            | ApiError::NoSuchCorsConfiguration(bucket)
+            | ApiError::NoSuchTagSet(bucket)
+            | ApiError::NoSuchBucketPolicy(bucket)
+            | ApiError::NoSuchLifecycleConfiguration(bucket)
+            | ApiError::InvalidBucketName { bucket, .. } => vec![("BucketName", bucket.clone())],
+            ApiError::ObjectNotFound(key) | ApiError::InvalidObjectKey(key) => vec![("Key", key.clone())],
+            ApiError::NoSuchVersion { key, version_id } => vec![("Key", key.clone()), ("VersionId", version_id.clone())],
+            ApiError::CorsRequestNotAllowed { method } => vec![("Method", method.clone())],
+            ApiError::NoSuchUpload(upload_id) => vec![("UploadId", upload_id.clone())],
+            ApiError::InvalidArgument { name, value, .. } => {
+                let mut details = vec![("ArgumentName", name.clone())];
+                if let Some(value) = value {
+                    details.push(("ArgumentValue", value.clone()));
+                }
+                details
+            }
+            ApiError::PartCountExhausted { upload_id, part_number, max_part_count } => vec![
+                ("UploadId", upload_id.clone()),
+                ("ArgumentName", "partNumber".to_string()),
+                ("ArgumentValue", part_number.to_string()),
+                ("MaxPartCount", max_part_count.to_string()),
+            ],
+            ApiError::EntityTooLarge { size, max_size } => vec![
+                ("ProposedSize", size.to_string()),
+                ("MaxSizeAllowed", max_size.to_string()),
+            ],
+            ApiError::EntityTooSmall { part_number, size, min_size } => {
+                let mut details = vec![("ProposedSize", size.to_string()), ("MinSizeAllowed", min_size.to_string())];
+                if let Some(part_number) = part_number {
+                    details.insert(0, ("PartNumber", part_number.to_string()));
+                }
+                details
+            }
+            ApiError::TooManyTags { count, limit } => vec![
+                ("TagCount", count.to_string()),
+                ("MaxTagCount", limit.to_string()),
+            ],
+            ApiError::MetadataTooLarge { size, max_size } => vec![
+                ("Size", size.to_string()),
+                ("MaxSizeAllowed", max_size.to_string()),
+            ],
+            ApiError::BadDigest { expected, calculated } => vec![
+                ("ExpectedDigest", expected.clone()),
+                ("CalculatedDigest", calculated.clone()),
+            ],
+            ApiError::BadChecksum { algorithm, expected, calculated } => vec![
+                ("ChecksumAlgorithm", algorithm.as_str().to_string()),
+                ("ExpectedChecksum", expected.clone()),
+                ("CalculatedChecksum", calculated.clone()),
+            ],
+            ApiError::XAmzContentSHA256Mismatch { client_computed, server_computed } => vec![
+                ("ClientComputedContentSHA256", client_computed.clone()),
+                ("S3ComputedContentSHA256", server_computed.clone()),
+            ],
+            ApiError::PreconditionFailed { condition } => vec![("Condition", condition.to_string())],
+            ApiError::InvalidRange { range, size } => vec![
+                ("RangeRequested", range.clone()),
+                ("ActualObjectSize", size.to_string()),
+            ],
+            ApiError::InvalidPartNumber { part_number, parts_count } => vec![
+                ("PartNumberRequested", part_number.to_string()),
+                ("ActualPartCount", parts_count.to_string()),
+            ],
+            ApiError::PermanentRedirect { bucket, endpoint, .. } => {
+                vec![("Bucket", bucket.clone()), ("Endpoint", endpoint.clone())]
+            }
+            ApiError::RequestExpired { expires_in_seconds, expires_at, server_time } => vec![
+                ("X-Amz-Expires", expires_in_seconds.to_string()),
+                ("Expires", expires_at.clone()),
+                ("ServerTime", server_time.clone()),
+            ],
+            ApiError::BucketQuotaExceeded { bucket, quota_bytes, usage, size } => vec![
+                ("BucketName", bucket.clone()),
+                ("QuotaBytes", quota_bytes.to_string()),
+                ("CurrentUsage", usage.to_string()),
+                ("ProposedSize", size.to_string()),
+            ],
+            ApiError::QuotaExceeded { access_key_id, quota, resets_at, .. } => vec![
+                ("AWSAccessKeyId", access_key_id.clone()),
+                ("Quota", quota.to_string()),
+                ("ResetTime", resets_at.clone()),
+            ],
+            _ => Vec::new(),
+        }
+    }
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        let (status, error_code, message) = match &self {
-            ApiError::BucketNotFound(_) => (StatusCode::NOT_FOUND, "NoSuchBucket", self.to_string()),
-            ApiError::ObjectNotFound(_) => (StatusCode::NOT_FOUND, "NoSuchKey", self.to_string()),
-            ApiError::BucketAlreadyExists(_) => (StatusCode::CONFLICT, "BucketAlreadyExists", self.to_string()),
-            ApiError::InvalidBucketName(_) => (StatusCode::BAD_REQUEST, "InvalidBucketName", self.to_string()),
-            ApiError::InvalidObjectKey(_) => (StatusCode::BAD_REQUEST, "InvalidObjectKey", self.to_string()),
-            ApiError::AuthenticationFailed(_) => (StatusCode::UNAUTHORIZED, "AccessDenied", self.to_string()),
-            ApiError::AuthorizationFailed(_) => (StatusCode::FORBIDDEN, "AccessDenied", self.to_string()),
-            ApiError::BadRequest(_) => (StatusCode::BAD_REQUEST, "InvalidRequest", self.to_string()),
-            ApiError::Storage(_) => (StatusCode::INTERNAL_SERVER_ERROR, "InternalError", "Storage operation failed".to_string()),
-            ApiError::Internal(_) | ApiError::Database(_) => {
-                tracing::error!("Internal error: {}", self);
-                (StatusCode::INTERNAL_SERVER_ERROR, "InternalError", "Internal server error".to_string())
+        // Outside the request_context middleware there is no id to share, so mint one
+        match crate::middleware::current_request_context() {
+            Some(context) => {
+                self.into_response_with_context(&context.request_id, Some(&context.host_id), Some(&context.resource))
             }
+            None => self.into_response_with_context(&uuid::Uuid::new_v4().to_string(), None, None),
+        }
+    }
+}
+
+impl ApiError {
+    pub fn into_response_with_context(self, request_id: &str, host_id: Option<&str>, resource: Option<&str>) -> Response {
+        if self.status_code().is_server_error() {
+            tracing::error!("Internal error: {}", self);
+        }
+
+        let mut body = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Error>");
+        let mut push = |name: &str, value: &str| {
+            body.push_str(&format!("<{}>{}</{}>", name, xml_escape(value), name));
         };
+        push("Code", self.code());
+        push("Message", self.message());
+        for (name, value) in self.details() {
+            push(name, &value);
+        }
+        if let Some(resource) = resource {
+            push("Resource", resource);
+        }
+        push("RequestId", request_id);
+        if let Some(host_id) = host_id {
+            push("HostId", host_id);
+        }
+        body.push_str("</Error>");
 
-        let body = Json(json!({
-            "Code": error_code,
-            "Message": message,
-            "RequestId": "00000000-0000-0000-0000-000000000000", // TODO: Add proper request ID
-        }));
+        let mut response = (
+            self.status_code(),
+            [(header::CONTENT_TYPE, "application/xml")],
+            body,
+        )
+            .into_response();
+        match &self {
+            ApiError::QuotaExceeded { retry_after_seconds, .. } | ApiError::SlowDown { retry_after_seconds } => {
+                response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(*retry_after_seconds));
+            }
+            ApiError::InvalidRange { size, .. } => {
+                if let Ok(value) = HeaderValue::from_str(&format!("bytes */{}", size)) {
+                    response.headers_mut().insert(header::CONTENT_RANGE, value);
+                }
+            }
+            ApiError::PermanentRedirect { region, .. } => {
+                if let Ok(value) = HeaderValue::from_str(region) {
+                    response.headers_mut().insert("x-amz-bucket-region", value);
+                }
+            }
+            _ => {}
+        }
 
-        (status, body).into_response()
+        response
     }
 }
 
-pub type ApiResult<T> = Result<T, ApiError>;
\ No newline at end of file
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+pub type ApiResult<T> = Result<T, ApiError>;
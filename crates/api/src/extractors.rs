@@ -1,11 +1,13 @@
 use axum::{
     async_trait,
-    extract::{FromRequestParts, Query},
+    extract::FromRequestParts,
     http::{request::Parts, StatusCode},
 };
 use serde::Deserialize;
 use std::collections::HashMap;
 
+// Query values are form-decoded: a bare `+` is a space and `%2B` a plus, the
+// same reading SigV4 canonicalization applies. Keys in paths keep `+` literal.
 #[derive(Debug, Deserialize)]
 pub struct ListObjectsQuery {
     #[serde(rename = "list-type")]
@@ -18,6 +20,90 @@ pub struct ListObjectsQuery {
     pub delimiter: Option<String>,
     #[serde(rename = "start-after")]
     pub start_after: Option<String>,
+    // ListObjects v1's counterpart to start-after
+    pub marker: Option<String>,
+    #[serde(rename = "fetch-owner")]
+    pub fetch_owner: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListBucketsQuery {
+    pub tagging: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ObjectVersionQuery {
+    #[serde(rename = "versionId")]
+    pub version_id: Option<String>,
+}
+
+// ?partNumber of a GetObject or HeadObject, checked by the handler so a bad
+// value gets S3's error rather than a rejected query
+#[derive(Debug, Deserialize)]
+pub struct PartNumberQuery {
+    #[serde(rename = "partNumber")]
+    pub part_number: Option<String>,
+}
+
+// response-* parameters of a GetObject, replacing the named response headers.
+// S3 only honors them on signed requests.
+#[derive(Debug, Deserialize)]
+pub struct ResponseHeaderOverrides {
+    #[serde(rename = "response-cache-control")]
+    pub cache_control: Option<String>,
+    #[serde(rename = "response-content-disposition")]
+    pub content_disposition: Option<String>,
+    #[serde(rename = "response-content-encoding")]
+    pub content_encoding: Option<String>,
+    #[serde(rename = "response-content-language")]
+    pub content_language: Option<String>,
+    #[serde(rename = "response-content-type")]
+    pub content_type: Option<String>,
+    #[serde(rename = "response-expires")]
+    pub expires: Option<String>,
+}
+
+impl ResponseHeaderOverrides {
+    pub fn headers(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("cache-control", &self.cache_control),
+            ("content-disposition", &self.content_disposition),
+            ("content-encoding", &self.content_encoding),
+            ("content-language", &self.content_language),
+            ("content-type", &self.content_type),
+            ("expires", &self.expires),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value.as_deref()?)))
+        .collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BucketSnapshotQuery {
+    pub at: Option<String>,
+    pub prefix: Option<String>,
+    #[serde(rename = "max-keys")]
+    pub max_keys: Option<u32>,
+    #[serde(rename = "start-after")]
+    pub start_after: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    pub bucket: Option<String>,
+    pub key_prefix: Option<String>,
+    pub access_key: Option<String>,
+    pub action: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub limit: Option<u32>,
+    pub after: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LifecyclePreviewQuery {
+    pub samples: Option<usize>,
 }
 
 #[derive(Debug)]
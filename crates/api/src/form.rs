@@ -0,0 +1,189 @@
+use axum::http::HeaderMap;
+use bytes::{Bytes, BytesMut};
+use futures::StreamExt;
+use ghostbay_engine::ByteStream;
+use std::collections::HashMap;
+
+use crate::upload::UploadError;
+
+// Fields before the file are read into memory, so their total is bounded
+const MAX_FIELDS_SIZE: usize = 20 * 1024;
+
+// A part's headers are a line or two; anything longer is not form data
+const MAX_LINE_LENGTH: usize = 4096;
+
+// The multipart/form-data body of a browser upload (POST Object). S3 takes
+// the fields up to the one named `file`, which carries the object's data and
+// is streamed from the body as it arrives; anything after it is ignored.
+pub struct PostForm {
+    // By lowercase name, as S3 reads form field names case-insensitively
+    pub fields: HashMap<String, String>,
+    // The file field's own filename and Content-Type, when the browser sent them
+    pub file_name: Option<String>,
+    pub file_content_type: Option<String>,
+    pub file: ByteStream,
+}
+
+// The boundary of a multipart/form-data Content-Type
+pub fn form_boundary(headers: &HeaderMap) -> Option<String> {
+    let content_type = headers.get("content-type").and_then(|v| v.to_str().ok())?;
+    let (media_type, parameters) = content_type.split_once(';')?;
+    if !media_type.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    parameters.split(';').find_map(|parameter| {
+        let (name, value) = parameter.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("boundary")
+            .then(|| value.trim().trim_matches('"').to_string())
+            .filter(|boundary| !boundary.is_empty())
+    })
+}
+
+// Reads the fields before the file. Fails with an UploadError when the body
+// is not form data, has no file or its fields are too large.
+pub async fn read_post_form(input: ByteStream, boundary: &str) -> Result<PostForm, anyhow::Error> {
+    let mut reader = Reader {
+        input,
+        // Starting the body with a line break lets the first delimiter be
+        // found like every other one
+        buffer: BytesMut::from(&b"\r\n"[..]),
+        delimiter: format!("\r\n--{}", boundary).into_bytes(),
+    };
+    let mut fields = HashMap::new();
+    let mut fields_size = 0;
+
+    loop {
+        let delimiter = reader.find_delimiter(MAX_FIELDS_SIZE - fields_size).await?;
+        if fields_size + delimiter > MAX_FIELDS_SIZE {
+            return Err(UploadError::MalformedForm("form fields are too large").into());
+        }
+        let _ = reader.buffer.split_to(delimiter + reader.delimiter.len());
+        // The closing delimiter is followed by "--" rather than a part
+        reader.fill(2).await?;
+        if reader.buffer.starts_with(b"--") {
+            return Err(UploadError::MalformedForm("the form has no file field").into());
+        }
+
+        let mut name = None;
+        let mut file_name = None;
+        let mut content_type = None;
+        reader.take_line().await?;
+        loop {
+            let line = reader.take_line().await?;
+            if line.is_empty() {
+                break;
+            }
+            let (header, value) = line.split_once(':').ok_or(UploadError::MalformedForm("part header is not a header"))?;
+            if header.trim().eq_ignore_ascii_case("content-disposition") {
+                for parameter in value.split(';').skip(1) {
+                    match parameter.trim().split_once('=') {
+                        Some(("name", value)) => name = Some(value.trim_matches('"').to_ascii_lowercase()),
+                        Some(("filename", value)) => file_name = Some(value.trim_matches('"').to_string()),
+                        _ => {}
+                    }
+                }
+            } else if header.trim().eq_ignore_ascii_case("content-type") {
+                content_type = Some(value.trim().to_string());
+            }
+        }
+        let name = name.ok_or(UploadError::MalformedForm("part has no field name"))?;
+
+        if name == "file" {
+            return Ok(PostForm {
+                fields,
+                file_name,
+                file_content_type: content_type,
+                file: reader.into_file(),
+            });
+        }
+
+        let end = reader.find_delimiter(MAX_FIELDS_SIZE - fields_size).await?;
+        let value = reader.buffer.split_to(end);
+        fields_size += name.len() + value.len();
+        if fields_size > MAX_FIELDS_SIZE {
+            return Err(UploadError::MalformedForm("form fields are too large").into());
+        }
+        let value = String::from_utf8(value.to_vec()).map_err(|_| UploadError::MalformedForm("field is not UTF-8"))?;
+        fields.insert(name, value);
+    }
+}
+
+struct Reader {
+    input: ByteStream,
+    buffer: BytesMut,
+    delimiter: Vec<u8>,
+}
+
+impl Reader {
+    async fn read_more(&mut self) -> anyhow::Result<()> {
+        match self.input.next().await {
+            Some(chunk) => {
+                self.buffer.extend_from_slice(&chunk?);
+                Ok(())
+            }
+            None => Err(UploadError::MalformedForm("the body ended inside the form").into()),
+        }
+    }
+
+    async fn fill(&mut self, len: usize) -> anyhow::Result<()> {
+        while self.buffer.len() < len {
+            self.read_more().await?;
+        }
+        Ok(())
+    }
+
+    // Offset of the next delimiter, reading no further than limit bytes ahead
+    async fn find_delimiter(&mut self, limit: usize) -> anyhow::Result<usize> {
+        loop {
+            if let Some(offset) = find(&self.buffer, &self.delimiter) {
+                return Ok(offset);
+            }
+            if self.buffer.len() > limit + self.delimiter.len() {
+                return Err(UploadError::MalformedForm("form fields are too large").into());
+            }
+            self.read_more().await?;
+        }
+    }
+
+    async fn take_line(&mut self) -> anyhow::Result<String> {
+        loop {
+            if let Some(end) = find(&self.buffer, b"\r\n") {
+                let line = self.buffer.split_to(end + 2);
+                let line = std::str::from_utf8(&line[..end]).map_err(|_| UploadError::MalformedForm("part header is not UTF-8"))?;
+                return Ok(line.to_string());
+            }
+            if self.buffer.len() > MAX_LINE_LENGTH {
+                return Err(UploadError::MalformedForm("part header is too long").into());
+            }
+            self.read_more().await?;
+        }
+    }
+
+    // The rest of the current part's data. Bytes that could be the start of
+    // the delimiter are held back until the next read settles it.
+    fn into_file(self) -> ByteStream {
+        let stream = futures::stream::try_unfold(Some(self), |reader| async move {
+            let Some(mut reader) = reader else {
+                return Ok(None);
+            };
+            loop {
+                if let Some(end) = find(&reader.buffer, &reader.delimiter) {
+                    let data = reader.buffer.split_to(end).freeze();
+                    return Ok((!data.is_empty()).then_some((data, None)));
+                }
+                let held_back = reader.delimiter.len() - 1;
+                if reader.buffer.len() > held_back {
+                    let data: Bytes = reader.buffer.split_to(reader.buffer.len() - held_back).freeze();
+                    return Ok(Some((data, Some(reader))));
+                }
+                reader.read_more().await?;
+            }
+        });
+        Box::pin(stream)
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
@@ -0,0 +1,435 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+
+use ghostbay_catalog::{
+    AuditFilter, AuditRepository, BucketRepository, LifecycleRepository, MultipartUploadRepository, ObjectRepository,
+    ObjectVersionRepository, MULTIPART_UPLOAD_EXPIRY_DAYS,
+};
+use std::collections::HashMap;
+use ghostbay_catalog::lifecycle::{LifecycleEvaluator, LifecycleReport};
+
+use super::{multipart::abort_upload, object::remove_object, resolve_bucket, storage_location};
+use crate::{
+    error::{ApiError, ApiResult},
+    extractors::{AuditLogQuery, BucketSnapshotQuery, LifecyclePreviewQuery},
+    responses::*,
+    AppState,
+};
+
+// Admin: the limits clients should plan uploads around
+pub async fn capabilities(State(state): State<AppState>) -> Json<CapabilitiesResponse> {
+    Json(CapabilitiesResponse {
+        region: state.regions.region.clone(),
+        etag_algorithm: state.storage.etag_algorithm().as_str().to_string(),
+        multipart: MultipartCapabilities {
+            min_part_size: state.multipart.min_part_size,
+            max_part_size: state.multipart.max_part_size,
+            max_part_count: state.multipart.max_part_count,
+            upload_expiry_days: MULTIPART_UPLOAD_EXPIRY_DAYS,
+        },
+    })
+}
+
+// Admin: lists the bucket as it looked at a point in time, from version history
+pub async fn bucket_snapshot(
+    Path(bucket_name): Path<String>,
+    Query(query): Query<BucketSnapshotQuery>,
+    State(state): State<AppState>,
+) -> ApiResult<Json<BucketSnapshotResponse>> {
+    let raw_at = query.at.ok_or_else(|| ApiError::InvalidArgument {
+        name: "at".to_string(),
+        value: None,
+        message: "The at parameter is required.",
+    })?;
+    let at = parse_timestamp("at", &raw_at)?;
+
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+
+    let max_keys = query.max_keys.unwrap_or(1000).clamp(1, 1000);
+
+    // Fetch one extra entry to learn whether another page follows
+    let version_repo = ObjectVersionRepository::new(state.catalog.pool().clone());
+    let mut versions = version_repo
+        .list_as_of(bucket.id, at, query.prefix.as_deref(), query.start_after.as_deref(), max_keys as i32 + 1)
+        .await?;
+
+    let is_truncated = versions.len() > max_keys as usize;
+    versions.truncate(max_keys as usize);
+    let next_start_after = if is_truncated {
+        versions.last().map(|v| v.key.clone())
+    } else {
+        None
+    };
+
+    let contents: Vec<SnapshotObjectInfo> = versions
+        .into_iter()
+        .map(|version| SnapshotObjectInfo {
+            key: version.key,
+            version_id: version.version_id.to_string(),
+            last_modified: version.created_at,
+            etag: version.etag,
+            size: version.size as u64,
+        })
+        .collect();
+
+    let response = BucketSnapshotResponse {
+        name: bucket_name,
+        at,
+        prefix: query.prefix,
+        key_count: contents.len() as u32,
+        max_keys,
+        is_truncated,
+        start_after: query.start_after,
+        next_start_after,
+        contents,
+    };
+
+    Ok(Json(response))
+}
+
+// Admin: searches the audit log, oldest entries first. NextCursor is passed
+// back as `after` to page through the results or to follow new entries.
+pub async fn search_audit_log(
+    Query(query): Query<AuditLogQuery>,
+    State(state): State<AppState>,
+) -> ApiResult<Json<AuditLogResponse>> {
+    let filter = AuditFilter {
+        bucket: query.bucket,
+        key_prefix: query.key_prefix,
+        access_key_id: query.access_key,
+        action: query.action,
+        from: query.from.as_deref().map(|raw| parse_timestamp("from", raw)).transpose()?,
+        to: query.to.as_deref().map(|raw| parse_timestamp("to", raw)).transpose()?,
+    };
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+
+    let audit_repo = AuditRepository::new(state.catalog.pool().clone());
+    let mut entries = audit_repo.search(&filter, query.after, limit as i32).await?;
+
+    let is_truncated = entries.len() > limit as usize;
+    entries.truncate(limit as usize);
+    let next_cursor = entries.last().map(|entry| entry.id).or(query.after);
+
+    let entries = entries
+        .into_iter()
+        .map(|entry| AuditLogEntry {
+            id: entry.id,
+            request_id: entry.request_id,
+            host_id: entry.host_id,
+            occurred_at: entry.occurred_at,
+            access_key_id: entry.access_key_id,
+            action: entry.action,
+            bucket: entry.bucket,
+            key: entry.key,
+            status: entry.status,
+        })
+        .collect();
+
+    Ok(Json(AuditLogResponse {
+        entries,
+        is_truncated,
+        next_cursor,
+    }))
+}
+
+// Admin: decodes an x-amz-id-2 value into the instance that served the request
+pub async fn lookup_host_id(
+    Path(host_id): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<Json<HostIdResponse>> {
+    let decoded = crate::host_id::decode_host_id(&host_id).ok_or_else(|| ApiError::InvalidArgument {
+        name: "host_id".to_string(),
+        value: Some(host_id.clone()),
+        message: "The value is not a host id issued by GhostBay.",
+    })?;
+
+    Ok(Json(HostIdResponse {
+        served_by_this_instance: decoded.instance_id == *state.instance_id,
+        host_id,
+        instance_id: decoded.instance_id,
+        nonce: decoded.nonce,
+    }))
+}
+
+fn parse_timestamp(name: &str, raw: &str) -> ApiResult<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc3339(raw)
+        .map(|at| at.with_timezone(&chrono::Utc))
+        .map_err(|_| ApiError::InvalidArgument {
+            name: name.to_string(),
+            value: Some(raw.to_string()),
+            message: "The value must be an RFC 3339 timestamp.",
+        })
+}
+
+// Admin: whether encoded uploads to the bucket are stored as sent or inflated
+pub async fn get_bucket_upload_encoding(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<Json<UploadEncodingConfiguration>> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+
+    Ok(Json(UploadEncodingConfiguration {
+        decompress_on_upload: bucket.decompress_on_upload,
+    }))
+}
+
+pub async fn put_bucket_upload_encoding(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+    Json(config): Json<UploadEncodingConfiguration>,
+) -> ApiResult<StatusCode> {
+    let bucket_repo = BucketRepository::new(state.catalog.pool().clone());
+    if !bucket_repo.set_decompress_on_upload(&bucket_name, config.decompress_on_upload).await? {
+        return Err(ApiError::BucketNotFound(bucket_name));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_bucket_lifecycle(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<Json<LifecycleConfiguration>> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+
+    let lifecycle_repo = LifecycleRepository::new(state.catalog.pool().clone());
+    let rules = lifecycle_repo.get_rules(bucket.id).await?;
+
+    Ok(Json(LifecycleConfiguration { rules }))
+}
+
+pub async fn put_bucket_lifecycle(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+    Json(config): Json<LifecycleConfiguration>,
+) -> ApiResult<StatusCode> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+
+    for rule in &config.rules {
+        if rule.id.is_empty() {
+            return Err(ApiError::InvalidArgument {
+                name: "ID".to_string(),
+                value: None,
+                message: "Every lifecycle rule must have an ID.",
+            });
+        }
+        if rule.expiration_days.is_none() && rule.abort_incomplete_upload_days.is_none() {
+            return Err(ApiError::InvalidArgument {
+                name: "ExpirationDays".to_string(),
+                value: None,
+                message: "Every lifecycle rule must set ExpirationDays or AbortIncompleteUploadDays.",
+            });
+        }
+        if let Some(days) = rule.expiration_days.filter(|days| *days < 0) {
+            return Err(ApiError::InvalidArgument {
+                name: "ExpirationDays".to_string(),
+                value: Some(days.to_string()),
+                message: "ExpirationDays must not be negative.",
+            });
+        }
+        if let Some(days) = rule.abort_incomplete_upload_days.filter(|days| *days < 0) {
+            return Err(ApiError::InvalidArgument {
+                name: "AbortIncompleteUploadDays".to_string(),
+                value: Some(days.to_string()),
+                message: "AbortIncompleteUploadDays must not be negative.",
+            });
+        }
+    }
+
+    let lifecycle_repo = LifecycleRepository::new(state.catalog.pool().clone());
+    lifecycle_repo.put_rules(bucket.id, &config.rules).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn preview_bucket_lifecycle(
+    Path(bucket_name): Path<String>,
+    Query(query): Query<LifecyclePreviewQuery>,
+    State(state): State<AppState>,
+) -> ApiResult<Json<LifecycleEvaluationResponse>> {
+    let samples = query.samples.unwrap_or(10).min(1000);
+    evaluate_lifecycle(&state, bucket_name, samples, true).await.map(Json)
+}
+
+pub async fn run_bucket_lifecycle(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<Json<LifecycleEvaluationResponse>> {
+    evaluate_lifecycle(&state, bucket_name, 0, false).await.map(Json)
+}
+
+// What one pass of the background lifecycle job did
+#[derive(Debug, Default)]
+pub struct LifecycleSweep {
+    pub buckets: usize,
+    pub expired_objects: u64,
+    pub expired_bytes: u64,
+    pub aborted_uploads: u64,
+}
+
+// Applies every bucket's lifecycle rules: expires the objects they match and
+// aborts multipart uploads older than their AbortIncompleteMultipartUpload
+// days. A bucket that fails is logged and skipped.
+pub async fn sweep_lifecycle(state: &AppState) -> ApiResult<LifecycleSweep> {
+    let buckets = BucketRepository::new(state.catalog.pool().clone()).list().await?;
+    let lifecycle_repo = LifecycleRepository::new(state.catalog.pool().clone());
+    let upload_repo = MultipartUploadRepository::new(state.catalog.pool().clone());
+    let now = chrono::Utc::now();
+    let mut sweep = LifecycleSweep::default();
+
+    for bucket in &buckets {
+        let rules = lifecycle_repo.get_rules(bucket.id).await?;
+        if !rules.iter().any(|rule| rule.enabled) {
+            continue;
+        }
+        sweep.buckets += 1;
+
+        if rules.iter().any(|rule| rule.enabled && rule.expiration_days.is_some()) {
+            match evaluate_lifecycle(state, bucket.name.clone(), 0, false).await {
+                Ok(report) => {
+                    sweep.expired_objects += report.rules.iter().map(|rule| rule.matched_objects).sum::<u64>();
+                    sweep.expired_bytes += report.rules.iter().map(|rule| rule.matched_bytes).sum::<u64>();
+                }
+                Err(e) => tracing::error!(bucket = %bucket.name, "Lifecycle expiration failed: {}", e),
+            }
+        }
+
+        if !rules.iter().any(|rule| rule.abort_incomplete_upload_days.is_some()) {
+            continue;
+        }
+        let mut marker: Option<(String, String)> = None;
+        loop {
+            let mut page = upload_repo
+                .list_by_bucket(
+                    bucket.id,
+                    "",
+                    marker.as_ref().map(|(key, _)| key.as_str()),
+                    marker.as_ref().map(|(_, upload_id)| upload_id.as_str()),
+                    1000,
+                )
+                .await?;
+            let truncated = page.len() > 1000;
+            page.truncate(1000);
+            marker = page.last().map(|upload| (upload.object_key.clone(), upload.upload_id.clone()));
+
+            for upload in page {
+                if !rules.iter().any(|rule| rule.aborts_upload(&upload.object_key, upload.created_at, now)) {
+                    continue;
+                }
+                match abort_upload(state, &bucket.name, &upload.object_key, &upload).await {
+                    Ok(()) => sweep.aborted_uploads += 1,
+                    Err(e) => tracing::error!(bucket = %bucket.name, upload_id = %upload.upload_id, "Failed to abort stale upload: {}", e),
+                }
+            }
+            if !truncated {
+                break;
+            }
+        }
+    }
+
+    Ok(sweep)
+}
+
+// Aborts the multipart uploads past the fixed expiry announced in their
+// x-amz-abort-date, whatever the bucket's rules say, and returns how many
+// were aborted. An upload that fails to abort is logged and retried next time.
+pub async fn abort_expired_uploads(state: &AppState) -> ApiResult<u64> {
+    let buckets = BucketRepository::new(state.catalog.pool().clone()).list().await?;
+    let bucket_names: HashMap<_, _> = buckets.iter().map(|bucket| (bucket.id, bucket.name.as_str())).collect();
+    let mut aborted = 0;
+    for upload in MultipartUploadRepository::new(state.catalog.pool().clone()).list_expired().await? {
+        let Some(bucket_name) = bucket_names.get(&upload.bucket_id) else {
+            continue;
+        };
+        match abort_upload(state, bucket_name, &upload.object_key, &upload).await {
+            Ok(()) => aborted += 1,
+            Err(e) => tracing::error!(bucket = %bucket_name, upload_id = %upload.upload_id, "Failed to abort expired upload: {}", e),
+        }
+    }
+    Ok(aborted)
+}
+
+// Expired objects are deleted this many at a time
+const EXPIRY_BATCH_SIZE: i32 = 1000;
+
+// What one pass of the object expiry sweep deleted
+#[derive(Debug, Default)]
+pub struct ExpirySweep {
+    pub expired_objects: u64,
+    pub expired_bytes: u64,
+}
+
+// Deletes the objects whose TTL has passed: their catalog rows first, then
+// their data. Data that fails to delete is logged and left behind, since
+// nothing refers to it any more.
+pub async fn sweep_expired_objects(state: &AppState) -> ApiResult<ExpirySweep> {
+    let object_repo = ObjectRepository::new(state.catalog.pool().clone());
+    let now = chrono::Utc::now();
+    let mut sweep = ExpirySweep::default();
+    loop {
+        let expired = object_repo.delete_expired(now, EXPIRY_BATCH_SIZE).await?;
+        for object in &expired {
+            sweep.expired_objects += 1;
+            sweep.expired_bytes += object.size as u64;
+            let deleted = match storage_location(&object.storage_path) {
+                Ok((storage_bucket, storage_key)) => {
+                    state.storage.delete_object(storage_bucket, storage_key).await.map_err(|e| ApiError::Storage(e.to_string()))
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = deleted {
+                tracing::error!(storage_path = %object.storage_path, "Failed to delete the data of an expired object: {}", e);
+            }
+        }
+        if expired.len() < EXPIRY_BATCH_SIZE as usize {
+            return Ok(sweep);
+        }
+    }
+}
+
+// Previews and real runs share this path; a dry run only skips the deletes
+async fn evaluate_lifecycle(
+    state: &AppState,
+    bucket_name: String,
+    samples: usize,
+    dry_run: bool,
+) -> ApiResult<LifecycleEvaluationResponse> {
+    let bucket = resolve_bucket(state, &bucket_name).await?;
+
+    let lifecycle_repo = LifecycleRepository::new(state.catalog.pool().clone());
+    let rules = lifecycle_repo.get_rules(bucket.id).await?;
+
+    let evaluated_at = chrono::Utc::now();
+    let mut evaluator = LifecycleEvaluator::new(state.catalog.pool().clone(), bucket.id, rules, evaluated_at);
+    let mut report = LifecycleReport::new(evaluator.rules(), samples);
+    let object_repo = ObjectRepository::new(state.catalog.pool().clone());
+
+    while let Some(matches) = evaluator.next_batch().await? {
+        for matched in matches {
+            if !dry_run {
+                // Skip keys overwritten since the scan read them
+                let current = object_repo.find_by_bucket_and_key(bucket.id, &matched.object.key).await?;
+                if current.is_none_or(|object| object.id != matched.object.id) {
+                    continue;
+                }
+
+                remove_object(state, &bucket, &matched.object.key).await?;
+            }
+            report.record(&matched);
+        }
+    }
+
+    if !dry_run {
+        tracing::info!(bucket = %bucket_name, "Lifecycle expiration completed");
+    }
+
+    Ok(LifecycleEvaluationResponse {
+        bucket: bucket_name,
+        evaluated_at,
+        dry_run,
+        rules: report.rules,
+    })
+}
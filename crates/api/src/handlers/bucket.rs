@@ -0,0 +1,815 @@
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use ghostbay_auth::{
+    acl::{Acl, Grant, Grantee, Permission, ALL_USERS_URI, AUTHENTICATED_USERS_URI},
+    policy::{actions, resource_arn, PolicyDocument},
+    AuthContext,
+};
+use ghostbay_catalog::{
+    BucketCorsRepository, BucketPolicyRepository, BucketRepository, BucketStats, BucketTagRepository, CreateBucketRequest,
+    LifecycleRepository, LifecycleRule, Object, ObjectRepository,
+};
+use uuid::Uuid;
+
+use super::{bucket_acl, check_key_policies, parse_xml_body, read_body, resolve_bucket, validate_tag_set};
+use crate::{
+    error::{ApiError, ApiResult},
+    extractors::{ListBucketsQuery, ListObjectsQuery, S3Headers},
+    middleware::AuditAction,
+    responses::*,
+    AppState,
+};
+
+// GET /?tagging also lists each bucket's tags, a GhostBay extension for
+// tooling that would otherwise call GetBucketTagging once per bucket
+pub async fn list_buckets(
+    State(state): State<AppState>,
+    Query(query): Query<ListBucketsQuery>,
+    auth: Option<Extension<AuthContext>>,
+) -> ApiResult<(Extension<AuditAction>, XmlResponse<ListBucketsResponse>)> {
+    check_key_policies(auth.as_ref().map(|Extension(auth)| auth), actions::LIST_ALL_MY_BUCKETS, &resource_arn(None, None))?;
+    let repo = BucketRepository::new(state.catalog.pool().clone());
+    let buckets = repo.list().await?;
+    let tag_repo = BucketTagRepository::new(state.catalog.pool().clone());
+
+    let mut bucket_infos = Vec::with_capacity(buckets.len());
+    for bucket in buckets {
+        let tag_set = match query.tagging {
+            Some(_) => Some(TagSet {
+                tags: tag_repo.get(bucket.id).await?.into_iter().map(|(key, value)| Tag { key, value }).collect(),
+            }),
+            None => None,
+        };
+        bucket_infos.push(BucketInfo {
+            name: bucket.name,
+            creation_date: bucket.created_at,
+            tag_set,
+        });
+    }
+
+    let response = ListBucketsResponse {
+        owner: Owner {
+            id: "ghostbay".to_string(),
+            display_name: "GhostBay".to_string(),
+        },
+        buckets: Buckets {
+            bucket: bucket_infos,
+        },
+    };
+
+    Ok((Extension(AuditAction("ListBuckets")), XmlResponse(response)))
+}
+
+pub async fn create_bucket(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+    auth: Option<Extension<AuthContext>>,
+    _headers: S3Headers,
+    body: Body,
+) -> ApiResult<Response> {
+    validate_bucket_name(&bucket_name)?;
+    let region = requested_region(&read_body(body).await?)?.unwrap_or_else(|| state.regions.region.clone());
+    let owner_access_key_id = auth.map(|Extension(auth)| auth.access_key_id);
+
+    let repo = BucketRepository::new(state.catalog.pool().clone());
+
+    if let Some(existing) = repo.find_by_name(&bucket_name).await? {
+        if existing.owner_access_key_id.is_some() && existing.owner_access_key_id == owner_access_key_id {
+            return Err(ApiError::BucketAlreadyOwnedByYou(bucket_name));
+        }
+        return Err(ApiError::BucketAlreadyExists(bucket_name));
+    }
+
+    let request = CreateBucketRequest {
+        name: bucket_name.clone(),
+        region,
+        owner_access_key_id,
+    };
+
+    repo.create(request).await?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("Location", format!("/{}", bucket_name))
+        .body(Body::empty())
+        .map_err(anyhow::Error::from)?)
+}
+
+// Region named by an optional CreateBucketConfiguration body. An empty
+// LocationConstraint is us-east-1, as GetBucketLocation reports it.
+fn requested_region(body: &[u8]) -> ApiResult<Option<String>> {
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(None);
+    }
+    let configuration: CreateBucketConfiguration = parse_xml_body(body, "CreateBucket")?;
+
+    let Some(region) = configuration.location_constraint.map(|region| region.trim().to_string()) else {
+        return Ok(None);
+    };
+    if region.is_empty() {
+        return Ok(Some("us-east-1".to_string()));
+    }
+    if !region.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') {
+        return Err(ApiError::InvalidArgument {
+            name: "LocationConstraint".to_string(),
+            value: Some(region),
+            message: "The specified location-constraint is not valid",
+        });
+    }
+    Ok(Some(region))
+}
+
+// HeadBucket: existence check that also tells the client the bucket's region
+pub async fn head_bucket(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("x-amz-bucket-region", bucket.region)
+        .body(Body::empty())
+        .map_err(anyhow::Error::from)?)
+}
+
+// GetBucketLocation: S3 reports us-east-1, its original region, as an empty
+// constraint
+pub async fn get_bucket_location(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<XmlResponse<LocationConstraint>> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let region = if bucket.region == "us-east-1" { String::new() } else { bucket.region };
+    Ok(XmlResponse(LocationConstraint { region }))
+}
+
+// GET /bucket?stats, a GhostBay extension: the bucket's object count and
+// size as JSON, read from the catalog rather than by listing
+pub async fn get_bucket_stats(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<Json<BucketStats>> {
+    resolve_bucket(&state, &bucket_name).await?;
+    let stats = BucketRepository::new(state.catalog.pool().clone()).stats(&bucket_name).await?;
+    Ok(Json(stats))
+}
+
+// The catalog keeps versioning as a flag, so a bucket that was never
+// versioned reads back as Suspended
+pub async fn get_bucket_versioning(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<XmlResponse<VersioningConfiguration>> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let status = if bucket.versioning_enabled { "Enabled" } else { "Suspended" };
+    Ok(XmlResponse(VersioningConfiguration {
+        status: Some(status.to_string()),
+    }))
+}
+
+pub async fn put_bucket_versioning(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+    body: Body,
+) -> ApiResult<Response> {
+    let configuration: VersioningConfiguration = parse_xml_body(&read_body(body).await?, "PutBucketVersioning")?;
+
+    let enabled = match configuration.status.as_deref() {
+        Some("Enabled") => true,
+        Some("Suspended") => false,
+        status => return Err(ApiError::IllegalVersioningConfiguration(status.unwrap_or_default().to_string())),
+    };
+
+    let repo = BucketRepository::new(state.catalog.pool().clone());
+    if !repo.set_versioning(&bucket_name, enabled).await? {
+        return Err(ApiError::BucketNotFound(bucket_name));
+    }
+
+    Ok(StatusCode::OK.into_response())
+}
+
+pub async fn get_bucket_cors(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<XmlResponse<CorsConfiguration>> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let rules = BucketCorsRepository::new(state.catalog.pool().clone())
+        .get_rules(bucket.id)
+        .await?
+        .ok_or(ApiError::NoSuchCorsConfiguration(bucket_name))?;
+    Ok(XmlResponse(CorsConfiguration { rules }))
+}
+
+pub async fn put_bucket_cors(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+    body: Body,
+) -> ApiResult<Response> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let configuration: CorsConfiguration = parse_xml_body(&read_body(body).await?, "PutBucketCors")?;
+    crate::cors::validate_rules(&configuration.rules).map_err(ApiError::BadRequest)?;
+
+    BucketCorsRepository::new(state.catalog.pool().clone())
+        .put_rules(bucket.id, &configuration.rules)
+        .await?;
+    Ok(StatusCode::OK.into_response())
+}
+
+pub async fn delete_bucket_cors(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    BucketCorsRepository::new(state.catalog.pool().clone())
+        .delete_rules(bucket.id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+pub async fn get_bucket_policy(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let policy = BucketPolicyRepository::new(state.catalog.pool().clone())
+        .get(bucket.id)
+        .await?
+        .ok_or(ApiError::NoSuchBucketPolicy(bucket_name))?;
+    Ok(([(header::CONTENT_TYPE, "application/json")], policy).into_response())
+}
+
+pub async fn put_bucket_policy(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+    body: Body,
+) -> ApiResult<Response> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let body = read_body(body).await?;
+    let document = std::str::from_utf8(&body)
+        .map_err(|_| ApiError::MalformedPolicy("Policies must be UTF-8 encoded JSON".to_string()))?;
+    PolicyDocument::parse(document, &bucket.name).map_err(|e| ApiError::MalformedPolicy(e.to_string()))?;
+
+    BucketPolicyRepository::new(state.catalog.pool().clone())
+        .put(bucket.id, document)
+        .await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+pub async fn delete_bucket_policy(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    BucketPolicyRepository::new(state.catalog.pool().clone())
+        .delete(bucket.id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+pub async fn get_bucket_acl(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<XmlResponse<AccessControlPolicy>> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let acl = bucket_acl(&bucket)?;
+    let grants = acl
+        .grants
+        .iter()
+        .map(|grant| {
+            let (grantee_type, id, uri) = match &grant.grantee {
+                Grantee::CanonicalUser(id) => ("CanonicalUser", Some(id.clone()), None),
+                Grantee::AllUsers => ("Group", None, Some(ALL_USERS_URI.to_string())),
+                Grantee::AuthenticatedUsers => ("Group", None, Some(AUTHENTICATED_USERS_URI.to_string())),
+            };
+            AclGrant {
+                grantee: AclGrantee {
+                    xmlns_xsi: xsi_namespace(),
+                    grantee_type: grantee_type.to_string(),
+                    display_name: id.clone(),
+                    id,
+                    uri,
+                },
+                permission: grant.permission.as_str().to_string(),
+            }
+        })
+        .collect();
+    Ok(XmlResponse(AccessControlPolicy {
+        owner: Some(Owner {
+            id: acl.owner.clone(),
+            display_name: acl.owner,
+        }),
+        access_control_list: AccessControlList { grants },
+    }))
+}
+
+// PutBucketAcl takes a canned ACL in x-amz-acl or an AccessControlPolicy
+// document, not both. Grants in x-amz-grant-* headers are not supported.
+pub async fn put_bucket_acl(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Body,
+) -> ApiResult<Response> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let owner = bucket_acl(&bucket)?.owner;
+    let body = read_body(body).await?;
+
+    let acl = match headers.get("x-amz-acl") {
+        Some(_) if !body.iter().all(u8::is_ascii_whitespace) => {
+            return Err(ApiError::BadRequest(
+                "Specifying both a canned ACL and an AccessControlPolicy is not allowed.".to_string(),
+            ));
+        }
+        Some(canned) => {
+            let canned = canned.to_str().unwrap_or_default();
+            Acl::canned(canned, &owner).map_err(|_| ApiError::InvalidArgument {
+                name: "x-amz-acl".to_string(),
+                value: Some(canned.to_string()),
+                message: "The canned ACL must be private, public-read, public-read-write or authenticated-read.",
+            })?
+        }
+        None => {
+            let policy: AccessControlPolicy = parse_xml_body(&body, "PutBucketAcl")?;
+            let grants = policy
+                .access_control_list
+                .grants
+                .into_iter()
+                .map(acl_grant)
+                .collect::<Result<_, _>>()
+                .map_err(|e| ApiError::MalformedAcl(e.to_string()))?;
+            Acl { owner, grants }
+        }
+    };
+
+    let document = serde_json::to_string(&acl).map_err(anyhow::Error::from)?;
+    BucketRepository::new(state.catalog.pool().clone())
+        .set_acl(&bucket.name, &document)
+        .await?;
+    Ok(StatusCode::OK.into_response())
+}
+
+fn acl_grant(grant: AclGrant) -> Result<Grant, ghostbay_auth::acl::AclError> {
+    let grantee = match (grant.grantee.grantee_type.as_str(), grant.grantee.id, grant.grantee.uri) {
+        ("CanonicalUser", Some(id), _) => Grantee::CanonicalUser(id),
+        ("Group", _, Some(uri)) => Grantee::from_group_uri(&uri)?,
+        (grantee_type, _, _) => return Err(ghostbay_auth::acl::AclError::InvalidGrantee(grantee_type.to_string())),
+    };
+    Ok(Grant {
+        grantee,
+        permission: Permission::parse(&grant.permission)?,
+    })
+}
+
+pub async fn get_bucket_lifecycle_configuration(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<XmlResponse<BucketLifecycleConfiguration>> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let rules = LifecycleRepository::new(state.catalog.pool().clone()).get_rules(bucket.id).await?;
+    if rules.is_empty() {
+        return Err(ApiError::NoSuchLifecycleConfiguration(bucket_name));
+    }
+    Ok(XmlResponse(BucketLifecycleConfiguration {
+        rules: rules.into_iter().map(lifecycle_rule_to_xml).collect(),
+    }))
+}
+
+pub async fn put_bucket_lifecycle_configuration(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+    body: Body,
+) -> ApiResult<Response> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let configuration: BucketLifecycleConfiguration =
+        parse_xml_body(&read_body(body).await?, "PutBucketLifecycleConfiguration")?;
+    if configuration.rules.is_empty() || configuration.rules.len() > 1000 {
+        return Err(ApiError::BadRequest(
+            "A lifecycle configuration must have between 1 and 1000 rules.".to_string(),
+        ));
+    }
+
+    let rules = configuration
+        .rules
+        .into_iter()
+        .map(lifecycle_rule_from_xml)
+        .collect::<ApiResult<Vec<_>>>()?;
+    for (index, rule) in rules.iter().enumerate() {
+        if rules[..index].iter().any(|other| other.id == rule.id) {
+            return Err(ApiError::InvalidArgument {
+                name: "ID".to_string(),
+                value: Some(rule.id.clone()),
+                message: "Rule ID must be unique. Found same ID for more than one rule.",
+            });
+        }
+    }
+
+    LifecycleRepository::new(state.catalog.pool().clone())
+        .put_rules(bucket.id, &rules)
+        .await?;
+    Ok(StatusCode::OK.into_response())
+}
+
+pub async fn delete_bucket_lifecycle(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    LifecycleRepository::new(state.catalog.pool().clone())
+        .delete_rules(bucket.id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+// Rules without an ID get a generated one, as in S3
+fn lifecycle_rule_from_xml(rule: BucketLifecycleRule) -> ApiResult<LifecycleRule> {
+    let id = rule.id.unwrap_or_else(|| Uuid::new_v4().simple().to_string());
+    if id.len() > 255 {
+        return Err(ApiError::InvalidArgument {
+            name: "ID".to_string(),
+            value: Some(id),
+            message: "ID length should not exceed allowed limit of 255",
+        });
+    }
+    let enabled = match rule.status.as_str() {
+        "Enabled" => true,
+        "Disabled" => false,
+        _ => {
+            return Err(ApiError::InvalidArgument {
+                name: "Status".to_string(),
+                value: Some(rule.status),
+                message: "Status must be Enabled or Disabled.",
+            });
+        }
+    };
+
+    let filter = rule.filter.unwrap_or_default();
+    if rule.prefix.is_some() && (filter.prefix.is_some() || filter.tag.is_some() || filter.and.is_some()) {
+        return Err(ApiError::BadRequest("A rule may have a Prefix or a Filter, not both.".to_string()));
+    }
+    let (and_prefix, and_tags) = filter.and.map(|and| (and.prefix, and.tags)).unwrap_or_default();
+    let prefix = rule.prefix.or(filter.prefix).or(and_prefix).filter(|prefix| !prefix.is_empty());
+    let tags = filter.tag.into_iter().chain(and_tags).map(|tag| (tag.key, tag.value)).collect();
+
+    let expiration_days = match rule.expiration {
+        Some(LifecycleExpiration { date: Some(date), .. }) => {
+            return Err(ApiError::InvalidArgument {
+                name: "Date".to_string(),
+                value: Some(date),
+                message: "Expiration by date is not supported; use Days.",
+            });
+        }
+        Some(LifecycleExpiration { days: Some(days), .. }) if days > 0 => Some(days),
+        Some(LifecycleExpiration { days, .. }) => {
+            return Err(ApiError::InvalidArgument {
+                name: "Days".to_string(),
+                value: days.map(|days| days.to_string()),
+                message: "'Days' for Expiration action must be a positive integer",
+            });
+        }
+        None => None,
+    };
+    let abort_incomplete_upload_days = match rule.abort_incomplete_multipart_upload {
+        Some(abort) if abort.days_after_initiation > 0 => Some(abort.days_after_initiation),
+        Some(abort) => {
+            return Err(ApiError::InvalidArgument {
+                name: "DaysAfterInitiation".to_string(),
+                value: Some(abort.days_after_initiation.to_string()),
+                message: "'DaysAfterInitiation' for AbortIncompleteMultipartUpload action must be a positive integer",
+            });
+        }
+        None => None,
+    };
+    if expiration_days.is_none() && abort_incomplete_upload_days.is_none() {
+        return Err(ApiError::InvalidArgument {
+            name: "Rule".to_string(),
+            value: Some(id),
+            message: "At least one action needs to be specified in a rule",
+        });
+    }
+
+    Ok(LifecycleRule {
+        id,
+        prefix,
+        tags,
+        expiration_days,
+        abort_incomplete_upload_days,
+        enabled,
+    })
+}
+
+fn lifecycle_rule_to_xml(rule: LifecycleRule) -> BucketLifecycleRule {
+    let mut tags: Vec<Tag> = rule.tags.into_iter().map(|(key, value)| Tag { key, value }).collect();
+    tags.sort_by(|a, b| a.key.cmp(&b.key));
+    let filter = match (rule.prefix, tags.len()) {
+        (prefix, 0) => LifecycleFilter {
+            prefix: Some(prefix.unwrap_or_default()),
+            ..Default::default()
+        },
+        (None, 1) => LifecycleFilter {
+            tag: tags.pop(),
+            ..Default::default()
+        },
+        (prefix, _) => LifecycleFilter {
+            and: Some(LifecycleFilterAnd { prefix, tags }),
+            ..Default::default()
+        },
+    };
+
+    BucketLifecycleRule {
+        id: Some(rule.id),
+        prefix: None,
+        filter: Some(filter),
+        status: if rule.enabled { "Enabled" } else { "Disabled" }.to_string(),
+        expiration: rule.expiration_days.map(|days| LifecycleExpiration { days: Some(days), date: None }),
+        abort_incomplete_multipart_upload: rule
+            .abort_incomplete_upload_days
+            .map(|days_after_initiation| AbortIncompleteMultipartUpload { days_after_initiation }),
+    }
+}
+
+pub async fn get_bucket_tagging(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<XmlResponse<Tagging>> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let tags = BucketTagRepository::new(state.catalog.pool().clone()).get(bucket.id).await?;
+    if tags.is_empty() {
+        return Err(ApiError::NoSuchTagSet(bucket_name));
+    }
+    Ok(XmlResponse(Tagging {
+        tag_set: TagSet {
+            tags: tags.into_iter().map(|(key, value)| Tag { key, value }).collect(),
+        },
+    }))
+}
+
+pub async fn put_bucket_tagging(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+    body: Body,
+) -> ApiResult<Response> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let tagging: Tagging = parse_xml_body(&read_body(body).await?, "PutBucketTagging")?;
+    let tags: Vec<(String, String)> = tagging.tag_set.tags.into_iter().map(|tag| (tag.key, tag.value)).collect();
+
+    // Buckets take up to 50 tags, against 10 on objects
+    if tags.len() > 50 {
+        return Err(ApiError::TooManyTags { count: tags.len(), limit: 50 });
+    }
+    validate_tag_set(&tags)?;
+
+    BucketTagRepository::new(state.catalog.pool().clone())
+        .replace(bucket.id, &tags)
+        .await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+pub async fn delete_bucket_tagging(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    BucketTagRepository::new(state.catalog.pool().clone())
+        .replace(bucket.id, &[])
+        .await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+pub async fn delete_bucket(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let repo = BucketRepository::new(state.catalog.pool().clone());
+
+    if !repo.delete(&bucket_name).await? {
+        return Err(ApiError::BucketNotFound(bucket_name));
+    }
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+pub async fn list_objects(
+    Path(bucket_name): Path<String>,
+    Query(query): Query<ListObjectsQuery>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+
+    let max_keys = query.max_keys.unwrap_or(1000).min(1000);
+    let v2 = query.list_type.as_deref() == Some("2");
+    // A continuation token resumes a previous listing and takes precedence
+    // over start-after, which only applies to the first page. Version 1
+    // resumes from its marker.
+    let start_after = match (&query.continuation_token, v2) {
+        (Some(token), true) => Some(decode_continuation_token(token)?),
+        (None, true) => query.start_after.clone(),
+        (_, false) => query.marker.clone().filter(|marker| !marker.is_empty()),
+    };
+
+    // Resuming from a rolled-up prefix skips the rest of the keys under it
+    let delimiter = query.delimiter.as_deref().filter(|delimiter| !delimiter.is_empty());
+    let resuming = if v2 { query.continuation_token.is_some() } else { start_after.is_some() };
+    let resume_prefix = match (&start_after, delimiter) {
+        (Some(marker), Some(delimiter)) if resuming && marker.ends_with(delimiter) => Some(marker.clone()),
+        _ => None,
+    };
+
+    let object_repo = ObjectRepository::new(state.catalog.pool().clone());
+    let page = list_page(
+        &object_repo,
+        bucket.id,
+        query.prefix.as_deref(),
+        delimiter,
+        start_after,
+        resume_prefix,
+        max_keys as usize,
+    )
+    .await?;
+
+    let fetch_owner = query.fetch_owner.unwrap_or(false);
+    let object_infos: Vec<ObjectInfo> = page
+        .objects
+        .into_iter()
+        .map(|obj| ObjectInfo {
+            key: obj.key,
+            last_modified: obj.updated_at,
+            etag: format!("\"{}\"", obj.etag),
+            size: obj.size as u64,
+            storage_class: "STANDARD".to_string(),
+            owner: fetch_owner.then(|| Owner {
+                id: "ghostbay".to_string(),
+                display_name: "GhostBay".to_string(),
+            }),
+        })
+        .collect();
+    let common_prefixes: Vec<CommonPrefix> = page
+        .common_prefixes
+        .into_iter()
+        .map(|prefix| CommonPrefix { prefix })
+        .collect();
+    let next_entry = page.last_entry.filter(|_| page.is_truncated);
+
+    if !v2 {
+        let response = ListObjectsV1Response {
+            name: bucket_name,
+            prefix: query.prefix.unwrap_or_default(),
+            marker: query.marker.unwrap_or_default(),
+            next_marker: next_entry.filter(|_| delimiter.is_some()),
+            max_keys,
+            delimiter: query.delimiter,
+            is_truncated: page.is_truncated,
+            contents: object_infos,
+            common_prefixes,
+        };
+        return Ok(XmlResponse(response).into_response());
+    }
+
+    let response = ListObjectsV2Response {
+        name: bucket_name,
+        prefix: query.prefix.unwrap_or_default(),
+        delimiter: query.delimiter,
+        start_after: query.start_after,
+        key_count: (object_infos.len() + common_prefixes.len()) as u32,
+        max_keys,
+        is_truncated: page.is_truncated,
+        continuation_token: query.continuation_token,
+        next_continuation_token: next_entry.map(|entry| BASE64.encode(entry)),
+        contents: object_infos,
+        common_prefixes,
+    };
+
+    Ok(XmlResponse(response).into_response())
+}
+
+#[derive(Default)]
+pub struct ListingPage {
+    pub objects: Vec<Object>,
+    pub common_prefixes: Vec<String>,
+    pub is_truncated: bool,
+    // Key or common prefix the page ended on, where the next page resumes
+    pub last_entry: Option<String>,
+}
+
+// Keys after the prefix up to and including the first delimiter are rolled up
+// into one common prefix. Keys and common prefixes both count against
+// max-keys, and a folder can hold more keys than one query returns, so pages
+// are read until max-keys entries are collected or the listing runs out.
+pub async fn list_page(
+    repo: &ObjectRepository,
+    bucket_id: Uuid,
+    prefix: Option<&str>,
+    delimiter: Option<&str>,
+    start_after: Option<String>,
+    mut rolled_up: Option<String>,
+    max_keys: usize,
+) -> ApiResult<ListingPage> {
+    let mut page = ListingPage::default();
+    if max_keys == 0 {
+        return Ok(page);
+    }
+
+    let prefix_len = prefix.map_or(0, str::len);
+    let mut cursor = start_after;
+    loop {
+        let batch = repo.list_by_bucket(bucket_id, prefix, cursor.as_deref(), max_keys as i32).await?;
+        let exhausted = batch.len() <= max_keys;
+
+        for object in batch.into_iter().take(max_keys) {
+            cursor = Some(object.key.clone());
+            if rolled_up.as_ref().is_some_and(|rolled_up| object.key.starts_with(rolled_up.as_str())) {
+                continue;
+            }
+
+            if page.objects.len() + page.common_prefixes.len() == max_keys {
+                page.is_truncated = true;
+                return Ok(page);
+            }
+
+            let common_prefix = delimiter.and_then(|delimiter| {
+                object.key[prefix_len..]
+                    .find(delimiter)
+                    .map(|index| object.key[..prefix_len + index + delimiter.len()].to_string())
+            });
+            match common_prefix {
+                Some(common_prefix) => {
+                    page.last_entry = Some(common_prefix.clone());
+                    page.common_prefixes.push(common_prefix.clone());
+                    rolled_up = Some(common_prefix);
+                }
+                None => {
+                    page.last_entry = Some(object.key.clone());
+                    page.objects.push(object);
+                }
+            }
+        }
+
+        if exhausted {
+            return Ok(page);
+        }
+    }
+}
+
+// Continuation tokens are the key or common prefix the previous page ended
+// on, base64 encoded so clients treat them as opaque
+fn decode_continuation_token(token: &str) -> ApiResult<String> {
+    BASE64
+        .decode(token)
+        .ok()
+        .and_then(|key| String::from_utf8(key).ok())
+        .ok_or_else(|| ApiError::InvalidArgument {
+            name: "continuation-token".to_string(),
+            value: Some(token.to_string()),
+            message: "The continuation token provided is incorrect.",
+        })
+}
+
+fn validate_bucket_name(name: &str) -> ApiResult<()> {
+    if name.is_empty() || name.len() < 3 || name.len() > 63 {
+        return Err(ApiError::InvalidBucketName {
+            bucket: name.to_string(),
+            reason: "Bucket name must be between 3 and 63 characters long",
+        });
+    }
+
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '.')
+    {
+        return Err(ApiError::InvalidBucketName {
+            bucket: name.to_string(),
+            reason: "Bucket name can only contain lowercase letters, numbers, hyphens, and periods",
+        });
+    }
+
+    let is_alphanumeric = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit();
+    if !name.starts_with(is_alphanumeric) || !name.ends_with(is_alphanumeric) {
+        return Err(ApiError::InvalidBucketName {
+            bucket: name.to_string(),
+            reason: "Bucket name must begin and end with a letter or number",
+        });
+    }
+
+    if ["--", "..", ".-", "-."].iter().any(|pair| name.contains(pair)) {
+        return Err(ApiError::InvalidBucketName {
+            bucket: name.to_string(),
+            reason: "Bucket name must not contain consecutive hyphens or periods",
+        });
+    }
+
+    if name.parse::<std::net::Ipv4Addr>().is_ok() {
+        return Err(ApiError::InvalidBucketName {
+            bucket: name.to_string(),
+            reason: "Bucket name must not be formatted as an IP address",
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,401 @@
+use axum::{
+    body::Body,
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use ghostbay_auth::{
+    acl::Acl,
+    policy::{actions, resource_arn, Authorizer, Decision, PolicyDocument, PolicyEvaluator},
+    AuthContext,
+};
+use ghostbay_catalog::{
+    Bucket, BucketPolicyRepository, BucketRepository, CreateObjectRequest, Object, ObjectRepository, ObjectVersionRepository,
+};
+use uuid::Uuid;
+
+use crate::{
+    checksum::Checksum,
+    error::{ApiError, ApiResult},
+    upload::QuotaRoom,
+    AppState,
+};
+
+mod admin;
+mod bucket;
+mod multipart;
+mod object;
+mod subresource;
+
+pub use admin::*;
+pub use bucket::*;
+pub use multipart::*;
+pub use object::*;
+pub use subresource::*;
+
+// Helpers shared by the per-resource handler modules
+
+pub async fn resolve_bucket(state: &AppState, bucket_name: &str) -> ApiResult<Bucket> {
+    BucketRepository::new(state.catalog.pool().clone())
+        .find_by_name(bucket_name)
+        .await?
+        .ok_or_else(|| ApiError::BucketNotFound(bucket_name.to_string()))
+}
+
+// Decides one action on the bucket, or on one of its objects when key is
+// given. The bucket's policy goes first: an explicit Deny fails the request
+// and an Allow grants it. Then a grant in an ACL put on the bucket, such as
+// public-read's to everyone, allows the action, as do the bucket's public
+// access flags for reads and listing. Otherwise a signed request needs one
+// of its key's policies to allow the action, and an anonymous one is
+// refused. Admin keys and the bucket's owner are exempt from the bucket
+// policy when managing the policy itself, so a bad one cannot lock them out.
+async fn authorize(state: &AppState, auth: Option<&AuthContext>, bucket: &Bucket, action: &str, key: Option<&str>) -> ApiResult<()> {
+    let document = BucketPolicyRepository::new(state.catalog.pool().clone()).get(bucket.id).await?;
+    let manages_policy = action.ends_with("BucketPolicy")
+        && auth.is_some_and(|auth| {
+            Authorizer::is_admin(auth) || bucket.owner_access_key_id.as_deref() == Some(auth.access_key_id.as_str())
+        });
+
+    let resource = resource_arn(Some(&bucket.name), key);
+    let decision = match document {
+        Some(document) if !manages_policy => {
+            // Stored policies were validated when they were put
+            let policy = PolicyDocument::parse(&document, &bucket.name)
+                .map_err(|e| anyhow::anyhow!("Stored policy of bucket {} is invalid: {}", bucket.name, e))?;
+            policy.evaluate(auth.map(|auth| auth.access_key_id.as_str()), action, &resource)
+        }
+        _ => Decision::NotApplicable,
+    };
+    match decision {
+        Decision::Deny => Err(ApiError::AuthorizationFailed(format!("{} on {} is denied by the bucket policy", action, resource))),
+        Decision::Allow => Ok(()),
+        Decision::NotApplicable
+            if bucket.acl.is_some() && bucket_acl(bucket)?.allows(auth.map(|auth| auth.access_key_id.as_str()), action) =>
+        {
+            Ok(())
+        }
+        Decision::NotApplicable if public_access(bucket, action) => Ok(()),
+        Decision::NotApplicable => check_key_policies(auth, action, &resource),
+    }
+}
+
+// What a bucket's public_read and public_list flags open to everyone. Writes
+// and every other operation still need a signed request.
+fn public_access(bucket: &Bucket, action: &str) -> bool {
+    match action {
+        actions::GET_OBJECT => bucket.public_read,
+        actions::LIST_BUCKET => bucket.public_list,
+        _ => false,
+    }
+}
+
+// The bucket's ACL: the one last put, or full control for its owner. Buckets
+// created by the CLI or provisioning are owned by ghostbay itself.
+fn bucket_acl(bucket: &Bucket) -> ApiResult<Acl> {
+    let owner = bucket.owner_access_key_id.as_deref().unwrap_or("ghostbay");
+    match &bucket.acl {
+        // Stored ACLs were validated when they were put
+        Some(document) => Ok(serde_json::from_str(document)
+            .map_err(|e| anyhow::anyhow!("Stored ACL of bucket {} is invalid: {}", bucket.name, e))?),
+        None => Ok(Acl::private(owner)),
+    }
+}
+
+// Whether the request's access key may take the action on the resource, an
+// ARN from resource_arn, by its own policies
+fn check_key_policies(auth: Option<&AuthContext>, action: &str, resource: &str) -> ApiResult<()> {
+    match auth {
+        Some(auth) if PolicyEvaluator::is_allowed(action, resource, auth) => Ok(()),
+        Some(auth) => Err(ApiError::AuthorizationFailed(format!(
+            "{} is not allowed by the policies of access key {}",
+            action, auth.access_key_id
+        ))),
+        None => Err(ApiError::AuthorizationFailed("anonymous requests are not allowed on this resource".to_string())),
+    }
+}
+
+// Refuses a write of `size` bytes that would take the bucket past its quota.
+// The object a write to `key` replaces stops counting against it. Returns the
+// room the quota leaves, for writes whose size is only known once streamed.
+async fn check_bucket_quota(state: &AppState, bucket: &Bucket, key: Option<&str>, size: u64) -> ApiResult<Option<QuotaRoom>> {
+    let Some(quota_bytes) = bucket.quota_bytes else {
+        return Ok(None);
+    };
+    let object_repo = ObjectRepository::new(state.catalog.pool().clone());
+    let usage = object_repo.bucket_usage(bucket.id).await?;
+    let replaced = match key {
+        Some(key) => object_repo.find_by_bucket_and_key(bucket.id, key).await?.map_or(0, |object| object.size as u64),
+        None => 0,
+    };
+    if usage.saturating_sub(replaced) + size > quota_bytes as u64 {
+        return Err(ApiError::BucketQuotaExceeded {
+            bucket: bucket.name.clone(),
+            quota_bytes: quota_bytes as u64,
+            usage,
+            size,
+        });
+    }
+    Ok(Some(QuotaRoom {
+        bucket: bucket.name.clone(),
+        quota_bytes: quota_bytes as u64,
+        usage,
+        room: quota_bytes as u64 - usage.saturating_sub(replaced),
+    }))
+}
+
+// Versioned buckets keep the data of overwritten and deleted versions in a
+// version store beside the buckets, as <bucket>/<version id>/<key> under
+// this directory. A version's storage_path says where its data is now.
+const VERSION_STORE: &str = ".versions";
+
+// Objects written before versioning have no version_id of their own; their
+// history entry uses the row id
+fn version_id(object: &Object) -> Uuid {
+    object.version_id.unwrap_or(object.id)
+}
+
+// Splits a storage_path into the engine bucket and key it names
+fn storage_location(storage_path: &str) -> ApiResult<(&str, &str)> {
+    storage_path
+        .split_once('/')
+        .ok_or_else(|| ApiError::Internal(anyhow::anyhow!("malformed storage path {}", storage_path)))
+}
+
+// An object past its TTL reads as missing until the expiry sweep deletes it
+fn is_expired(object: &Object) -> bool {
+    object.expires_at.is_some_and(|expires_at| expires_at <= Utc::now())
+}
+
+fn is_archived(storage_path: &str) -> bool {
+    storage_path.strip_prefix(VERSION_STORE).is_some_and(|rest| rest.starts_with('/'))
+}
+
+// Before a versioned bucket's key is overwritten or deleted, copies its
+// current data into the version store so the version stays readable
+async fn archive_current_version(state: &AppState, bucket: &Bucket, key: &str) -> ApiResult<()> {
+    if !bucket.versioning_enabled {
+        return Ok(());
+    }
+    let object_repo = ObjectRepository::new(state.catalog.pool().clone());
+    let Some(current) = object_repo.find_by_bucket_and_key(bucket.id, key).await? else {
+        return Ok(());
+    };
+
+    let version_id = version_id(&current);
+    let (source_bucket, source_key) = storage_location(&current.storage_path)?;
+    let archive_key = format!("{}/{}/{}", bucket.name, version_id, key);
+    state.storage
+        .copy_object(source_bucket, source_key, VERSION_STORE, &archive_key)
+        .await
+        .map_err(|e| ApiError::Storage(e.to_string()))?;
+
+    ObjectVersionRepository::new(state.catalog.pool().clone())
+        .set_storage_path(bucket.id, key, version_id, &format!("{}/{}", VERSION_STORE, archive_key))
+        .await?;
+    Ok(())
+}
+
+// Records a newly written object as the current one for its key, replacing
+// any current row in one transaction. Nothing is archived here: in a
+// versioned bucket, callers run archive_current_version before writing.
+async fn store_object(state: &AppState, request: CreateObjectRequest, etag: String) -> ApiResult<Object> {
+    let object = ObjectRepository::new(state.catalog.pool().clone()).replace(request, etag).await?;
+    Ok(object)
+}
+
+// The source bucket and key of CopyObject and UploadPartCopy.
+// x-amz-copy-source is `bucket/key` or `/bucket/key` with the key URL-encoded.
+fn copy_source(headers: &HeaderMap) -> ApiResult<(String, String)> {
+    let copy_source = headers
+        .get("x-amz-copy-source")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let parsed = copy_source.trim_start_matches('/').split_once('/').and_then(|(bucket, key)| {
+        let key = urlencoding::decode(key).ok()?.into_owned();
+        (!bucket.is_empty() && !key.is_empty()).then(|| (bucket.to_string(), key))
+    });
+    parsed.ok_or_else(|| ApiError::InvalidArgument {
+        name: "x-amz-copy-source".to_string(),
+        value: Some(copy_source.to_string()),
+        message: "Copy Source must mention the source bucket and key: sourcebucket/sourcekey.",
+    })
+}
+
+// The catalog says the object exists but its file is gone. That is corruption,
+// not a missing key, so answer 500 and flag the row for fsck instead of a 404.
+async fn missing_blob(state: &AppState, bucket_name: &str, object: &Object) -> ApiError {
+    tracing::error!(
+        bucket = %bucket_name,
+        key = %object.key,
+        storage_path = %object.storage_path,
+        "Object is in the catalog but its data file is missing"
+    );
+    crate::metrics::MISSING_BLOB_TOTAL.inc();
+
+    let object_repo = ObjectRepository::new(state.catalog.pool().clone());
+    if let Err(e) = object_repo.mark_needs_repair(object.id).await {
+        tracing::error!("Failed to flag {}/{} for repair: {}", bucket_name, object.key, e);
+    }
+
+    ApiError::Internal(anyhow::anyhow!("data file for {}/{} is missing", bucket_name, object.key))
+}
+
+async fn read_body(body: Body) -> ApiResult<Bytes> {
+    axum::body::to_bytes(body, usize::MAX).await.map_err(|e| {
+        tracing::warn!("Failed to read request body: {}", e);
+        ApiError::BadRequest("The request body could not be read.".to_string())
+    })
+}
+
+// Request documents such as CreateBucketConfiguration; `operation` names the
+// request in debug logs
+fn parse_xml_body<T: serde::de::DeserializeOwned>(body: &[u8], operation: &str) -> ApiResult<T> {
+    std::str::from_utf8(body)
+        .map_err(|e| e.to_string())
+        .and_then(|body| quick_xml::de::from_str(body).map_err(|e| e.to_string()))
+        .map_err(|e| {
+            tracing::debug!("Invalid {} body: {}", operation, e);
+            ApiError::BadRequest("The XML you provided was not well-formed or did not validate against our published schema.".to_string())
+        })
+}
+
+// 200 with an empty body, as PutObject and UploadPart answer
+fn etag_response(etag: &str) -> ApiResult<Response> {
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("ETag", format!("\"{}\"", etag))
+        .body(Body::empty())
+        .map_err(anyhow::Error::from)?)
+}
+
+// Echoes the checksum an upload was verified against
+fn insert_checksum(response: &mut Response, checksum: Option<&Checksum>) -> ApiResult<()> {
+    if let Some(checksum) = checksum {
+        let value = checksum.value.parse().map_err(anyhow::Error::from)?;
+        response.headers_mut().insert(checksum.algorithm.header_name(), value);
+    }
+    Ok(())
+}
+
+fn http_date(at: &DateTime<Utc>) -> String {
+    at.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+// S3's rules for a tag set, on objects and buckets alike: distinct keys of up
+// to 128 characters and values of up to 256. Callers check the tag count.
+fn validate_tag_set(tags: &[(String, String)]) -> ApiResult<()> {
+    for (index, (tag_key, tag_value)) in tags.iter().enumerate() {
+        if tag_key.is_empty() || tag_key.chars().count() > 128 {
+            return Err(ApiError::InvalidTag("The TagKey you have provided is invalid"));
+        }
+        if tag_value.chars().count() > 256 {
+            return Err(ApiError::InvalidTag("The TagValue you have provided is invalid"));
+        }
+        if tags[..index].iter().any(|(other, _)| other == tag_key) {
+            return Err(ApiError::InvalidTag("Cannot provide multiple Tags with the same key"));
+        }
+    }
+    Ok(())
+}
+
+// S3 limits user metadata to 2 KB, counting the bytes of each name and value
+const MAX_USER_METADATA_SIZE: usize = 2 * 1024;
+
+// User metadata from x-amz-meta-* headers, keyed without the prefix
+fn user_metadata(headers: &HeaderMap) -> ApiResult<Option<serde_json::Value>> {
+    let mut metadata = serde_json::Map::new();
+    let mut size = 0;
+    for (header_name, header_value) in headers.iter() {
+        if let Some(name) = header_name.as_str().strip_prefix("x-amz-meta-")
+            && let Ok(value) = header_value.to_str()
+        {
+            size += name.len() + value.len();
+            metadata.insert(name.to_string(), serde_json::Value::String(value.to_string()));
+        }
+    }
+    if size > MAX_USER_METADATA_SIZE {
+        return Err(ApiError::MetadataTooLarge { size, max_size: MAX_USER_METADATA_SIZE });
+    }
+    Ok(if metadata.is_empty() { None } else { Some(serde_json::Value::Object(metadata)) })
+}
+
+// Standard headers stored as given on upload and returned on reads, S3's
+// system-defined metadata. Content-Type and Content-Encoding have columns.
+const SYSTEM_METADATA_HEADERS: [&str; 4] = ["cache-control", "content-disposition", "content-language", "expires"];
+
+fn system_metadata(headers: &HeaderMap) -> Option<serde_json::Value> {
+    let metadata: serde_json::Map<String, serde_json::Value> = SYSTEM_METADATA_HEADERS
+        .iter()
+        .filter_map(|name| {
+            let value = headers.get(*name)?.to_str().ok()?;
+            Some((name.to_string(), serde_json::Value::String(value.to_string())))
+        })
+        .collect();
+    if metadata.is_empty() { None } else { Some(serde_json::Value::Object(metadata)) }
+}
+
+fn with_system_metadata(mut response: axum::http::response::Builder, object: &Object) -> axum::http::response::Builder {
+    let metadata = object.system_metadata.as_deref().and_then(|metadata| serde_json::from_str::<serde_json::Value>(metadata).ok());
+    if let Some(serde_json::Value::Object(metadata)) = metadata {
+        for (name, value) in metadata {
+            if let serde_json::Value::String(value) = value {
+                response = response.header(name, value);
+            }
+        }
+    }
+    response
+}
+
+// Adds an object's stored user metadata back as x-amz-meta-* headers
+fn with_user_metadata(mut response: axum::http::response::Builder, object: &Object) -> axum::http::response::Builder {
+    let metadata = object.metadata.as_deref().and_then(|metadata| serde_json::from_str::<serde_json::Value>(metadata).ok());
+    if let Some(serde_json::Value::Object(metadata)) = metadata {
+        for (name, value) in metadata {
+            if let serde_json::Value::String(value) = value {
+                response = response.header(format!("x-amz-meta-{}", name), value);
+            }
+        }
+    }
+    response
+}
+
+// A TTL given on upload, as the time the object expires: seconds in
+// x-ghostbay-ttl-seconds or whole days in x-amz-expiration-days
+fn object_expiry(headers: &HeaderMap) -> ApiResult<Option<DateTime<Utc>>> {
+    let header = |name: &'static str| headers.get(name).map(|value| (name, value.to_str().unwrap_or_default()));
+    let (name, value, unit_seconds) = match (header("x-ghostbay-ttl-seconds"), header("x-amz-expiration-days")) {
+        (None, None) => return Ok(None),
+        (Some((name, value)), None) => (name, value, 1),
+        (None, Some((name, value))) => (name, value, 24 * 60 * 60),
+        (Some(_), Some((name, value))) => {
+            return Err(ApiError::InvalidArgument {
+                name: name.to_string(),
+                value: Some(value.to_string()),
+                message: "Only one of x-ghostbay-ttl-seconds and x-amz-expiration-days may be given.",
+            });
+        }
+    };
+    let expires_at = value
+        .parse::<i64>()
+        .ok()
+        .filter(|ttl| *ttl > 0)
+        .and_then(|ttl| ttl.checked_mul(unit_seconds))
+        .and_then(chrono::Duration::try_seconds)
+        .and_then(|ttl| Utc::now().checked_add_signed(ttl));
+    match expires_at {
+        Some(expires_at) => Ok(Some(expires_at)),
+        None => Err(ApiError::InvalidArgument {
+            name: name.to_string(),
+            value: Some(value.to_string()),
+            message: "The TTL must be a positive whole number.",
+        }),
+    }
+}
+
+// x-amz-expiration as S3 sends it for lifecycle expiry; a TTL has no rule, so
+// rule-id names the TTL instead
+fn expiration_header(expires_at: &DateTime<Utc>) -> String {
+    format!("expiry-date=\"{}\", rule-id=\"object-ttl\"", http_date(expires_at))
+}
@@ -0,0 +1,571 @@
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+
+use ghostbay_auth::{policy::actions, AuthContext, ChunkSigner};
+use ghostbay_catalog::{
+    CreateObjectRequest, MultipartPartRepository, MultipartUpload, MultipartUploadRepository, ObjectPart, ObjectPartRepository,
+    ObjectRepository, ObjectTagRepository,
+};
+use ghostbay_engine::{CompleteMultipartUploadRequest, CreateMultipartUploadRequest, GetObjectRequest, MultipartUploadPart, UploadPartRequest};
+
+use super::{
+    archive_current_version, authorize, check_bucket_quota, copy_source, etag_response, http_date, insert_checksum, is_expired,
+    missing_blob, read_body, resolve_bucket, storage_location, store_object, system_metadata, user_metadata, version_id,
+};
+use crate::{
+    error::{ApiError, ApiResult},
+    metrics::ACTIVE_MULTIPART_UPLOADS,
+    responses::*,
+    upload::{upload_body, upload_error, with_quota, UploadBody},
+    AppState,
+};
+
+// Names the rule behind x-amz-abort-date; uploads left incomplete expire after a fixed period
+const UPLOAD_EXPIRY_RULE_ID: &str = "ghostbay-incomplete-upload-expiry";
+
+// Largest pages ListParts, ListMultipartUploads and GetObjectAttributes
+// return, as in S3
+pub(super) const MAX_LISTED_PARTS: u32 = 1000;
+const MAX_LISTED_UPLOADS: u32 = 1000;
+
+pub async fn create_multipart_upload(
+    Path((bucket_name, key)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<(HeaderMap, XmlResponse<crate::responses::InitiateMultipartUploadResponse>)> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+
+    let content_type = headers
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("binary/octet-stream")
+        .to_string();
+
+    let metadata = user_metadata(&headers)?;
+    let storage_request = CreateMultipartUploadRequest {
+        bucket: bucket_name.clone(),
+        key: key.clone(),
+        content_type: content_type.clone(),
+        metadata: metadata.clone(),
+    };
+
+    let upload_id = state.storage.create_multipart_upload(storage_request).await
+        .map_err(|e| ApiError::Storage(e.to_string()))?;
+
+    // Store upload in database
+    let multipart_repo = MultipartUploadRepository::new(state.catalog.pool().clone());
+    let multipart_upload = multipart_repo.create(bucket.id, &key, &upload_id, &content_type, metadata, system_metadata(&headers)).await?;
+    ACTIVE_MULTIPART_UPLOADS.inc();
+
+    let mut response_headers = HeaderMap::new();
+    if let Some(expires_at) = multipart_upload.expires_at {
+        let abort_date = http_date(&expires_at);
+        response_headers.insert("x-amz-abort-date", abort_date.parse().map_err(anyhow::Error::from)?);
+        response_headers.insert("x-amz-abort-rule-id", axum::http::HeaderValue::from_static(UPLOAD_EXPIRY_RULE_ID));
+    }
+
+    let response = crate::responses::InitiateMultipartUploadResponse {
+        bucket: bucket_name,
+        key,
+        upload_id,
+    };
+
+    Ok((response_headers, XmlResponse(response)))
+}
+
+pub async fn upload_part(
+    Path((bucket_name, key)): Path<(String, String)>,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+    State(state): State<AppState>,
+    chunk_signer: Option<Extension<ChunkSigner>>,
+    headers: HeaderMap,
+    body: Body,
+) -> ApiResult<Response> {
+    let (upload, part_number) = part_target(&state, &params).await?;
+    let upload_id = &upload.upload_id;
+    let UploadBody { stream, content_length, received, checksum } =
+        upload_body(body, &headers, chunk_signer.map(|Extension(signer)| signer), Some(state.multipart.max_part_size))?;
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let quota = check_bucket_quota(&state, &bucket, None, content_length.unwrap_or_default()).await?;
+
+    let storage_request = UploadPartRequest {
+        bucket: bucket_name,
+        key,
+        upload_id: upload_id.clone(),
+        part_number,
+        data: with_quota(stream, quota),
+    };
+
+    let _part_lock = state.part_locks.lock(upload_id, part_number).await;
+    let etag = state.storage.upload_part(storage_request).await.map_err(upload_error)?;
+    let body_len = received.load(std::sync::atomic::Ordering::Relaxed) as i64;
+
+    // Store part in database
+    let part_repo = MultipartPartRepository::new(state.catalog.pool().clone());
+    let storage_path = format!("{}/part_{:05}", upload_id, part_number);
+    let digest = state.storage.etag_algorithm().part_digest(&etag);
+    let _part = part_repo.create(upload.id, part_number, etag.clone(), digest, body_len, storage_path).await?;
+
+    let mut response = etag_response(&etag)?;
+    insert_checksum(&mut response, checksum.get())?;
+    Ok(response)
+}
+
+// UploadPartCopy: an UploadPart carrying x-amz-copy-source takes the part's
+// data from an existing object, or the byte range of it that
+// x-amz-copy-source-range names, instead of from the body
+pub async fn upload_part_copy(
+    Path((bucket_name, key)): Path<(String, String)>,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+    State(state): State<AppState>,
+    auth: Option<Extension<AuthContext>>,
+    headers: HeaderMap,
+) -> ApiResult<XmlResponse<CopyPartResult>> {
+    let (upload, part_number) = part_target(&state, &params).await?;
+    let (source_bucket_name, source_key) = copy_source(&headers)?;
+
+    let source_bucket = resolve_bucket(&state, &source_bucket_name).await?;
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    // Dispatch authorized the write; the source is read under its own bucket's policy
+    let auth = auth.map(|Extension(auth)| auth);
+    authorize(&state, auth.as_ref(), &source_bucket, actions::GET_OBJECT, Some(&source_key)).await?;
+
+    let source = ObjectRepository::new(state.catalog.pool().clone())
+        .find_by_bucket_and_key(source_bucket.id, &source_key)
+        .await?
+        .filter(|source| !is_expired(source))
+        .ok_or_else(|| ApiError::ObjectNotFound(source_key.clone()))?;
+    let range = match headers.get("x-amz-copy-source-range") {
+        Some(range) => Some(parse_copy_source_range(range.to_str().unwrap_or_default(), source.size as u64)?),
+        None => None,
+    };
+    let size = range.map_or(source.size as u64, |(first, last)| last - first + 1);
+    if size > state.multipart.max_part_size {
+        return Err(ApiError::EntityTooLarge { size, max_size: state.multipart.max_part_size });
+    }
+    check_bucket_quota(&state, &bucket, None, size).await?;
+
+    let (storage_bucket, storage_key) = storage_location(&source.storage_path)?;
+    let get_request = GetObjectRequest {
+        bucket: storage_bucket.to_string(),
+        key: storage_key.to_string(),
+        range: range.map(|(first, last)| (first, Some(last))),
+    };
+    let storage_response = state.storage
+        .get_object(get_request)
+        .await
+        .map_err(|e| ApiError::Storage(e.to_string()))?;
+    let Some(storage_response) = storage_response else {
+        return Err(missing_blob(&state, &source_bucket_name, &source).await);
+    };
+
+    let storage_request = UploadPartRequest {
+        bucket: bucket_name,
+        key,
+        upload_id: upload.upload_id.clone(),
+        part_number,
+        data: storage_response.data,
+    };
+    let _part_lock = state.part_locks.lock(&upload.upload_id, part_number).await;
+    let etag = state.storage.upload_part(storage_request).await.map_err(upload_error)?;
+
+    let part_repo = MultipartPartRepository::new(state.catalog.pool().clone());
+    let storage_path = format!("{}/part_{:05}", upload.upload_id, part_number);
+    let digest = state.storage.etag_algorithm().part_digest(&etag);
+    let part = part_repo.create(upload.id, part_number, etag.clone(), digest, size as i64, storage_path).await?;
+
+    Ok(XmlResponse(CopyPartResult {
+        etag: format!("\"{}\"", etag),
+        last_modified: part.created_at,
+    }))
+}
+
+// x-amz-copy-source-range is `bytes=first-last`, zero-based and inclusive,
+// and must lie within the source object
+fn parse_copy_source_range(range: &str, size: u64) -> ApiResult<(u64, u64)> {
+    let invalid = |message| ApiError::InvalidArgument {
+        name: "x-amz-copy-source-range".to_string(),
+        value: Some(range.to_string()),
+        message,
+    };
+    let (first, last) = range
+        .strip_prefix("bytes=")
+        .and_then(|spec| spec.split_once('-'))
+        .and_then(|(first, last)| Some((first.parse::<u64>().ok()?, last.parse::<u64>().ok()?)))
+        .ok_or_else(|| {
+            invalid("The x-amz-copy-source-range value must be of the form bytes=first-last where first and last are the zero-based offsets of the first and last bytes to copy")
+        })?;
+    if first > last || last >= size {
+        return Err(invalid("Range specified is not valid for source object"));
+    }
+    Ok((first, last))
+}
+
+// The upload and part number an UploadPart or UploadPartCopy writes to
+async fn part_target(state: &AppState, params: &std::collections::HashMap<String, String>) -> ApiResult<(MultipartUpload, i32)> {
+    let upload_id = params.get("uploadId")
+        .ok_or_else(|| ApiError::InvalidArgument {
+            name: "uploadId".to_string(),
+            value: None,
+            message: "The uploadId parameter is required.",
+        })?;
+    
+    let raw_part_number = params.get("partNumber")
+        .ok_or_else(|| ApiError::InvalidArgument {
+            name: "partNumber".to_string(),
+            value: None,
+            message: "The partNumber parameter is required.",
+        })?;
+    let part_number: i32 = raw_part_number
+        .parse()
+        .ok()
+        .filter(|n| *n >= 1)
+        .ok_or_else(|| ApiError::InvalidArgument {
+            name: "partNumber".to_string(),
+            value: Some(raw_part_number.clone()),
+            message: "Part number must be a positive integer.",
+        })?;
+
+    // Verify upload exists
+    let multipart_repo = MultipartUploadRepository::new(state.catalog.pool().clone());
+    let upload = multipart_repo.find_by_upload_id(upload_id).await?
+        .ok_or_else(|| ApiError::NoSuchUpload(upload_id.clone()))?;
+
+    // Past the last part number the upload cannot grow, but it is not lost:
+    // the error tells the client to complete it with what it has or abort it
+    if part_number > state.multipart.max_part_count {
+        return Err(ApiError::PartCountExhausted {
+            upload_id: upload_id.clone(),
+            part_number,
+            max_part_count: state.multipart.max_part_count,
+        });
+    }
+    Ok((upload, part_number))
+}
+
+pub async fn complete_multipart_upload(
+    Path((bucket_name, key)): Path<(String, String)>,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+    State(state): State<AppState>,
+    body: Body,
+) -> ApiResult<XmlResponse<crate::responses::CompleteMultipartUploadResponse>> {
+    let request = parse_complete_request(&read_body(body).await?)?;
+
+    let upload_id = params.get("uploadId")
+        .ok_or_else(|| ApiError::InvalidArgument {
+            name: "uploadId".to_string(),
+            value: None,
+            message: "The uploadId parameter is required.",
+        })?;
+
+    // Verify upload exists and get bucket
+    let multipart_repo = MultipartUploadRepository::new(state.catalog.pool().clone());
+    let upload = multipart_repo.find_by_upload_id(upload_id).await?
+        .ok_or_else(|| ApiError::NoSuchUpload(upload_id.clone()))?;
+
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+
+    if upload.bucket_id != bucket.id || upload.object_key != key {
+        return Err(ApiError::NoSuchUpload(upload_id.clone()));
+    }
+
+    // Every part but the last must meet the minimum size, and the object is
+    // only as large as the parts it was completed with
+    let part_repo = MultipartPartRepository::new(state.catalog.pool().clone());
+    let parts_list = part_repo.list_by_upload(upload.id).await?;
+    let mut requested: Vec<_> = request.complete_multipart_upload.part.iter().map(|p| p.part_number).collect();
+    requested.sort_unstable();
+    let mut total_size: i64 = 0;
+    let mut object_parts = Vec::with_capacity(requested.len());
+    for (index, part_number) in requested.iter().enumerate() {
+        let Some(part) = parts_list.iter().find(|p| p.part_number == *part_number) else {
+            continue;
+        };
+        let min_size = state.multipart.min_part_size;
+        if index + 1 < requested.len() && (part.size as u64) < min_size {
+            return Err(ApiError::EntityTooSmall { part_number: Some(*part_number), size: part.size as u64, min_size });
+        }
+        total_size += part.size;
+        object_parts.push(ObjectPart { part_number: *part_number, size: part.size });
+    }
+
+    // Convert request parts to storage format. Recorded parts are passed as
+    // stored, so the object's ETag comes from their digests rather than the
+    // ETags the client echoes back.
+    let parts: Vec<MultipartUploadPart> = request.complete_multipart_upload.part
+        .into_iter()
+        .map(|p| {
+            let recorded = parts_list.iter().find(|part| part.part_number == p.part_number);
+            MultipartUploadPart {
+                part_number: p.part_number,
+                etag: recorded.map_or_else(|| p.etag.trim_matches('"').to_string(), |part| part.etag.clone()),
+                size: 0, // Size will be determined by storage engine
+                digest: recorded.and_then(|part| part.digest.clone()),
+            }
+        })
+        .collect();
+
+    archive_current_version(&state, &bucket, &key).await?;
+
+    let storage_request = CompleteMultipartUploadRequest {
+        bucket: bucket_name.clone(),
+        key: key.clone(),
+        upload_id: upload_id.clone(),
+        parts,
+    };
+
+    let etag = state.storage.complete_multipart_upload(storage_request).await
+        .map_err(|e| ApiError::Storage(e.to_string()))?;
+
+    // Create object record in catalog, with the content type and metadata
+    // the upload was started with
+    let storage_path = format!("{}/{}", bucket_name, key);
+    let parse = |json: Option<&str>| json.map(serde_json::from_str).transpose().map_err(anyhow::Error::from);
+    let metadata = parse(upload.metadata.as_deref())?;
+    let system_metadata = parse(upload.system_metadata.as_deref())?;
+    
+    let create_request = CreateObjectRequest {
+        bucket_id: bucket.id,
+        key: key.clone(),
+        content_type: upload.content_type.clone().unwrap_or_else(|| "binary/octet-stream".to_string()),
+        size: total_size,
+        storage_path,
+        metadata,
+        etag_algorithm: state.storage.etag_algorithm().as_str().to_string(),
+        content_encoding: None,
+        checksum_algorithm: None,
+        checksum_value: None,
+        system_metadata,
+        // TTLs are only taken on single-request uploads
+        expires_at: None,
+    };
+
+    let object = store_object(&state, create_request, etag.clone()).await?;
+    ObjectTagRepository::new(state.catalog.pool().clone())
+        .replace(bucket.id, &key, &[])
+        .await?;
+    // Kept for GetObjectAttributes once the upload's part records are gone
+    ObjectPartRepository::new(state.catalog.pool().clone())
+        .create(bucket.id, version_id(&object), &object_parts)
+        .await?;
+
+    // Clean up multipart upload records
+    part_repo.delete_by_upload(upload.id).await?;
+    if multipart_repo.delete(upload_id).await? {
+        ACTIVE_MULTIPART_UPLOADS.dec();
+    }
+
+    let location = format!("https://{}.s3.amazonaws.com/{}", bucket_name, key);
+    let response = crate::responses::CompleteMultipartUploadResponse {
+        location,
+        bucket: bucket_name,
+        key,
+        etag: format!("\"{}\"", etag),
+    };
+
+    Ok(XmlResponse(response))
+}
+
+pub async fn abort_multipart_upload(
+    Path((bucket_name, key)): Path<(String, String)>,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let upload_id = params.get("uploadId")
+        .ok_or_else(|| ApiError::InvalidArgument {
+            name: "uploadId".to_string(),
+            value: None,
+            message: "The uploadId parameter is required.",
+        })?;
+
+    // Verify upload exists
+    let multipart_repo = MultipartUploadRepository::new(state.catalog.pool().clone());
+    let upload = multipart_repo.find_by_upload_id(upload_id).await?
+        .ok_or_else(|| ApiError::NoSuchUpload(upload_id.clone()))?;
+
+    abort_upload(&state, &bucket_name, &key, &upload).await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+// Drops an upload's parts from storage and its records from the catalog
+pub(super) async fn abort_upload(state: &AppState, bucket_name: &str, key: &str, upload: &MultipartUpload) -> ApiResult<()> {
+    state.storage.abort_multipart_upload(bucket_name, key, &upload.upload_id).await
+        .map_err(|e| ApiError::Storage(e.to_string()))?;
+
+    let part_repo = MultipartPartRepository::new(state.catalog.pool().clone());
+    part_repo.delete_by_upload(upload.id).await?;
+    if MultipartUploadRepository::new(state.catalog.pool().clone()).delete(&upload.upload_id).await? {
+        ACTIVE_MULTIPART_UPLOADS.dec();
+    }
+    Ok(())
+}
+
+pub async fn list_parts(
+    Path((bucket_name, key)): Path<(String, String)>,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> ApiResult<XmlResponse<ListPartsResponse>> {
+    let upload_id = params.get("uploadId")
+        .ok_or_else(|| ApiError::InvalidArgument {
+            name: "uploadId".to_string(),
+            value: None,
+            message: "The uploadId parameter is required.",
+        })?;
+
+    let max_parts: u32 = match params.get("max-parts") {
+        Some(raw) => raw.parse().map_err(|_| ApiError::InvalidArgument {
+            name: "max-parts".to_string(),
+            value: Some(raw.clone()),
+            message: "Provided max-parts not an integer or within integer range",
+        })?,
+        None => MAX_LISTED_PARTS,
+    };
+    let max_parts = max_parts.min(MAX_LISTED_PARTS);
+
+    let part_number_marker: i32 = match params.get("part-number-marker") {
+        Some(raw) => raw.parse().ok().filter(|n| *n >= 0).ok_or_else(|| ApiError::InvalidArgument {
+            name: "part-number-marker".to_string(),
+            value: Some(raw.clone()),
+            message: "Provided part-number-marker not a non-negative integer",
+        })?,
+        None => 0,
+    };
+
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+
+    let multipart_repo = MultipartUploadRepository::new(state.catalog.pool().clone());
+    let upload = multipart_repo.find_by_upload_id(upload_id).await?
+        .filter(|upload| upload.bucket_id == bucket.id && upload.object_key == key)
+        .ok_or_else(|| ApiError::NoSuchUpload(upload_id.clone()))?;
+
+    let part_repo = MultipartPartRepository::new(state.catalog.pool().clone());
+    let mut remaining = part_repo.list_by_upload(upload.id).await?
+        .into_iter()
+        .filter(|part| part.part_number > part_number_marker)
+        .peekable();
+
+    let parts: Vec<ListedPart> = remaining
+        .by_ref()
+        .take(max_parts as usize)
+        .map(|part| ListedPart {
+            part_number: part.part_number,
+            last_modified: part.created_at,
+            etag: format!("\"{}\"", part.etag),
+            size: part.size as u64,
+        })
+        .collect();
+    let is_truncated = remaining.peek().is_some();
+    let next_part_number_marker = if is_truncated {
+        parts.last().map(|part| part.part_number)
+    } else {
+        None
+    };
+
+    let response = ListPartsResponse {
+        bucket: bucket_name,
+        key,
+        upload_id: upload_id.clone(),
+        storage_class: "STANDARD".to_string(),
+        part_number_marker,
+        next_part_number_marker,
+        max_parts,
+        is_truncated,
+        part: parts,
+    };
+
+    Ok(XmlResponse(response))
+}
+
+pub async fn list_multipart_uploads(
+    Path(bucket_name): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+    State(state): State<AppState>,
+) -> ApiResult<XmlResponse<ListMultipartUploadsResponse>> {
+    let max_uploads: u32 = match params.get("max-uploads") {
+        Some(raw) => raw.parse().map_err(|_| ApiError::InvalidArgument {
+            name: "max-uploads".to_string(),
+            value: Some(raw.clone()),
+            message: "Provided max-uploads not an integer or within integer range",
+        })?,
+        None => MAX_LISTED_UPLOADS,
+    };
+    let max_uploads = max_uploads.min(MAX_LISTED_UPLOADS);
+
+    let prefix = params.get("prefix").cloned().unwrap_or_default();
+    let key_marker = params.get("key-marker").filter(|marker| !marker.is_empty());
+    // S3 ignores the upload id marker unless a key marker comes with it
+    let upload_id_marker = params.get("upload-id-marker").filter(|_| key_marker.is_some());
+
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+
+    let multipart_repo = MultipartUploadRepository::new(state.catalog.pool().clone());
+    let mut uploads = multipart_repo
+        .list_by_bucket(
+            bucket.id,
+            &prefix,
+            key_marker.map(String::as_str),
+            upload_id_marker.map(String::as_str),
+            max_uploads as i32,
+        )
+        .await?;
+
+    let is_truncated = uploads.len() > max_uploads as usize;
+    uploads.truncate(max_uploads as usize);
+    let (next_key_marker, next_upload_id_marker) = match uploads.last() {
+        Some(last) if is_truncated => (Some(last.object_key.clone()), Some(last.upload_id.clone())),
+        _ => (None, None),
+    };
+
+    let owner = || Owner {
+        id: "ghostbay".to_string(),
+        display_name: "GhostBay".to_string(),
+    };
+    let upload = uploads
+        .into_iter()
+        .map(|upload| UploadInfo {
+            key: upload.object_key,
+            upload_id: upload.upload_id,
+            initiator: owner(),
+            owner: owner(),
+            storage_class: "STANDARD".to_string(),
+            initiated: upload.created_at,
+        })
+        .collect();
+
+    let response = ListMultipartUploadsResponse {
+        bucket: bucket_name,
+        key_marker: key_marker.cloned().unwrap_or_default(),
+        upload_id_marker: upload_id_marker.cloned().unwrap_or_default(),
+        next_key_marker,
+        next_upload_id_marker,
+        prefix,
+        max_uploads,
+        is_truncated,
+        upload,
+    };
+
+    Ok(XmlResponse(response))
+}
+
+// SDKs send the S3 XML document; the JSON form is still accepted from older clients
+fn parse_complete_request(bytes: &[u8]) -> ApiResult<crate::responses::CompleteMultipartUploadRequest> {
+    let parsed = if bytes.trim_ascii_start().starts_with(b"<") {
+        std::str::from_utf8(bytes)
+            .map_err(|e| e.to_string())
+            .and_then(|body| quick_xml::de::from_str(body).map_err(|e| e.to_string()))
+            .map(|data| crate::responses::CompleteMultipartUploadRequest { complete_multipart_upload: data })
+    } else {
+        serde_json::from_slice(bytes).map_err(|e| e.to_string())
+    };
+
+    parsed.map_err(|e| {
+        tracing::debug!("Invalid CompleteMultipartUpload body: {}", e);
+        ApiError::BadRequest("The request body is not well-formed.".to_string())
+    })
+}
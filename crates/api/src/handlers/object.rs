@@ -0,0 +1,1007 @@
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use std::collections::HashMap;
+
+use ghostbay_auth::{policy::actions, post_policy::PostPolicy, AuthContext, ChunkSigner, PostPolicyValidationRequest};
+use ghostbay_catalog::{
+    Bucket, CreateObjectRequest, Object, ObjectPartRepository, ObjectRepository, ObjectTagRepository, ObjectVersionRepository,
+};
+use ghostbay_engine::{ByteStream, GetObjectRequest, PutObjectRequest};
+use uuid::Uuid;
+
+use super::{
+    archive_current_version, authorize, check_bucket_quota, copy_source, etag_response, expiration_header, http_date, insert_checksum, is_archived, is_expired,
+    missing_blob, multipart::MAX_LISTED_PARTS, object_expiry, parse_xml_body, read_body, resolve_bucket, SYSTEM_METADATA_HEADERS, storage_location, store_object, system_metadata, user_metadata, validate_tag_set, version_id, with_system_metadata, with_user_metadata,
+};
+use crate::{
+    checksum::{Checksum, ChecksumAlgorithm},
+    encoding::{decode_body, stored_content_encoding, UploadEncoding},
+    error::{ApiError, ApiResult},
+    extractors::{ObjectVersionQuery, PartNumberQuery, ResponseHeaderOverrides},
+    form::{form_boundary, read_post_form},
+    responses::*,
+    upload::{upload_body, upload_error, upload_stream, with_min_size, with_quota, UploadBody, UploadError},
+    AppState,
+};
+
+pub async fn put_object(
+    Path((bucket_name, key)): Path<(String, String)>,
+    State(state): State<AppState>,
+    chunk_signer: Option<Extension<ChunkSigner>>,
+    headers: HeaderMap,
+    body: Body,
+) -> ApiResult<Response> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let tags = request_tags(&headers)?;
+    let metadata = user_metadata(&headers)?;
+    let expires_at = object_expiry(&headers)?;
+    let chunk_signer = chunk_signer.map(|Extension(signer)| signer);
+    let UploadBody { stream, content_length, received, checksum } = upload_body(body, &headers, chunk_signer, None)?;
+    // Encoded uploads count at their size as sent
+    let quota = check_bucket_quota(&state, &bucket, Some(&key), content_length.unwrap_or_default()).await?;
+    let stream = with_quota(stream, quota);
+
+    let content_type = headers
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("binary/octet-stream")
+        .to_string();
+
+    // Encoded bodies are stored as sent and served with their Content-Encoding,
+    // unless the bucket inflates gzip and zstd uploads before storing them
+    let content_encoding = stored_content_encoding(&headers);
+    let decode = content_encoding
+        .as_deref()
+        .filter(|_| bucket.decompress_on_upload)
+        .and_then(UploadEncoding::parse);
+
+    let (stream, content_length, decoded) = match decode {
+        Some(encoding) => {
+            let decoded = decode_body(encoding, stream, content_length);
+            (decoded.stream, None, Some(decoded.decoded))
+        }
+        None => (stream, content_length, None),
+    };
+
+    archive_current_version(&state, &bucket, &key).await?;
+
+    let storage_request = PutObjectRequest {
+        bucket: bucket_name.clone(),
+        key: key.clone(),
+        content_type: content_type.clone(),
+        content_length,
+        data: stream,
+    };
+
+    let etag = state.storage.put_object(storage_request).await.map_err(upload_error)?;
+    let size = decoded.unwrap_or(received).load(std::sync::atomic::Ordering::Relaxed);
+
+    // Store metadata in catalog
+    let storage_path = format!("{}/{}", bucket_name, key);
+    // A checksum describes the bytes as sent, so inflated uploads keep none
+    let stored_checksum = checksum.get().filter(|_| decode.is_none());
+    
+    let create_request = CreateObjectRequest {
+        bucket_id: bucket.id,
+        key: key.clone(),
+        content_type,
+        size: size as i64,
+        storage_path,
+        metadata,
+        etag_algorithm: state.storage.etag_algorithm().as_str().to_string(),
+        content_encoding: if decode.is_some() { None } else { content_encoding },
+        checksum_algorithm: stored_checksum.map(|checksum| checksum.algorithm.as_str().to_string()),
+        checksum_value: stored_checksum.map(|checksum| checksum.value.clone()),
+        system_metadata: system_metadata(&headers),
+        expires_at,
+    };
+
+    let object = store_object(&state, create_request, etag.clone()).await?;
+    ObjectTagRepository::new(state.catalog.pool().clone())
+        .replace(bucket.id, &key, &tags)
+        .await?;
+
+    let mut response = etag_response(&etag)?;
+    insert_checksum(&mut response, checksum.get())?;
+    if let Some(expires_at) = &object.expires_at {
+        response.headers_mut().insert("x-amz-expiration", expiration_header(expires_at).parse().map_err(anyhow::Error::from)?);
+    }
+    if bucket.versioning_enabled {
+        response.headers_mut().insert("x-amz-version-id", version_id(&object).to_string().parse().map_err(anyhow::Error::from)?);
+    }
+    Ok(response)
+}
+
+// POST Object: a browser upload, posted to the bucket as multipart/form-data.
+// The form authenticates itself: x-amz-signature signs its policy field with
+// the key in x-amz-credential, and the policy has to allow the other fields.
+// A form without a policy is anonymous, for buckets that allow anonymous
+// writes. Key quotas only apply to header-signed requests, so not here.
+pub async fn post_object(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Body,
+) -> ApiResult<Response> {
+    let boundary = form_boundary(&headers).ok_or(ApiError::MalformedPostRequest("the body is not multipart/form-data"))?;
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let body: ByteStream = Box::pin(
+        body.into_data_stream()
+            .map(|chunk| chunk.map_err(|e| UploadError::Read(e.to_string()).into())),
+    );
+    let form = read_post_form(body, &boundary).await.map_err(upload_error)?;
+    let field = |name: &str| form.fields.get(name).map(String::as_str);
+
+    let key = field("key").ok_or_else(|| ApiError::InvalidArgument {
+        name: "key".to_string(),
+        value: None,
+        message: "Bucket POST must contain a field named 'key'.  If it is specified, please check the order of the fields.",
+    })?;
+    // ${filename} stands for the name of the file the browser sent
+    let key = key.replace("${filename}", form.file_name.as_deref().unwrap_or_default());
+    if key.is_empty() {
+        return Err(ApiError::InvalidArgument {
+            name: "key".to_string(),
+            value: None,
+            message: "User key must have a length greater than 0.",
+        });
+    }
+    ghostbay_engine::validate_storage_key(&key).map_err(|_| ApiError::InvalidObjectKey(key.clone()))?;
+
+    let (auth, size_range) = match field("policy") {
+        Some(policy) => {
+            let document = BASE64
+                .decode(policy)
+                .map_err(|_| ApiError::InvalidPolicyDocument("Invalid Policy: Invalid Base64 Encoding.".to_string()))?;
+            let post_policy = PostPolicy::parse(&document).map_err(|e| ApiError::InvalidPolicyDocument(e.to_string()))?;
+            let required = |name: &'static str| {
+                field(name).map(str::to_string).ok_or_else(|| ApiError::InvalidArgument {
+                    name: name.to_string(),
+                    value: None,
+                    message: "Bucket POST with a policy must contain the x-amz-algorithm, x-amz-credential, x-amz-date and x-amz-signature fields.",
+                })
+            };
+            let request = PostPolicyValidationRequest {
+                algorithm: required("x-amz-algorithm")?,
+                credential: required("x-amz-credential")?,
+                date: required("x-amz-date")?,
+                policy: policy.to_string(),
+                signature: required("x-amz-signature")?,
+            };
+            let auth = state.auth.validate_post_policy(&request).await.map_err(|e| {
+                tracing::debug!("Rejected POST upload signed by {}: {}", request.credential, e);
+                ApiError::AuthorizationFailed(e.to_string())
+            })?;
+            post_policy
+                .check(&bucket_name, &form.fields, Utc::now())
+                .map_err(|e| ApiError::PostPolicyDenied(format!("Invalid according to Policy: {}", e)))?;
+            (Some(auth), post_policy.content_length_range())
+        }
+        None => (None, None),
+    };
+    authorize(&state, auth.as_ref(), &bucket, actions::PUT_OBJECT, Some(&key)).await?;
+    // The file's size is only known once it has been read
+    let quota = check_bucket_quota(&state, &bucket, Some(&key), 0).await?;
+
+    let headers = form_headers(&form.fields);
+    let file = match size_range {
+        Some((min_size, _)) if min_size > 0 => with_min_size(form.file, min_size),
+        _ => form.file,
+    };
+    let UploadBody { stream, received, checksum, .. } =
+        upload_stream(file, &headers, None, size_range.map(|(_, max_size)| max_size))?;
+    let stream = with_quota(stream, quota);
+    let content_type = field("content-type")
+        .or(form.file_content_type.as_deref())
+        .unwrap_or("binary/octet-stream")
+        .to_string();
+    let metadata = user_metadata(&headers)?;
+    let expires_at = object_expiry(&headers)?;
+
+    archive_current_version(&state, &bucket, &key).await?;
+
+    let storage_request = PutObjectRequest {
+        bucket: bucket_name.clone(),
+        key: key.clone(),
+        content_type: content_type.clone(),
+        content_length: None,
+        data: stream,
+    };
+    let etag = state.storage.put_object(storage_request).await.map_err(upload_error)?;
+
+    let stored_checksum = checksum.get();
+    let create_request = CreateObjectRequest {
+        bucket_id: bucket.id,
+        key: key.clone(),
+        content_type,
+        size: received.load(std::sync::atomic::Ordering::Relaxed) as i64,
+        storage_path: format!("{}/{}", bucket_name, key),
+        metadata,
+        etag_algorithm: state.storage.etag_algorithm().as_str().to_string(),
+        content_encoding: None,
+        checksum_algorithm: stored_checksum.map(|checksum| checksum.algorithm.as_str().to_string()),
+        checksum_value: stored_checksum.map(|checksum| checksum.value.clone()),
+        system_metadata: system_metadata(&headers),
+        expires_at,
+    };
+    let object = store_object(&state, create_request, etag.clone()).await?;
+    ObjectTagRepository::new(state.catalog.pool().clone())
+        .replace(bucket.id, &key, &[])
+        .await?;
+
+    // success_action_status picks the response: 201 with a PostResponse
+    // document, an empty 200, or by default an empty 204
+    let location = format!("https://{}.s3.amazonaws.com/{}", bucket_name, key);
+    let mut response = match field("success_action_status") {
+        Some("201") => (
+            StatusCode::CREATED,
+            XmlResponse(PostResponse {
+                location: location.clone(),
+                bucket: bucket_name,
+                key,
+                etag: format!("\"{}\"", etag),
+            }),
+        )
+            .into_response(),
+        Some("200") => StatusCode::OK.into_response(),
+        _ => StatusCode::NO_CONTENT.into_response(),
+    };
+    let response_headers = response.headers_mut();
+    response_headers.insert("ETag", format!("\"{}\"", etag).parse().map_err(anyhow::Error::from)?);
+    response_headers.insert("Location", location.parse().map_err(anyhow::Error::from)?);
+    if bucket.versioning_enabled {
+        response_headers.insert("x-amz-version-id", version_id(&object).to_string().parse().map_err(anyhow::Error::from)?);
+    }
+    insert_checksum(&mut response, checksum.get())?;
+    Ok(response)
+}
+
+// The fields of a POST upload that a PUT would send as headers
+fn form_headers(fields: &HashMap<String, String>) -> HeaderMap {
+    fields
+        .iter()
+        .filter(|(name, _)| {
+            name.as_str() == "content-type"
+                || SYSTEM_METADATA_HEADERS.contains(&name.as_str())
+                || name.starts_with("x-amz-meta-")
+                || name.starts_with("x-amz-checksum-")
+                || matches!(name.as_str(), "x-ghostbay-ttl-seconds" | "x-amz-expiration-days")
+        })
+        .filter_map(|(name, value)| Some((name.parse().ok()?, value.parse().ok()?)))
+        .collect()
+}
+
+// CopyObject: a PUT carrying x-amz-copy-source duplicates an existing object.
+// x-amz-metadata-directive COPY (the default) keeps the source's content type
+// and metadata; REPLACE takes them from this request instead.
+pub async fn copy_object(
+    Path((bucket_name, key)): Path<(String, String)>,
+    State(state): State<AppState>,
+    auth: Option<Extension<AuthContext>>,
+    headers: HeaderMap,
+) -> ApiResult<XmlResponse<CopyObjectResult>> {
+    let (source_bucket_name, source_key) = copy_source(&headers)?;
+
+    let replace_metadata = match headers.get("x-amz-metadata-directive").map(|v| v.to_str().unwrap_or_default()) {
+        None | Some("COPY") => false,
+        Some("REPLACE") => true,
+        Some(other) => {
+            return Err(ApiError::InvalidArgument {
+                name: "x-amz-metadata-directive".to_string(),
+                value: Some(other.to_string()),
+                message: "Unknown metadata directive.",
+            });
+        }
+    };
+    if !replace_metadata && source_bucket_name == bucket_name && source_key == key {
+        return Err(ApiError::BadRequest(
+            "This copy request is illegal because it is trying to copy an object to itself without changing the object's metadata.".to_string(),
+        ));
+    }
+
+    let source_bucket = resolve_bucket(&state, &source_bucket_name).await?;
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    // Dispatch authorized the write; the source is read under its own bucket's policy
+    let auth = auth.map(|Extension(auth)| auth);
+    authorize(&state, auth.as_ref(), &source_bucket, actions::GET_OBJECT, Some(&source_key)).await?;
+
+    let object_repo = ObjectRepository::new(state.catalog.pool().clone());
+    let source = object_repo
+        .find_by_bucket_and_key(source_bucket.id, &source_key)
+        .await?
+        .filter(|source| !is_expired(source))
+        .ok_or_else(|| ApiError::ObjectNotFound(source_key.clone()))?;
+    // The copy is a new object; it only expires if this request gives a TTL
+    let expires_at = object_expiry(&headers)?;
+
+    // x-amz-tagging-directive works like the metadata directive, for tags
+    let tag_repo = ObjectTagRepository::new(state.catalog.pool().clone());
+    let tags = match headers.get("x-amz-tagging-directive").map(|v| v.to_str().unwrap_or_default()) {
+        None | Some("COPY") => tag_repo.get(source_bucket.id, &source_key).await?,
+        Some("REPLACE") => request_tags(&headers)?,
+        Some(other) => {
+            return Err(ApiError::InvalidArgument {
+                name: "x-amz-tagging-directive".to_string(),
+                value: Some(other.to_string()),
+                message: "Unknown tagging directive.",
+            });
+        }
+    };
+    check_bucket_quota(&state, &bucket, Some(&key), source.size as u64).await?;
+
+    archive_current_version(&state, &bucket, &key).await?;
+
+    let etag = state.storage
+        .copy_object(&source_bucket_name, &source_key, &bucket_name, &key)
+        .await
+        .map_err(|e| ApiError::Storage(e.to_string()))?;
+
+    let (content_type, metadata, content_encoding, system_metadata) = if replace_metadata {
+        let content_type = headers
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("binary/octet-stream")
+            .to_string();
+        (content_type, user_metadata(&headers)?, stored_content_encoding(&headers), system_metadata(&headers))
+    } else {
+        let parse = |json: Option<&str>| json.map(serde_json::from_str).transpose().map_err(anyhow::Error::from);
+        let metadata = parse(source.metadata.as_deref())?;
+        let system_metadata = parse(source.system_metadata.as_deref())?;
+        (source.content_type, metadata, source.content_encoding, system_metadata)
+    };
+
+    let create_request = CreateObjectRequest {
+        bucket_id: bucket.id,
+        key: key.clone(),
+        content_type,
+        size: source.size,
+        storage_path: format!("{}/{}", bucket_name, key),
+        metadata,
+        etag_algorithm: state.storage.etag_algorithm().as_str().to_string(),
+        content_encoding,
+        // The bytes are unchanged, so the source's checksum still holds
+        checksum_algorithm: source.checksum_algorithm,
+        checksum_value: source.checksum_value,
+        system_metadata,
+        expires_at,
+    };
+    let object = store_object(&state, create_request, etag.clone()).await?;
+    tag_repo.replace(bucket.id, &key, &tags).await?;
+
+    Ok(XmlResponse(CopyObjectResult {
+        etag: format!("\"{}\"", etag),
+        last_modified: object.updated_at,
+    }))
+}
+
+pub async fn get_object(
+    Path((bucket_name, key)): Path<(String, String)>,
+    Query(query): Query<ObjectVersionQuery>,
+    Query(part): Query<PartNumberQuery>,
+    Query(overrides): Query<ResponseHeaderOverrides>,
+    State(state): State<AppState>,
+    auth: Option<Extension<AuthContext>>,
+    headers: HeaderMap,
+) -> ApiResult<Response> {
+    let overrides = overrides.headers();
+    if !overrides.is_empty() && auth.is_none() {
+        return Err(ApiError::BadRequest(
+            "Request specific response headers cannot be used for anonymous GET requests.".to_string(),
+        ));
+    }
+    let part_number = requested_part(&part, &headers)?;
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let object = find_object(&state, &bucket, &key, query.version_id.as_deref()).await?;
+    let (storage_bucket, storage_key) = storage_location(&object.storage_path)?;
+
+    // Preconditions are decided before the range, as in S3
+    if let Some(response) = check_preconditions(&headers, &object)? {
+        return Ok(response);
+    }
+
+    let size = object.size as u64;
+    let (range, parts_count) = match part_number {
+        Some(part_number) => part_range(&state, &object, part_number).await?,
+        None => match headers.get("range").and_then(|v| v.to_str().ok()) {
+            Some(range) => (parse_range_header(range, size)?, None),
+            None => (None, None),
+        },
+    };
+
+    let get_request = GetObjectRequest {
+        bucket: storage_bucket.to_string(),
+        key: storage_key.to_string(),
+        range: range.map(|(start, end)| (start, Some(end))),
+    };
+
+    let storage_response = state.storage
+        .get_object(get_request)
+        .await
+        .map_err(|e| ApiError::Storage(e.to_string()))?;
+    let Some(storage_response) = storage_response else {
+        return Err(missing_blob(&state, &bucket_name, &object).await);
+    };
+
+    // Headers describe the catalog's record of the object, which is what PUT
+    // returned and what listings show; the engine only supplies the bytes
+    let mut response = Response::builder()
+        .header("Content-Type", &object.content_type)
+        .header("Accept-Ranges", "bytes")
+        .header("ETag", format!("\"{}\"", object.etag))
+        .header("Last-Modified", http_date(&object.updated_at));
+    response = with_user_metadata(with_system_metadata(response, &object), &object);
+    if let Some(content_encoding) = &object.content_encoding {
+        response = response.header("Content-Encoding", content_encoding);
+    }
+    // A stored checksum covers the whole object, not a range of it
+    if range.is_none()
+        && let Some(checksum) = Checksum::stored(object.checksum_algorithm.as_deref(), object.checksum_value.as_deref())
+    {
+        response = response.header(checksum.algorithm.header_name(), checksum.value);
+    }
+    if bucket.versioning_enabled || query.version_id.is_some() {
+        response = response.header("x-amz-version-id", version_id(&object).to_string());
+    }
+    if let Some(parts_count) = parts_count {
+        response = response.header("x-amz-mp-parts-count", parts_count.to_string());
+    }
+    if let Some(expires_at) = &object.expires_at {
+        response = response.header("x-amz-expiration", expiration_header(expires_at));
+    }
+    let tag_count = ObjectTagRepository::new(state.catalog.pool().clone()).get(bucket.id, &key).await?.len();
+    if tag_count > 0 {
+        response = response.header("x-amz-tagging-count", tag_count.to_string());
+    }
+    response = match range {
+        Some((start, end)) => response
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header("Content-Length", (end - start + 1).to_string())
+            .header("Content-Range", format!("bytes {}-{}/{}", start, end, size)),
+        None => response
+            .status(StatusCode::OK)
+            .header("Content-Length", size.to_string()),
+    };
+
+    // Convert the stream to a Body
+    let stream = storage_response.data.map(|result| {
+        result.map_err(std::io::Error::other)
+    });
+
+    let body = Body::from_stream(stream);
+    let mut response = response.body(body).map_err(anyhow::Error::from)?;
+    for (name, value) in overrides {
+        let header_value = value.parse().map_err(|_| ApiError::InvalidArgument {
+            name: format!("response-{}", name),
+            value: Some(value.to_string()),
+            message: "The value is not a valid header value.",
+        })?;
+        response.headers_mut().insert(name, header_value);
+    }
+
+    Ok(response)
+}
+
+pub async fn head_object(
+    Path((bucket_name, key)): Path<(String, String)>,
+    Query(query): Query<ObjectVersionQuery>,
+    Query(part): Query<PartNumberQuery>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<Response> {
+    let part_number = requested_part(&part, &headers)?;
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let object = find_object(&state, &bucket, &key, query.version_id.as_deref()).await?;
+    let (storage_bucket, storage_key) = storage_location(&object.storage_path)?;
+
+    // The engine is only asked whether the data is still there
+    let metadata = state.storage
+        .head_object(storage_bucket, storage_key)
+        .await
+        .map_err(|e| ApiError::Storage(e.to_string()))?;
+    if metadata.is_none() {
+        return Err(missing_blob(&state, &bucket_name, &object).await);
+    }
+    if let Some(response) = check_preconditions(&headers, &object)? {
+        return Ok(response);
+    }
+
+    let (range, parts_count) = match part_number {
+        Some(part_number) => part_range(&state, &object, part_number).await?,
+        None => (None, None),
+    };
+
+    let size = object.size as u64;
+    let mut response = match range {
+        Some((start, end)) => Response::builder()
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header("Content-Length", (end - start + 1).to_string())
+            .header("Content-Range", format!("bytes {}-{}/{}", start, end, size)),
+        None => Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Length", size.to_string()),
+    };
+    response = response
+        .header("Content-Type", &object.content_type)
+        .header("Accept-Ranges", "bytes")
+        .header("ETag", format!("\"{}\"", object.etag))
+        .header("Last-Modified", http_date(&object.updated_at));
+    response = with_user_metadata(with_system_metadata(response, &object), &object);
+    if let Some(content_encoding) = &object.content_encoding {
+        response = response.header("Content-Encoding", content_encoding);
+    }
+    if range.is_none()
+        && let Some(checksum) = Checksum::stored(object.checksum_algorithm.as_deref(), object.checksum_value.as_deref())
+    {
+        response = response.header(checksum.algorithm.header_name(), checksum.value);
+    }
+    if bucket.versioning_enabled || query.version_id.is_some() {
+        response = response.header("x-amz-version-id", version_id(&object).to_string());
+    }
+    if let Some(parts_count) = parts_count {
+        response = response.header("x-amz-mp-parts-count", parts_count.to_string());
+    }
+    if let Some(expires_at) = &object.expires_at {
+        response = response.header("x-amz-expiration", expiration_header(expires_at));
+    }
+    let tag_count = ObjectTagRepository::new(state.catalog.pool().clone()).get(bucket.id, &key).await?.len();
+    if tag_count > 0 {
+        response = response.header("x-amz-tagging-count", tag_count.to_string());
+    }
+
+    Ok(response.body(Body::empty()).map_err(anyhow::Error::from)?)
+}
+
+// GetObjectAttributes: GET /bucket/key?attributes. x-amz-object-attributes
+// lists the attributes wanted; names S3 may add later are ignored.
+pub async fn get_object_attributes(
+    Path((bucket_name, key)): Path<(String, String)>,
+    Query(query): Query<ObjectVersionQuery>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<Response> {
+    let attributes: Vec<&str> = headers
+        .get_all("x-amz-object-attributes")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect();
+    if attributes.is_empty() {
+        return Err(ApiError::InvalidArgument {
+            name: "x-amz-object-attributes".to_string(),
+            value: None,
+            message: "The x-amz-object-attributes header specifying the attributes to be retrieved is either missing or empty",
+        });
+    }
+    let wanted = |name: &str| attributes.contains(&name);
+
+    let max_parts: u32 = match headers.get("x-amz-max-parts").and_then(|v| v.to_str().ok()) {
+        Some(raw) => raw.parse().map_err(|_| ApiError::InvalidArgument {
+            name: "x-amz-max-parts".to_string(),
+            value: Some(raw.to_string()),
+            message: "Provided max-parts not an integer or within integer range",
+        })?,
+        None => MAX_LISTED_PARTS,
+    };
+    let max_parts = max_parts.min(MAX_LISTED_PARTS);
+    let part_number_marker: i32 = match headers.get("x-amz-part-number-marker").and_then(|v| v.to_str().ok()) {
+        Some(raw) => raw.parse().ok().filter(|n| *n >= 0).ok_or_else(|| ApiError::InvalidArgument {
+            name: "x-amz-part-number-marker".to_string(),
+            value: Some(raw.to_string()),
+            message: "Provided part-number-marker not a non-negative integer",
+        })?,
+        None => 0,
+    };
+
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let object = find_object(&state, &bucket, &key, query.version_id.as_deref()).await?;
+
+    let mut response = GetObjectAttributesResponse::default();
+    if wanted("ETag") {
+        response.etag = Some(object.etag.clone());
+    }
+    if wanted("Checksum")
+        && let Some(checksum) = Checksum::stored(object.checksum_algorithm.as_deref(), object.checksum_value.as_deref())
+    {
+        let mut stored = ObjectChecksum::default();
+        let slot = match checksum.algorithm {
+            ChecksumAlgorithm::Crc32 => &mut stored.crc32,
+            ChecksumAlgorithm::Crc32c => &mut stored.crc32c,
+            ChecksumAlgorithm::Crc64Nvme => &mut stored.crc64nvme,
+            ChecksumAlgorithm::Sha1 => &mut stored.sha1,
+            ChecksumAlgorithm::Sha256 => &mut stored.sha256,
+        };
+        *slot = Some(checksum.value);
+        response.checksum = Some(stored);
+    }
+    // Only objects completed from a multipart upload have parts
+    if wanted("ObjectParts") {
+        let parts = ObjectPartRepository::new(state.catalog.pool().clone()).list(version_id(&object)).await?;
+        if !parts.is_empty() {
+            let total_parts_count = parts.len();
+            let mut remaining = parts.into_iter().filter(|part| part.part_number > part_number_marker).peekable();
+            let listed: Vec<ObjectPartInfo> = remaining
+                .by_ref()
+                .take(max_parts as usize)
+                .map(|part| ObjectPartInfo { part_number: part.part_number, size: part.size as u64 })
+                .collect();
+            let is_truncated = remaining.peek().is_some();
+            response.object_parts = Some(ObjectParts {
+                total_parts_count,
+                part_number_marker,
+                next_part_number_marker: if is_truncated { listed.last().map(|part| part.part_number) } else { None },
+                max_parts,
+                is_truncated,
+                part: listed,
+            });
+        }
+    }
+    if wanted("StorageClass") {
+        response.storage_class = Some("STANDARD".to_string());
+    }
+    if wanted("ObjectSize") {
+        response.object_size = Some(object.size as u64);
+    }
+
+    let mut response = XmlResponse(response).into_response();
+    let headers = response.headers_mut();
+    headers.insert("Last-Modified", http_date(&object.updated_at).parse().map_err(anyhow::Error::from)?);
+    if bucket.versioning_enabled || query.version_id.is_some() {
+        headers.insert("x-amz-version-id", version_id(&object).to_string().parse().map_err(anyhow::Error::from)?);
+    }
+    Ok(response)
+}
+
+// ?partNumber, which addresses a part in place of a Range
+fn requested_part(query: &PartNumberQuery, headers: &HeaderMap) -> ApiResult<Option<i32>> {
+    let Some(raw) = query.part_number.as_deref() else {
+        return Ok(None);
+    };
+    let part_number = raw
+        .parse()
+        .ok()
+        .filter(|n| (1..=10000).contains(n))
+        .ok_or_else(|| ApiError::InvalidArgument {
+            name: "partNumber".to_string(),
+            value: Some(raw.to_string()),
+            message: "Part number must be an integer between 1 and 10000, inclusive",
+        })?;
+    if headers.contains_key("range") {
+        return Err(ApiError::BadRequest("Cannot specify both Range header and partNumber query parameter".to_string()));
+    }
+    Ok(Some(part_number))
+}
+
+// The bytes of one part of an object completed from a multipart upload, and
+// how many parts it has. Any other object is a single part, so part 1 is the
+// whole object, without a parts count.
+async fn part_range(state: &AppState, object: &Object, part_number: i32) -> ApiResult<(Option<(u64, u64)>, Option<usize>)> {
+    let parts = ObjectPartRepository::new(state.catalog.pool().clone()).list(version_id(object)).await?;
+    if parts.is_empty() {
+        return match part_number {
+            1 => Ok((None, None)),
+            _ => Err(ApiError::InvalidPartNumber { part_number, parts_count: 1 }),
+        };
+    }
+
+    let parts_count = parts.len();
+    // An empty last part has no bytes to serve
+    let Some(index) = parts.iter().position(|part| part.part_number == part_number && part.size > 0) else {
+        return Err(ApiError::InvalidPartNumber { part_number, parts_count });
+    };
+    let start = parts[..index].iter().map(|part| part.size as u64).sum::<u64>();
+    let end = start + parts[index].size as u64 - 1;
+    Ok((Some((start, end)), Some(parts_count)))
+}
+
+// The current object, or with ?versionId the version it names. An old version
+// can be read once its data is in the version store; versions overwritten
+// while the bucket was not versioned are gone, as are delete markers.
+async fn find_object(state: &AppState, bucket: &Bucket, key: &str, requested_version: Option<&str>) -> ApiResult<Object> {
+    let object_repo = ObjectRepository::new(state.catalog.pool().clone());
+    let current = object_repo.find_by_bucket_and_key(bucket.id, key).await?.filter(|current| !is_expired(current));
+    let Some(requested_version) = requested_version else {
+        return current.ok_or_else(|| ApiError::ObjectNotFound(key.to_string()));
+    };
+
+    let wanted = Uuid::parse_str(requested_version).map_err(|_| ApiError::InvalidArgument {
+        name: "versionId".to_string(),
+        value: Some(requested_version.to_string()),
+        message: "Invalid version id specified",
+    })?;
+    if let Some(current) = current.filter(|current| version_id(current) == wanted) {
+        return Ok(current);
+    }
+
+    let no_such_version = || ApiError::NoSuchVersion {
+        key: key.to_string(),
+        version_id: requested_version.to_string(),
+    };
+    let version = ObjectVersionRepository::new(state.catalog.pool().clone())
+        .find(bucket.id, key, wanted)
+        .await?
+        .ok_or_else(no_such_version)?;
+    if version.is_delete_marker || !is_archived(&version.storage_path) {
+        return Err(no_such_version());
+    }
+
+    Ok(Object {
+        id: version.id,
+        bucket_id: version.bucket_id,
+        key: version.key,
+        version_id: Some(version.version_id),
+        etag: version.etag,
+        etag_algorithm: version.etag_algorithm,
+        size: version.size,
+        content_type: version.content_type,
+        created_at: version.created_at,
+        updated_at: version.created_at,
+        storage_path: version.storage_path,
+        metadata: version.metadata,
+        content_encoding: version.content_encoding,
+        checksum_algorithm: version.checksum_algorithm,
+        checksum_value: version.checksum_value,
+        system_metadata: version.system_metadata,
+        expires_at: None,
+    })
+}
+
+
+pub async fn delete_object(
+    Path((bucket_name, key)): Path<(String, String)>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    remove_object(&state, &bucket, &key).await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+// DeleteObjects: POST /bucket?delete with up to 1000 keys. Each key succeeds or
+// fails on its own and is reported in the result; Quiet leaves out the keys
+// that were deleted.
+pub async fn delete_objects(
+    Path(bucket_name): Path<String>,
+    State(state): State<AppState>,
+    auth: Option<Extension<AuthContext>>,
+    body: Body,
+) -> ApiResult<XmlResponse<DeleteObjectsResult>> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    let body = read_body(body).await?;
+
+    let request: DeleteObjectsRequest = parse_xml_body(&body, "DeleteObjects")?;
+    if request.object.is_empty() || request.object.len() > 1000 {
+        return Err(ApiError::BadRequest(
+            "A DeleteObjects request must name between 1 and 1000 keys.".to_string(),
+        ));
+    }
+
+    let mut result = DeleteObjectsResult::default();
+    let auth = auth.map(|Extension(auth)| auth);
+    for object in request.object {
+        let removed = match authorize(&state, auth.as_ref(), &bucket, actions::DELETE_OBJECT, Some(&object.key)).await {
+            Ok(()) => remove_object(&state, &bucket, &object.key).await,
+            Err(e) => Err(e),
+        };
+        match removed {
+            Ok(()) => {
+                if !request.quiet {
+                    result.deleted.push(DeletedObject { key: object.key });
+                }
+            }
+            Err(e) => {
+                tracing::warn!(bucket = %bucket_name, key = %object.key, "DeleteObjects failed for key: {}", e);
+                result.errors.push(DeleteError {
+                    key: object.key,
+                    code: e.code().to_string(),
+                    message: e.message().to_string(),
+                });
+            }
+        }
+    }
+
+    Ok(XmlResponse(result))
+}
+
+// Deleting a key that does not exist succeeds, as in S3. In a versioned
+// bucket the deleted version is kept and a delete marker takes its place.
+pub(super) async fn remove_object(state: &AppState, bucket: &Bucket, key: &str) -> ApiResult<()> {
+    archive_current_version(state, bucket, key).await?;
+
+    // Delete from catalog first
+    let object_repo = ObjectRepository::new(state.catalog.pool().clone());
+    object_repo.delete(bucket.id, key).await?;
+    ObjectTagRepository::new(state.catalog.pool().clone())
+        .replace(bucket.id, key, &[])
+        .await?;
+
+    // Delete from storage
+    state.storage
+        .delete_object(&bucket.name, key)
+        .await
+        .map_err(|e| ApiError::Storage(e.to_string()))?;
+
+    Ok(())
+}
+
+pub async fn get_object_tagging(
+    Path((bucket_name, key)): Path<(String, String)>,
+    State(state): State<AppState>,
+) -> ApiResult<XmlResponse<Tagging>> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    find_object(&state, &bucket, &key, None).await?;
+
+    let tags = ObjectTagRepository::new(state.catalog.pool().clone()).get(bucket.id, &key).await?;
+    Ok(XmlResponse(Tagging {
+        tag_set: TagSet {
+            tags: tags.into_iter().map(|(key, value)| Tag { key, value }).collect(),
+        },
+    }))
+}
+
+pub async fn put_object_tagging(
+    Path((bucket_name, key)): Path<(String, String)>,
+    State(state): State<AppState>,
+    body: Body,
+) -> ApiResult<Response> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    find_object(&state, &bucket, &key, None).await?;
+
+    let tagging: Tagging = parse_xml_body(&read_body(body).await?, "PutObjectTagging")?;
+    let tags: Vec<(String, String)> = tagging.tag_set.tags.into_iter().map(|tag| (tag.key, tag.value)).collect();
+    validate_object_tags(&tags)?;
+
+    ObjectTagRepository::new(state.catalog.pool().clone())
+        .replace(bucket.id, &key, &tags)
+        .await?;
+    Ok(StatusCode::OK.into_response())
+}
+
+pub async fn delete_object_tagging(
+    Path((bucket_name, key)): Path<(String, String)>,
+    State(state): State<AppState>,
+) -> ApiResult<Response> {
+    let bucket = resolve_bucket(&state, &bucket_name).await?;
+    find_object(&state, &bucket, &key, None).await?;
+
+    ObjectTagRepository::new(state.catalog.pool().clone())
+        .replace(bucket.id, &key, &[])
+        .await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+// Tags from x-amz-tagging, which is encoded like a URL query: k1=v1&k2=v2
+fn request_tags(headers: &HeaderMap) -> ApiResult<Vec<(String, String)>> {
+    let Some(header) = headers.get("x-amz-tagging") else {
+        return Ok(Vec::new());
+    };
+    let malformed = || {
+        ApiError::InvalidTag(
+            "The header 'x-amz-tagging' shall be encoded as UTF-8 then URLEncoded URL query parameters without tag name duplicates.",
+        )
+    };
+    let decode = |value: &str| urlencoding::decode(&value.replace('+', " ")).map(|value| value.into_owned());
+
+    let mut tags = Vec::new();
+    for pair in header.to_str().map_err(|_| malformed())?.split('&').filter(|pair| !pair.is_empty()) {
+        let (tag_key, tag_value) = pair.split_once('=').unwrap_or((pair, ""));
+        tags.push((decode(tag_key).map_err(|_| malformed())?, decode(tag_value).map_err(|_| malformed())?));
+    }
+    validate_object_tags(&tags)?;
+    Ok(tags)
+}
+
+// Objects carry at most 10 tags
+fn validate_object_tags(tags: &[(String, String)]) -> ApiResult<()> {
+    if tags.len() > 10 {
+        return Err(ApiError::TooManyTags { count: tags.len(), limit: 10 });
+    }
+    validate_tag_set(tags)
+}
+
+// Evaluates conditional request headers in the order RFC 7232 gives them.
+// A failed If-Match or If-Unmodified-Since is a 412; a failed If-None-Match
+// or If-Modified-Since answers 304 Not Modified, returned as Some. If-Match
+// overrides If-Unmodified-Since and If-None-Match overrides If-Modified-Since.
+// ETags compare strongly, as S3 does.
+fn check_preconditions(headers: &HeaderMap, object: &Object) -> ApiResult<Option<Response>> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    // HTTP dates have whole seconds
+    let last_modified = object.updated_at.timestamp();
+
+    match header("if-match") {
+        Some(if_match) if !etag_matches(if_match, &object.etag) => {
+            return Err(ApiError::PreconditionFailed { condition: "If-Match" });
+        }
+        Some(_) => {}
+        None => {
+            if let Some(since) = header("if-unmodified-since").and_then(parse_http_date)
+                && last_modified > since
+            {
+                return Err(ApiError::PreconditionFailed { condition: "If-Unmodified-Since" });
+            }
+        }
+    }
+
+    let not_modified = match header("if-none-match") {
+        Some(if_none_match) => etag_matches(if_none_match, &object.etag),
+        None => header("if-modified-since")
+            .and_then(parse_http_date)
+            .is_some_and(|since| last_modified <= since),
+    };
+    if !not_modified {
+        return Ok(None);
+    }
+
+    // Content-Length is the size a 200 would have carried (RFC 9110 8.6)
+    let response = Response::builder()
+        .status(StatusCode::NOT_MODIFIED)
+        .header("Content-Length", object.size.to_string())
+        .header("ETag", format!("\"{}\"", object.etag))
+        .header("Last-Modified", http_date(&object.updated_at))
+        .body(Body::empty())
+        .map_err(anyhow::Error::from)?;
+    Ok(Some(response))
+}
+
+// A list of entity tags, or "*" for any. Weak tags never match strongly.
+fn etag_matches(header: &str, etag: &str) -> bool {
+    header.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || (!candidate.starts_with("W/") && candidate.trim_matches('"') == etag)
+    })
+}
+
+// Seconds since the epoch of an HTTP-date; invalid dates are ignored
+fn parse_http_date(value: &str) -> Option<i64> {
+    DateTime::parse_from_rfc2822(value).ok().map(|date| date.timestamp())
+}
+
+// Resolves a Range header against the object size into inclusive byte
+// offsets. Of several ranges only the first is served, since S3 cannot return
+// multipart/byteranges; malformed headers are ignored and the whole object is
+// served. A range that starts past the end, an empty suffix, or any range of
+// an empty object is unsatisfiable.
+fn parse_range_header(range: &str, size: u64) -> ApiResult<Option<(u64, u64)>> {
+    let first_range = range.strip_prefix("bytes=").and_then(|spec| spec.split(',').next());
+    let Some((first, last)) = first_range.and_then(|spec| spec.trim().split_once('-')) else {
+        return Ok(None);
+    };
+    let unsatisfiable = || ApiError::InvalidRange { range: range.to_string(), size };
+
+    if first.is_empty() {
+        // Suffix range: the last N bytes
+        let Ok(suffix) = last.parse::<u64>() else {
+            return Ok(None);
+        };
+        if suffix == 0 || size == 0 {
+            return Err(unsatisfiable());
+        }
+        return Ok(Some((size.saturating_sub(suffix), size - 1)));
+    }
+
+    let Ok(start) = first.parse::<u64>() else {
+        return Ok(None);
+    };
+    let end = if last.is_empty() {
+        None
+    } else {
+        match last.parse::<u64>() {
+            Ok(end) if end >= start => Some(end),
+            _ => return Ok(None),
+        }
+    };
+    if start >= size {
+        return Err(unsatisfiable());
+    }
+
+    Ok(Some((start, end.unwrap_or(size - 1).min(size - 1))))
+}
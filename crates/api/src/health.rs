@@ -0,0 +1,134 @@
+use anyhow::Result;
+use axum::{extract::State, Json};
+use chrono::{DateTime, Utc};
+use ghostbay_catalog::{BucketRepository, MultipartUploadRepository, ObjectRepository};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use crate::{
+    metrics::{ACTIVE_MULTIPART_UPLOADS, OBJECTS_TOTAL, STORAGE_BYTES},
+    AppState,
+};
+
+// Body of GET /health. Monitoring scripts parse this, so fields are only ever
+// added, never renamed or removed:
+//
+//   status, service, version   as before
+//   stats                      null until the first refresh, then:
+//     refreshed_at             RFC 3339 time the counters were computed
+//     buckets, objects, bytes  catalog totals
+//     data_dir_free_bytes      free space under the data directory, or null
+//     catalog_file_bytes       size of the SQLite file, or null if in memory
+//   jobs                       per background job: failing, last_run_at, last_error
+//   jobs_failing               true if any job's most recent run failed
+//
+// Counters are cached and refreshed by a background job, never per request.
+#[derive(Debug, Serialize)]
+pub struct HealthResponse {
+    pub status: &'static str,
+    pub service: &'static str,
+    pub version: &'static str,
+    pub stats: Option<HealthStats>,
+    pub jobs: BTreeMap<&'static str, JobStatus>,
+    pub jobs_failing: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthStats {
+    pub refreshed_at: DateTime<Utc>,
+    pub buckets: u64,
+    pub objects: u64,
+    pub bytes: u64,
+    pub data_dir_free_bytes: Option<u64>,
+    pub catalog_file_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub failing: bool,
+    pub last_run_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct HealthState {
+    catalog_file: Option<PathBuf>,
+    stats: RwLock<Option<HealthStats>>,
+    jobs: RwLock<BTreeMap<&'static str, JobStatus>>,
+}
+
+impl HealthState {
+    pub fn new(catalog_file: Option<PathBuf>) -> Self {
+        Self {
+            catalog_file,
+            ..Default::default()
+        }
+    }
+
+    // Background jobs report every run here so /health can flag failures
+    pub fn record_job(&self, name: &'static str, result: &Result<()>) {
+        let status = JobStatus {
+            failing: result.is_err(),
+            last_run_at: Utc::now(),
+            last_error: result.as_ref().err().map(|e| e.to_string()),
+        };
+        self.jobs.write().unwrap_or_else(|e| e.into_inner()).insert(name, status);
+    }
+}
+
+// Recomputes the cached counters; run by the gateway's health refresh job
+pub async fn refresh_health_stats(state: &AppState) -> Result<()> {
+    let buckets = BucketRepository::new(state.catalog.pool().clone()).count().await?;
+    let (objects, bytes) = ObjectRepository::new(state.catalog.pool().clone()).totals().await?;
+
+    let data_dir_free_bytes = match state.storage.available_space() {
+        Ok(free) => Some(free),
+        Err(e) => {
+            tracing::debug!("Free space unavailable: {}", e);
+            None
+        }
+    };
+    let catalog_file_bytes = match &state.health.catalog_file {
+        Some(path) => Some(tokio::fs::metadata(path).await?.len()),
+        None => None,
+    };
+
+    let stats = HealthStats {
+        refreshed_at: Utc::now(),
+        buckets,
+        objects,
+        bytes,
+        data_dir_free_bytes,
+        catalog_file_bytes,
+    };
+    *state.health.stats.write().unwrap_or_else(|e| e.into_inner()) = Some(stats);
+
+    // The per-bucket gauges behind /metrics; reset so deleted buckets drop out
+    let per_bucket = ObjectRepository::new(state.catalog.pool().clone()).totals_by_bucket().await?;
+    OBJECTS_TOTAL.reset();
+    STORAGE_BYTES.reset();
+    for (bucket, objects, bytes) in per_bucket {
+        OBJECTS_TOTAL.with_label_values(&[&bucket]).set(objects as i64);
+        STORAGE_BYTES.with_label_values(&[&bucket]).set(bytes as i64);
+    }
+    let uploads = MultipartUploadRepository::new(state.catalog.pool().clone()).count().await?;
+    ACTIVE_MULTIPART_UPLOADS.set(uploads as i64);
+    Ok(())
+}
+
+pub async fn health_check(State(state): State<AppState>) -> Json<HealthResponse> {
+    let stats = state.health.stats.read().unwrap_or_else(|e| e.into_inner()).clone();
+    let jobs = state.health.jobs.read().unwrap_or_else(|e| e.into_inner()).clone();
+    let jobs_failing = jobs.values().any(|job| job.failing);
+
+    Json(HealthResponse {
+        status: "healthy",
+        service: "ghostbay",
+        version: env!("CARGO_PKG_VERSION"),
+        stats,
+        jobs,
+        jobs_failing,
+    })
+}
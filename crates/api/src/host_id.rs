@@ -0,0 +1,48 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+// x-amz-id-2 values are opaque to clients, but encode the instance that
+// served the request followed by random bytes: base64url(instance_id, 0x00,
+// 16 random bytes). Support tooling decodes them with decode_host_id.
+const NONCE_LEN: usize = 16;
+
+pub fn generate_host_id(instance_id: &str) -> String {
+    let mut token = Vec::with_capacity(instance_id.len() + 1 + NONCE_LEN);
+    token.extend_from_slice(instance_id.as_bytes());
+    token.push(0);
+    token.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+    URL_SAFE_NO_PAD.encode(token)
+}
+
+// x-amz-request-id values are short like AWS's: 16 uppercase base36 digits
+// drawn from a random UUID, about 82 bits
+const REQUEST_ID_LEN: usize = 16;
+
+pub fn generate_request_id() -> String {
+    let mut value = uuid::Uuid::new_v4().as_u128();
+    let mut id = String::with_capacity(REQUEST_ID_LEN);
+    for _ in 0..REQUEST_ID_LEN {
+        id.push(char::from_digit((value % 36) as u32, 36).unwrap().to_ascii_uppercase());
+        value /= 36;
+    }
+    id
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedHostId {
+    pub instance_id: String,
+    pub nonce: String,
+}
+
+// None for anything generate_host_id could not have produced
+pub fn decode_host_id(host_id: &str) -> Option<DecodedHostId> {
+    let token = URL_SAFE_NO_PAD.decode(host_id.trim_end_matches('=')).ok()?;
+    let split = token.len().checked_sub(NONCE_LEN + 1)?;
+    let (instance_id, rest) = token.split_at(split);
+    if rest[0] != 0 {
+        return None;
+    }
+    Some(DecodedHostId {
+        instance_id: String::from_utf8(instance_id.to_vec()).ok()?,
+        nonce: rest[1..].iter().map(|byte| format!("{:02x}", byte)).collect(),
+    })
+}
@@ -1,23 +1,27 @@
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::Json,
     routing::{delete, get, post, put},
     Router,
 };
-use serde_json::{json, Value};
 use tower::ServiceBuilder;
 use tower_http::{
     compression::CompressionLayer,
-    cors::CorsLayer,
     trace::TraceLayer,
 };
 
+pub mod checksum;
+pub mod chunked;
+pub mod cors;
+pub mod encoding;
 pub mod handlers;
+pub mod health;
+pub mod host_id;
 pub mod middleware;
+pub mod metrics;
 pub mod error;
 pub mod extractors;
+pub mod form;
 pub mod responses;
+pub mod upload;
 
 pub use error::*;
 pub use handlers::*;
@@ -25,38 +29,88 @@ pub use handlers::*;
 #[derive(Clone)]
 pub struct AppState {
     pub catalog: ghostbay_catalog::CatalogService,
-    pub storage: std::sync::Arc<ghostbay_engine::LocalStorageEngine>,
+    pub storage: std::sync::Arc<dyn ghostbay_engine::StorageEngine>,
     pub auth: std::sync::Arc<ghostbay_auth::AuthService>,
+    pub regions: std::sync::Arc<RegionRouting>,
+    pub multipart: MultipartLimits,
+    pub health: std::sync::Arc<health::HealthState>,
+    // Names this gateway in x-amz-id-2 host ids and metric labels
+    pub instance_id: std::sync::Arc<str>,
+    pub rate_limiter: std::sync::Arc<middleware::RateLimiter>,
+    pub part_locks: std::sync::Arc<upload::PartLocks>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RegionRouting {
+    // Region of this node; new buckets are created here
+    pub region: String,
+    // Endpoints of the nodes serving other regions
+    pub endpoints: std::collections::HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MultipartLimits {
+    // Every part but the last must be at least this large, checked on completion
+    pub min_part_size: u64,
+    pub max_part_size: u64,
+    pub max_part_count: i32,
+}
+
+impl Default for MultipartLimits {
+    fn default() -> Self {
+        Self {
+            min_part_size: 5 * 1024 * 1024,
+            max_part_size: 5 * 1024 * 1024 * 1024,
+            max_part_count: 10_000,
+        }
+    }
 }
 
 pub fn create_router() -> Router<AppState> {
+    // Every admin route requires a key with the admin policy
+    let admin = Router::new()
+        .route("/admin/v1/capabilities", get(handlers::capabilities))
+        .route("/admin/v1/audit", get(handlers::search_audit_log))
+        .route("/admin/v1/host-ids/:host_id", get(handlers::lookup_host_id))
+        .route("/admin/v1/buckets/:bucket/snapshot", get(handlers::bucket_snapshot))
+        .route("/admin/v1/buckets/:bucket/upload-encoding", get(handlers::get_bucket_upload_encoding).put(handlers::put_bucket_upload_encoding))
+        .route("/admin/v1/buckets/:bucket/lifecycle", get(handlers::get_bucket_lifecycle).put(handlers::put_bucket_lifecycle))
+        .route("/admin/v1/buckets/:bucket/lifecycle/preview", post(handlers::preview_bucket_lifecycle))
+        .route("/admin/v1/buckets/:bucket/lifecycle/run", post(handlers::run_bucket_lifecycle))
+        .route_layer(axum::middleware::from_fn(middleware::require_admin));
+
     Router::new()
         // S3 API routes
         .route("/", get(handlers::list_buckets))
-        .route("/:bucket", put(handlers::create_bucket))
-        .route("/:bucket", get(handlers::list_objects))
-        .route("/:bucket", delete(handlers::delete_bucket))
-        // Object routes with conditional multipart handling
-        .route("/:bucket/*key", put(handlers::put_object_or_part))
-        .route("/:bucket/*key", post(handlers::create_multipart_upload_or_complete))
-        .route("/:bucket/*key", delete(handlers::delete_object_or_abort_upload))
-        .route("/:bucket/*key", get(handlers::get_object))
-        .route("/:bucket/*key", axum::routing::head(handlers::head_object))
+        .route("/:bucket", put(handlers::bucket_put))
+        .route("/:bucket", get(handlers::bucket_get))
+        .route("/:bucket", axum::routing::head(handlers::bucket_head))
+        .route("/:bucket", post(handlers::bucket_post))
+        .route("/:bucket", delete(handlers::bucket_delete))
+        // aws-sdk-rust addresses buckets with a trailing slash
+        .route(
+            "/:bucket/",
+            put(handlers::bucket_put)
+                .get(handlers::bucket_get)
+                .head(handlers::bucket_head)
+                .post(handlers::bucket_post)
+                .delete(handlers::bucket_delete),
+        )
+        // Object routes; sub-resources are dispatched on query parameters
+        .route("/:bucket/*key", put(handlers::object_put))
+        .route("/:bucket/*key", post(handlers::object_post))
+        .route("/:bucket/*key", delete(handlers::object_delete))
+        .route("/:bucket/*key", get(handlers::object_get))
+        .route("/:bucket/*key", axum::routing::head(handlers::object_head))
+        // Admin API
+        .merge(admin)
         // Health check
-        .route("/health", get(health_check))
+        .route("/health", get(health::health_check))
+        .route("/metrics", get(metrics::export))
         // Apply middleware
         .layer(
             ServiceBuilder::new()
                 .layer(TraceLayer::new_for_http())
-                .layer(CompressionLayer::new())
-                .layer(CorsLayer::permissive()),
+                .layer(CompressionLayer::new()),
         )
 }
-
-async fn health_check() -> Json<Value> {
-    Json(json!({
-        "status": "healthy",
-        "service": "ghostbay",
-        "version": env!("CARGO_PKG_VERSION")
-    }))
-}
\ No newline at end of file
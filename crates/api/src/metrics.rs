@@ -0,0 +1,99 @@
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use prometheus::{
+    register_histogram_vec, register_int_counter, register_int_counter_vec, register_int_gauge,
+    register_int_gauge_vec, Encoder, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, TextEncoder,
+};
+use prometheus::proto::LabelPair;
+use std::sync::LazyLock;
+
+use crate::AppState;
+
+// Requests by method, matched route pattern (never the raw path) and status
+pub static REQUESTS_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("ghostbay_requests_total", "Requests answered", &["method", "route", "status"])
+        .expect("ghostbay_requests_total is registered once")
+});
+
+pub static REQUEST_DURATION_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "ghostbay_request_duration_seconds",
+        "Time from receiving a request to sending its response headers",
+        &["method", "route"]
+    )
+    .expect("ghostbay_request_duration_seconds is registered once")
+});
+
+// Request and response body bytes of requests addressed to a bucket
+pub static BYTES_IN_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("ghostbay_bytes_in_total", "Request body bytes received per bucket", &["bucket"])
+        .expect("ghostbay_bytes_in_total is registered once")
+});
+
+pub static BYTES_OUT_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("ghostbay_bytes_out_total", "Response body bytes sent per bucket", &["bucket"])
+        .expect("ghostbay_bytes_out_total is registered once")
+});
+
+// Catalog totals per bucket, refreshed with the /health counters
+pub static OBJECTS_TOTAL: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!("ghostbay_objects_total", "Objects stored per bucket", &["bucket"])
+        .expect("ghostbay_objects_total is registered once")
+});
+
+pub static STORAGE_BYTES: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!("ghostbay_storage_bytes", "Bytes stored per bucket", &["bucket"])
+        .expect("ghostbay_storage_bytes is registered once")
+});
+
+pub static ACTIVE_MULTIPART_UPLOADS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!("ghostbay_active_multipart_uploads", "Multipart uploads started but not completed or aborted")
+        .expect("ghostbay_active_multipart_uploads is registered once")
+});
+
+// Handler panics caught and converted into 500 responses
+pub static PANICS_TOTAL: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!("panics_total", "Handler panics converted into InternalError responses")
+        .expect("panics_total is registered once")
+});
+
+// Unix time of the last catalog backup that completed; 0 until one has
+pub static CATALOG_BACKUP_LAST_SUCCESS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "catalog_backup_last_success_timestamp_seconds",
+        "Unix time at which the last scheduled catalog backup completed"
+    )
+    .expect("catalog_backup_last_success_timestamp_seconds is registered once")
+});
+
+// Reads that found a catalog row but no file behind it
+pub static MISSING_BLOB_TOTAL: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!("missing_blob_total", "Objects present in the catalog whose data file is missing")
+        .expect("missing_blob_total is registered once")
+});
+
+// GET /metrics in the Prometheus text format. Only encodes what the
+// middleware and background jobs have already recorded. Every series is
+// labelled with the instance id so replicas can be told apart.
+pub async fn export(State(state): State<AppState>) -> Response {
+    let mut families = prometheus::gather();
+    for family in &mut families {
+        for metric in family.mut_metric().iter_mut() {
+            let mut label = LabelPair::new();
+            label.set_name("instance_id".to_string());
+            label.set_value(state.instance_id.to_string());
+            metric.mut_label().push(label);
+        }
+    }
+
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+    if let Err(e) = encoder.encode(&families, &mut buffer) {
+        tracing::error!("Failed to encode metrics: {}", e);
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    ([(header::CONTENT_TYPE, encoder.format_type().to_string())], buffer).into_response()
+}
@@ -1,2 +1,646 @@
-// Placeholder for middleware functionality
-// TODO: Add authentication, logging, and other middleware
\ No newline at end of file
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, MatchedPath, RawPathParams, Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use futures::StreamExt;
+use ghostbay_auth::{
+    is_presigned_query, parse_authorization_header, parse_presigned_query, policy::Authorizer, AuthContext,
+    SignatureValidationRequest, UsageCounters,
+};
+use ghostbay_catalog::{AuditRepository, BucketCorsConfig, BucketCorsRepository, BucketRepository, NewAuditEntry};
+use governor::{clock::Clock, DefaultKeyedRateLimiter, Quota};
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroU32;
+use std::time::Instant;
+use tracing::Instrument;
+
+use crate::{
+    chunked::STREAMING_SIGNED_PAYLOAD,
+    cors,
+    error::ApiError,
+    host_id::{generate_host_id, generate_request_id},
+    metrics::{BYTES_IN_TOTAL, BYTES_OUT_TOTAL, PANICS_TOTAL, REQUESTS_TOTAL, REQUEST_DURATION_SECONDS},
+    AppState,
+};
+
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    pub request_id: String,
+    // x-amz-id-2: which instance served the request, see host_id
+    pub host_id: String,
+    // Request path, echoed as <Resource> in error documents
+    pub resource: String,
+}
+
+tokio::task_local! {
+    static REQUEST_CONTEXT: RequestContext;
+}
+
+thread_local! {
+    // Captured by the panic hook at the panic site, since the stack has
+    // already unwound by the time CatchPanicLayer sees the payload
+    static PANIC_BACKTRACE: RefCell<Option<Backtrace>> = const { RefCell::new(None) };
+}
+
+pub fn current_request_context() -> Option<RequestContext> {
+    REQUEST_CONTEXT.try_with(|context| context.clone()).ok()
+}
+
+// Assigns every request an id and host id and scopes them over the rest of
+// the stack, so error documents, audit entries and log lines built anywhere
+// below carry the same ids as the x-amz-request-id and x-amz-id-2 headers
+// added here. The context is also put in the request extensions for
+// extractors; x-request-id repeats the id for non-S3 proxies and tooling.
+pub async fn request_context(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    let context = RequestContext {
+        request_id: generate_request_id(),
+        host_id: generate_host_id(&state.instance_id),
+        resource: request.uri().path().to_string(),
+    };
+    let request_id = context.request_id.clone();
+    let host_id = context.host_id.clone();
+    // The root span of the request; record_metrics fills in bucket and key
+    // once the route is known
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        host_id = %host_id,
+        method = %request.method(),
+        bucket = tracing::field::Empty,
+        key = tracing::field::Empty,
+        status_code = tracing::field::Empty,
+    );
+    request.extensions_mut().insert(context.clone());
+
+    let mut response = REQUEST_CONTEXT.scope(context, next.run(request)).instrument(span.clone()).await;
+    span.record("status_code", response.status().as_u16());
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert("x-amz-request-id", value.clone());
+        response.headers_mut().insert("x-request-id", value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&host_id) {
+        response.headers_mut().insert("x-amz-id-2", value);
+    }
+    response
+}
+
+// Authenticates SigV4 requests, signed either in the Authorization header or
+// in the query string of a presigned URL, and attaches the key's AuthContext
+// for the layers below. Unsigned requests pass through anonymously; the S3
+// handlers refuse them unless a bucket policy allows anonymous access.
+pub async fn authenticate(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    let validation = if is_presigned_query(request.uri().query().unwrap_or("")) {
+        presigned_validation(&request)
+    } else if request.headers().contains_key(header::AUTHORIZATION) {
+        header_validation(&request)
+    } else {
+        return next.run(request).await;
+    };
+
+    let validation = match validation {
+        Ok(validation) => validation,
+        Err(e) => return e.into_response(),
+    };
+    // A body sent in signed chunks also gets the signer that checks them
+    let validated = if validation.payload_hash.starts_with(STREAMING_SIGNED_PAYLOAD) {
+        state.auth.validate_streaming_signature(&validation).await.map(|(context, signer)| (context, Some(signer)))
+    } else {
+        state.auth.validate_signature(&validation).await.map(|context| (context, None))
+    };
+    match validated {
+        Ok((auth_context, chunk_signer)) => {
+            request.extensions_mut().insert(auth_context);
+            if let Some(chunk_signer) = chunk_signer {
+                request.extensions_mut().insert(chunk_signer);
+            }
+            next.run(request).await
+        }
+        Err(e) => {
+            tracing::debug!("Rejected signed request for {}: {}", validation.access_key_id, e);
+            ApiError::AuthorizationFailed(e.to_string()).into_response()
+        }
+    }
+}
+
+// A request signed in its Authorization header. The payload is covered by
+// the x-amz-content-sha256 header, which S3 requires on every such request
+// and which may also be UNSIGNED-PAYLOAD or one of the streaming markers.
+fn header_validation(request: &Request) -> Result<SignatureValidationRequest, ApiError> {
+    let authorization = request.headers().get(header::AUTHORIZATION).and_then(|v| v.to_str().ok()).unwrap_or_default();
+    let auth_info = parse_authorization_header(authorization).map_err(|e| ApiError::AuthorizationFailed(e.to_string()))?;
+
+    let payload_hash = request
+        .headers()
+        .get("x-amz-content-sha256")
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| ApiError::BadRequest("Missing required header for this request: x-amz-content-sha256".to_string()))?;
+
+    // X-Amz-Date takes precedence over Date, as in SigV4
+    let timestamp = match request.headers().get("x-amz-date").and_then(|v| v.to_str().ok()) {
+        Some(date) => chrono::NaiveDateTime::parse_from_str(date, "%Y%m%dT%H%M%SZ").map(|date| date.and_utc()).ok(),
+        None => request
+            .headers()
+            .get(header::DATE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|date| chrono::DateTime::parse_from_rfc2822(date).ok())
+            .map(|date| date.with_timezone(&chrono::Utc)),
+    };
+    let timestamp = timestamp.ok_or_else(|| ApiError::AuthorizationFailed("request has no valid X-Amz-Date or Date header".to_string()))?;
+
+    Ok(SignatureValidationRequest {
+        signed_headers: signed_header_values(request, &auth_info.signed_headers)?,
+        access_key_id: auth_info.access_key_id,
+        signature: auth_info.signature,
+        method: request.method().to_string(),
+        uri: request.uri().path().to_string(),
+        query_string: request.uri().query().unwrap_or_default().to_string(),
+        payload_hash: payload_hash.to_string(),
+        timestamp,
+        region: auth_info.region,
+        service: auth_info.service,
+        expires_in_seconds: None,
+    })
+}
+
+fn presigned_validation(request: &Request) -> Result<SignatureValidationRequest, ApiError> {
+    let presigned = parse_presigned_query(request.uri().query().unwrap_or(""))
+        .map_err(|e| ApiError::AuthorizationFailed(e.to_string()))?;
+
+    // Checked before the signature so an expired link gets a clear answer
+    let now = chrono::Utc::now();
+    if now > presigned.expires_at() {
+        return Err(ApiError::RequestExpired {
+            expires_in_seconds: presigned.expires_in_seconds,
+            expires_at: presigned.expires_at().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            server_time: now.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        });
+    }
+
+    Ok(SignatureValidationRequest {
+        signed_headers: signed_header_values(request, &presigned.signed_headers)?,
+        access_key_id: presigned.access_key_id,
+        signature: presigned.signature,
+        method: request.method().to_string(),
+        uri: request.uri().path().to_string(),
+        query_string: presigned.signed_query,
+        payload_hash: "UNSIGNED-PAYLOAD".to_string(),
+        timestamp: presigned.timestamp,
+        region: presigned.region,
+        service: presigned.service,
+        expires_in_seconds: Some(presigned.expires_in_seconds),
+    })
+}
+
+// The values of the headers a signature covers, as the signer saw them
+fn signed_header_values(request: &Request, names: &[String]) -> Result<HashMap<String, String>, ApiError> {
+    let mut signed_headers = HashMap::new();
+    for name in names {
+        let values: Vec<&str> = request.headers().get_all(name.as_str()).iter().filter_map(|v| v.to_str().ok()).collect();
+        let value = match (values.is_empty(), name.as_str()) {
+            (false, _) => values.join(","),
+            // HTTP/2 carries the host in the URI rather than a Host header
+            (true, "host") => request.uri().authority().map(|authority| authority.to_string()).unwrap_or_default(),
+            (true, _) => return Err(ApiError::AuthorizationFailed(format!("signed header {} is missing", name))),
+        };
+        signed_headers.insert(name.clone(), value);
+    }
+    Ok(signed_headers)
+}
+
+// Guards the admin API: only requests signed with an admin key get through.
+// Reads the AuthContext, so it has to run inside authentication.
+pub async fn require_admin(request: Request, next: Next) -> Response {
+    match request.extensions().get::<AuthContext>() {
+        Some(auth) if Authorizer::is_admin(auth) => next.run(request).await,
+        Some(auth) => {
+            ApiError::AuthorizationFailed(format!("{} is not an admin key", auth.access_key_id)).into_response()
+        }
+        None => ApiError::AuthorizationFailed("the admin API requires a signed request".to_string()).into_response(),
+    }
+}
+
+// Names the S3 operation that produced a response, for the audit log.
+// Requests without one are recorded by method and route instead.
+#[derive(Debug, Clone, Copy)]
+pub struct AuditAction(pub &'static str);
+
+// Records every S3 and admin request in the audit log once its response is
+// ready. The insert runs in the background to keep it off the request path.
+// Reads the AuthContext, so it has to run inside authentication. Console
+// requests authenticate in their handlers and attach it to the response.
+pub async fn record_audit(
+    State(state): State<AppState>,
+    matched_path: Option<MatchedPath>,
+    params: Option<RawPathParams>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let route = matched_path
+        .as_ref()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    // Polling the log itself would otherwise flood it
+    if route == "/health" || route == "/metrics" || route == "/admin/v1/audit" {
+        return next.run(request).await;
+    }
+
+    let method = request.method().clone();
+    let access_key_id = request.extensions().get::<AuthContext>().map(|auth| auth.access_key_id.clone());
+    let param = |name: &str| {
+        params
+            .as_ref()
+            .and_then(|params| params.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string()))
+    };
+    let (bucket, key) = (param("bucket"), param("key"));
+
+    let response = next.run(request).await;
+
+    let access_key_id =
+        access_key_id.or_else(|| response.extensions().get::<AuthContext>().map(|auth| auth.access_key_id.clone()));
+    let action = match response.extensions().get::<AuditAction>() {
+        Some(AuditAction(action)) => action.to_string(),
+        None => format!("{} {}", method, route),
+    };
+    let context = current_request_context();
+    let entry = NewAuditEntry {
+        request_id: context
+            .as_ref()
+            .map(|context| context.request_id.clone())
+            .unwrap_or_else(generate_request_id),
+        host_id: context.map(|context| context.host_id),
+        occurred_at: chrono::Utc::now(),
+        access_key_id,
+        action,
+        bucket,
+        key,
+        status: response.status().as_u16(),
+    };
+    let repo = AuditRepository::new(state.catalog.pool().clone());
+    // Written off the response path, but traced as part of the request
+    tokio::spawn(
+        async move {
+            if let Err(e) = repo.record(&entry).await {
+                tracing::error!(request_id = %entry.request_id, "Failed to record audit entry: {}", e);
+            }
+        }
+        .in_current_span(),
+    );
+
+    response
+}
+
+// Enforces per-access-key quotas and accounts the request, upload and
+// download volume of every authenticated request against its key.
+pub async fn enforce_key_quota(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(auth_context) = request.extensions().get::<AuthContext>().cloned() else {
+        return next.run(request).await;
+    };
+
+    let usage = state.auth.usage().clone();
+    match usage.check(&auth_context.access_key_id, &auth_context.quota).await {
+        Ok(Some(violation)) => {
+            return ApiError::QuotaExceeded {
+                access_key_id: auth_context.access_key_id,
+                quota: violation.quota,
+                resets_at: violation.resets_at.to_rfc3339(),
+                retry_after_seconds: violation.retry_after_seconds,
+            }
+            .into_response();
+        }
+        Ok(None) => {}
+        // Failing open keeps the service available if the usage table is unreachable
+        Err(e) => tracing::error!("Failed to check quota for {}: {}", auth_context.access_key_id, e),
+    }
+
+    let access_key_id = auth_context.access_key_id;
+    usage.record(&access_key_id, UsageCounters { requests: 1, ..Default::default() });
+
+    let (parts, body) = request.into_parts();
+    let tracker = usage.clone();
+    let key_id = access_key_id.clone();
+    let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+        if let Ok(bytes) = &chunk {
+            tracker.record(&key_id, UsageCounters { bytes_in: bytes.len() as i64, ..Default::default() });
+        }
+        chunk
+    }));
+
+    let response = next.run(Request::from_parts(parts, body)).await;
+
+    let (parts, body) = response.into_parts();
+    let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+        if let Ok(bytes) = &chunk {
+            usage.record(&access_key_id, UsageCounters { bytes_out: bytes.len() as i64, ..Default::default() });
+        }
+        chunk
+    }));
+
+    Response::from_parts(parts, body)
+}
+
+// Request rates allowed per client address and per access key. Each limiter
+// keeps a token bucket per address or key in a DashMap; retain_recent drops
+// the ones that have refilled, so idle clients do not accumulate.
+pub struct RateLimiter {
+    per_ip: Option<DefaultKeyedRateLimiter<IpAddr>>,
+    per_key: Option<DefaultKeyedRateLimiter<String>>,
+}
+
+impl RateLimiter {
+    // Rates are in requests per second and 0 leaves that limit off. burst is
+    // how many requests may arrive at once; 0 allows one second's worth.
+    pub fn new(per_ip: u32, per_key: u32, burst: u32) -> Self {
+        let quota = |rate: u32| {
+            let rate = NonZeroU32::new(rate)?;
+            Some(Quota::per_second(rate).allow_burst(NonZeroU32::new(burst).unwrap_or(rate)))
+        };
+        Self {
+            per_ip: quota(per_ip).map(DefaultKeyedRateLimiter::dashmap),
+            per_key: quota(per_key).map(DefaultKeyedRateLimiter::dashmap),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.per_ip.is_some() || self.per_key.is_some()
+    }
+
+    pub fn retain_recent(&self) {
+        if let Some(limiter) = &self.per_ip {
+            limiter.retain_recent();
+        }
+        if let Some(limiter) = &self.per_key {
+            limiter.retain_recent();
+        }
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(0, 0, 0)
+    }
+}
+
+// Fails with SlowDown once the key is out of requests, giving the wait for
+// Retry-After in whole seconds, rounded up
+fn check_rate<K: std::hash::Hash + Eq + Clone>(limiter: &DefaultKeyedRateLimiter<K>, key: &K) -> Result<(), ApiError> {
+    limiter.check_key(key).map_err(|not_until| {
+        let wait = not_until.wait_time_from(limiter.clock().now());
+        ApiError::SlowDown { retry_after_seconds: wait.as_secs() + u64::from(wait.subsec_nanos() > 0) }
+    })
+}
+
+fn is_rate_limited_route(request: &Request) -> bool {
+    !matches!(request.uri().path(), "/health" | "/metrics")
+}
+
+// Throttles each client address. Runs outside authentication so requests
+// with bad signatures are throttled too. Behind a proxy every request shares
+// the proxy's address.
+pub async fn limit_client_rate(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let client = request.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(address)| address.ip());
+    if let (Some(limiter), Some(client)) = (&state.rate_limiter.per_ip, client)
+        && is_rate_limited_route(&request)
+        && let Err(e) = check_rate(limiter, &client)
+    {
+        tracing::debug!("Throttled requests from {}", client);
+        return e.into_response();
+    }
+    next.run(request).await
+}
+
+// Throttles each access key; anonymous requests are left to the per-address
+// limit. Reads the AuthContext, so it has to run inside authentication.
+pub async fn limit_key_rate(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let access_key_id = request.extensions().get::<AuthContext>().map(|auth| &auth.access_key_id);
+    if let (Some(limiter), Some(access_key_id)) = (&state.rate_limiter.per_key, access_key_id)
+        && is_rate_limited_route(&request)
+        && let Err(e) = check_rate(limiter, access_key_id)
+    {
+        tracing::debug!("Throttled requests signed with {}", access_key_id);
+        return e.into_response();
+    }
+    next.run(request).await
+}
+
+// Counts and times every request for /metrics, and the body bytes moved in
+// and out of each bucket. Labels use the matched route so raw keys never
+// become label values.
+pub async fn record_metrics(
+    matched_path: Option<MatchedPath>,
+    params: Option<RawPathParams>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let route = matched_path
+        .as_ref()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let bucket = params
+        .as_ref()
+        .and_then(|params| params.iter().find(|(key, _)| *key == "bucket").map(|(_, value)| value.to_string()));
+    let span = tracing::Span::current();
+    if let Some(bucket) = &bucket {
+        span.record("bucket", bucket.as_str());
+    }
+    if let Some((_, key)) = params.as_ref().and_then(|params| params.iter().find(|(key, _)| *key == "key")) {
+        span.record("key", key);
+    }
+
+    let request = match &bucket {
+        Some(bucket) => {
+            let bytes_in = BYTES_IN_TOTAL.with_label_values(&[bucket]);
+            let (parts, body) = request.into_parts();
+            let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+                if let Ok(bytes) = &chunk {
+                    bytes_in.inc_by(bytes.len() as u64);
+                }
+                chunk
+            }));
+            Request::from_parts(parts, body)
+        }
+        None => request,
+    };
+
+    let response = next.run(request).await;
+
+    REQUEST_DURATION_SECONDS
+        .with_label_values(&[&method, &route])
+        .observe(started.elapsed().as_secs_f64());
+    REQUESTS_TOTAL
+        .with_label_values(&[&method, &route, response.status().as_str()])
+        .inc();
+
+    let Some(bucket) = bucket else {
+        return response;
+    };
+    let bytes_out = BYTES_OUT_TOTAL.with_label_values(&[&bucket]);
+    let (parts, body) = response.into_parts();
+    let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+        if let Ok(bytes) = &chunk {
+            bytes_out.inc_by(bytes.len() as u64);
+        }
+        chunk
+    }));
+    Response::from_parts(parts, body)
+}
+
+// Applies the addressed bucket's CORS configuration. Browsers send preflight
+// requests unsigned, so they are answered here, outside authentication; other
+// cross-origin requests get the allow headers of the rule that matches them.
+pub async fn apply_bucket_cors(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let bucket_name = request.uri().path().trim_start_matches('/').split('/').next().unwrap_or("");
+    let origin = request.headers().get(header::ORIGIN).and_then(|v| v.to_str().ok()).map(str::to_string);
+    let Some(origin) = origin.filter(|_| !bucket_name.is_empty() && !matches!(bucket_name, "health" | "metrics" | "admin" | "console")) else {
+        return next.run(request).await;
+    };
+    let rules = bucket_cors_rules(&state, bucket_name).await;
+
+    if request.method() == Method::OPTIONS {
+        let Some(method) = request.headers().get(header::ACCESS_CONTROL_REQUEST_METHOD).and_then(|v| v.to_str().ok()) else {
+            return ApiError::BadRequest("Invalid Access-Control-Request-Method: null".to_string()).into_response();
+        };
+        let requested_headers: Vec<String> = request
+            .headers()
+            .get_all(header::ACCESS_CONTROL_REQUEST_HEADERS)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(|name| name.trim().to_ascii_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+
+        let Some(rule) = cors::find_rule(&rules, &origin, method, &requested_headers) else {
+            return ApiError::CorsRequestNotAllowed { method: method.to_string() }.into_response();
+        };
+        let mut response = StatusCode::OK.into_response();
+        let headers = response.headers_mut();
+        set_cors_headers(headers, rule, &origin);
+        if !requested_headers.is_empty() {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, header_value(&requested_headers.join(", ")));
+        }
+        if let Some(max_age) = rule.max_age_seconds {
+            headers.insert(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from(max_age));
+        }
+        return response;
+    }
+
+    let method = request.method().as_str().to_string();
+    let mut response = next.run(request).await;
+    if let Some(rule) = cors::find_rule(&rules, &origin, &method, &[]) {
+        let headers = response.headers_mut();
+        set_cors_headers(headers, rule, &origin);
+        if !rule.expose_headers.is_empty() {
+            headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, header_value(&rule.expose_headers.join(", ")));
+        }
+    }
+    response
+}
+
+// A bucket without a configuration, or one that cannot be read, allows no
+// cross-origin requests
+async fn bucket_cors_rules(state: &AppState, bucket_name: &str) -> Vec<BucketCorsConfig> {
+    let lookup = async {
+        let Some(bucket) = BucketRepository::new(state.catalog.pool().clone()).find_by_name(bucket_name).await? else {
+            return Ok(None);
+        };
+        BucketCorsRepository::new(state.catalog.pool().clone()).get_rules(bucket.id).await
+    };
+    match lookup.await {
+        Ok(rules) => rules.unwrap_or_default(),
+        Err(e) => {
+            tracing::warn!("Failed to load the CORS configuration of bucket {}: {}", bucket_name, e);
+            Vec::new()
+        }
+    }
+}
+
+fn set_cors_headers(headers: &mut HeaderMap, rule: &BucketCorsConfig, origin: &str) {
+    let allowed_origin = cors::allowed_origin(rule, origin);
+    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, header_value(allowed_origin));
+    headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, header_value(&rule.allowed_methods.join(", ")));
+    if allowed_origin != "*" {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+    }
+    headers.append(header::VARY, HeaderValue::from_static("Origin, Access-Control-Request-Headers, Access-Control-Request-Method"));
+}
+
+// Configured values are stored as given, so one that is not a valid header
+// value is dropped rather than failing the response
+fn header_value(value: &str) -> HeaderValue {
+    HeaderValue::from_str(value).unwrap_or_else(|_| HeaderValue::from_static(""))
+}
+
+// Answers requests for buckets homed in another region with an S3-style
+// PermanentRedirect, so SDKs retry against the node that owns the bucket.
+pub async fn redirect_foreign_buckets(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let bucket_name = request.uri().path().trim_start_matches('/').split('/').next().unwrap_or("");
+    if bucket_name.is_empty() || matches!(bucket_name, "health" | "metrics" | "admin" | "console") || state.regions.endpoints.is_empty() {
+        return next.run(request).await;
+    }
+
+    let repo = BucketRepository::new(state.catalog.pool().clone());
+    match repo.find_by_name(bucket_name).await {
+        Ok(Some(bucket)) if bucket.region != state.regions.region => {
+            if let Some(endpoint) = state.regions.endpoints.get(&bucket.region) {
+                return ApiError::PermanentRedirect {
+                    bucket: bucket.name,
+                    region: bucket.region,
+                    endpoint: endpoint.clone(),
+                }
+                .into_response();
+            }
+        }
+        Ok(_) => {}
+        // Let the handler surface the failure with its own error response
+        Err(e) => tracing::warn!("Failed to look up region of bucket {}: {}", bucket_name, e),
+    }
+
+    next.run(request).await
+}
+
+pub fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        PANIC_BACKTRACE.with(|slot| *slot.borrow_mut() = Some(Backtrace::force_capture()));
+        previous(info);
+    }));
+}
+
+// Used with CatchPanicLayer: a panicking handler becomes an InternalError
+// response and the connection and server keep serving.
+pub fn handle_panic(payload: Box<dyn Any + Send + 'static>) -> Response {
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    };
+    let backtrace = PANIC_BACKTRACE
+        .with(|slot| slot.borrow_mut().take())
+        .map(|backtrace| backtrace.to_string())
+        .unwrap_or_else(|| "unavailable".to_string());
+
+    PANICS_TOTAL.inc();
+    let (request_id, host_id, resource) = match current_request_context() {
+        Some(context) => (context.request_id, Some(context.host_id), Some(context.resource)),
+        None => (generate_request_id(), None, None),
+    };
+    tracing::error!(request_id = %request_id, "Handler panicked: {}\n{}", message, backtrace);
+
+    let mut response = ApiError::Internal(anyhow::anyhow!("handler panicked: {}", message))
+        .into_response_with_context(&request_id, host_id.as_deref(), resource.as_deref());
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert("x-amz-request-id", value);
+    }
+    response
+}
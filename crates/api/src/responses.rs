@@ -1,6 +1,51 @@
+use axum::{
+    http::header,
+    response::{IntoResponse, Response},
+};
 use chrono::{DateTime, Utc};
+use ghostbay_catalog::{lifecycle::LifecycleRuleReport, BucketCorsConfig, LifecycleRule};
 use serde::{Deserialize, Serialize};
 
+use crate::error::ApiError;
+
+const S3_NAMESPACE: &str = "http://s3.amazonaws.com/doc/2006-03-01/";
+
+// Root element an S3 response document is serialized under
+pub trait XmlRoot {
+    const ROOT: &'static str;
+}
+
+// S3 API counterpart to axum's Json: serializes the document as XML in the
+// S3 namespace. Admin endpoints keep returning JSON.
+pub struct XmlResponse<T>(pub T);
+
+impl<T: Serialize + XmlRoot> IntoResponse for XmlResponse<T> {
+    fn into_response(self) -> Response {
+        let document = match quick_xml::se::to_string_with_root(T::ROOT, &self.0) {
+            Ok(document) => document,
+            Err(e) => return ApiError::Internal(anyhow::anyhow!("failed to serialize {}: {}", T::ROOT, e)).into_response(),
+        };
+        // The serializer has no notion of namespaces, so declare it on the root by hand
+        let document = document.replacen(
+            &format!("<{}", T::ROOT),
+            &format!("<{} xmlns=\"{}\"", T::ROOT, S3_NAMESPACE),
+            1,
+        );
+
+        (
+            [(header::CONTENT_TYPE, "application/xml")],
+            format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}", document),
+        )
+            .into_response()
+    }
+}
+
+// S3 documents carry millisecond UTC timestamps; some SDKs reject the
+// nanosecond precision chrono emits by default
+fn s3_timestamp<S: serde::Serializer>(at: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&at.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string())
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct ListBucketsResponse {
@@ -8,10 +53,17 @@ pub struct ListBucketsResponse {
     pub buckets: Buckets,
 }
 
+impl XmlRoot for ListBucketsResponse {
+    const ROOT: &'static str = "ListAllMyBucketsResult";
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct Owner {
+    #[serde(rename = "ID")]
     pub id: String,
+    // Optional in the AccessControlPolicy a client puts
+    #[serde(default)]
     pub display_name: String,
 }
 
@@ -25,39 +77,364 @@ pub struct Buckets {
 #[serde(rename_all = "PascalCase")]
 pub struct BucketInfo {
     pub name: String,
+    #[serde(serialize_with = "s3_timestamp")]
     pub creation_date: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag_set: Option<TagSet>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LocationConstraint {
+    #[serde(rename = "$text")]
+    pub region: String,
+}
+
+impl XmlRoot for LocationConstraint {
+    const ROOT: &'static str = "LocationConstraint";
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CreateBucketConfiguration {
+    #[serde(default)]
+    pub location_constraint: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CorsConfiguration {
+    #[serde(rename = "CORSRule", default)]
+    pub rules: Vec<BucketCorsConfig>,
+}
+
+impl XmlRoot for CorsConfiguration {
+    const ROOT: &'static str = "CORSConfiguration";
+}
+
+// Body of the object and bucket tagging operations
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Tagging {
+    #[serde(default)]
+    pub tag_set: TagSet,
+}
+
+impl XmlRoot for Tagging {
+    const ROOT: &'static str = "Tagging";
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TagSet {
+    #[serde(rename = "Tag", default)]
+    pub tags: Vec<Tag>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Tag {
+    pub key: String,
+    pub value: String,
+}
+
+// Bucket ACL, as GetBucketAcl returns it and PutBucketAcl takes it. The
+// Owner of a document put is ignored; a bucket's owner does not change.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AccessControlPolicy {
+    #[serde(default)]
+    pub owner: Option<Owner>,
+    #[serde(default)]
+    pub access_control_list: AccessControlList,
+}
+
+impl XmlRoot for AccessControlPolicy {
+    const ROOT: &'static str = "AccessControlPolicy";
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AccessControlList {
+    #[serde(rename = "Grant", default)]
+    pub grants: Vec<AclGrant>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AclGrant {
+    pub grantee: AclGrantee,
+    pub permission: String,
+}
+
+// A CanonicalUser grantee has an ID, a Group one a URI. The deserializer
+// matches attributes by local name, without their namespace prefix.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AclGrantee {
+    #[serde(rename(serialize = "@xmlns:xsi"), skip_deserializing, default = "xsi_namespace")]
+    pub xmlns_xsi: String,
+    #[serde(rename(serialize = "@xsi:type", deserialize = "@type"))]
+    pub grantee_type: String,
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "DisplayName", default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(rename = "URI", default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+}
+
+pub(crate) fn xsi_namespace() -> String {
+    "http://www.w3.org/2001/XMLSchema-instance".to_string()
+}
+
+// S3's LifecycleConfiguration document, as PutBucketLifecycleConfiguration
+// takes it. Rules are stored as LifecycleRule, which the admin API edits as
+// JSON; only Days-based expiration and AbortIncompleteMultipartUpload map
+// onto it.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BucketLifecycleConfiguration {
+    #[serde(rename = "Rule", default)]
+    pub rules: Vec<BucketLifecycleRule>,
+}
+
+impl XmlRoot for BucketLifecycleConfiguration {
+    const ROOT: &'static str = "LifecycleConfiguration";
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BucketLifecycleRule {
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    // The prefix outside a Filter, from the original schema
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<LifecycleFilter>,
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiration: Option<LifecycleExpiration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abort_incomplete_multipart_upload: Option<AbortIncompleteMultipartUpload>,
 }
 
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LifecycleFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<Tag>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub and: Option<LifecycleFilterAnd>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LifecycleFilterAnd {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(rename = "Tag", default)]
+    pub tags: Vec<Tag>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LifecycleExpiration {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub days: Option<i64>,
+    // Date-based expiration is refused rather than ignored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AbortIncompleteMultipartUpload {
+    pub days_after_initiation: i64,
+}
+
+// Body of PutBucketVersioning and GetBucketVersioning. MfaDelete is accepted
+// and ignored.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct VersioningConfiguration {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+impl XmlRoot for VersioningConfiguration {
+    const ROOT: &'static str = "VersioningConfiguration";
+}
+
+// Element presence follows S3: Name, Prefix (empty when not supplied), KeyCount,
+// MaxKeys and IsTruncated are always present; Delimiter, StartAfter and the
+// continuation tokens only when supplied or applicable; Contents and
+// CommonPrefixes are omitted entirely rather than sent empty.
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct ListObjectsV2Response {
     pub name: String,
-    pub prefix: Option<String>,
+    pub prefix: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delimiter: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_after: Option<String>,
     pub key_count: u32,
     pub max_keys: u32,
     pub is_truncated: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub continuation_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub next_continuation_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contents: Vec<ObjectInfo>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub common_prefixes: Vec<CommonPrefix>,
+}
+
+impl XmlRoot for ListObjectsV2Response {
+    const ROOT: &'static str = "ListBucketResult";
+}
+
+// The original ListObjects, for requests without list-type=2: Marker in
+// place of StartAfter and the continuation tokens, and no KeyCount.
+// NextMarker is only sent on a truncated listing with a delimiter; without
+// one, clients resume from the last key.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListObjectsV1Response {
+    pub name: String,
+    pub prefix: String,
+    pub marker: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_marker: Option<String>,
+    pub max_keys: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delimiter: Option<String>,
+    pub is_truncated: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub contents: Vec<ObjectInfo>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub common_prefixes: Vec<CommonPrefix>,
+}
+
+impl XmlRoot for ListObjectsV1Response {
+    const ROOT: &'static str = "ListBucketResult";
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CommonPrefix {
+    pub prefix: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct ObjectInfo {
     pub key: String,
-    #[serde(rename = "LastModified")]
+    #[serde(rename = "LastModified", serialize_with = "s3_timestamp")]
     pub last_modified: DateTime<Utc>,
     #[serde(rename = "ETag")]
     pub etag: String,
     pub size: u64,
     pub storage_class: String,
-    pub owner: Owner,
+    // Only listed when the request asks for it with fetch-owner=true
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<Owner>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BucketSnapshotResponse {
+    pub name: String,
+    pub at: DateTime<Utc>,
+    pub prefix: Option<String>,
+    pub key_count: u32,
+    pub max_keys: u32,
+    pub is_truncated: bool,
+    pub start_after: Option<String>,
+    pub next_start_after: Option<String>,
+    pub contents: Vec<SnapshotObjectInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SnapshotObjectInfo {
+    pub key: String,
+    pub version_id: String,
+    #[serde(rename = "LastModified")]
+    pub last_modified: DateTime<Utc>,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+    pub size: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
-pub struct CopyObjectResponse {
-    pub copy_object_result: CopyObjectResult,
+pub struct AuditLogResponse {
+    pub entries: Vec<AuditLogEntry>,
+    pub is_truncated: bool,
+    // Pass back as `after` for the next page, or to poll for new entries
+    pub next_cursor: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AuditLogEntry {
+    pub id: i64,
+    pub request_id: String,
+    pub host_id: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+    pub access_key_id: Option<String>,
+    pub action: String,
+    pub bucket: Option<String>,
+    pub key: Option<String>,
+    pub status: u16,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct HostIdResponse {
+    pub host_id: String,
+    pub instance_id: String,
+    pub nonce: String,
+    pub served_by_this_instance: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CapabilitiesResponse {
+    pub region: String,
+    pub etag_algorithm: String,
+    pub multipart: MultipartCapabilities,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MultipartCapabilities {
+    pub min_part_size: u64,
+    pub max_part_size: u64,
+    pub max_part_count: i32,
+    pub upload_expiry_days: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct UploadEncodingConfiguration {
+    // Inflate gzip and zstd encoded uploads instead of storing them encoded
+    pub decompress_on_upload: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LifecycleConfiguration {
+    pub rules: Vec<LifecycleRule>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LifecycleEvaluationResponse {
+    pub bucket: String,
+    pub evaluated_at: DateTime<Utc>,
+    pub dry_run: bool,
+    pub rules: Vec<LifecycleRuleReport>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -65,10 +442,71 @@ pub struct CopyObjectResponse {
 pub struct CopyObjectResult {
     #[serde(rename = "ETag")]
     pub etag: String,
-    #[serde(rename = "LastModified")]
+    #[serde(rename = "LastModified", serialize_with = "s3_timestamp")]
+    pub last_modified: DateTime<Utc>,
+}
+
+impl XmlRoot for CopyObjectResult {
+    const ROOT: &'static str = "CopyObjectResult";
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CopyPartResult {
+    #[serde(rename = "ETag")]
+    pub etag: String,
+    #[serde(rename = "LastModified", serialize_with = "s3_timestamp")]
     pub last_modified: DateTime<Utc>,
 }
 
+impl XmlRoot for CopyPartResult {
+    const ROOT: &'static str = "CopyPartResult";
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeleteObjectsRequest {
+    #[serde(default)]
+    pub quiet: bool,
+    #[serde(default)]
+    pub object: Vec<ObjectIdentifier>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ObjectIdentifier {
+    pub key: String,
+    #[serde(default)]
+    pub version_id: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeleteObjectsResult {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub deleted: Vec<DeletedObject>,
+    #[serde(rename = "Error", skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<DeleteError>,
+}
+
+impl XmlRoot for DeleteObjectsResult {
+    const ROOT: &'static str = "DeleteResult";
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeletedObject {
+    pub key: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeleteError {
+    pub key: String,
+    pub code: String,
+    pub message: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct InitiateMultipartUploadResponse {
@@ -77,6 +515,10 @@ pub struct InitiateMultipartUploadResponse {
     pub upload_id: String,
 }
 
+impl XmlRoot for InitiateMultipartUploadResponse {
+    const ROOT: &'static str = "InitiateMultipartUploadResult";
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct CompleteMultipartUploadResponse {
@@ -87,6 +529,25 @@ pub struct CompleteMultipartUploadResponse {
     pub etag: String,
 }
 
+impl XmlRoot for CompleteMultipartUploadResponse {
+    const ROOT: &'static str = "CompleteMultipartUploadResult";
+}
+
+// Body of a POST upload answered with success_action_status 201
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PostResponse {
+    pub location: String,
+    pub bucket: String,
+    pub key: String,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+}
+
+impl XmlRoot for PostResponse {
+    const ROOT: &'static str = "PostResponse";
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct Part {
@@ -95,6 +556,132 @@ pub struct Part {
     pub part_number: i32,
 }
 
+// Parts of an upload in progress, in part number order. NextPartNumberMarker
+// is only sent when the listing is truncated.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListPartsResponse {
+    pub bucket: String,
+    pub key: String,
+    pub upload_id: String,
+    pub storage_class: String,
+    pub part_number_marker: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_part_number_marker: Option<i32>,
+    pub max_parts: u32,
+    pub is_truncated: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub part: Vec<ListedPart>,
+}
+
+impl XmlRoot for ListPartsResponse {
+    const ROOT: &'static str = "ListPartsResult";
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListedPart {
+    pub part_number: i32,
+    #[serde(serialize_with = "s3_timestamp")]
+    pub last_modified: DateTime<Utc>,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+    pub size: u64,
+}
+
+// GetObjectAttributes: only the attributes asked for in
+// x-amz-object-attributes are present
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct GetObjectAttributesResponse {
+    #[serde(rename = "ETag", default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<ObjectChecksum>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_parts: Option<ObjectParts>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_class: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_size: Option<u64>,
+}
+
+impl XmlRoot for GetObjectAttributesResponse {
+    const ROOT: &'static str = "GetObjectAttributesResponse";
+}
+
+// The one checksum an object was stored with, under its algorithm's element
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ObjectChecksum {
+    #[serde(rename = "ChecksumCRC32", default, skip_serializing_if = "Option::is_none")]
+    pub crc32: Option<String>,
+    #[serde(rename = "ChecksumCRC32C", default, skip_serializing_if = "Option::is_none")]
+    pub crc32c: Option<String>,
+    #[serde(rename = "ChecksumCRC64NVME", default, skip_serializing_if = "Option::is_none")]
+    pub crc64nvme: Option<String>,
+    #[serde(rename = "ChecksumSHA1", default, skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(rename = "ChecksumSHA256", default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+// Paged like ListParts, by x-amz-max-parts and x-amz-part-number-marker
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ObjectParts {
+    #[serde(rename = "PartsCount")]
+    pub total_parts_count: usize,
+    pub part_number_marker: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_part_number_marker: Option<i32>,
+    pub max_parts: u32,
+    pub is_truncated: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub part: Vec<ObjectPartInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ObjectPartInfo {
+    pub part_number: i32,
+    pub size: u64,
+}
+
+// Markers and Prefix are always present, empty when not supplied; the Next
+// markers only when the listing is truncated
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListMultipartUploadsResponse {
+    pub bucket: String,
+    pub key_marker: String,
+    pub upload_id_marker: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_key_marker: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_upload_id_marker: Option<String>,
+    pub prefix: String,
+    pub max_uploads: u32,
+    pub is_truncated: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub upload: Vec<UploadInfo>,
+}
+
+impl XmlRoot for ListMultipartUploadsResponse {
+    const ROOT: &'static str = "ListMultipartUploadsResult";
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct UploadInfo {
+    pub key: String,
+    pub upload_id: String,
+    pub initiator: Owner,
+    pub owner: Owner,
+    pub storage_class: String,
+    #[serde(serialize_with = "s3_timestamp")]
+    pub initiated: DateTime<Utc>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CompleteMultipartUploadRequest {
     #[serde(rename = "CompleteMultipartUpload")]
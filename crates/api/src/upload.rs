@@ -0,0 +1,381 @@
+use axum::{body::Body, http::HeaderMap};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use futures::StreamExt;
+use ghostbay_auth::ChunkSigner;
+use ghostbay_engine::ByteStream;
+use md5::{Digest, Md5};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+
+use crate::{
+    checksum::{request_checksum, trailing_checksum_algorithm, Checksum, ChecksumAlgorithm, ChecksumHasher},
+    chunked::{decode_aws_chunked, is_aws_chunked, Trailers},
+    encoding::DecodeError,
+    error::ApiError,
+};
+
+// Object and part bodies go to the storage engine as they arrive instead of
+// being read into memory first. The digests a request declares (Content-MD5,
+// a signed x-amz-content-sha256, x-amz-checksum-*) are computed on the way and
+// compared when the body ends; a mismatch fails the stream, so the engine
+// discards what it wrote and nothing is stored. aws-chunked bodies are
+// unframed first, and all of this applies to the payload they carry.
+//
+// hyper answers Expect: 100-continue when the body is first polled, so
+// handlers make every check that can fail (bucket, upload, quota) before the
+// stream reaches the engine; a rejected upload then gets its error without
+// the client sending the body at all.
+pub struct UploadBody {
+    pub stream: ByteStream,
+    // From Content-Length, or x-amz-decoded-content-length for aws-chunked
+    // bodies, when the client sent one
+    pub content_length: Option<u64>,
+    // Bytes received so far; the upload's size once the stream is drained
+    pub received: Arc<AtomicU64>,
+    // The x-amz-checksum-* the body was verified against, once it has been.
+    // It may arrive in the trailer of an aws-chunked body.
+    pub checksum: Arc<OnceLock<Checksum>>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UploadError {
+    #[error("Content-MD5 {expected} does not match the body's {calculated}")]
+    BadDigest { expected: String, calculated: String },
+    #[error("x-amz-content-sha256 {client_computed} does not match the body's {server_computed}")]
+    Sha256Mismatch { client_computed: String, server_computed: String },
+    #[error("{} {expected} does not match the body's {calculated}", algorithm.header_name())]
+    BadChecksum { algorithm: ChecksumAlgorithm, expected: String, calculated: String },
+    #[error("body exceeds {max_size} bytes")]
+    TooLarge { size: u64, max_size: u64 },
+    #[error("body could not be read: {0}")]
+    Read(String),
+    #[error("malformed aws-chunked body: {0}")]
+    MalformedChunk(&'static str),
+    #[error("aws-chunked chunk or trailer signature does not match")]
+    ChunkSignatureMismatch,
+    #[error("body of {received} bytes does not match its declared {expected}")]
+    IncompleteBody { expected: u64, received: u64 },
+    #[error("trailer does not carry the {} named in x-amz-trailer", algorithm.header_name())]
+    MissingTrailingChecksum { algorithm: ChecksumAlgorithm },
+    #[error("malformed multipart/form-data body: {0}")]
+    MalformedForm(&'static str),
+    #[error("body of {size} bytes is below the {min_size} byte minimum")]
+    TooSmall { size: u64, min_size: u64 },
+    #[error("body of at least {size} bytes would take bucket {} past its quota", quota.bucket)]
+    QuotaExceeded { quota: QuotaRoom, size: u64 },
+}
+
+impl From<UploadError> for ApiError {
+    fn from(error: UploadError) -> Self {
+        match error {
+            UploadError::BadDigest { expected, calculated } => ApiError::BadDigest { expected, calculated },
+            UploadError::Sha256Mismatch { client_computed, server_computed } => {
+                ApiError::XAmzContentSHA256Mismatch { client_computed, server_computed }
+            }
+            UploadError::BadChecksum { algorithm, expected, calculated } => {
+                ApiError::BadChecksum { algorithm, expected, calculated }
+            }
+            UploadError::TooLarge { size, max_size } => ApiError::EntityTooLarge { size, max_size },
+            UploadError::Read(_) => ApiError::BadRequest("The request body could not be read.".to_string()),
+            UploadError::MalformedChunk(_) => ApiError::BadRequest("The aws-chunked request body is malformed.".to_string()),
+            UploadError::ChunkSignatureMismatch => {
+                ApiError::AuthorizationFailed("aws-chunked chunk signature does not match".to_string())
+            }
+            UploadError::IncompleteBody { expected, received } => ApiError::IncompleteBody { expected, received },
+            UploadError::MissingTrailingChecksum { algorithm } => ApiError::InvalidArgument {
+                name: algorithm.header_name().to_string(),
+                value: None,
+                message: "The trailer is missing the checksum named in x-amz-trailer.",
+            },
+            UploadError::MalformedForm(reason) => ApiError::MalformedPostRequest(reason),
+            UploadError::TooSmall { size, min_size } => ApiError::EntityTooSmall { part_number: None, size, min_size },
+            UploadError::QuotaExceeded { quota, size } => ApiError::BucketQuotaExceeded {
+                bucket: quota.bucket,
+                quota_bytes: quota.quota_bytes,
+                usage: quota.usage,
+                size,
+            },
+        }
+    }
+}
+
+// The checksum to verify: from a header, or from the trailers once an
+// aws-chunked body ends
+enum ExpectedChecksum {
+    Header(String),
+    Trailer(Trailers),
+}
+
+struct Verifier {
+    content_md5: Option<(String, Md5)>,
+    content_sha256: Option<(String, sha2::Sha256)>,
+    checksum: Option<(ChecksumAlgorithm, ExpectedChecksum, ChecksumHasher)>,
+    verified_checksum: Arc<OnceLock<Checksum>>,
+    received: Arc<AtomicU64>,
+    // x-amz-decoded-content-length, which nothing else enforces
+    decoded_length: Option<u64>,
+    max_size: Option<u64>,
+}
+
+impl Verifier {
+    fn update(&mut self, chunk: &[u8]) -> Result<(), UploadError> {
+        let size = self.received.fetch_add(chunk.len() as u64, Ordering::Relaxed) + chunk.len() as u64;
+        if let Some(max_size) = self.max_size.filter(|max_size| size > *max_size) {
+            return Err(UploadError::TooLarge { size, max_size });
+        }
+        if let Some((_, hasher)) = self.content_md5.as_mut() {
+            hasher.update(chunk);
+        }
+        if let Some((_, hasher)) = self.content_sha256.as_mut() {
+            hasher.update(chunk);
+        }
+        if let Some((_, _, hasher)) = self.checksum.as_mut() {
+            hasher.update(chunk);
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), UploadError> {
+        let received = self.received.load(Ordering::Relaxed);
+        if let Some(expected) = self.decoded_length.filter(|expected| *expected != received) {
+            return Err(UploadError::IncompleteBody { expected, received });
+        }
+        if let Some((expected, hasher)) = self.content_md5 {
+            let calculated = BASE64.encode(hasher.finalize());
+            if calculated != expected {
+                return Err(UploadError::BadDigest { expected, calculated });
+            }
+        }
+        if let Some((client_computed, hasher)) = self.content_sha256 {
+            let server_computed = format!("{:x}", hasher.finalize());
+            if !server_computed.eq_ignore_ascii_case(&client_computed) {
+                return Err(UploadError::Sha256Mismatch { client_computed, server_computed });
+            }
+        }
+        if let Some((algorithm, expected, hasher)) = self.checksum {
+            let expected = match expected {
+                ExpectedChecksum::Header(value) => value,
+                ExpectedChecksum::Trailer(trailers) => trailers
+                    .get()
+                    .and_then(|trailers| trailers.iter().find(|(name, _)| name == algorithm.header_name()))
+                    .map(|(_, value)| value.clone())
+                    .ok_or(UploadError::MissingTrailingChecksum { algorithm })?,
+            };
+            let calculated = hasher.finalize();
+            if calculated != expected {
+                return Err(UploadError::BadChecksum { algorithm, expected, calculated });
+            }
+            let _ = self.verified_checksum.set(Checksum { algorithm, value: calculated });
+        }
+        Ok(())
+    }
+}
+
+// Checks the request's digest headers are well formed and wraps the body to
+// verify them. max_size bounds the body whether or not Content-Length
+// announced it. chunk_signer is set by authentication for bodies sent in
+// signed chunks.
+pub fn upload_body(
+    body: Body,
+    headers: &HeaderMap,
+    chunk_signer: Option<ChunkSigner>,
+    max_size: Option<u64>,
+) -> Result<UploadBody, ApiError> {
+    let body: ByteStream = Box::pin(
+        body.into_data_stream()
+            .map(|chunk| chunk.map_err(|e| UploadError::Read(e.to_string()).into())),
+    );
+    upload_stream(body, headers, chunk_signer, max_size)
+}
+
+// upload_body for data that is not the whole request body, such as the file
+// of a POST upload, with the headers describing it
+pub fn upload_stream(
+    body: ByteStream,
+    headers: &HeaderMap,
+    chunk_signer: Option<ChunkSigner>,
+    max_size: Option<u64>,
+) -> Result<UploadBody, ApiError> {
+    let aws_chunked = is_aws_chunked(headers);
+    let length_header = if aws_chunked { "x-amz-decoded-content-length" } else { "content-length" };
+    let content_length = headers
+        .get(length_header)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if aws_chunked && content_length.is_none() {
+        return Err(ApiError::MissingContentLength);
+    }
+    if let (Some(size), Some(max_size)) = (content_length, max_size)
+        && size > max_size
+    {
+        return Err(ApiError::EntityTooLarge { size, max_size });
+    }
+
+    let content_md5 = match headers.get("content-md5") {
+        Some(value) => {
+            let expected = value.to_str().unwrap_or_default().to_string();
+            let well_formed = BASE64.decode(&expected).is_ok_and(|digest| digest.len() == 16);
+            if !well_formed {
+                return Err(ApiError::InvalidDigest);
+            }
+            Some((expected, Md5::new()))
+        }
+        None => None,
+    };
+    // UNSIGNED-PAYLOAD and the STREAMING-* values sign no whole-body hash
+    let content_sha256 = headers
+        .get("x-amz-content-sha256")
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| v.len() == 64 && v.bytes().all(|b| b.is_ascii_hexdigit()))
+        .map(|expected| (expected.to_string(), sha2::Sha256::new()));
+    let header_checksum = request_checksum(headers)?;
+    let trailing_checksum = trailing_checksum_algorithm(headers)?.filter(|_| aws_chunked);
+    if header_checksum.is_some() && trailing_checksum.is_some() {
+        return Err(ApiError::BadRequest(
+            "Expecting a single x-amz-checksum- header. Multiple checksum Types are not allowed.".to_string(),
+        ));
+    }
+
+    let (body, trailers) = if aws_chunked {
+        let (body, trailers) = decode_aws_chunked(body, chunk_signer);
+        (body, Some(trailers))
+    } else {
+        (body, None)
+    };
+    let checksum = match (header_checksum, trailing_checksum, trailers) {
+        (Some(checksum), _, _) => Some((checksum.algorithm, ExpectedChecksum::Header(checksum.value))),
+        (None, Some(algorithm), Some(trailers)) => Some((algorithm, ExpectedChecksum::Trailer(trailers))),
+        _ => None,
+    };
+
+    let received = Arc::new(AtomicU64::new(0));
+    let verified_checksum = Arc::new(OnceLock::new());
+    let verifier = Verifier {
+        content_md5,
+        content_sha256,
+        checksum: checksum.map(|(algorithm, expected)| (algorithm, expected, algorithm.hasher())),
+        verified_checksum: verified_checksum.clone(),
+        received: received.clone(),
+        decoded_length: content_length.filter(|_| aws_chunked),
+        max_size,
+    };
+
+    let stream = futures::stream::unfold(Some((body, verifier)), |state| async move {
+        let (mut body, mut verifier) = state?;
+        let failed = |error: anyhow::Error| Some((Err(error), None));
+        match body.next().await {
+            Some(Ok(chunk)) => match verifier.update(&chunk) {
+                Ok(()) => Some((Ok(chunk), Some((body, verifier)))),
+                Err(e) => failed(e.into()),
+            },
+            Some(Err(e)) => failed(e),
+            None => verifier.finish().err().and_then(|e| failed(e.into())),
+        }
+    });
+
+    Ok(UploadBody {
+        stream: Box::pin(stream),
+        content_length,
+        received,
+        checksum: verified_checksum,
+    })
+}
+
+// Fails the stream at its end if fewer than min_size bytes came through
+pub fn with_min_size(stream: ByteStream, min_size: u64) -> ByteStream {
+    Box::pin(futures::stream::try_unfold((stream, 0u64), move |(mut stream, size)| async move {
+        match stream.next().await {
+            Some(chunk) => {
+                let chunk = chunk?;
+                let size = size + chunk.len() as u64;
+                Ok(Some((chunk, (stream, size))))
+            }
+            None if size < min_size => Err(UploadError::TooSmall { size, min_size }.into()),
+            None => Ok(None),
+        }
+    }))
+}
+
+// What a bucket's quota leaves for one write, after the object it replaces
+#[derive(Debug, Clone)]
+pub struct QuotaRoom {
+    pub bucket: String,
+    pub quota_bytes: u64,
+    pub usage: u64,
+    pub room: u64,
+}
+
+// Fails the stream as soon as more bytes than the quota leaves room for have
+// come through. A declared length is checked before the body is read; this
+// holds chunked bodies, which declare none, to the same limit, and the engine
+// discards what it wrote when the stream fails.
+pub fn with_quota(stream: ByteStream, quota: Option<QuotaRoom>) -> ByteStream {
+    let Some(quota) = quota else {
+        return stream;
+    };
+    Box::pin(futures::stream::try_unfold((stream, 0u64), move |(mut stream, size)| {
+        let quota = quota.clone();
+        async move {
+            let Some(chunk) = stream.next().await else {
+                return Ok(None);
+            };
+            let chunk = chunk?;
+            let size = size + chunk.len() as u64;
+            if size > quota.room {
+                return Err(UploadError::QuotaExceeded { quota, size }.into());
+            }
+            Ok(Some((chunk, (stream, size))))
+        }
+    }))
+}
+
+// Maps a failed write to a client error when the body, rather than storage,
+// was the cause
+pub fn upload_error(error: anyhow::Error) -> ApiError {
+    let error = match error.downcast::<UploadError>() {
+        Ok(upload_error) => {
+            tracing::debug!("Rejected upload: {}", upload_error);
+            return upload_error.into();
+        }
+        Err(error) => error,
+    };
+    match error.downcast::<DecodeError>() {
+        Ok(decode_error) => {
+            tracing::debug!("Rejected encoded upload: {}", decode_error);
+            decode_error.into()
+        }
+        Err(error) => ApiError::Storage(error.to_string()),
+    }
+}
+
+// Serializes writes of the same part of an upload, so the part's file and its
+// catalog record always come from the same write. Concurrent uploads of one
+// part otherwise race between the engine's rename and the catalog update,
+// and the record could name the ETag of the write whose file was replaced.
+#[derive(Debug, Default)]
+pub struct PartLocks {
+    // By upload id and part number, dropped once no write holds them
+    locks: Mutex<HashMap<(String, i32), Weak<PartLock>>>,
+}
+
+type PartLock = tokio::sync::Mutex<()>;
+
+impl PartLocks {
+    pub async fn lock(&self, upload_id: &str, part_number: i32) -> tokio::sync::OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.locks.lock().unwrap();
+            locks.retain(|_, lock| lock.strong_count() > 0);
+            let key = (upload_id.to_string(), part_number);
+            match locks.get(&key).and_then(Weak::upgrade) {
+                Some(lock) => lock,
+                None => {
+                    let lock = Arc::new(PartLock::new(()));
+                    locks.insert(key, Arc::downgrade(&lock));
+                    lock
+                }
+            }
+        };
+        lock.lock_owned().await
+    }
+}
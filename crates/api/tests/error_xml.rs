@@ -0,0 +1,175 @@
+// Every ApiError renders to the status, headers and XML body recorded under
+// tests/golden/errors. Run with UPDATE_GOLDEN=1 to rewrite the files after a
+// deliberate change, then review the diff.
+
+use ghostbay_api::{checksum::ChecksumAlgorithm, ApiError};
+use std::path::PathBuf;
+
+fn errors() -> Vec<ApiError> {
+    vec![
+        ApiError::BucketNotFound("photos".to_string()),
+        ApiError::ObjectNotFound("cats/tom.jpg".to_string()),
+        ApiError::BucketAlreadyExists("photos".to_string()),
+        ApiError::BucketAlreadyOwnedByYou("photos".to_string()),
+        ApiError::InvalidBucketName { bucket: "Bad_Name".to_string(), reason: "Bucket names must not contain uppercase letters." },
+        ApiError::InvalidObjectKey("".to_string()),
+        ApiError::NoSuchVersion { key: "cats/tom.jpg".to_string(), version_id: "3f2b".to_string() },
+        ApiError::NoSuchCorsConfiguration("photos".to_string()),
+        ApiError::NoSuchBucketPolicy("photos".to_string()),
+        ApiError::MalformedPolicy("Policy has invalid action: iam:PassRole".to_string()),
+        ApiError::MalformedAcl("unknown grantee".to_string()),
+        ApiError::MalformedPostRequest("missing file field"),
+        ApiError::InvalidPolicyDocument("Invalid Policy: Invalid expiration".to_string()),
+        ApiError::PostPolicyDenied("Policy Condition failed: [\"eq\", \"$key\", \"a\"]".to_string()),
+        ApiError::NoSuchLifecycleConfiguration("photos".to_string()),
+        ApiError::NoSuchTagSet("photos".to_string()),
+        ApiError::CorsRequestNotAllowed { method: "DELETE".to_string() },
+        ApiError::NoSuchUpload("9b1c".to_string()),
+        ApiError::InvalidArgument { name: "max-keys".to_string(), value: Some("-1".to_string()), message: "Argument max-keys must be an integer between 0 and 2147483647" },
+        ApiError::PartCountExhausted { upload_id: "9b1c".to_string(), part_number: 10_001, max_part_count: 10_000 },
+        ApiError::IllegalVersioningConfiguration("Sometimes".to_string()),
+        ApiError::InvalidTag("The TagKey you have provided is invalid"),
+        ApiError::TooManyTags { count: 11, limit: 10 },
+        ApiError::InvalidDigest,
+        ApiError::BadDigest { expected: "1B2M2Y8AsgTpgAmY7PhCfg==".to_string(), calculated: "XUFAKrxLKna5cZ2REBfFkg==".to_string() },
+        ApiError::BadChecksum { algorithm: ChecksumAlgorithm::Crc32, expected: "AAAAAA==".to_string(), calculated: "NhCmhg==".to_string() },
+        ApiError::XAmzContentSHA256Mismatch { client_computed: "abc".to_string(), server_computed: "def".to_string() },
+        ApiError::PreconditionFailed { condition: "If-Match" },
+        ApiError::InvalidRange { range: "bytes=100-200".to_string(), size: 50 },
+        ApiError::InvalidPartNumber { part_number: 3, parts_count: 2 },
+        ApiError::MetadataTooLarge { size: 2049, max_size: 2048 },
+        ApiError::MissingContentLength,
+        ApiError::IncompleteBody { expected: 10, received: 4 },
+        ApiError::EntityTooLarge { size: 6_000_000_000, max_size: 5_368_709_120 },
+        ApiError::EntityTooSmall { part_number: Some(1), size: 1024, min_size: 5_242_880 },
+        ApiError::AuthenticationFailed("unknown access key".to_string()),
+        ApiError::AuthorizationFailed("signature does not match".to_string()),
+        ApiError::RequestExpired {
+            expires_in_seconds: 60,
+            expires_at: "2026-01-01T00:01:00Z".to_string(),
+            server_time: "2026-01-01T00:05:00Z".to_string(),
+        },
+        ApiError::Internal(anyhow::anyhow!("disk on fire")),
+        ApiError::Database(sqlx::Error::RowNotFound),
+        ApiError::Storage("short write".to_string()),
+        ApiError::BadRequest("The aws-chunked request body is malformed.".to_string()),
+        ApiError::PermanentRedirect { bucket: "photos".to_string(), region: "eu-west-1".to_string(), endpoint: "eu.example.com".to_string() },
+        ApiError::BucketQuotaExceeded { bucket: "photos".to_string(), quota_bytes: 1000, usage: 900, size: 200 },
+        ApiError::QuotaExceeded {
+            access_key_id: "GBEXAMPLE".to_string(),
+            quota: "bytes_out",
+            resets_at: "2026-02-01T00:00:00+00:00".to_string(),
+            retry_after_seconds: 3600,
+        },
+        ApiError::SlowDown { retry_after_seconds: 2 },
+    ]
+}
+
+// Names each golden file. Exhaustive, so a new variant fails to compile
+// until it is added to errors() above.
+fn variant(error: &ApiError) -> &'static str {
+    match error {
+        ApiError::BucketNotFound(_) => "BucketNotFound",
+        ApiError::ObjectNotFound(_) => "ObjectNotFound",
+        ApiError::BucketAlreadyExists(_) => "BucketAlreadyExists",
+        ApiError::BucketAlreadyOwnedByYou(_) => "BucketAlreadyOwnedByYou",
+        ApiError::InvalidBucketName { .. } => "InvalidBucketName",
+        ApiError::InvalidObjectKey(_) => "InvalidObjectKey",
+        ApiError::NoSuchVersion { .. } => "NoSuchVersion",
+        ApiError::NoSuchCorsConfiguration(_) => "NoSuchCorsConfiguration",
+        ApiError::NoSuchBucketPolicy(_) => "NoSuchBucketPolicy",
+        ApiError::MalformedPolicy(_) => "MalformedPolicy",
+        ApiError::MalformedAcl(_) => "MalformedAcl",
+        ApiError::MalformedPostRequest(_) => "MalformedPostRequest",
+        ApiError::InvalidPolicyDocument(_) => "InvalidPolicyDocument",
+        ApiError::PostPolicyDenied(_) => "PostPolicyDenied",
+        ApiError::NoSuchLifecycleConfiguration(_) => "NoSuchLifecycleConfiguration",
+        ApiError::NoSuchTagSet(_) => "NoSuchTagSet",
+        ApiError::CorsRequestNotAllowed { .. } => "CorsRequestNotAllowed",
+        ApiError::NoSuchUpload(_) => "NoSuchUpload",
+        ApiError::InvalidArgument { .. } => "InvalidArgument",
+        ApiError::PartCountExhausted { .. } => "PartCountExhausted",
+        ApiError::IllegalVersioningConfiguration(_) => "IllegalVersioningConfiguration",
+        ApiError::InvalidTag(_) => "InvalidTag",
+        ApiError::TooManyTags { .. } => "TooManyTags",
+        ApiError::InvalidDigest => "InvalidDigest",
+        ApiError::BadDigest { .. } => "BadDigest",
+        ApiError::BadChecksum { .. } => "BadChecksum",
+        ApiError::XAmzContentSHA256Mismatch { .. } => "XAmzContentSHA256Mismatch",
+        ApiError::PreconditionFailed { .. } => "PreconditionFailed",
+        ApiError::InvalidRange { .. } => "InvalidRange",
+        ApiError::InvalidPartNumber { .. } => "InvalidPartNumber",
+        ApiError::MetadataTooLarge { .. } => "MetadataTooLarge",
+        ApiError::MissingContentLength => "MissingContentLength",
+        ApiError::IncompleteBody { .. } => "IncompleteBody",
+        ApiError::EntityTooLarge { .. } => "EntityTooLarge",
+        ApiError::EntityTooSmall { .. } => "EntityTooSmall",
+        ApiError::AuthenticationFailed(_) => "AuthenticationFailed",
+        ApiError::AuthorizationFailed(_) => "AuthorizationFailed",
+        ApiError::RequestExpired { .. } => "RequestExpired",
+        ApiError::Internal(_) => "Internal",
+        ApiError::Database(_) => "Database",
+        ApiError::Storage(_) => "Storage",
+        ApiError::BadRequest(_) => "BadRequest",
+        ApiError::PermanentRedirect { .. } => "PermanentRedirect",
+        ApiError::BucketQuotaExceeded { .. } => "BucketQuotaExceeded",
+        ApiError::QuotaExceeded { .. } => "QuotaExceeded",
+        ApiError::SlowDown { .. } => "SlowDown",
+    }
+}
+
+// The status line, every header and the body, as a client would see them
+async fn render(error: ApiError) -> String {
+    let response = error.into_response_with_context("4442587FB7D0A2F9", Some("aG9zdA=="), Some("/photos/cats/tom.jpg"));
+    let mut rendered = format!("{}\n", response.status());
+    let mut headers: Vec<String> = response
+        .headers()
+        .iter()
+        .map(|(name, value)| format!("{}: {}\n", name, value.to_str().unwrap()))
+        .collect();
+    headers.sort();
+    rendered.extend(headers);
+    rendered.push('\n');
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    rendered.push_str(std::str::from_utf8(&body).unwrap());
+    rendered.push('\n');
+    rendered
+}
+
+#[tokio::test]
+async fn every_error_matches_its_golden_file() {
+    let golden_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/errors");
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut mismatched = Vec::new();
+
+    for error in errors() {
+        let path = golden_dir.join(format!("{}.txt", variant(&error)));
+        let rendered = render(error).await;
+        if update {
+            std::fs::create_dir_all(&golden_dir).unwrap();
+            std::fs::write(&path, &rendered).unwrap();
+            continue;
+        }
+        let golden = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        if golden != rendered {
+            mismatched.push(format!("{}:\n--- golden\n{}--- rendered\n{}", path.display(), golden, rendered));
+        }
+    }
+    assert!(mismatched.is_empty(), "{}", mismatched.join("\n"));
+}
+
+#[test]
+fn every_variant_has_a_golden_file() {
+    let golden_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/errors");
+    let names: Vec<&str> = errors().iter().map(variant).collect();
+    let mut files: Vec<String> = std::fs::read_dir(&golden_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().trim_end_matches(".txt").to_string())
+        .collect();
+    files.sort();
+    let mut expected: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+    expected.sort();
+    expected.dedup();
+    assert_eq!(expected.len(), names.len(), "a variant is listed twice in errors()");
+    assert_eq!(files, expected);
+}
@@ -0,0 +1,230 @@
+// Listing documents render to the status, headers and XML body recorded
+// under tests/golden/listings, one file per case. The cases pin down which
+// elements S3 sends empty and which it leaves out, which strict .NET and
+// Java clients depend on. Run with UPDATE_GOLDEN=1 to rewrite the files
+// after a deliberate change, then review the diff.
+
+use axum::response::IntoResponse;
+use chrono::{DateTime, TimeZone, Utc};
+use ghostbay_api::responses::*;
+use std::path::PathBuf;
+
+fn at(second: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, second).unwrap()
+}
+
+fn owner() -> Owner {
+    Owner { id: "ghostbay".to_string(), display_name: "GhostBay".to_string() }
+}
+
+fn object(key: &str, owner: Option<Owner>) -> ObjectInfo {
+    ObjectInfo {
+        key: key.to_string(),
+        last_modified: at(5),
+        etag: "\"5d41402abc4b2a76b9719d911017c592\"".to_string(),
+        size: 5,
+        storage_class: "STANDARD".to_string(),
+        owner,
+    }
+}
+
+fn prefixes(prefixes: &[&str]) -> Vec<CommonPrefix> {
+    prefixes.iter().map(|prefix| CommonPrefix { prefix: prefix.to_string() }).collect()
+}
+
+fn v2(prefix: &str, delimiter: Option<&str>) -> ListObjectsV2Response {
+    ListObjectsV2Response {
+        name: "photos".to_string(),
+        prefix: prefix.to_string(),
+        delimiter: delimiter.map(str::to_string),
+        start_after: None,
+        key_count: 0,
+        max_keys: 1000,
+        is_truncated: false,
+        continuation_token: None,
+        next_continuation_token: None,
+        contents: Vec::new(),
+        common_prefixes: Vec::new(),
+    }
+}
+
+fn v1(prefix: &str, delimiter: Option<&str>) -> ListObjectsV1Response {
+    ListObjectsV1Response {
+        name: "photos".to_string(),
+        prefix: prefix.to_string(),
+        marker: String::new(),
+        next_marker: None,
+        max_keys: 1000,
+        delimiter: delimiter.map(str::to_string),
+        is_truncated: false,
+        contents: Vec::new(),
+        common_prefixes: Vec::new(),
+    }
+}
+
+fn uploads() -> ListMultipartUploadsResponse {
+    ListMultipartUploadsResponse {
+        bucket: "photos".to_string(),
+        key_marker: String::new(),
+        upload_id_marker: String::new(),
+        next_key_marker: None,
+        next_upload_id_marker: None,
+        prefix: String::new(),
+        max_uploads: 1000,
+        is_truncated: false,
+        upload: Vec::new(),
+    }
+}
+
+fn upload(key: &str, upload_id: &str) -> UploadInfo {
+    UploadInfo {
+        key: key.to_string(),
+        upload_id: upload_id.to_string(),
+        initiator: owner(),
+        owner: owner(),
+        storage_class: "STANDARD".to_string(),
+        initiated: at(6),
+    }
+}
+
+// Each case's golden file name and the response it renders
+fn cases() -> Vec<(&'static str, axum::response::Response)> {
+    vec![
+        (
+            "ListBuckets-empty",
+            XmlResponse(ListBucketsResponse { owner: owner(), buckets: Buckets { bucket: Vec::new() } }).into_response(),
+        ),
+        (
+            "ListBuckets",
+            XmlResponse(ListBucketsResponse {
+                owner: owner(),
+                buckets: Buckets {
+                    bucket: ["logs", "photos"]
+                        .iter()
+                        .map(|name| BucketInfo { name: name.to_string(), creation_date: at(1), tag_set: None })
+                        .collect(),
+                },
+            })
+            .into_response(),
+        ),
+        ("ListObjectsV2-empty-bucket", XmlResponse(v2("", None)).into_response()),
+        ("ListObjectsV2-prefix-without-matches", XmlResponse(v2("cats/", None)).into_response()),
+        (
+            "ListObjectsV2-delimiter-with-only-common-prefixes",
+            XmlResponse(ListObjectsV2Response {
+                key_count: 2,
+                common_prefixes: prefixes(&["cats/", "dogs/"]),
+                ..v2("", Some("/"))
+            })
+            .into_response(),
+        ),
+        ("ListObjectsV2-delimiter-with-neither", XmlResponse(v2("birds/", Some("/"))).into_response()),
+        (
+            "ListObjectsV2-truncated",
+            XmlResponse(ListObjectsV2Response {
+                start_after: Some("a.txt".to_string()),
+                key_count: 2,
+                max_keys: 2,
+                is_truncated: true,
+                continuation_token: Some("Yi50eHQ=".to_string()),
+                next_continuation_token: Some("ZG9ncy8=".to_string()),
+                contents: vec![object("c.txt", Some(owner()))],
+                common_prefixes: prefixes(&["dogs/"]),
+                ..v2("", Some("/"))
+            })
+            .into_response(),
+        ),
+        ("ListObjectsV1-empty-bucket", XmlResponse(v1("", None)).into_response()),
+        ("ListObjectsV1-delimiter-with-neither", XmlResponse(v1("birds/", Some("/"))).into_response()),
+        (
+            "ListObjectsV1-truncated-with-delimiter",
+            XmlResponse(ListObjectsV1Response {
+                marker: "a.txt".to_string(),
+                next_marker: Some("dogs/".to_string()),
+                max_keys: 2,
+                is_truncated: true,
+                contents: vec![object("c.txt", None)],
+                common_prefixes: prefixes(&["dogs/"]),
+                ..v1("", Some("/"))
+            })
+            .into_response(),
+        ),
+        (
+            "ListObjectsV1-truncated-without-delimiter",
+            XmlResponse(ListObjectsV1Response {
+                max_keys: 2,
+                is_truncated: true,
+                contents: vec![object("a.txt", None), object("b.txt", None)],
+                ..v1("", None)
+            })
+            .into_response(),
+        ),
+        ("ListMultipartUploads-empty", XmlResponse(uploads()).into_response()),
+        (
+            "ListMultipartUploads-truncated",
+            XmlResponse(ListMultipartUploadsResponse {
+                key_marker: "a.bin".to_string(),
+                next_key_marker: Some("c.bin".to_string()),
+                next_upload_id_marker: Some("7e2a".to_string()),
+                prefix: "b".to_string(),
+                max_uploads: 2,
+                is_truncated: true,
+                upload: vec![upload("b.bin", "3f9c"), upload("c.bin", "7e2a")],
+                ..uploads()
+            })
+            .into_response(),
+        ),
+    ]
+}
+
+// The status line, every header and the body, as a client would see them
+async fn render(response: axum::response::Response) -> String {
+    let mut rendered = format!("{}\n", response.status());
+    let mut headers: Vec<String> = response
+        .headers()
+        .iter()
+        .map(|(name, value)| format!("{}: {}\n", name, value.to_str().unwrap()))
+        .collect();
+    headers.sort();
+    rendered.extend(headers);
+    rendered.push('\n');
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    rendered.push_str(std::str::from_utf8(&body).unwrap());
+    rendered.push('\n');
+    rendered
+}
+
+#[tokio::test]
+async fn every_listing_matches_its_golden_file() {
+    let golden_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/listings");
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut mismatched = Vec::new();
+
+    for (name, response) in cases() {
+        let path = golden_dir.join(format!("{}.txt", name));
+        let rendered = render(response).await;
+        if update {
+            std::fs::create_dir_all(&golden_dir).unwrap();
+            std::fs::write(&path, &rendered).unwrap();
+            continue;
+        }
+        let golden = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        if golden != rendered {
+            mismatched.push(format!("{}:\n--- golden\n{}--- rendered\n{}", path.display(), golden, rendered));
+        }
+    }
+    assert!(mismatched.is_empty(), "{}", mismatched.join("\n"));
+}
+
+#[test]
+fn every_golden_file_has_a_case() {
+    let golden_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/listings");
+    let mut files: Vec<String> = std::fs::read_dir(&golden_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().trim_end_matches(".txt").to_string())
+        .collect();
+    files.sort();
+    let mut expected: Vec<String> = cases().into_iter().map(|(name, _)| name.to_string()).collect();
+    expected.sort();
+    assert_eq!(files, expected);
+}
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+
+// Bucket ACLs, S3's older grant model: a list of grants, each giving one
+// grantee one permission on the bucket and, as ghostbay has no object ACLs,
+// on its objects. Canonical user grantees are access key ids. A request that
+// no grant covers is left to bucket and key policies.
+pub const ALL_USERS_URI: &str = "http://acs.amazonaws.com/groups/global/AllUsers";
+pub const AUTHENTICATED_USERS_URI: &str = "http://acs.amazonaws.com/groups/global/AuthenticatedUsers";
+
+#[derive(Debug, thiserror::Error)]
+pub enum AclError {
+    #[error("Unknown canned ACL: {0}")]
+    UnknownCannedAcl(String),
+    #[error("Invalid permission: {0}")]
+    InvalidPermission(String),
+    #[error("Invalid grantee: {0}")]
+    InvalidGrantee(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Permission {
+    FullControl,
+    Read,
+    Write,
+    ReadAcp,
+    WriteAcp,
+}
+
+impl Permission {
+    pub fn parse(permission: &str) -> Result<Self, AclError> {
+        match permission {
+            "FULL_CONTROL" => Ok(Permission::FullControl),
+            "READ" => Ok(Permission::Read),
+            "WRITE" => Ok(Permission::Write),
+            "READ_ACP" => Ok(Permission::ReadAcp),
+            "WRITE_ACP" => Ok(Permission::WriteAcp),
+            _ => Err(AclError::InvalidPermission(permission.to_string())),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Permission::FullControl => "FULL_CONTROL",
+            Permission::Read => "READ",
+            Permission::Write => "WRITE",
+            Permission::ReadAcp => "READ_ACP",
+            Permission::WriteAcp => "WRITE_ACP",
+        }
+    }
+
+    // The permission an S3 action needs; None for actions no grant covers,
+    // such as managing the bucket's policy
+    fn for_action(action: &str) -> Option<Self> {
+        match action {
+            "s3:ListBucket" | "s3:GetObject" => Some(Permission::Read),
+            "s3:PutObject" | "s3:DeleteObject" => Some(Permission::Write),
+            "s3:GetBucketAcl" => Some(Permission::ReadAcp),
+            "s3:PutBucketAcl" => Some(Permission::WriteAcp),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Grantee {
+    CanonicalUser(String),
+    AllUsers,
+    AuthenticatedUsers,
+}
+
+impl Grantee {
+    pub fn from_group_uri(uri: &str) -> Result<Self, AclError> {
+        match uri {
+            ALL_USERS_URI => Ok(Grantee::AllUsers),
+            AUTHENTICATED_USERS_URI => Ok(Grantee::AuthenticatedUsers),
+            _ => Err(AclError::InvalidGrantee(uri.to_string())),
+        }
+    }
+
+    fn matches(&self, access_key_id: Option<&str>) -> bool {
+        match self {
+            Grantee::CanonicalUser(id) => Some(id.as_str()) == access_key_id,
+            Grantee::AllUsers => true,
+            Grantee::AuthenticatedUsers => access_key_id.is_some(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Grant {
+    pub grantee: Grantee,
+    pub permission: Permission,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Acl {
+    pub owner: String,
+    pub grants: Vec<Grant>,
+}
+
+impl Acl {
+    // What buckets have until an ACL is put: full control for the owner
+    pub fn private(owner: &str) -> Self {
+        Self {
+            owner: owner.to_string(),
+            grants: vec![Grant {
+                grantee: Grantee::CanonicalUser(owner.to_string()),
+                permission: Permission::FullControl,
+            }],
+        }
+    }
+
+    // The ACLs x-amz-acl names. S3's object-only and log-delivery ones are
+    // not supported.
+    pub fn canned(name: &str, owner: &str) -> Result<Self, AclError> {
+        let mut acl = Self::private(owner);
+        let public = |grantee, permission| Grant { grantee, permission };
+        match name {
+            "private" => {}
+            "public-read" => acl.grants.push(public(Grantee::AllUsers, Permission::Read)),
+            "public-read-write" => {
+                acl.grants.push(public(Grantee::AllUsers, Permission::Read));
+                acl.grants.push(public(Grantee::AllUsers, Permission::Write));
+            }
+            "authenticated-read" => acl.grants.push(public(Grantee::AuthenticatedUsers, Permission::Read)),
+            _ => return Err(AclError::UnknownCannedAcl(name.to_string())),
+        }
+        Ok(acl)
+    }
+
+    // access_key_id is None for anonymous requests
+    pub fn allows(&self, access_key_id: Option<&str>, action: &str) -> bool {
+        let Some(permission) = Permission::for_action(action) else {
+            return false;
+        };
+        self.grants.iter().any(|grant| {
+            grant.grantee.matches(access_key_id)
+                && (grant.permission == permission || grant.permission == Permission::FullControl)
+        })
+    }
+}
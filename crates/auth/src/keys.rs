@@ -1,10 +1,15 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use ghostbay_catalog::telemetry::record_query;
 use sqlx::{Row, SqlitePool};
+use std::time::Instant;
 use uuid::Uuid;
 use rand::Rng;
 
+use crate::policy::validate_key_policies;
+use crate::quota::KeyQuota;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccessKey {
     pub id: Uuid,
@@ -15,6 +20,7 @@ pub struct AccessKey {
     pub is_active: bool,
     pub policies: Vec<String>,
     pub description: Option<String>,
+    pub quota: KeyQuota,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +28,7 @@ pub struct CreateAccessKeyRequest {
     pub policies: Vec<String>,
     pub description: Option<String>,
     pub expires_at: Option<DateTime<Utc>>,
+    pub quota: KeyQuota,
 }
 
 pub struct AccessKeyRepository {
@@ -34,16 +41,23 @@ impl AccessKeyRepository {
     }
 
     pub async fn create(&self, req: CreateAccessKeyRequest) -> Result<AccessKey> {
+        self.create_with_credentials(generate_access_key_id(), generate_secret_access_key(), req).await
+    }
+
+    // Used by provisioning, where ids and secrets are declared up front
+    #[tracing::instrument(skip(self, secret_access_key, req), fields(db.operation = "INSERT", db.rows = tracing::field::Empty))]
+    pub async fn create_with_credentials(&self, access_key_id: String, secret_access_key: String, req: CreateAccessKeyRequest) -> Result<AccessKey> {
+        validate_key_policies(&req.policies)?;
+        let started = Instant::now();
         let id = Uuid::new_v4();
-        let access_key_id = generate_access_key_id();
-        let secret_access_key = generate_secret_access_key();
         let now = Utc::now();
         let policies_json = serde_json::to_string(&req.policies)?;
 
         sqlx::query(
             r#"
-            INSERT INTO access_keys (id, access_key_id, secret_access_key, created_at, expires_at, is_active, policies, description)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO access_keys (id, access_key_id, secret_access_key, created_at, expires_at, is_active, policies, description,
+                                     max_requests_per_day, max_bytes_in_per_month, max_bytes_out_per_month)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#
         )
         .bind(id.to_string())
@@ -54,8 +68,13 @@ impl AccessKeyRepository {
         .bind(true)
         .bind(&policies_json)
         .bind(&req.description)
+        .bind(req.quota.max_requests_per_day)
+        .bind(req.quota.max_bytes_in_per_month)
+        .bind(req.quota.max_bytes_out_per_month)
         .execute(&self.pool)
-        .await?;
+        .await
+        .context("AccessKeyRepository::create_with_credentials")?;
+        record_query(started, 1);
 
         Ok(AccessKey {
             id,
@@ -66,16 +85,21 @@ impl AccessKeyRepository {
             is_active: true,
             policies: req.policies,
             description: req.description,
+            quota: req.quota,
         })
     }
 
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
     pub async fn find_by_access_key_id(&self, access_key_id: &str) -> Result<Option<AccessKey>> {
+        let started = Instant::now();
         let row = sqlx::query(
-            "SELECT id, access_key_id, secret_access_key, created_at, expires_at, is_active, policies, description FROM access_keys WHERE access_key_id = ? AND is_active = true"
+            "SELECT id, access_key_id, secret_access_key, created_at, expires_at, is_active, policies, description, max_requests_per_day, max_bytes_in_per_month, max_bytes_out_per_month FROM access_keys WHERE access_key_id = ? AND is_active = true"
         )
         .bind(access_key_id)
         .fetch_optional(&self.pool)
-        .await?;
+        .await
+        .context("AccessKeyRepository::find_by_access_key_id")?;
+        record_query(started, row.is_some() as u64);
 
         if let Some(row) = row {
             let id: String = row.get("id");
@@ -86,6 +110,11 @@ impl AccessKeyRepository {
             let is_active: bool = row.get("is_active");
             let policies_json: String = row.get("policies");
             let description: Option<String> = row.get("description");
+            let quota = KeyQuota {
+                max_requests_per_day: row.get("max_requests_per_day"),
+                max_bytes_in_per_month: row.get("max_bytes_in_per_month"),
+                max_bytes_out_per_month: row.get("max_bytes_out_per_month"),
+            };
 
             let policies: Vec<String> = serde_json::from_str(&policies_json)?;
             let access_key = AccessKey {
@@ -100,6 +129,7 @@ impl AccessKeyRepository {
                 is_active,
                 policies,
                 description,
+                quota,
             };
             Ok(Some(access_key))
         } else {
@@ -107,20 +137,25 @@ impl AccessKeyRepository {
         }
     }
 
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
     pub async fn list(&self, include_inactive: bool) -> Result<Vec<AccessKey>> {
+        let started = Instant::now();
         let rows = if include_inactive {
             sqlx::query(
-                "SELECT id, access_key_id, secret_access_key, created_at, expires_at, is_active, policies, description FROM access_keys ORDER BY created_at DESC"
+                "SELECT id, access_key_id, secret_access_key, created_at, expires_at, is_active, policies, description, max_requests_per_day, max_bytes_in_per_month, max_bytes_out_per_month FROM access_keys ORDER BY created_at DESC"
             )
             .fetch_all(&self.pool)
-            .await?
+            .await
+            .context("AccessKeyRepository::list")?
         } else {
             sqlx::query(
-                "SELECT id, access_key_id, secret_access_key, created_at, expires_at, is_active, policies, description FROM access_keys WHERE is_active = true ORDER BY created_at DESC"
+                "SELECT id, access_key_id, secret_access_key, created_at, expires_at, is_active, policies, description, max_requests_per_day, max_bytes_in_per_month, max_bytes_out_per_month FROM access_keys WHERE is_active = true ORDER BY created_at DESC"
             )
             .fetch_all(&self.pool)
-            .await?
+            .await
+            .context("AccessKeyRepository::list")?
         };
+        record_query(started, rows.len() as u64);
 
         let mut access_keys = Vec::new();
         for row in rows {
@@ -132,6 +167,11 @@ impl AccessKeyRepository {
             let is_active: bool = row.get("is_active");
             let policies_json: String = row.get("policies");
             let description: Option<String> = row.get("description");
+            let quota = KeyQuota {
+                max_requests_per_day: row.get("max_requests_per_day"),
+                max_bytes_in_per_month: row.get("max_bytes_in_per_month"),
+                max_bytes_out_per_month: row.get("max_bytes_out_per_month"),
+            };
 
             let policies: Vec<String> = serde_json::from_str(&policies_json)?;
             let access_key = AccessKey {
@@ -146,6 +186,7 @@ impl AccessKeyRepository {
                 is_active,
                 policies,
                 description,
+                quota,
             };
             access_keys.push(access_key);
         }
@@ -153,29 +194,39 @@ impl AccessKeyRepository {
         Ok(access_keys)
     }
 
+    #[tracing::instrument(skip(self), fields(db.operation = "UPDATE", db.rows = tracing::field::Empty))]
     pub async fn deactivate(&self, access_key_id: &str) -> Result<bool> {
+        let started = Instant::now();
         let result = sqlx::query(
             "UPDATE access_keys SET is_active = false WHERE access_key_id = ?"
         )
         .bind(access_key_id)
         .execute(&self.pool)
-        .await?;
+        .await
+        .context("AccessKeyRepository::deactivate")?;
+        record_query(started, result.rows_affected());
 
         Ok(result.rows_affected() > 0)
     }
 
+    #[tracing::instrument(skip(self), fields(db.operation = "DELETE", db.rows = tracing::field::Empty))]
     pub async fn delete(&self, access_key_id: &str) -> Result<bool> {
+        let started = Instant::now();
         let result = sqlx::query(
             "DELETE FROM access_keys WHERE access_key_id = ?"
         )
         .bind(access_key_id)
         .execute(&self.pool)
-        .await?;
+        .await
+        .context("AccessKeyRepository::delete")?;
+        record_query(started, result.rows_affected());
 
         Ok(result.rows_affected() > 0)
     }
 
+    #[tracing::instrument(skip(self), fields(db.operation = "UPDATE", db.rows = tracing::field::Empty))]
     pub async fn rotate(&self, access_key_id: &str) -> Result<Option<AccessKey>> {
+        let started = Instant::now();
         let existing = self.find_by_access_key_id(access_key_id).await?;
         if let Some(existing_key) = existing {
             let new_secret = generate_secret_access_key();
@@ -188,7 +239,9 @@ impl AccessKeyRepository {
             .bind(now.to_rfc3339())
             .bind(access_key_id)
             .execute(&self.pool)
-            .await?;
+            .await
+            .context("AccessKeyRepository::rotate")?;
+            record_query(started, 1);
 
             Ok(Some(AccessKey {
                 secret_access_key: new_secret,
@@ -200,14 +253,84 @@ impl AccessKeyRepository {
         }
     }
 
+    #[tracing::instrument(skip(self, quota), fields(db.operation = "UPDATE", db.rows = tracing::field::Empty))]
+    pub async fn set_quota(&self, access_key_id: &str, quota: &KeyQuota) -> Result<bool> {
+        let started = Instant::now();
+        let result = sqlx::query(
+            "UPDATE access_keys SET max_requests_per_day = ?, max_bytes_in_per_month = ?, max_bytes_out_per_month = ? WHERE access_key_id = ?"
+        )
+        .bind(quota.max_requests_per_day)
+        .bind(quota.max_bytes_in_per_month)
+        .bind(quota.max_bytes_out_per_month)
+        .bind(access_key_id)
+        .execute(&self.pool)
+        .await
+        .context("AccessKeyRepository::set_quota")?;
+        record_query(started, result.rows_affected());
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(skip(self, policies), fields(db.operation = "UPDATE", db.rows = tracing::field::Empty))]
+    pub async fn set_policies(&self, access_key_id: &str, policies: &[String]) -> Result<bool> {
+        validate_key_policies(policies)?;
+        let started = Instant::now();
+        let result = sqlx::query(
+            "UPDATE access_keys SET policies = ? WHERE access_key_id = ?"
+        )
+        .bind(serde_json::to_string(policies)?)
+        .bind(access_key_id)
+        .execute(&self.pool)
+        .await
+        .context("AccessKeyRepository::set_policies")?;
+        record_query(started, result.rows_affected());
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(skip(self, secret_access_key), fields(db.operation = "UPDATE", db.rows = tracing::field::Empty))]
+    pub async fn set_secret(&self, access_key_id: &str, secret_access_key: &str) -> Result<bool> {
+        let started = Instant::now();
+        let result = sqlx::query(
+            "UPDATE access_keys SET secret_access_key = ? WHERE access_key_id = ?"
+        )
+        .bind(secret_access_key)
+        .bind(access_key_id)
+        .execute(&self.pool)
+        .await
+        .context("AccessKeyRepository::set_secret")?;
+        record_query(started, result.rows_affected());
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(skip(self), fields(db.operation = "UPDATE", db.rows = tracing::field::Empty))]
+    pub async fn activate(&self, access_key_id: &str) -> Result<bool> {
+        let started = Instant::now();
+        let result = sqlx::query(
+            "UPDATE access_keys SET is_active = true WHERE access_key_id = ?"
+        )
+        .bind(access_key_id)
+        .execute(&self.pool)
+        .await
+        .context("AccessKeyRepository::activate")?;
+        record_query(started, result.rows_affected());
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(skip(self), fields(db.operation = "UPDATE", db.rows = tracing::field::Empty))]
     pub async fn cleanup_expired(&self) -> Result<u64> {
+        let started = Instant::now();
         let now = Utc::now();
         let result = sqlx::query(
             "UPDATE access_keys SET is_active = false WHERE expires_at IS NOT NULL AND expires_at < ?"
         )
         .bind(now.to_rfc3339())
         .execute(&self.pool)
-        .await?;
+        .await
+        .context("AccessKeyRepository::cleanup_expired")?;
+        record_query(started, result.rows_affected());
 
         Ok(result.rows_affected())
     }
@@ -3,14 +3,20 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 use std::collections::HashMap;
+use std::sync::Arc;
 
+pub mod acl;
 pub mod sigv4;
 pub mod keys;
 pub mod policy;
+pub mod post_policy;
+pub mod provisioning;
+pub mod quota;
 
 pub use sigv4::*;
 pub use keys::*;
-pub use policy::*;
+pub use provisioning::*;
+pub use quota::*;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthContext {
@@ -18,19 +24,35 @@ pub struct AuthContext {
     pub authenticated: bool,
     pub policies: Vec<String>,
     pub session_token: Option<String>,
+    pub quota: KeyQuota,
 }
 
 pub struct AuthService {
     key_repo: AccessKeyRepository,
+    usage: Arc<UsageTracker>,
+    // How far a signed request's X-Amz-Date may be from this server's clock
+    max_clock_skew: chrono::Duration,
 }
 
 impl AuthService {
     pub fn new(pool: SqlitePool) -> Self {
         Self {
-            key_repo: AccessKeyRepository::new(pool),
+            key_repo: AccessKeyRepository::new(pool.clone()),
+            usage: Arc::new(UsageTracker::new(pool)),
+            max_clock_skew: chrono::Duration::minutes(DEFAULT_CLOCK_SKEW_MINUTES as i64),
         }
     }
 
+    // Tighter for higher security, looser where clocks are known to drift
+    pub fn clock_skew_minutes(mut self, minutes: u64) -> Self {
+        self.max_clock_skew = chrono::Duration::minutes(minutes as i64);
+        self
+    }
+
+    pub fn usage(&self) -> &Arc<UsageTracker> {
+        &self.usage
+    }
+
     pub async fn get_access_key(&self, access_key_id: &str) -> Result<Option<AccessKey>> {
         self.key_repo.find_by_access_key_id(access_key_id).await
     }
@@ -40,40 +62,116 @@ impl AuthService {
     }
 
     pub async fn validate_signature(&self, request: &SignatureValidationRequest) -> Result<AuthContext> {
-        let access_key = self.get_access_key(&request.access_key_id).await?
-            .ok_or_else(|| anyhow::anyhow!("Access key not found"))?;
-
-        if let Some(expires_at) = access_key.expires_at {
-            if chrono::Utc::now() > expires_at {
-                return Err(anyhow::anyhow!("Access key expired"));
-            }
-        }
+        let access_key = self.verify_signature(request).await?;
+        Ok(auth_context(access_key))
+    }
 
-        // Use SigV4 validator to verify the signature
-        let is_valid = SigV4Validator::validate_signature(
+    // For requests whose body is sent in signed aws-chunked chunks: validates
+    // the request's own signature, then seeds the signer for the chunks with it
+    pub async fn validate_streaming_signature(&self, request: &SignatureValidationRequest) -> Result<(AuthContext, ChunkSigner)> {
+        let access_key = self.verify_signature(request).await?;
+        let signer = ChunkSigner::new(
             &access_key.secret_access_key,
-            &access_key.access_key_id,
-            &request.method,
-            &request.uri,
-            &request.query_string,
-            &request.signed_headers,
-            &request.payload_hash,
             &request.signature,
             request.timestamp,
             &request.region,
             &request.service,
         )?;
+        Ok((auth_context(access_key), signer))
+    }
+
+    // A browser upload form: x-amz-credential names the key and scope that
+    // signed the policy field, and x-amz-date the day the scope is for
+    pub async fn validate_post_policy(&self, request: &PostPolicyValidationRequest) -> Result<AuthContext> {
+        if request.algorithm != "AWS4-HMAC-SHA256" {
+            return Err(anyhow::anyhow!("Unsupported x-amz-algorithm {}", request.algorithm));
+        }
+        let credential_parts: Vec<&str> = request.credential.split('/').collect();
+        if credential_parts.len() != 5 {
+            return Err(anyhow::anyhow!("Invalid credential format"));
+        }
+        let timestamp = chrono::NaiveDateTime::parse_from_str(&request.date, "%Y%m%dT%H%M%SZ")
+            .map_err(|_| anyhow::anyhow!("Invalid x-amz-date {}", request.date))?
+            .and_utc();
+        if timestamp.format("%Y%m%d").to_string() != credential_parts[1] {
+            return Err(anyhow::anyhow!("x-amz-date does not match the credential's date"));
+        }
+
+        let access_key = self.active_key(credential_parts[0]).await?;
+        let is_valid = SigV4Validator::validate_post_policy_signature(
+            &access_key.secret_access_key,
+            &request.policy,
+            &request.signature,
+            timestamp,
+            credential_parts[2],
+            credential_parts[3],
+        )?;
+        if !is_valid {
+            return Err(anyhow::anyhow!("Invalid signature"));
+        }
+        Ok(auth_context(access_key))
+    }
+
+    async fn active_key(&self, access_key_id: &str) -> Result<AccessKey> {
+        let access_key = self.get_access_key(access_key_id).await?
+            .ok_or_else(|| anyhow::anyhow!("Access key not found"))?;
+
+        if let Some(expires_at) = access_key.expires_at
+            && chrono::Utc::now() > expires_at
+        {
+            return Err(anyhow::anyhow!("Access key expired"));
+        }
+        Ok(access_key)
+    }
+
+    async fn verify_signature(&self, request: &SignatureValidationRequest) -> Result<AccessKey> {
+        let access_key = self.active_key(&request.access_key_id).await?;
+
+        // Use SigV4 validator to verify the signature
+        let is_valid = match request.expires_in_seconds {
+            Some(expires_in_seconds) => SigV4Validator::validate_presigned_signature(
+                &access_key.secret_access_key,
+                &request.method,
+                &request.uri,
+                &request.query_string,
+                &request.signed_headers,
+                &request.signature,
+                request.timestamp,
+                expires_in_seconds,
+                &request.region,
+                &request.service,
+                self.max_clock_skew,
+            )?,
+            None => SigV4Validator::validate_signature(
+                &access_key.secret_access_key,
+                &access_key.access_key_id,
+                &request.method,
+                &request.uri,
+                &request.query_string,
+                &request.signed_headers,
+                &request.payload_hash,
+                &request.signature,
+                request.timestamp,
+                &request.region,
+                &request.service,
+                self.max_clock_skew,
+            )?,
+        };
 
         if !is_valid {
             return Err(anyhow::anyhow!("Invalid signature"));
         }
+        Ok(access_key)
+    }
+}
 
-        Ok(AuthContext {
-            access_key_id: access_key.access_key_id,
-            authenticated: true,
-            policies: access_key.policies,
-            session_token: None,
-        })
+fn auth_context(access_key: AccessKey) -> AuthContext {
+    AuthContext {
+        access_key_id: access_key.access_key_id,
+        authenticated: true,
+        policies: access_key.policies,
+        session_token: None,
+        quota: access_key.quota,
     }
 }
 
@@ -89,4 +187,17 @@ pub struct SignatureValidationRequest {
     pub timestamp: DateTime<Utc>,
     pub region: String,
     pub service: String,
+    // Lifetime of a presigned URL; None when the request is signed in its headers
+    pub expires_in_seconds: Option<u64>,
+}
+
+// The x-amz-* fields of a browser upload form, as sent
+#[derive(Debug, Clone)]
+pub struct PostPolicyValidationRequest {
+    pub algorithm: String,
+    pub credential: String,
+    pub date: String,
+    // The base64 policy document, which is what is signed
+    pub policy: String,
+    pub signature: String,
 }
\ No newline at end of file
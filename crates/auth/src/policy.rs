@@ -1 +1,479 @@
-// Placeholder for policy evaluation functionality
\ No newline at end of file
+use serde::Deserialize;
+
+use crate::AuthContext;
+
+// IAM-style bucket policies. A policy is a list of statements, each granting
+// or denying a set of actions on a set of resources to a set of principals.
+// Evaluation follows AWS: an explicit Deny wins over any Allow, and a request
+// no statement applies to is left to the caller's default.
+pub const MAX_POLICY_SIZE: usize = 20 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PolicyError {
+    #[error("Policies must be no larger than {} bytes", MAX_POLICY_SIZE)]
+    TooLarge,
+    #[error("{0}")]
+    Malformed(String),
+    #[error("Policy has no statements")]
+    NoStatements,
+    #[error("Policy has invalid action: {0}")]
+    InvalidAction(String),
+    #[error("Policy has invalid resource: {0}")]
+    InvalidResource(String),
+    #[error("Invalid principal in policy")]
+    InvalidPrincipal,
+    #[error("Unknown access key policy: {0}")]
+    UnknownKeyPolicy(String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyDocument {
+    #[serde(rename = "Version")]
+    pub version: Option<String>,
+    #[serde(rename = "Id")]
+    pub id: Option<String>,
+    #[serde(rename = "Statement", deserialize_with = "one_or_many")]
+    pub statements: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Statement {
+    #[serde(rename = "Sid")]
+    pub sid: Option<String>,
+    #[serde(rename = "Effect")]
+    pub effect: Effect,
+    #[serde(rename = "Principal")]
+    pub principal: Principal,
+    #[serde(rename = "Action", deserialize_with = "one_or_many")]
+    pub actions: Vec<String>,
+    #[serde(rename = "Resource", deserialize_with = "one_or_many")]
+    pub resources: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Effect {
+    Allow,
+    Deny,
+}
+
+// "*" or {"AWS": ...} naming access key ids, where "*" is everyone including
+// anonymous callers
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Principal {
+    Wildcard(String),
+    Aws {
+        #[serde(rename = "AWS", deserialize_with = "one_or_many")]
+        aws: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Allow,
+    Deny,
+    NotApplicable,
+}
+
+// Policy fields take either a single value or a list. Going through a JSON
+// value, rather than an untagged enum, keeps the error of a bad element.
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::de::DeserializeOwned,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    let values = match value {
+        serde_json::Value::Array(_) => serde_json::from_value(value),
+        value => serde_json::from_value(value).map(|value| vec![value]),
+    };
+    values.map_err(serde::de::Error::custom)
+}
+
+impl PolicyDocument {
+    // Parses a policy for bucket_name and checks every statement only
+    // addresses that bucket and its objects
+    pub fn parse(document: &str, bucket_name: &str) -> Result<Self, PolicyError> {
+        if document.len() > MAX_POLICY_SIZE {
+            return Err(PolicyError::TooLarge);
+        }
+        let policy: PolicyDocument = serde_json::from_str(document).map_err(|e| PolicyError::Malformed(e.to_string()))?;
+        if policy.statements.is_empty() {
+            return Err(PolicyError::NoStatements);
+        }
+
+        let bucket_arn = format!("arn:aws:s3:::{}", bucket_name);
+        for statement in &policy.statements {
+            if let Some(action) = statement.actions.iter().find(|action| *action != "*" && !action.starts_with("s3:")) {
+                return Err(PolicyError::InvalidAction(action.clone()));
+            }
+            if statement.actions.is_empty() {
+                return Err(PolicyError::InvalidAction(String::new()));
+            }
+            let outside_bucket = |resource: &&String| {
+                resource.as_str() != bucket_arn && !resource.starts_with(&format!("{}/", bucket_arn))
+            };
+            if let Some(resource) = statement.resources.iter().find(outside_bucket) {
+                return Err(PolicyError::InvalidResource(resource.clone()));
+            }
+            if statement.resources.is_empty() {
+                return Err(PolicyError::InvalidResource(String::new()));
+            }
+            match &statement.principal {
+                Principal::Wildcard(principal) if principal != "*" => return Err(PolicyError::InvalidPrincipal),
+                Principal::Aws { aws } if aws.is_empty() => return Err(PolicyError::InvalidPrincipal),
+                _ => {}
+            }
+        }
+        Ok(policy)
+    }
+
+    // access_key_id is None for anonymous requests; resource is the ARN of
+    // the bucket or object the action addresses
+    pub fn evaluate(&self, access_key_id: Option<&str>, action: &str, resource: &str) -> Decision {
+        let mut decision = Decision::NotApplicable;
+        for statement in &self.statements {
+            if !statement.applies(access_key_id, action, resource) {
+                continue;
+            }
+            match statement.effect {
+                Effect::Deny => return Decision::Deny,
+                Effect::Allow => decision = Decision::Allow,
+            }
+        }
+        decision
+    }
+}
+
+impl Statement {
+    fn applies(&self, access_key_id: Option<&str>, action: &str, resource: &str) -> bool {
+        self.principal.matches(access_key_id)
+            && self.actions.iter().any(|pattern| wildcard_match(&pattern.to_ascii_lowercase(), &action.to_ascii_lowercase()))
+            && self.resources.iter().any(|pattern| wildcard_match(pattern, resource))
+    }
+}
+
+impl Principal {
+    fn matches(&self, access_key_id: Option<&str>) -> bool {
+        match self {
+            Principal::Wildcard(_) => true,
+            Principal::Aws { aws } => aws
+                .iter()
+                .any(|principal| principal == "*" || Some(principal.as_str()) == access_key_id),
+        }
+    }
+}
+
+// Policies attached to access keys, by name: admin, read-only and read-write
+// cover every bucket, read:<bucket> and readwrite:<bucket> a single one.
+// Admin keys may also use the console. A request is allowed when any of its
+// key's policies allows it; bucket policies are applied on top.
+pub const ADMIN_POLICY: &str = "admin";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyPolicy {
+    Admin,
+    ReadOnly,
+    ReadWrite,
+    ReadBucket(String),
+    ReadWriteBucket(String),
+}
+
+impl KeyPolicy {
+    pub fn parse(policy: &str) -> Result<Self, PolicyError> {
+        let unknown = || PolicyError::UnknownKeyPolicy(policy.to_string());
+        match policy {
+            ADMIN_POLICY => return Ok(KeyPolicy::Admin),
+            "read-only" => return Ok(KeyPolicy::ReadOnly),
+            "read-write" => return Ok(KeyPolicy::ReadWrite),
+            _ => {}
+        }
+        let (scope, bucket) = policy.split_once(':').ok_or_else(unknown)?;
+        if bucket.is_empty() {
+            return Err(unknown());
+        }
+        match scope {
+            "read" => Ok(KeyPolicy::ReadBucket(bucket.to_string())),
+            "readwrite" => Ok(KeyPolicy::ReadWriteBucket(bucket.to_string())),
+            _ => Err(unknown()),
+        }
+    }
+
+    // bucket is None for actions on no bucket in particular, like ListBuckets
+    pub fn allows(&self, action: &str, bucket: Option<&str>) -> bool {
+        match self {
+            KeyPolicy::Admin | KeyPolicy::ReadWrite => true,
+            KeyPolicy::ReadOnly => is_read_action(action),
+            KeyPolicy::ReadBucket(scope) => bucket == Some(scope.as_str()) && is_read_action(action),
+            KeyPolicy::ReadWriteBucket(scope) => bucket == Some(scope.as_str()),
+        }
+    }
+}
+
+// Gets and lists; everything else changes state
+fn is_read_action(action: &str) -> bool {
+    action.starts_with("s3:Get") || action.starts_with("s3:List")
+}
+
+// Checks a list of policy names before it is stored on a key
+pub fn validate_key_policies(policies: &[String]) -> Result<(), PolicyError> {
+    policies.iter().try_for_each(|policy| KeyPolicy::parse(policy).map(|_| ()))
+}
+
+pub struct Authorizer;
+
+impl Authorizer {
+    // Whether the policies of the request's key allow `action` on `bucket`.
+    // Names that no longer parse grant nothing.
+    pub fn check(auth: &AuthContext, action: &str, bucket: Option<&str>) -> bool {
+        auth.policies.iter().any(|policy| match KeyPolicy::parse(policy) {
+            Ok(policy) => policy.allows(action, bucket),
+            Err(e) => {
+                tracing::warn!(access_key_id = %auth.access_key_id, "Ignoring policy: {}", e);
+                false
+            }
+        })
+    }
+
+    pub fn is_admin(auth: &AuthContext) -> bool {
+        auth.policies.iter().any(|policy| policy == ADMIN_POLICY)
+    }
+}
+
+// The s3:* names of the actions checked most often. Others are spelled out
+// where they are checked.
+pub mod actions {
+    pub const GET_OBJECT: &str = "s3:GetObject";
+    pub const PUT_OBJECT: &str = "s3:PutObject";
+    pub const DELETE_OBJECT: &str = "s3:DeleteObject";
+    pub const LIST_BUCKET: &str = "s3:ListBucket";
+    pub const LIST_ALL_MY_BUCKETS: &str = "s3:ListAllMyBuckets";
+    pub const CREATE_BUCKET: &str = "s3:CreateBucket";
+    pub const DELETE_BUCKET: &str = "s3:DeleteBucket";
+}
+
+// "arn:aws:s3:::bucket" or "arn:aws:s3:::bucket/key"; "*" stands for no
+// bucket in particular
+pub fn resource_arn(bucket: Option<&str>, key: Option<&str>) -> String {
+    match (bucket, key) {
+        (Some(bucket), Some(key)) => format!("arn:aws:s3:::{}/{}", bucket, key),
+        (Some(bucket), None) => format!("arn:aws:s3:::{}", bucket),
+        (None, _) => "*".to_string(),
+    }
+}
+
+pub struct PolicyEvaluator;
+
+impl PolicyEvaluator {
+    // Whether any of the context's key policies grants `action` on `resource`,
+    // an ARN as made by resource_arn. Key policies are scoped by bucket, so
+    // only the bucket part of the resource is looked at.
+    pub fn is_allowed(action: &str, resource: &str, context: &AuthContext) -> bool {
+        let bucket = resource
+            .strip_prefix("arn:aws:s3:::")
+            .map(|path| path.split_once('/').map_or(path, |(bucket, _)| bucket))
+            .filter(|bucket| !bucket.is_empty() && *bucket != "*");
+        Authorizer::check(context, action, bucket)
+    }
+}
+
+// IAM wildcards: '*' matches any run of characters and '?' any single one
+pub fn wildcard_match(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+    let (mut p, mut v) = (0, 0);
+    let mut backtrack = None;
+    while v < value.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, v));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            v = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(statements: &str) -> PolicyDocument {
+        PolicyDocument::parse(&format!(r#"{{"Version":"2012-10-17","Statement":{}}}"#, statements), "photos").unwrap()
+    }
+
+    #[test]
+    fn explicit_deny_wins_over_allow() {
+        let document = policy(
+            r#"[
+                {"Effect":"Allow","Principal":"*","Action":"s3:*","Resource":"arn:aws:s3:::photos/*"},
+                {"Effect":"Deny","Principal":{"AWS":"AKIDENIED"},"Action":"s3:DeleteObject","Resource":"arn:aws:s3:::photos/*"}
+            ]"#,
+        );
+        let object = "arn:aws:s3:::photos/cat.jpg";
+        assert_eq!(document.evaluate(Some("AKIDENIED"), "s3:DeleteObject", object), Decision::Deny);
+        assert_eq!(document.evaluate(Some("AKIDENIED"), "s3:GetObject", object), Decision::Allow);
+        assert_eq!(document.evaluate(Some("AKIOTHER"), "s3:DeleteObject", object), Decision::Allow);
+
+        // Statement order does not matter
+        let reversed = policy(
+            r#"[
+                {"Effect":"Deny","Principal":"*","Action":"s3:GetObject","Resource":"arn:aws:s3:::photos/private/*"},
+                {"Effect":"Allow","Principal":"*","Action":"s3:GetObject","Resource":"arn:aws:s3:::photos/*"}
+            ]"#,
+        );
+        assert_eq!(reversed.evaluate(None, "s3:GetObject", "arn:aws:s3:::photos/private/a"), Decision::Deny);
+        assert_eq!(reversed.evaluate(None, "s3:GetObject", "arn:aws:s3:::photos/public/a"), Decision::Allow);
+    }
+
+    #[test]
+    fn wildcard_resources_and_actions() {
+        let document = policy(
+            r#"{"Effect":"Allow","Principal":"*","Action":["s3:Get*","s3:ListBucket"],"Resource":["arn:aws:s3:::photos","arn:aws:s3:::photos/2024-??/*.jpg"]}"#,
+        );
+        assert_eq!(document.evaluate(None, "s3:GetObject", "arn:aws:s3:::photos/2024-06/beach.jpg"), Decision::Allow);
+        assert_eq!(document.evaluate(None, "s3:getobjecttagging", "arn:aws:s3:::photos/2024-06/a/b.jpg"), Decision::Allow);
+        assert_eq!(document.evaluate(None, "s3:ListBucket", "arn:aws:s3:::photos"), Decision::Allow);
+        assert_eq!(document.evaluate(None, "s3:GetObject", "arn:aws:s3:::photos/2024-6/beach.jpg"), Decision::NotApplicable);
+        assert_eq!(document.evaluate(None, "s3:GetObject", "arn:aws:s3:::photos/2024-06/beach.png"), Decision::NotApplicable);
+
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("a*b*c", "aXXbYYbc"));
+        assert!(!wildcard_match("a?c", "ac"));
+        assert!(!wildcard_match("abc", "abcd"));
+    }
+
+    #[test]
+    fn unmatched_requests_are_not_applicable() {
+        let document = policy(
+            r#"{"Effect":"Allow","Principal":{"AWS":["AKIREADER"]},"Action":"s3:GetObject","Resource":"arn:aws:s3:::photos/*"}"#,
+        );
+        let object = "arn:aws:s3:::photos/cat.jpg";
+        assert_eq!(document.evaluate(Some("AKIREADER"), "s3:GetObject", object), Decision::Allow);
+        // Another principal, an anonymous caller, another action, another resource
+        assert_eq!(document.evaluate(Some("AKIOTHER"), "s3:GetObject", object), Decision::NotApplicable);
+        assert_eq!(document.evaluate(None, "s3:GetObject", object), Decision::NotApplicable);
+        assert_eq!(document.evaluate(Some("AKIREADER"), "s3:PutObject", object), Decision::NotApplicable);
+        assert_eq!(document.evaluate(Some("AKIREADER"), "s3:GetObject", "arn:aws:s3:::photos"), Decision::NotApplicable);
+    }
+
+    // Every action the handlers check, split by whether it only reads
+    const READ_ACTIONS: &[&str] = &[
+        "s3:GetObject",
+        "s3:GetObjectTagging",
+        "s3:ListBucket",
+        "s3:ListAllMyBuckets",
+        "s3:ListBucketMultipartUploads",
+        "s3:ListMultipartUploadParts",
+        "s3:GetBucketLocation",
+        "s3:GetBucketVersioning",
+        "s3:GetBucketCORS",
+        "s3:GetBucketTagging",
+        "s3:GetBucketPolicy",
+        "s3:GetBucketAcl",
+        "s3:GetLifecycleConfiguration",
+    ];
+    const WRITE_ACTIONS: &[&str] = &[
+        "s3:PutObject",
+        "s3:DeleteObject",
+        "s3:PutObjectTagging",
+        "s3:DeleteObjectTagging",
+        "s3:AbortMultipartUpload",
+        "s3:CreateBucket",
+        "s3:DeleteBucket",
+        "s3:PutBucketVersioning",
+        "s3:PutBucketCORS",
+        "s3:PutBucketTagging",
+        "s3:PutBucketPolicy",
+        "s3:DeleteBucketPolicy",
+        "s3:PutBucketAcl",
+        "s3:PutLifecycleConfiguration",
+    ];
+
+    fn context(policies: &[&str]) -> AuthContext {
+        AuthContext {
+            access_key_id: "AKITEST".to_string(),
+            authenticated: true,
+            policies: policies.iter().map(|policy| policy.to_string()).collect(),
+            session_token: None,
+            quota: Default::default(),
+        }
+    }
+
+    #[test]
+    fn built_in_policies_grant_by_action_and_bucket() {
+        // (policy, reads allowed on, writes allowed on) for the key's own
+        // bucket, another bucket, and no bucket in particular
+        let matrix: [(&str, [bool; 3], [bool; 3]); 5] = [
+            ("admin", [true, true, true], [true, true, true]),
+            ("read-write", [true, true, true], [true, true, true]),
+            ("read-only", [true, true, true], [false, false, false]),
+            ("read:photos", [true, false, false], [false, false, false]),
+            ("readwrite:photos", [true, false, false], [true, false, false]),
+        ];
+        let buckets = [Some("photos"), Some("photos-backup"), None];
+        for (policy, reads, writes) in matrix {
+            let auth = context(&[policy]);
+            for (bucket, (read, write)) in buckets.iter().zip(reads.into_iter().zip(writes)) {
+                for action in READ_ACTIONS {
+                    assert_eq!(Authorizer::check(&auth, action, *bucket), read, "{} {} on {:?}", policy, action, bucket);
+                }
+                for action in WRITE_ACTIONS {
+                    assert_eq!(Authorizer::check(&auth, action, *bucket), write, "{} {} on {:?}", policy, action, bucket);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn key_policies_add_up_and_unknown_ones_grant_nothing() {
+        let auth = context(&["read:photos", "readwrite:uploads"]);
+        assert!(Authorizer::check(&auth, "s3:GetObject", Some("photos")));
+        assert!(!Authorizer::check(&auth, "s3:PutObject", Some("photos")));
+        assert!(Authorizer::check(&auth, "s3:PutObject", Some("uploads")));
+
+        let auth = context(&["superuser", "write:photos", "read:"]);
+        for action in READ_ACTIONS.iter().chain(WRITE_ACTIONS) {
+            assert!(!Authorizer::check(&auth, action, Some("photos")), "{}", action);
+        }
+        assert!(!Authorizer::check(&context(&[]), "s3:GetObject", Some("photos")));
+        assert!(Authorizer::is_admin(&context(&["read-only", "admin"])));
+        assert!(!Authorizer::is_admin(&context(&["read-write"])));
+    }
+
+    #[test]
+    fn evaluator_scopes_key_policies_by_the_resource_bucket() {
+        let context = context(&["read:photos", "readwrite:uploads"]);
+        let photo = resource_arn(Some("photos"), Some("2024/beach.jpg"));
+        assert!(PolicyEvaluator::is_allowed(actions::GET_OBJECT, &photo, &context));
+        assert!(PolicyEvaluator::is_allowed(actions::LIST_BUCKET, &resource_arn(Some("photos"), None), &context));
+        assert!(!PolicyEvaluator::is_allowed(actions::PUT_OBJECT, &photo, &context));
+        assert!(PolicyEvaluator::is_allowed(actions::DELETE_OBJECT, &resource_arn(Some("uploads"), Some("a")), &context));
+        assert!(!PolicyEvaluator::is_allowed(actions::GET_OBJECT, &resource_arn(Some("photos-backup"), Some("a")), &context));
+        assert!(!PolicyEvaluator::is_allowed(actions::CREATE_BUCKET, &resource_arn(None, None), &context));
+        assert!(!PolicyEvaluator::is_allowed(actions::LIST_ALL_MY_BUCKETS, "*", &context));
+    }
+
+    #[test]
+    fn rejects_policies_outside_the_bucket() {
+        let parse = |statement: &str| PolicyDocument::parse(&format!(r#"{{"Statement":{}}}"#, statement), "photos");
+        assert!(matches!(
+            parse(r#"{"Effect":"Allow","Principal":"*","Action":"s3:*","Resource":"arn:aws:s3:::photos-backup/*"}"#),
+            Err(PolicyError::InvalidResource(_))
+        ));
+        assert!(matches!(
+            parse(r#"{"Effect":"Allow","Principal":"*","Action":"iam:PassRole","Resource":"arn:aws:s3:::photos"}"#),
+            Err(PolicyError::InvalidAction(_))
+        ));
+        assert!(matches!(parse("[]"), Err(PolicyError::NoStatements)));
+    }
+}
@@ -0,0 +1,223 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+// The policy document of a browser upload (POST Object). It is signed along
+// with the form and says what the form may contain: until its expiration,
+// every field has to satisfy the conditions on it, and every field has to
+// be covered by a condition, apart from the few S3 exempts. Field names are
+// case-insensitive; values are compared exactly.
+#[derive(Debug, thiserror::Error)]
+pub enum PostPolicyError {
+    #[error("Invalid Policy: {0}")]
+    Malformed(String),
+    #[error("Policy expired.")]
+    Expired,
+    #[error("Policy Condition failed: {0}")]
+    ConditionFailed(String),
+    #[error("Extra input fields: {0}")]
+    ExtraField(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    // {"field": "value"} or ["eq", "$field", "value"]
+    Eq { field: String, value: String },
+    // ["starts-with", "$field", "prefix"]; an empty prefix allows any value
+    StartsWith { field: String, prefix: String },
+    // ["content-length-range", min, max], bounding the file's size in bytes
+    ContentLengthRange { min: u64, max: u64 },
+}
+
+#[derive(Debug, Clone)]
+pub struct PostPolicy {
+    pub expiration: DateTime<Utc>,
+    pub conditions: Vec<Condition>,
+}
+
+#[derive(Deserialize)]
+struct RawPolicy {
+    expiration: String,
+    conditions: Vec<Value>,
+}
+
+// Fields a policy need not mention
+fn is_exempt(field: &str) -> bool {
+    matches!(field, "policy" | "x-amz-signature" | "file") || field.starts_with("x-ignore-")
+}
+
+impl PostPolicy {
+    // document is the policy field decoded from base64
+    pub fn parse(document: &[u8]) -> Result<Self, PostPolicyError> {
+        let raw: RawPolicy = serde_json::from_slice(document).map_err(|e| PostPolicyError::Malformed(e.to_string()))?;
+        let expiration = DateTime::parse_from_rfc3339(&raw.expiration)
+            .map_err(|_| PostPolicyError::Malformed(format!("Invalid expiration {}", raw.expiration)))?
+            .with_timezone(&Utc);
+        let conditions = raw.conditions.iter().map(Condition::parse).collect::<Result<_, _>>()?;
+        Ok(Self { expiration, conditions })
+    }
+
+    // fields are the form's fields by lowercase name, without the file; the
+    // bucket is the one the form was posted to
+    pub fn check(&self, bucket: &str, fields: &HashMap<String, String>, now: DateTime<Utc>) -> Result<(), PostPolicyError> {
+        if now > self.expiration {
+            return Err(PostPolicyError::Expired);
+        }
+        let value = |field: &str| if field == "bucket" { Some(bucket) } else { fields.get(field).map(String::as_str) };
+        for condition in &self.conditions {
+            let satisfied = match condition {
+                Condition::Eq { field, value: expected } => value(field) == Some(expected.as_str()),
+                Condition::StartsWith { field, prefix } => value(field).is_some_and(|value| value.starts_with(prefix.as_str())),
+                // Checked against the file as it is received
+                Condition::ContentLengthRange { .. } => true,
+            };
+            if !satisfied {
+                return Err(PostPolicyError::ConditionFailed(condition.to_string()));
+            }
+        }
+
+        let covered = |field: &str| {
+            self.conditions.iter().any(|condition| match condition {
+                Condition::Eq { field: name, .. } | Condition::StartsWith { field: name, .. } => name == field,
+                Condition::ContentLengthRange { .. } => false,
+            })
+        };
+        let mut extra: Vec<&str> = fields.keys().map(String::as_str).filter(|field| !is_exempt(field) && !covered(field)).collect();
+        if !extra.is_empty() {
+            extra.sort_unstable();
+            return Err(PostPolicyError::ExtraField(extra.join(", ")));
+        }
+        Ok(())
+    }
+
+    // The smallest and largest file the policy allows, if it bounds them
+    pub fn content_length_range(&self) -> Option<(u64, u64)> {
+        self.conditions.iter().find_map(|condition| match condition {
+            Condition::ContentLengthRange { min, max } => Some((*min, *max)),
+            _ => None,
+        })
+    }
+}
+
+impl Condition {
+    fn parse(condition: &Value) -> Result<Self, PostPolicyError> {
+        let malformed = || PostPolicyError::Malformed(format!("Invalid condition {}", condition));
+        match condition {
+            Value::Object(map) if map.len() == 1 => {
+                let (field, value) = map.iter().next().ok_or_else(malformed)?;
+                Ok(Condition::Eq {
+                    field: field.trim_start_matches('$').to_ascii_lowercase(),
+                    value: value.as_str().ok_or_else(malformed)?.to_string(),
+                })
+            }
+            Value::Array(items) if items.len() == 3 => {
+                let operator = items[0].as_str().ok_or_else(malformed)?.to_ascii_lowercase();
+                if operator == "content-length-range" {
+                    // Bounds may be given as numbers or as numeric strings
+                    let bound = |value: &Value| {
+                        value
+                            .as_u64()
+                            .or_else(|| value.as_str().and_then(|value| value.parse().ok()))
+                            .ok_or_else(malformed)
+                    };
+                    return Ok(Condition::ContentLengthRange { min: bound(&items[1])?, max: bound(&items[2])? });
+                }
+                let field = items[1]
+                    .as_str()
+                    .and_then(|field| field.strip_prefix('$'))
+                    .ok_or_else(malformed)?
+                    .to_ascii_lowercase();
+                let value = items[2].as_str().ok_or_else(malformed)?.to_string();
+                match operator.as_str() {
+                    "eq" => Ok(Condition::Eq { field, value }),
+                    "starts-with" => Ok(Condition::StartsWith { field, prefix: value }),
+                    _ => Err(malformed()),
+                }
+            }
+            _ => Err(malformed()),
+        }
+    }
+}
+
+// As S3 quotes a failed condition: ["eq", "$key", "uploads/a.txt"]
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Condition::Eq { field, value } => write!(f, "[\"eq\", \"${}\", \"{}\"]", field, value),
+            Condition::StartsWith { field, prefix } => write!(f, "[\"starts-with\", \"${}\", \"{}\"]", field, prefix),
+            Condition::ContentLengthRange { min, max } => write!(f, "[\"content-length-range\", {}, {}]", min, max),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn policy(conditions: &str) -> PostPolicy {
+        let expiration = (Utc::now() + Duration::hours(1)).to_rfc3339();
+        PostPolicy::parse(format!(r#"{{"expiration":"{}","conditions":{}}}"#, expiration, conditions).as_bytes()).unwrap()
+    }
+
+    fn fields(fields: &[(&str, &str)]) -> HashMap<String, String> {
+        fields.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn eq_needs_the_exact_value() {
+        let policy = policy(r#"[{"bucket":"uploads"},["eq","$key","avatars/me.png"],{"Content-Type":"image/png"}]"#);
+        let form = fields(&[("key", "avatars/me.png"), ("content-type", "image/png")]);
+        assert!(policy.check("uploads", &form, Utc::now()).is_ok());
+
+        let error = policy.check("other", &form, Utc::now()).unwrap_err();
+        assert_eq!(error.to_string(), r#"Policy Condition failed: ["eq", "$bucket", "uploads"]"#);
+        let form = fields(&[("key", "avatars/me.png.exe"), ("content-type", "image/png")]);
+        assert!(matches!(policy.check("uploads", &form, Utc::now()), Err(PostPolicyError::ConditionFailed(_))));
+        let form = fields(&[("key", "avatars/me.png")]);
+        assert!(matches!(policy.check("uploads", &form, Utc::now()), Err(PostPolicyError::ConditionFailed(_))));
+    }
+
+    #[test]
+    fn starts_with_checks_the_prefix() {
+        let policy = policy(r#"[["starts-with","$key","user/42/"],["starts-with","$Content-Type",""]]"#);
+        let form = fields(&[("key", "user/42/photo.jpg"), ("content-type", "whatever/at-all")]);
+        assert!(policy.check("uploads", &form, Utc::now()).is_ok());
+
+        let form = fields(&[("key", "user/43/photo.jpg"), ("content-type", "image/jpeg")]);
+        let error = policy.check("uploads", &form, Utc::now()).unwrap_err();
+        assert_eq!(error.to_string(), r#"Policy Condition failed: ["starts-with", "$key", "user/42/"]"#);
+    }
+
+    #[test]
+    fn content_length_range_is_parsed_and_left_to_the_upload() {
+        let policy = policy(r#"[["content-length-range",1,"1048576"],["eq","$key","a"]]"#);
+        assert_eq!(policy.content_length_range(), Some((1, 1_048_576)));
+        assert!(policy.check("uploads", &fields(&[("key", "a")]), Utc::now()).is_ok());
+
+        let unbounded = PostPolicy::parse(br#"{"expiration":"2030-01-01T00:00:00Z","conditions":[["eq","$key","a"]]}"#).unwrap();
+        assert_eq!(unbounded.content_length_range(), None);
+        for bad in [r#"["content-length-range",-1,10]"#, r#"["content-length-range","ten",10]"#] {
+            let document = format!(r#"{{"expiration":"2030-01-01T00:00:00Z","conditions":[{}]}}"#, bad);
+            assert!(matches!(PostPolicy::parse(document.as_bytes()), Err(PostPolicyError::Malformed(_))), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn expired_policies_are_refused() {
+        let expired = PostPolicy::parse(br#"{"expiration":"2020-01-01T00:00:00.000Z","conditions":[["eq","$key","a"]]}"#).unwrap();
+        let form = fields(&[("key", "a")]);
+        assert!(matches!(expired.check("uploads", &form, Utc::now()), Err(PostPolicyError::Expired)));
+        let before = DateTime::parse_from_rfc3339("2019-12-31T23:59:59Z").unwrap().with_timezone(&Utc);
+        assert!(expired.check("uploads", &form, before).is_ok());
+    }
+
+    #[test]
+    fn uncovered_fields_are_extra() {
+        let policy = policy(r#"[["eq","$key","a"]]"#);
+        let form = fields(&[("key", "a"), ("policy", "..."), ("x-amz-signature", "..."), ("x-ignore-me", "1"), ("acl", "public-read")]);
+        let error = policy.check("uploads", &form, Utc::now()).unwrap_err();
+        assert_eq!(error.to_string(), "Extra input fields: acl");
+    }
+}
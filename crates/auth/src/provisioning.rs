@@ -0,0 +1,257 @@
+use anyhow::{Context, Result};
+use ghostbay_catalog::{BucketRepository, CreateBucketRequest};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::fmt;
+use std::path::Path;
+
+use crate::keys::{AccessKeyRepository, CreateAccessKeyRequest};
+use crate::quota::KeyQuota;
+
+// Declared state from provisioning.toml. Applying it creates what is missing
+// and corrects drift on what exists; nothing absent from the file is deleted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProvisioningFile {
+    #[serde(default)]
+    pub keys: Vec<ProvisionedKey>,
+    #[serde(default)]
+    pub buckets: Vec<ProvisionedBucket>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvisionedKey {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    #[serde(default)]
+    pub policies: Vec<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub quota: KeyQuota,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvisionedBucket {
+    pub name: String,
+    #[serde(default = "default_region")]
+    pub region: String,
+    #[serde(default)]
+    pub versioning: bool,
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+impl ProvisioningFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read provisioning file {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("Invalid provisioning file {}", path.display()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProvisioningChange {
+    pub resource: String,
+    pub change: String,
+    // False in check mode, and for drift that cannot be corrected in place
+    pub applied: bool,
+}
+
+impl fmt::Display for ProvisioningChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.resource, self.change)
+    }
+}
+
+// With `check_only`, reports the drift without changing anything
+pub async fn apply_provisioning(pool: &SqlitePool, file: &ProvisioningFile, check_only: bool) -> Result<Vec<ProvisioningChange>> {
+    let mut changes = Vec::new();
+    let mut record = |resource: String, change: &str, applied: bool| {
+        changes.push(ProvisioningChange { resource, change: change.to_string(), applied });
+    };
+
+    let key_repo = AccessKeyRepository::new(pool.clone());
+    let existing_keys = key_repo.list(true).await?;
+    for declared in &file.keys {
+        let resource = format!("key {}", declared.access_key_id);
+        let Some(existing) = existing_keys.iter().find(|key| key.access_key_id == declared.access_key_id) else {
+            if !check_only {
+                let request = CreateAccessKeyRequest {
+                    policies: declared.policies.clone(),
+                    description: declared.description.clone(),
+                    expires_at: None,
+                    quota: declared.quota.clone(),
+                };
+                key_repo
+                    .create_with_credentials(declared.access_key_id.clone(), declared.secret_access_key.clone(), request)
+                    .await?;
+            }
+            record(resource, "create", !check_only);
+            continue;
+        };
+
+        if !existing.is_active {
+            if !check_only {
+                key_repo.activate(&declared.access_key_id).await?;
+            }
+            record(resource.clone(), "reactivate", !check_only);
+        }
+        if existing.secret_access_key != declared.secret_access_key {
+            if !check_only {
+                key_repo.set_secret(&declared.access_key_id, &declared.secret_access_key).await?;
+            }
+            record(resource.clone(), "update secret", !check_only);
+        }
+        if existing.policies != declared.policies {
+            if !check_only {
+                key_repo.set_policies(&declared.access_key_id, &declared.policies).await?;
+            }
+            record(resource.clone(), "update policies", !check_only);
+        }
+        if existing.quota != declared.quota {
+            if !check_only {
+                key_repo.set_quota(&declared.access_key_id, &declared.quota).await?;
+            }
+            record(resource, "update quota", !check_only);
+        }
+    }
+
+    let bucket_repo = BucketRepository::new(pool.clone());
+    for declared in &file.buckets {
+        let resource = format!("bucket {}", declared.name);
+        let Some(existing) = bucket_repo.find_by_name(&declared.name).await? else {
+            if !check_only {
+                bucket_repo
+                    .create(CreateBucketRequest {
+                        name: declared.name.clone(),
+                        region: declared.region.clone(),
+                        owner_access_key_id: None,
+                    })
+                    .await?;
+                if declared.versioning {
+                    bucket_repo.set_versioning(&declared.name, true).await?;
+                }
+            }
+            record(resource, "create", !check_only);
+            continue;
+        };
+
+        // Objects are already stored under the existing region, so never move them
+        if existing.region != declared.region {
+            record(
+                resource.clone(),
+                &format!("region is {} but {} is declared; not changed", existing.region, declared.region),
+                false,
+            );
+        }
+        if existing.versioning_enabled != declared.versioning {
+            if !check_only {
+                bucket_repo.set_versioning(&declared.name, declared.versioning).await?;
+            }
+            let change = if declared.versioning { "enable versioning" } else { "suspend versioning" };
+            record(resource, change, !check_only);
+        }
+    }
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ghostbay_catalog::CatalogService;
+
+    const PROVISIONING: &str = r#"
+        [[keys]]
+        access_key_id = "GBCIKEY0000000000001"
+        secret_access_key = "ci-secret-000000000000000000000000000000"
+        policies = ["read-write"]
+
+        [[keys]]
+        access_key_id = "GBCIADMIN00000000001"
+        secret_access_key = "ci-admin-secret-0000000000000000000000000"
+        policies = ["admin"]
+
+        [[buckets]]
+        name = "artifacts"
+        versioning = true
+
+        [[buckets]]
+        name = "cache"
+        region = "eu-west-1"
+    "#;
+
+    // The catalog steps of a server boot: open, migrate, apply the file
+    async fn boot(database_url: &str, file: &Path, check_only: bool) -> (SqlitePool, Vec<ProvisioningChange>) {
+        let catalog = CatalogService::new(database_url).await.unwrap();
+        ghostbay_catalog::migrations::ensure_database_exists(database_url).await.unwrap();
+        ghostbay_catalog::migrations::run_migrations(catalog.pool()).await.unwrap();
+        let changes = apply_provisioning(catalog.pool(), &ProvisioningFile::load(file).unwrap(), check_only).await.unwrap();
+        (catalog.pool().clone(), changes)
+    }
+
+    async fn count(pool: &SqlitePool, table: &str) -> i64 {
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table)).fetch_one(pool).await.unwrap()
+    }
+
+    fn summary(changes: &[ProvisioningChange]) -> Vec<String> {
+        changes.iter().map(ToString::to_string).collect()
+    }
+
+    #[tokio::test]
+    async fn booting_twice_creates_nothing_twice() {
+        let dir = tempfile::tempdir().unwrap();
+        let database_url = format!("sqlite:{}?mode=rwc", dir.path().join("catalog.db").display());
+        let file = dir.path().join("provisioning.toml");
+        std::fs::write(&file, PROVISIONING).unwrap();
+
+        let (pool, changes) = boot(&database_url, &file, false).await;
+        assert_eq!(
+            summary(&changes),
+            ["key GBCIKEY0000000000001: create", "key GBCIADMIN00000000001: create", "bucket artifacts: create", "bucket cache: create"]
+        );
+        pool.close().await;
+
+        let (pool, changes) = boot(&database_url, &file, false).await;
+        assert!(changes.is_empty(), "{:?}", summary(&changes));
+        assert_eq!((count(&pool, "access_keys").await, count(&pool, "buckets").await), (2, 2));
+
+        let buckets = BucketRepository::new(pool.clone());
+        let artifacts = buckets.find_by_name("artifacts").await.unwrap().unwrap();
+        assert!(artifacts.versioning_enabled);
+        assert_eq!(buckets.find_by_name("cache").await.unwrap().unwrap().region, "eu-west-1");
+        pool.close().await;
+    }
+
+    #[tokio::test]
+    async fn drift_is_reported_then_corrected_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let database_url = format!("sqlite:{}?mode=rwc", dir.path().join("catalog.db").display());
+        let file = dir.path().join("provisioning.toml");
+        std::fs::write(&file, PROVISIONING).unwrap();
+        boot(&database_url, &file, false).await.0.close().await;
+
+        // Edited by hand between boots
+        let (pool, _) = boot(&database_url, &file, true).await;
+        let keys = AccessKeyRepository::new(pool.clone());
+        keys.set_policies("GBCIKEY0000000000001", &["read-only".to_string()]).await.unwrap();
+        BucketRepository::new(pool.clone()).set_versioning("artifacts", false).await.unwrap();
+        pool.close().await;
+
+        let expected = ["key GBCIKEY0000000000001: update policies", "bucket artifacts: enable versioning"];
+        let (pool, changes) = boot(&database_url, &file, true).await;
+        assert_eq!(summary(&changes), expected);
+        assert!(changes.iter().all(|change| !change.applied));
+        pool.close().await;
+
+        let (pool, changes) = boot(&database_url, &file, false).await;
+        assert_eq!(summary(&changes), expected);
+        assert!(changes.iter().all(|change| change.applied));
+        assert_eq!((count(&pool, "access_keys").await, count(&pool, "buckets").await), (2, 2));
+        pool.close().await;
+
+        let (_, changes) = boot(&database_url, &file, false).await;
+        assert!(changes.is_empty(), "{:?}", summary(&changes));
+    }
+}
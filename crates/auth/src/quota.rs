@@ -0,0 +1,430 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use ghostbay_catalog::telemetry::record_query;
+use sqlx::{Row, SqlitePool};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyQuota {
+    pub max_requests_per_day: Option<i64>,
+    pub max_bytes_in_per_month: Option<i64>,
+    pub max_bytes_out_per_month: Option<i64>,
+}
+
+impl KeyQuota {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_requests_per_day.is_none()
+            && self.max_bytes_in_per_month.is_none()
+            && self.max_bytes_out_per_month.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum UsagePeriod {
+    Day,
+    Month,
+}
+
+impl UsagePeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UsagePeriod::Day => "day",
+            UsagePeriod::Month => "month",
+        }
+    }
+
+    pub fn start(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let date = match self {
+            UsagePeriod::Day => at.date_naive(),
+            UsagePeriod::Month => NaiveDate::from_ymd_opt(at.year(), at.month(), 1).unwrap(),
+        };
+        date.and_hms_opt(0, 0, 0).unwrap().and_utc()
+    }
+
+    pub fn end(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let start = self.start(at);
+        match self {
+            UsagePeriod::Day => start + Duration::days(1),
+            UsagePeriod::Month => {
+                let (year, month) = if start.month() == 12 {
+                    (start.year() + 1, 1)
+                } else {
+                    (start.year(), start.month() + 1)
+                };
+                NaiveDate::from_ymd_opt(year, month, 1)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap()
+                    .and_utc()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageCounters {
+    pub requests: i64,
+    pub bytes_in: i64,
+    pub bytes_out: i64,
+}
+
+impl UsageCounters {
+    fn add(&mut self, other: &UsageCounters) {
+        self.requests += other.requests;
+        self.bytes_in += other.bytes_in;
+        self.bytes_out += other.bytes_out;
+    }
+
+    fn subtract(&mut self, other: &UsageCounters) {
+        self.requests -= other.requests;
+        self.bytes_in -= other.bytes_in;
+        self.bytes_out -= other.bytes_out;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyUsage {
+    pub access_key_id: String,
+    pub period: UsagePeriod,
+    pub period_start: DateTime<Utc>,
+    pub counters: UsageCounters,
+}
+
+#[derive(Debug, Clone)]
+pub struct QuotaViolation {
+    pub quota: &'static str,
+    pub resets_at: DateTime<Utc>,
+    pub retry_after_seconds: u64,
+}
+
+pub struct KeyUsageRepository {
+    pool: SqlitePool,
+}
+
+impl KeyUsageRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    #[tracing::instrument(skip(self, delta), fields(db.operation = "UPSERT", db.rows = tracing::field::Empty))]
+    pub async fn add(&self, access_key_id: &str, period: UsagePeriod, period_start: DateTime<Utc>, delta: &UsageCounters) -> Result<()> {
+        let started = Instant::now();
+        sqlx::query(
+            r#"
+            INSERT INTO key_usage (access_key_id, period, period_start, requests, bytes_in, bytes_out, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (access_key_id, period, period_start) DO UPDATE SET
+                requests = requests + excluded.requests,
+                bytes_in = bytes_in + excluded.bytes_in,
+                bytes_out = bytes_out + excluded.bytes_out,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(access_key_id)
+        .bind(period.as_str())
+        .bind(period_start.to_rfc3339())
+        .bind(delta.requests)
+        .bind(delta.bytes_in)
+        .bind(delta.bytes_out)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .context("KeyUsageRepository::add")?;
+        record_query(started, 1);
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn get(&self, access_key_id: &str, period: UsagePeriod, period_start: DateTime<Utc>) -> Result<UsageCounters> {
+        let started = Instant::now();
+        let row = sqlx::query(
+            "SELECT requests, bytes_in, bytes_out FROM key_usage WHERE access_key_id = ? AND period = ? AND period_start = ?"
+        )
+        .bind(access_key_id)
+        .bind(period.as_str())
+        .bind(period_start.to_rfc3339())
+        .fetch_optional(&self.pool)
+        .await
+        .context("KeyUsageRepository::get")?;
+        record_query(started, row.is_some() as u64);
+
+        Ok(row
+            .map(|row| UsageCounters {
+                requests: row.get("requests"),
+                bytes_in: row.get("bytes_in"),
+                bytes_out: row.get("bytes_out"),
+            })
+            .unwrap_or_default())
+    }
+
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn list_by_key(&self, access_key_id: &str) -> Result<Vec<KeyUsage>> {
+        let started = Instant::now();
+        let rows = sqlx::query(
+            "SELECT access_key_id, period, period_start, requests, bytes_in, bytes_out FROM key_usage WHERE access_key_id = ? ORDER BY period_start DESC, period"
+        )
+        .bind(access_key_id)
+        .fetch_all(&self.pool)
+        .await
+        .context("KeyUsageRepository::list_by_key")?;
+        record_query(started, rows.len() as u64);
+
+        let mut usage = Vec::new();
+        for row in rows {
+            let period = match row.get::<String, _>("period").as_str() {
+                "day" => UsagePeriod::Day,
+                "month" => UsagePeriod::Month,
+                other => return Err(anyhow::anyhow!("Unknown usage period: {}", other)),
+            };
+            usage.push(KeyUsage {
+                access_key_id: row.get("access_key_id"),
+                period,
+                period_start: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("period_start"))?.with_timezone(&Utc),
+                counters: UsageCounters {
+                    requests: row.get("requests"),
+                    bytes_in: row.get("bytes_in"),
+                    bytes_out: row.get("bytes_out"),
+                },
+            });
+        }
+
+        Ok(usage)
+    }
+}
+
+type UsageSlot = (String, UsagePeriod, DateTime<Utc>);
+
+struct TrackerState {
+    // Latest wall-clock time observed. Accounting never moves backwards, so a
+    // clock stepped back cannot reopen an already closed period.
+    high_water: DateTime<Utc>,
+    persisted: HashMap<UsageSlot, UsageCounters>,
+    pending: HashMap<UsageSlot, UsageCounters>,
+}
+
+// Accumulates per-key usage in memory and periodically flushes it to the
+// key_usage table, so quota checks stay cheap and counts survive restarts.
+// A delta stays in pending until its write commits and then moves to
+// persisted under the same lock, so a total never misses it or counts it
+// twice. Loads from the table and flushes are serialized by `io`, so a load
+// never reads a write whose delta is still pending.
+pub struct UsageTracker {
+    repo: KeyUsageRepository,
+    state: Mutex<TrackerState>,
+    io: tokio::sync::Mutex<()>,
+}
+
+impl UsageTracker {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            repo: KeyUsageRepository::new(pool),
+            state: Mutex::new(TrackerState {
+                high_water: Utc::now(),
+                persisted: HashMap::new(),
+                pending: HashMap::new(),
+            }),
+            io: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    fn now(state: &mut TrackerState) -> DateTime<Utc> {
+        state.high_water = state.high_water.max(Utc::now());
+        state.high_water
+    }
+
+    pub fn record(&self, access_key_id: &str, delta: UsageCounters) {
+        let mut state = self.state.lock().unwrap();
+        let now = Self::now(&mut state);
+
+        for period in [UsagePeriod::Day, UsagePeriod::Month] {
+            let slot = (access_key_id.to_string(), period, period.start(now));
+            state.pending.entry(slot).or_default().add(&delta);
+        }
+    }
+
+    pub async fn current(&self, access_key_id: &str, period: UsagePeriod) -> Result<UsageCounters> {
+        let slot = {
+            let mut state = self.state.lock().unwrap();
+            let now = Self::now(&mut state);
+            (access_key_id.to_string(), period, period.start(now))
+        };
+
+        if let Some(total) = self.cached_total(&slot) {
+            return Ok(total);
+        }
+
+        let _io = self.io.lock().await;
+        // Loaded by another caller while this one waited
+        if let Some(total) = self.cached_total(&slot) {
+            return Ok(total);
+        }
+        let loaded = self.repo.get(&slot.0, period, slot.2).await?;
+        let mut state = self.state.lock().unwrap();
+        let mut total = *state.persisted.entry(slot.clone()).or_insert(loaded);
+        if let Some(pending) = state.pending.get(&slot) {
+            total.add(pending);
+        }
+
+        Ok(total)
+    }
+
+    fn cached_total(&self, slot: &UsageSlot) -> Option<UsageCounters> {
+        let state = self.state.lock().unwrap();
+        let mut total = *state.persisted.get(slot)?;
+        if let Some(pending) = state.pending.get(slot) {
+            total.add(pending);
+        }
+        Some(total)
+    }
+
+    // Returns the first exceeded limit, if any. Limits are checked before a
+    // request starts, so a transfer already in flight is allowed to finish.
+    pub async fn check(&self, access_key_id: &str, quota: &KeyQuota) -> Result<Option<QuotaViolation>> {
+        if quota.is_unlimited() {
+            return Ok(None);
+        }
+
+        let day = self.current(access_key_id, UsagePeriod::Day).await?;
+        let month = self.current(access_key_id, UsagePeriod::Month).await?;
+
+        let exceeded = if quota.max_requests_per_day.is_some_and(|max| day.requests >= max) {
+            Some((UsagePeriod::Day, "MaxRequestsPerDay"))
+        } else if quota.max_bytes_in_per_month.is_some_and(|max| month.bytes_in >= max) {
+            Some((UsagePeriod::Month, "MaxBytesInPerMonth"))
+        } else if quota.max_bytes_out_per_month.is_some_and(|max| month.bytes_out >= max) {
+            Some((UsagePeriod::Month, "MaxBytesOutPerMonth"))
+        } else {
+            None
+        };
+
+        Ok(exceeded.map(|(period, quota)| {
+            let now = Self::now(&mut self.state.lock().unwrap());
+            let resets_at = period.end(now);
+            QuotaViolation {
+                quota,
+                resets_at,
+                retry_after_seconds: (resets_at - now).num_seconds().max(1) as u64,
+            }
+        }))
+    }
+
+    pub async fn flush(&self) -> Result<()> {
+        let _io = self.io.lock().await;
+        let (pending, current_high_water) = {
+            let state = self.state.lock().unwrap();
+            (state.pending.clone(), state.high_water)
+        };
+
+        // A failed write leaves its delta pending for the next flush
+        let mut result = Ok(());
+        for (slot, delta) in pending {
+            if let Err(e) = self.repo.add(&slot.0, slot.1, slot.2, &delta).await {
+                result = Err(e);
+                continue;
+            }
+
+            let mut state = self.state.lock().unwrap();
+            if let Some(pending) = state.pending.get_mut(&slot) {
+                pending.subtract(&delta);
+                if *pending == UsageCounters::default() {
+                    state.pending.remove(&slot);
+                }
+            }
+            if let Some(persisted) = state.persisted.get_mut(&slot) {
+                persisted.add(&delta);
+            }
+        }
+
+        let mut state = self.state.lock().unwrap();
+        // Cached totals for periods that have closed are no longer needed
+        state
+            .persisted
+            .retain(|(_, period, start), _| *start >= period.start(current_high_water));
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::sync::Arc;
+
+    async fn pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        ghostbay_catalog::migrations::run_migrations(&pool).await.unwrap();
+        pool
+    }
+
+    fn bytes_out(bytes_out: i64) -> UsageCounters {
+        UsageCounters { bytes_out, ..Default::default() }
+    }
+
+    // Downloads stream their bytes in while flushes run: the total a quota
+    // check sees must never dip below what was recorded, or overshoot it
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn totals_stay_exact_while_flushing() {
+        let pool = pool().await;
+        let tracker = Arc::new(UsageTracker::new(pool.clone()));
+        const CHUNKS: i64 = 2000;
+
+        let flusher = {
+            let tracker = tracker.clone();
+            tokio::spawn(async move {
+                for _ in 0..200 {
+                    tracker.flush().await.unwrap();
+                    tokio::task::yield_now().await;
+                }
+            })
+        };
+        let downloader = {
+            let tracker = tracker.clone();
+            tokio::spawn(async move {
+                for recorded in 1..=CHUNKS {
+                    tracker.record("reader", bytes_out(1));
+                    let seen = tracker.current("reader", UsagePeriod::Month).await.unwrap().bytes_out;
+                    assert_eq!(seen, recorded, "after {} chunks", recorded);
+                    if recorded % 16 == 0 {
+                        tokio::task::yield_now().await;
+                    }
+                }
+            })
+        };
+        downloader.await.unwrap();
+        flusher.await.unwrap();
+        tracker.flush().await.unwrap();
+
+        assert_eq!(tracker.current("reader", UsagePeriod::Month).await.unwrap().bytes_out, CHUNKS);
+        // A restarted tracker reads the same total back from the table
+        let restarted = UsageTracker::new(pool);
+        assert_eq!(restarted.current("reader", UsagePeriod::Month).await.unwrap().bytes_out, CHUNKS);
+        assert_eq!(restarted.current("reader", UsagePeriod::Day).await.unwrap().bytes_out, CHUNKS);
+    }
+
+    #[tokio::test]
+    async fn quota_is_crossed_by_an_in_flight_download() {
+        let tracker = UsageTracker::new(pool().await);
+        let quota = KeyQuota { max_bytes_out_per_month: Some(1000), ..Default::default() };
+
+        // Checked before the download starts, so the one that crosses the
+        // limit finishes, and only the next request is refused
+        assert!(tracker.check("reader", &quota).await.unwrap().is_none());
+        tracker.record("reader", bytes_out(600));
+        tracker.flush().await.unwrap();
+        assert!(tracker.check("reader", &quota).await.unwrap().is_none());
+        tracker.record("reader", bytes_out(300));
+        tracker.flush().await.unwrap();
+        tracker.record("reader", bytes_out(300));
+
+        let violation = tracker.check("reader", &quota).await.unwrap().unwrap();
+        assert_eq!(violation.quota, "MaxBytesOutPerMonth");
+        tracker.flush().await.unwrap();
+        assert!(tracker.check("reader", &quota).await.unwrap().is_some());
+        assert_eq!(tracker.current("reader", UsagePeriod::Month).await.unwrap().bytes_out, 1200);
+    }
+}
@@ -3,12 +3,20 @@ use chrono::{DateTime, Duration, Utc};
 use ring::{digest, hmac};
 use std::collections::HashMap;
 
+// Largest clock difference tolerated between a signer and this server,
+// unless AuthService is configured otherwise
+pub const DEFAULT_CLOCK_SKEW_MINUTES: u64 = 15;
+
+// Longest lifetime S3 accepts for a presigned URL (seven days)
+pub const MAX_PRESIGNED_EXPIRES_SECONDS: u64 = 7 * 24 * 60 * 60;
+
 pub struct SigV4Validator;
 
 impl SigV4Validator {
+    #[allow(clippy::too_many_arguments)]
     pub fn validate_signature(
         secret_key: &str,
-        access_key: &str,
+        _access_key: &str,
         method: &str,
         uri: &str,
         query_string: &str,
@@ -18,31 +26,94 @@ impl SigV4Validator {
         timestamp: DateTime<Utc>,
         region: &str,
         service: &str,
+        max_clock_skew: Duration,
     ) -> Result<bool> {
-        // Validate timestamp (within 15 minutes)
         let now = Utc::now();
-        let max_age = Duration::minutes(15);
-        if (now - timestamp).abs() > max_age {
+        if (now - timestamp).abs() > max_clock_skew {
             return Err(anyhow::anyhow!("Request timestamp too old"));
         }
 
-        let canonical_request = Self::create_canonical_request(
-            method, uri, query_string, headers, payload_hash
-        );
+        Self::verify_signature(
+            secret_key, method, uri, query_string, headers, payload_hash, signature, timestamp, region, service
+        )
+    }
 
-        let string_to_sign = Self::create_string_to_sign(
-            &canonical_request, timestamp, region, service
-        );
+    // Presigned URLs are used long after they were signed, so instead of the
+    // clock-skew window they are valid from X-Amz-Date for X-Amz-Expires seconds
+    #[allow(clippy::too_many_arguments)]
+    pub fn validate_presigned_signature(
+        secret_key: &str,
+        method: &str,
+        uri: &str,
+        query_string: &str,
+        headers: &HashMap<String, String>,
+        signature: &str,
+        timestamp: DateTime<Utc>,
+        expires_in_seconds: u64,
+        region: &str,
+        service: &str,
+        max_clock_skew: Duration,
+    ) -> Result<bool> {
+        let now = Utc::now();
+        if timestamp - now > max_clock_skew {
+            return Err(anyhow::anyhow!("Request is not valid yet"));
+        }
+        if now > presigned_expires_at(timestamp, expires_in_seconds) {
+            return Err(anyhow::anyhow!("Request has expired"));
+        }
+
+        Self::verify_signature(
+            secret_key, method, uri, query_string, headers, "UNSIGNED-PAYLOAD", signature, timestamp, region, service
+        )
+    }
 
+    // Browser upload forms sign their base64 policy document in place of a
+    // canonical request; the policy's expiration bounds how long it is valid
+    pub fn validate_post_policy_signature(
+        secret_key: &str,
+        policy: &str,
+        signature: &str,
+        timestamp: DateTime<Utc>,
+        region: &str,
+        service: &str,
+    ) -> Result<bool> {
+        let signing_key = Self::get_signing_key(secret_key, timestamp, region, service)?;
+        Ok(Self::calculate_signature(&signing_key, policy) == signature)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn verify_signature(
+        secret_key: &str,
+        method: &str,
+        uri: &str,
+        query_string: &str,
+        headers: &HashMap<String, String>,
+        payload_hash: &str,
+        signature: &str,
+        timestamp: DateTime<Utc>,
+        region: &str,
+        service: &str,
+    ) -> Result<bool> {
         let signing_key = Self::get_signing_key(
             secret_key, timestamp, region, service
         )?;
+        let signs = |canonical_uri: &str| {
+            let canonical_request = Self::create_canonical_request(
+                method, canonical_uri, query_string, headers, payload_hash
+            );
+            let string_to_sign = Self::create_string_to_sign(
+                &canonical_request, timestamp, region, service
+            );
+            Self::calculate_signature(&signing_key, &string_to_sign) == signature
+        };
 
-        let expected_signature = Self::calculate_signature(&signing_key, &string_to_sign);
-
-        Ok(expected_signature == signature)
+        // Clients that send a path spelled other than canonically, such as a
+        // bare `+`, may sign it as sent rather than re-encoded
+        let canonical_uri = Self::canonical_uri_encode(uri);
+        Ok(signs(&canonical_uri) || (canonical_uri != uri && signs(uri)))
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn generate_presigned_url(
         secret_key: &str,
         access_key: &str,
@@ -55,7 +126,6 @@ impl SigV4Validator {
         host: &str,
     ) -> Result<String> {
         let now = Utc::now();
-        let expires = now + Duration::seconds(expires_in_seconds as i64);
         
         let mut query_params = HashMap::new();
         query_params.insert("X-Amz-Algorithm".to_string(), "AWS4-HMAC-SHA256".to_string());
@@ -65,7 +135,13 @@ impl SigV4Validator {
         query_params.insert("X-Amz-Expires".to_string(), expires_in_seconds.to_string());
         query_params.insert("X-Amz-SignedHeaders".to_string(), "host".to_string());
 
-        let uri = format!("/{}/{}", bucket, key);
+        // Keys are encoded per segment, so `+` and spaces go out as `%2B` and `%20`
+        let encoded_key = key
+            .split('/')
+            .map(|segment| urlencoding::encode(segment).into_owned())
+            .collect::<Vec<_>>()
+            .join("/");
+        let uri = format!("/{}/{}", bucket, encoded_key);
         let query_string = Self::build_query_string(&query_params);
         
         let headers = {
@@ -75,7 +151,7 @@ impl SigV4Validator {
         };
 
         let canonical_request = Self::create_canonical_request(
-            method, &uri, &query_string, &headers, "UNSIGNED-PAYLOAD"
+            method, &Self::canonical_uri_encode(&uri), &query_string, &headers, "UNSIGNED-PAYLOAD"
         );
 
         let string_to_sign = Self::create_string_to_sign(
@@ -90,12 +166,11 @@ impl SigV4Validator {
 
     fn create_canonical_request(
         method: &str,
-        uri: &str,
+        canonical_uri: &str,
         query_string: &str,
         headers: &HashMap<String, String>,
         payload_hash: &str,
     ) -> String {
-        let canonical_uri = Self::canonical_uri_encode(uri);
         let canonical_query = Self::canonical_query_string(query_string);
         let (canonical_headers, signed_headers) = Self::canonical_headers(headers);
 
@@ -170,18 +245,24 @@ impl SigV4Validator {
         hex::encode(signature.as_ref())
     }
 
+    // Takes the path as sent on the wire. In paths `+` is a literal plus and
+    // `%2B` decodes to the same character, matching how routing decodes keys,
+    // so both spellings canonicalize to `%2B`.
     fn canonical_uri_encode(uri: &str) -> String {
         if uri.is_empty() {
             "/".to_string()
         } else {
             // URI encode path segments but keep slashes
             uri.split('/')
-                .map(|segment| urlencoding::encode(segment).to_string())
+                .map(|segment| urlencoding::encode(&decode_path_segment(segment)).into_owned())
                 .collect::<Vec<_>>()
                 .join("/")
         }
     }
 
+    // Takes the query as sent on the wire. Components are decoded the way the
+    // query extractors decode them (a bare `+` is a space, `%2B` is a plus) and
+    // re-encoded per SigV4: space as `%20`, plus as `%2B`, sorted by name then value.
     fn canonical_query_string(query: &str) -> String {
         if query.is_empty() {
             return String::new();
@@ -189,17 +270,22 @@ impl SigV4Validator {
 
         let mut params: Vec<_> = query
             .split('&')
+            .filter(|param| !param.is_empty())
             .map(|param| {
-                if let Some((key, value)) = param.split_once('=') {
-                    format!("{}={}", urlencoding::encode(key), urlencoding::encode(value))
-                } else {
-                    format!("{}=", urlencoding::encode(param))
-                }
+                let (key, value) = param.split_once('=').unwrap_or((param, ""));
+                (
+                    urlencoding::encode(&decode_query_component(key)).into_owned(),
+                    urlencoding::encode(&decode_query_component(value)).into_owned(),
+                )
             })
             .collect();
 
         params.sort();
-        params.join("&")
+        params
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join("&")
     }
 
     fn canonical_headers(headers: &HashMap<String, String>) -> (String, String) {
@@ -237,6 +323,19 @@ impl SigV4Validator {
     }
 }
 
+fn decode_path_segment(segment: &str) -> String {
+    urlencoding::decode(segment)
+        .map(|decoded| decoded.into_owned())
+        .unwrap_or_else(|_| segment.to_string())
+}
+
+fn decode_query_component(component: &str) -> String {
+    let component = component.replace('+', " ");
+    urlencoding::decode(&component)
+        .map(|decoded| decoded.into_owned())
+        .unwrap_or(component)
+}
+
 pub fn parse_authorization_header(auth_header: &str) -> Result<SigV4AuthInfo> {
     if !auth_header.starts_with("AWS4-HMAC-SHA256 ") {
         return Err(anyhow::anyhow!("Invalid authorization header format"));
@@ -277,6 +376,95 @@ pub fn parse_authorization_header(auth_header: &str) -> Result<SigV4AuthInfo> {
     })
 }
 
+// A request is presigned when its SigV4 signature travels in the query string
+pub fn is_presigned_query(query: &str) -> bool {
+    query.split('&').any(|param| {
+        let key = param.split_once('=').map_or(param, |(key, _)| key);
+        matches!(decode_query_component(key).as_str(), "X-Amz-Algorithm" | "X-Amz-Signature")
+    })
+}
+
+pub fn parse_presigned_query(query: &str) -> Result<PresignedAuthInfo> {
+    let mut params = HashMap::new();
+    let mut signed_query = Vec::new();
+    for param in query.split('&').filter(|param| !param.is_empty()) {
+        let (key, value) = param.split_once('=').unwrap_or((param, ""));
+        let key = decode_query_component(key);
+        // The signature covers every other parameter, exactly as sent
+        if key != "X-Amz-Signature" {
+            signed_query.push(param);
+        }
+        params.insert(key, decode_query_component(value));
+    }
+    let param = |name: &str| {
+        params
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Missing {} in query string", name))
+    };
+
+    let algorithm = param("X-Amz-Algorithm")?;
+    if algorithm != "AWS4-HMAC-SHA256" {
+        return Err(anyhow::anyhow!("Unsupported X-Amz-Algorithm {}", algorithm));
+    }
+
+    let credential = param("X-Amz-Credential")?;
+    let credential_parts: Vec<&str> = credential.split('/').collect();
+    if credential_parts.len() != 5 {
+        return Err(anyhow::anyhow!("Invalid credential format"));
+    }
+
+    let date = param("X-Amz-Date")?;
+    let timestamp = chrono::NaiveDateTime::parse_from_str(&date, "%Y%m%dT%H%M%SZ")
+        .map_err(|_| anyhow::anyhow!("Invalid X-Amz-Date {}", date))?
+        .and_utc();
+
+    let expires = param("X-Amz-Expires")?;
+    let expires_in_seconds: u64 = expires
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid X-Amz-Expires {}", expires))?;
+    if expires_in_seconds == 0 || expires_in_seconds > MAX_PRESIGNED_EXPIRES_SECONDS {
+        return Err(anyhow::anyhow!(
+            "X-Amz-Expires must be between 1 and {} seconds",
+            MAX_PRESIGNED_EXPIRES_SECONDS
+        ));
+    }
+
+    Ok(PresignedAuthInfo {
+        access_key_id: credential_parts[0].to_string(),
+        region: credential_parts[2].to_string(),
+        service: credential_parts[3].to_string(),
+        signed_headers: param("X-Amz-SignedHeaders")?.split(';').map(|s| s.to_string()).collect(),
+        signature: param("X-Amz-Signature")?,
+        timestamp,
+        expires_in_seconds,
+        signed_query: signed_query.join("&"),
+    })
+}
+
+pub fn presigned_expires_at(timestamp: DateTime<Utc>, expires_in_seconds: u64) -> DateTime<Utc> {
+    timestamp + Duration::seconds(expires_in_seconds.min(MAX_PRESIGNED_EXPIRES_SECONDS) as i64)
+}
+
+#[derive(Debug, Clone)]
+pub struct PresignedAuthInfo {
+    pub access_key_id: String,
+    pub region: String,
+    pub service: String,
+    pub signed_headers: Vec<String>,
+    pub signature: String,
+    pub timestamp: DateTime<Utc>,
+    pub expires_in_seconds: u64,
+    // Query string without X-Amz-Signature, as covered by the signature
+    pub signed_query: String,
+}
+
+impl PresignedAuthInfo {
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        presigned_expires_at(self.timestamp, self.expires_in_seconds)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SigV4AuthInfo {
     pub access_key_id: String,
@@ -290,4 +478,59 @@ pub struct SigV4AuthInfo {
 pub fn hash_payload(payload: &[u8]) -> String {
     let digest = digest::digest(&digest::SHA256, payload);
     hex::encode(digest.as_ref())
-}
\ No newline at end of file
+}
+// Checks the chunk signatures of a STREAMING-AWS4-HMAC-SHA256-PAYLOAD body.
+// Each chunk is signed over the hash of its data and the signature before
+// it, so the chain starts from the seed signature of the Authorization
+// header and a chunk cannot be dropped, reordered or altered.
+#[derive(Debug, Clone)]
+pub struct ChunkSigner {
+    signing_key: hmac::Key,
+    timestamp: String,
+    credential_scope: String,
+    previous_signature: String,
+}
+
+impl ChunkSigner {
+    pub fn new(secret_key: &str, seed_signature: &str, timestamp: DateTime<Utc>, region: &str, service: &str) -> Result<Self> {
+        Ok(Self {
+            signing_key: SigV4Validator::get_signing_key(secret_key, timestamp, region, service)?,
+            timestamp: timestamp.format("%Y%m%dT%H%M%SZ").to_string(),
+            credential_scope: format!("{}/{}/{}/aws4_request", timestamp.format("%Y%m%d"), region, service),
+            previous_signature: seed_signature.to_string(),
+        })
+    }
+
+    // chunk_hash is the hex SHA-256 of the chunk's data; the final, empty
+    // chunk is signed too
+    pub fn verify_chunk(&mut self, chunk_hash: &str, signature: &str) -> bool {
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256-PAYLOAD\n{}\n{}\n{}\n{}\n{}",
+            self.timestamp,
+            self.credential_scope,
+            self.previous_signature,
+            hash_payload(b""),
+            chunk_hash
+        );
+        self.verify(&string_to_sign, signature)
+    }
+
+    // trailer_hash is the hex SHA-256 of the trailing headers, each as
+    // "name:value\n"
+    pub fn verify_trailer(&mut self, trailer_hash: &str, signature: &str) -> bool {
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256-TRAILER\n{}\n{}\n{}\n{}",
+            self.timestamp, self.credential_scope, self.previous_signature, trailer_hash
+        );
+        self.verify(&string_to_sign, signature)
+    }
+
+    fn verify(&mut self, string_to_sign: &str, signature: &str) -> bool {
+        let expected = SigV4Validator::calculate_signature(&self.signing_key, string_to_sign);
+        if expected != signature {
+            return false;
+        }
+        self.previous_signature = expected;
+        true
+    }
+}
@@ -0,0 +1,168 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
+use sqlx::{ConnectOptions, Connection};
+use std::path::{Path, PathBuf};
+
+// Snapshots are named ghostbay-catalog-<UTC time>.db, so their names sort by age
+pub const BACKUP_FILE_PREFIX: &str = "ghostbay-catalog-";
+pub const BACKUP_FILE_EXTENSION: &str = ".db";
+const BACKUP_TIME_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+#[derive(Debug, Clone)]
+pub struct CatalogBackup {
+    pub path: PathBuf,
+    pub created_at: DateTime<Utc>,
+    pub size: u64,
+}
+
+// Writes a consistent snapshot of the live catalog into backup_dir. VACUUM
+// INTO runs inside a read transaction, so writers carry on while it copies.
+// The snapshot is written under a temporary name and renamed once complete.
+pub async fn backup_catalog(pool: &SqlitePool, backup_dir: &Path) -> Result<CatalogBackup> {
+    std::fs::create_dir_all(backup_dir).with_context(|| format!("Failed to create backup directory {}", backup_dir.display()))?;
+
+    let created_at = Utc::now();
+    let file_name = format!("{}{}{}", BACKUP_FILE_PREFIX, created_at.format(BACKUP_TIME_FORMAT), BACKUP_FILE_EXTENSION);
+    let path = backup_dir.join(&file_name);
+    let temp_path = backup_dir.join(format!("{}.tmp", file_name));
+
+    sqlx::query("VACUUM INTO ?")
+        .bind(temp_path.to_string_lossy().into_owned())
+        .execute(pool)
+        .await
+        .with_context(|| format!("Failed to write catalog snapshot {}", temp_path.display()))?;
+
+    let size = persist(&temp_path, &path)?;
+    Ok(CatalogBackup { path, created_at, size })
+}
+
+// Snapshots in backup_dir, oldest first. Other files are ignored.
+pub fn list_backups(backup_dir: &Path) -> Result<Vec<CatalogBackup>> {
+    let entries = match std::fs::read_dir(backup_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read backup directory {}", backup_dir.display())),
+    };
+
+    let mut backups = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let Some(created_at) = file_name.to_str().and_then(parse_backup_time) else {
+            continue;
+        };
+        backups.push(CatalogBackup {
+            path: entry.path(),
+            created_at,
+            size: entry.metadata()?.len(),
+        });
+    }
+
+    backups.sort_by_key(|backup| backup.created_at);
+    Ok(backups)
+}
+
+fn parse_backup_time(file_name: &str) -> Option<DateTime<Utc>> {
+    let time = file_name.strip_prefix(BACKUP_FILE_PREFIX)?.strip_suffix(BACKUP_FILE_EXTENSION)?;
+    NaiveDateTime::parse_from_str(time, BACKUP_TIME_FORMAT).ok().map(|time| time.and_utc())
+}
+
+// Deletes all but the newest `keep` snapshots and returns what was removed
+pub fn prune_backups(backup_dir: &Path, keep: usize) -> Result<Vec<CatalogBackup>> {
+    let mut backups = list_backups(backup_dir)?;
+    let excess = backups.len().saturating_sub(keep);
+    let pruned: Vec<CatalogBackup> = backups.drain(..excess).collect();
+    for backup in &pruned {
+        std::fs::remove_file(&backup.path).with_context(|| format!("Failed to remove {}", backup.path.display()))?;
+    }
+    Ok(pruned)
+}
+
+// Replaces the catalog file with a snapshot. The caller must hold the
+// catalog lock, since a running server would keep writing to the old file.
+// The replaced catalog is kept next to it as <file>.pre-restore-<time>.
+pub async fn restore_catalog(snapshot: &Path, database_file: &Path) -> Result<Option<PathBuf>> {
+    check_snapshot(snapshot).await?;
+
+    let file_name = database_file
+        .file_name()
+        .ok_or_else(|| anyhow!("{} is not a file path", database_file.display()))?
+        .to_string_lossy()
+        .into_owned();
+    let temp_path = database_file.with_file_name(format!("{}.restore.tmp", file_name));
+    std::fs::copy(snapshot, &temp_path)
+        .with_context(|| format!("Failed to copy {} to {}", snapshot.display(), temp_path.display()))?;
+
+    let previous = if database_file.exists() {
+        let previous = database_file.with_file_name(format!(
+            "{}.pre-restore-{}",
+            file_name,
+            Utc::now().format(BACKUP_TIME_FORMAT)
+        ));
+        std::fs::rename(database_file, &previous)
+            .with_context(|| format!("Failed to move {} aside", database_file.display()))?;
+        Some(previous)
+    } else {
+        None
+    };
+
+    // A write-ahead log left by the old catalog must not be replayed onto the snapshot
+    for suffix in ["-wal", "-shm"] {
+        let sidecar = database_file.with_file_name(format!("{}{}", file_name, suffix));
+        match std::fs::remove_file(&sidecar) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_context(|| format!("Failed to remove {}", sidecar.display())),
+        }
+    }
+
+    persist(&temp_path, database_file)?;
+    Ok(previous)
+}
+
+// Refuses files that are not intact GhostBay catalogs
+async fn check_snapshot(snapshot: &Path) -> Result<()> {
+    let mut connection = SqliteConnectOptions::new()
+        .filename(snapshot)
+        .read_only(true)
+        .connect()
+        .await
+        .with_context(|| format!("Failed to open {}", snapshot.display()))?;
+
+    let integrity: String = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_one(&mut connection)
+        .await
+        .with_context(|| format!("{} is not a SQLite database", snapshot.display()))?;
+    if integrity != "ok" {
+        return Err(anyhow!("{} failed the integrity check: {}", snapshot.display(), integrity));
+    }
+
+    let tables: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name IN ('buckets', 'objects')",
+    )
+    .fetch_one(&mut connection)
+    .await?;
+    if tables != 2 {
+        return Err(anyhow!("{} is not a GhostBay catalog", snapshot.display()));
+    }
+
+    connection.close().await?;
+    Ok(())
+}
+
+// Flushes a finished file and moves it into place atomically
+fn persist(temp_path: &Path, path: &Path) -> Result<u64> {
+    let file = std::fs::OpenOptions::new().append(true).open(temp_path)?;
+    file.sync_all()?;
+    let size = file.metadata()?.len();
+    drop(file);
+    std::fs::rename(temp_path, path).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    #[cfg(unix)]
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::File::open(parent)?.sync_all()?;
+    }
+
+    Ok(size)
+}
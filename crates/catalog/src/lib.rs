@@ -1,12 +1,13 @@
 use anyhow::Result;
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, SqlitePool};
-use uuid::Uuid;
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
 
+pub mod backup;
 pub mod models;
 pub mod repository;
 pub mod migrations;
+pub mod lifecycle;
+pub mod telemetry;
 
 pub use models::*;
 pub use repository::*;
@@ -25,4 +26,24 @@ impl CatalogService {
     pub fn pool(&self) -> &SqlitePool {
         &self.pool
     }
-}
\ No newline at end of file
+}
+
+// Lock file a server holds on its catalog, next to the SQLite file
+pub fn catalog_lock_path(database_file: &Path) -> PathBuf {
+    database_file.with_file_name(format!(
+        "{}.lock",
+        database_file.file_name().unwrap_or_default().to_string_lossy()
+    ))
+}
+
+// Path of the SQLite file behind a database URL, or None for in-memory databases
+pub fn database_file(database_url: &str) -> Option<PathBuf> {
+    let path = database_url.strip_prefix("sqlite:")?;
+    let path = path.strip_prefix("//").unwrap_or(path);
+    let path = path.split('?').next().unwrap_or(path);
+    if path.is_empty() || path == ":memory:" {
+        None
+    } else {
+        Some(PathBuf::from(path))
+    }
+}
@@ -0,0 +1,243 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::models::{LifecycleRule, Object};
+use crate::repository::{ObjectRepository, ObjectTagRepository};
+
+const SCAN_BATCH_SIZE: i32 = 1000;
+
+impl LifecycleRule {
+    // Whether an upload started at initiated is stale under this rule
+    pub fn aborts_upload(&self, object_key: &str, initiated: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        self.enabled
+            && self.prefix.as_deref().is_none_or(|prefix| object_key.starts_with(prefix))
+            && self.abort_incomplete_upload_days.is_some_and(|days| initiated + Duration::days(days) <= now)
+    }
+
+    // The one matcher behind both the expiration job and its previews
+    pub fn matches(&self, object: &Object, tags: &HashMap<String, String>, now: DateTime<Utc>) -> bool {
+        self.enabled
+            && self.prefix.as_deref().is_none_or(|prefix| object.key.starts_with(prefix))
+            && self.tags.iter().all(|(key, value)| tags.get(key) == Some(value))
+            && self.expiration_days.is_some_and(|days| object.updated_at + Duration::days(days) <= now)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LifecycleMatch {
+    // Index into the bucket's rule list
+    pub rule: usize,
+    pub object: Object,
+}
+
+// Walks a bucket in key order and yields the objects due for expiration.
+// An object matched by several rules is attributed to the first one only,
+// so per-rule counts never double count.
+pub struct LifecycleEvaluator {
+    objects: ObjectRepository,
+    tags: ObjectTagRepository,
+    bucket_id: Uuid,
+    rules: Vec<LifecycleRule>,
+    now: DateTime<Utc>,
+    start_after: Option<String>,
+    done: bool,
+}
+
+impl LifecycleEvaluator {
+    pub fn new(pool: SqlitePool, bucket_id: Uuid, rules: Vec<LifecycleRule>, now: DateTime<Utc>) -> Self {
+        Self {
+            objects: ObjectRepository::new(pool.clone()),
+            tags: ObjectTagRepository::new(pool),
+            bucket_id,
+            rules,
+            now,
+            start_after: None,
+            done: false,
+        }
+    }
+
+    pub fn rules(&self) -> &[LifecycleRule] {
+        &self.rules
+    }
+
+    // Returns None once the whole bucket has been scanned
+    pub async fn next_batch(&mut self) -> Result<Option<Vec<LifecycleMatch>>> {
+        if self.done || !self.rules.iter().any(|rule| rule.enabled && rule.expiration_days.is_some()) {
+            return Ok(None);
+        }
+
+        let page = self.objects.list_page(self.bucket_id, self.start_after.as_deref(), SCAN_BATCH_SIZE).await?;
+        let (first_key, last_key) = match (page.first(), page.last()) {
+            (Some(first), Some(last)) => (first.key.clone(), last.key.clone()),
+            _ => {
+                self.done = true;
+                return Ok(None);
+            }
+        };
+        self.done = page.len() < SCAN_BATCH_SIZE as usize;
+
+        let tags = if self.rules.iter().any(|rule| !rule.tags.is_empty()) {
+            self.tags.list_in_range(self.bucket_id, &first_key, &last_key).await?
+        } else {
+            HashMap::new()
+        };
+        self.start_after = Some(last_key);
+
+        let untagged = HashMap::new();
+        let matches = page
+            .into_iter()
+            .filter_map(|object| {
+                let object_tags = tags.get(&object.key).unwrap_or(&untagged);
+                self.rules
+                    .iter()
+                    .position(|rule| rule.matches(&object, object_tags, self.now))
+                    .map(|rule| LifecycleMatch { rule, object })
+            })
+            .collect();
+
+        Ok(Some(matches))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LifecycleRuleReport {
+    #[serde(rename = "ID")]
+    pub id: String,
+    pub matched_objects: u64,
+    pub matched_bytes: u64,
+    pub sample_keys: Vec<String>,
+}
+
+pub struct LifecycleReport {
+    pub rules: Vec<LifecycleRuleReport>,
+    sample_limit: usize,
+}
+
+impl LifecycleReport {
+    pub fn new(rules: &[LifecycleRule], sample_limit: usize) -> Self {
+        Self {
+            rules: rules
+                .iter()
+                .map(|rule| LifecycleRuleReport {
+                    id: rule.id.clone(),
+                    matched_objects: 0,
+                    matched_bytes: 0,
+                    sample_keys: Vec::new(),
+                })
+                .collect(),
+            sample_limit,
+        }
+    }
+
+    pub fn record(&mut self, matched: &LifecycleMatch) {
+        let report = &mut self.rules[matched.rule];
+        report.matched_objects += 1;
+        report.matched_bytes += matched.object.size as u64;
+        if report.sample_keys.len() < self.sample_limit {
+            report.sample_keys.push(matched.object.key.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(prefix: Option<&str>, tags: &[(&str, &str)], expiration_days: Option<i64>) -> LifecycleRule {
+        LifecycleRule {
+            id: "rule".to_string(),
+            prefix: prefix.map(str::to_string),
+            tags: tags.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect(),
+            expiration_days,
+            abort_incomplete_upload_days: None,
+            enabled: true,
+        }
+    }
+
+    fn object(key: &str, age_days: i64, now: DateTime<Utc>) -> Object {
+        let updated_at = now - Duration::days(age_days);
+        Object {
+            id: Uuid::new_v4(),
+            bucket_id: Uuid::new_v4(),
+            key: key.to_string(),
+            version_id: None,
+            etag: "etag".to_string(),
+            etag_algorithm: "md5".to_string(),
+            size: 1,
+            content_type: "application/octet-stream".to_string(),
+            created_at: updated_at,
+            updated_at,
+            storage_path: key.to_string(),
+            metadata: None,
+            content_encoding: None,
+            checksum_algorithm: None,
+            checksum_value: None,
+            system_metadata: None,
+            expires_at: None,
+        }
+    }
+
+    fn tags(tags: &[(&str, &str)]) -> HashMap<String, String> {
+        tags.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn matches_on_prefix() {
+        let now = Utc::now();
+        let rule = rule(Some("logs/"), &[], Some(30));
+        assert!(rule.matches(&object("logs/app.log", 31, now), &tags(&[]), now));
+        assert!(!rule.matches(&object("data/app.log", 31, now), &tags(&[]), now));
+        assert!(!rule.matches(&object("log", 31, now), &tags(&[]), now));
+    }
+
+    #[test]
+    fn requires_every_tag() {
+        let now = Utc::now();
+        let rule = rule(None, &[("class", "temp"), ("team", "ops")], Some(1));
+        let old = object("key", 2, now);
+        assert!(rule.matches(&old, &tags(&[("class", "temp"), ("team", "ops"), ("extra", "x")]), now));
+        assert!(!rule.matches(&old, &tags(&[("class", "temp")]), now));
+        assert!(!rule.matches(&old, &tags(&[("class", "temp"), ("team", "dev")]), now));
+        assert!(!rule.matches(&old, &tags(&[]), now));
+    }
+
+    #[test]
+    fn expires_once_old_enough() {
+        let now = Utc::now();
+        let rule = rule(None, &[], Some(7));
+        assert!(!rule.matches(&object("key", 6, now), &tags(&[]), now));
+        assert!(rule.matches(&object("key", 7, now), &tags(&[]), now));
+        assert!(rule.matches(&object("key", 100, now), &tags(&[]), now));
+    }
+
+    #[test]
+    fn combines_prefix_tags_and_age() {
+        let now = Utc::now();
+        let rule = rule(Some("tmp/"), &[("class", "temp")], Some(3));
+        let temp = tags(&[("class", "temp")]);
+        assert!(rule.matches(&object("tmp/a", 3, now), &temp, now));
+        assert!(!rule.matches(&object("tmp/a", 2, now), &temp, now));
+        assert!(!rule.matches(&object("tmp/a", 3, now), &tags(&[]), now));
+        assert!(!rule.matches(&object("keep/a", 3, now), &temp, now));
+    }
+
+    #[test]
+    fn disabled_and_abort_only_rules_expire_nothing() {
+        let now = Utc::now();
+        let old = object("key", 365, now);
+        let mut disabled = rule(None, &[], Some(1));
+        disabled.enabled = false;
+        assert!(!disabled.matches(&old, &tags(&[]), now));
+
+        let mut abort_only = rule(None, &[], None);
+        abort_only.abort_incomplete_upload_days = Some(1);
+        assert!(!abort_only.matches(&old, &tags(&[]), now));
+        assert!(abort_only.aborts_upload("key", now - Duration::days(1), now));
+        assert!(!abort_only.aborts_upload("key", now - Duration::hours(23), now));
+    }
+}
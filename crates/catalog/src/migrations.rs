@@ -1,5 +1,5 @@
 use anyhow::Result;
-use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool};
+use sqlx::{migrate::MigrateDatabase, Row, Sqlite, SqlitePool};
 
 pub async fn ensure_database_exists(database_url: &str) -> Result<()> {
     if !Sqlite::database_exists(database_url).await.unwrap_or(false) {
@@ -49,6 +49,39 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<()> {
     .execute(pool)
     .await?;
 
+    // Create object_versions table (append-only history of puts and deletes)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS object_versions (
+            id TEXT PRIMARY KEY NOT NULL,
+            bucket_id TEXT NOT NULL,
+            key TEXT NOT NULL,
+            version_id TEXT NOT NULL,
+            etag TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            content_type TEXT NOT NULL,
+            storage_path TEXT NOT NULL,
+            is_delete_marker BOOLEAN NOT NULL DEFAULT FALSE,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (bucket_id) REFERENCES buckets (id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Seed history for objects written before versions were recorded
+    sqlx::query(
+        r#"
+        INSERT INTO object_versions (id, bucket_id, key, version_id, etag, size, content_type, storage_path, is_delete_marker, created_at)
+        SELECT o.id, o.bucket_id, o.key, COALESCE(o.version_id, o.id), o.etag, o.size, o.content_type, o.storage_path, FALSE, o.updated_at
+        FROM objects o
+        WHERE NOT EXISTS (SELECT 1 FROM object_versions v WHERE v.bucket_id = o.bucket_id AND v.key = o.key)
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
     // Create multipart_uploads table
     sqlx::query(
         r#"
@@ -66,6 +99,96 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<()> {
     .execute(pool)
     .await?;
 
+    // Create object_tags table (one row per tag, keyed by object key)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS object_tags (
+            bucket_id TEXT NOT NULL,
+            key TEXT NOT NULL,
+            tag_key TEXT NOT NULL,
+            tag_value TEXT NOT NULL,
+            PRIMARY KEY (bucket_id, key, tag_key),
+            FOREIGN KEY (bucket_id) REFERENCES buckets (id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create object_parts table (the parts a multipart upload completed with,
+    // one row per part of each object version)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS object_parts (
+            bucket_id TEXT NOT NULL,
+            version_id TEXT NOT NULL,
+            part_number INTEGER NOT NULL,
+            size INTEGER NOT NULL,
+            PRIMARY KEY (version_id, part_number),
+            FOREIGN KEY (bucket_id) REFERENCES buckets (id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create bucket_tags table (one row per tag)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS bucket_tags (
+            bucket_id TEXT NOT NULL,
+            tag_key TEXT NOT NULL,
+            tag_value TEXT NOT NULL,
+            PRIMARY KEY (bucket_id, tag_key),
+            FOREIGN KEY (bucket_id) REFERENCES buckets (id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create bucket_lifecycle table (rules stored as a JSON array)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS bucket_lifecycle (
+            bucket_id TEXT PRIMARY KEY NOT NULL,
+            rules TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (bucket_id) REFERENCES buckets (id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create bucket_cors table (rules stored as a JSON array)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS bucket_cors (
+            bucket_id TEXT PRIMARY KEY NOT NULL,
+            rules TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (bucket_id) REFERENCES buckets (id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create bucket_policies table (the policy document as submitted)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS bucket_policies (
+            bucket_id TEXT PRIMARY KEY NOT NULL,
+            policy TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (bucket_id) REFERENCES buckets (id) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
     // Create multipart_parts table
     sqlx::query(
         r#"
@@ -103,6 +226,103 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<()> {
     .execute(pool)
     .await?;
 
+    // Optional per-key usage quotas
+    add_column_if_missing(pool, "access_keys", "max_requests_per_day", "INTEGER").await?;
+    add_column_if_missing(pool, "access_keys", "max_bytes_in_per_month", "INTEGER").await?;
+    add_column_if_missing(pool, "access_keys", "max_bytes_out_per_month", "INTEGER").await?;
+
+    // Algorithm used to compute each object's ETag
+    add_column_if_missing(pool, "objects", "etag_algorithm", "TEXT NOT NULL DEFAULT 'md5'").await?;
+
+    // Content-Encoding of the stored bytes, and whether a bucket inflates encoded uploads
+    add_column_if_missing(pool, "objects", "content_encoding", "TEXT").await?;
+    add_column_if_missing(pool, "object_versions", "content_encoding", "TEXT").await?;
+    add_column_if_missing(pool, "buckets", "decompress_on_upload", "BOOLEAN NOT NULL DEFAULT FALSE").await?;
+
+    // Access key that created a bucket through the S3 API
+    add_column_if_missing(pool, "buckets", "owner_access_key_id", "TEXT").await?;
+
+    // Set when a read finds the object's file missing; reported by fsck
+    add_column_if_missing(pool, "objects", "needs_repair", "BOOLEAN NOT NULL DEFAULT FALSE").await?;
+
+    // Optional per-bucket storage quota
+    add_column_if_missing(pool, "buckets", "quota_bytes", "INTEGER").await?;
+
+    // Bucket ACL, as JSON: its owner and grants
+    add_column_if_missing(pool, "buckets", "acl", "TEXT").await?;
+
+    // Anonymous access to a bucket's objects and to its listing, set apart
+    // from the ACL
+    add_column_if_missing(pool, "buckets", "public_read", "BOOLEAN NOT NULL DEFAULT FALSE").await?;
+    add_column_if_missing(pool, "buckets", "public_list", "BOOLEAN NOT NULL DEFAULT FALSE").await?;
+
+    // Additional checksum (x-amz-checksum-*) an object was uploaded with
+    add_column_if_missing(pool, "objects", "checksum_algorithm", "TEXT").await?;
+    add_column_if_missing(pool, "objects", "checksum_value", "TEXT").await?;
+
+    // Content type and user metadata given when a multipart upload starts,
+    // applied to the object it completes into
+    add_column_if_missing(pool, "multipart_uploads", "content_type", "TEXT").await?;
+    add_column_if_missing(pool, "multipart_uploads", "metadata", "TEXT").await?;
+
+    // Cache-Control, Content-Disposition, Content-Language and Expires given on
+    // upload, returned on reads
+    add_column_if_missing(pool, "objects", "system_metadata", "TEXT").await?;
+    add_column_if_missing(pool, "multipart_uploads", "system_metadata", "TEXT").await?;
+
+    // What a version was written with, so old versions read back whole;
+    // versions recorded before these were kept have none
+    add_column_if_missing(pool, "object_versions", "etag_algorithm", "TEXT NOT NULL DEFAULT 'md5'").await?;
+    add_column_if_missing(pool, "object_versions", "metadata", "TEXT").await?;
+    add_column_if_missing(pool, "object_versions", "checksum_algorithm", "TEXT").await?;
+    add_column_if_missing(pool, "object_versions", "checksum_value", "TEXT").await?;
+    add_column_if_missing(pool, "object_versions", "system_metadata", "TEXT").await?;
+
+    // When an object uploaded with a TTL expires; NULL keeps it until deleted
+    add_column_if_missing(pool, "objects", "expires_at", "TEXT").await?;
+
+    // Binary digest of each part, completed into the multipart ETag
+    add_column_if_missing(pool, "multipart_parts", "digest", "BLOB").await?;
+
+    // Create key_usage table (one row per key and accounting period)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS key_usage (
+            access_key_id TEXT NOT NULL,
+            period TEXT NOT NULL,
+            period_start TEXT NOT NULL,
+            requests INTEGER NOT NULL DEFAULT 0,
+            bytes_in INTEGER NOT NULL DEFAULT 0,
+            bytes_out INTEGER NOT NULL DEFAULT 0,
+            updated_at TEXT NOT NULL,
+            PRIMARY KEY (access_key_id, period, period_start)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create audit_log table (append-only; pruned by age)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            request_id TEXT NOT NULL,
+            occurred_at TEXT NOT NULL,
+            access_key_id TEXT,
+            action TEXT NOT NULL,
+            bucket TEXT,
+            key TEXT,
+            status INTEGER NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // x-amz-id-2 of the request, naming the gateway instance that served it
+    add_column_if_missing(pool, "audit_log", "host_id", "TEXT").await?;
+
     // Create useful indexes
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_objects_bucket_key ON objects (bucket_id, key)")
         .execute(pool)
@@ -112,10 +332,41 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<()> {
         .execute(pool)
         .await?;
     
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_objects_expires_at ON objects (expires_at) WHERE expires_at IS NOT NULL")
+        .execute(pool)
+        .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_object_versions_bucket_key_created ON object_versions (bucket_id, key, created_at)")
+        .execute(pool)
+        .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_audit_log_bucket_time ON audit_log (bucket, occurred_at)")
+        .execute(pool)
+        .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_audit_log_time ON audit_log (occurred_at)")
+        .execute(pool)
+        .await?;
+
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_access_keys_active ON access_keys (access_key_id, is_active)")
         .execute(pool)
         .await?;
 
     tracing::info!("Database migrations completed successfully");
     Ok(())
-}
\ No newline at end of file
+}
+
+// SQLite has no ADD COLUMN IF NOT EXISTS, so check the table schema first
+async fn add_column_if_missing(pool: &SqlitePool, table: &str, column: &str, definition: &str) -> Result<()> {
+    let columns = sqlx::query(&format!("PRAGMA table_info({})", table))
+        .fetch_all(pool)
+        .await?;
+
+    if !columns.iter().any(|row| row.get::<String, _>("name") == column) {
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(())
+}
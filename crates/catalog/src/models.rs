@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,6 +11,26 @@ pub struct Bucket {
     pub updated_at: DateTime<Utc>,
     pub versioning_enabled: bool,
     pub region: String,
+    // Inflate gzip or zstd encoded uploads before storing them
+    pub decompress_on_upload: bool,
+    // None for buckets created by the CLI or provisioning
+    pub owner_access_key_id: Option<String>,
+    // Most bytes the bucket may hold; None for no limit
+    pub quota_bytes: Option<i64>,
+    // JSON-encoded ACL set by PutBucketAcl; None is private to the owner
+    pub acl: Option<String>,
+    // Anyone may GET and HEAD its objects, for serving static assets
+    pub public_read: bool,
+    // Anyone may list it
+    pub public_list: bool,
+}
+
+// Current objects of a bucket; old versions and multipart uploads in
+// progress are not counted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BucketStats {
+    pub object_count: u64,
+    pub total_bytes: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,12 +40,46 @@ pub struct Object {
     pub key: String,
     pub version_id: Option<Uuid>,
     pub etag: String,
+    pub etag_algorithm: String,
     pub size: i64,
     pub content_type: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub storage_path: String,
     pub metadata: Option<String>, // JSON serialized metadata
+    // Content-Encoding the stored bytes are in, returned on reads
+    pub content_encoding: Option<String>,
+    // x-amz-checksum-* the upload was verified against: CRC32, CRC32C, SHA1
+    // or SHA256, and the base64 digest
+    pub checksum_algorithm: Option<String>,
+    pub checksum_value: Option<String>,
+    // JSON object of the standard headers stored with the object, by
+    // lowercase header name
+    pub system_metadata: Option<String>,
+    // Set by a TTL given on upload; expired objects read as missing until
+    // the expiry sweep deletes them
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectVersion {
+    pub id: Uuid,
+    pub bucket_id: Uuid,
+    pub key: String,
+    pub version_id: Uuid,
+    pub etag: String,
+    pub size: i64,
+    pub content_type: String,
+    pub storage_path: String,
+    pub content_encoding: Option<String>,
+    pub is_delete_marker: bool,
+    pub created_at: DateTime<Utc>,
+    // Kept with each version so an old one reads back as it was written
+    pub etag_algorithm: String,
+    pub metadata: Option<String>,
+    pub checksum_algorithm: Option<String>,
+    pub checksum_value: Option<String>,
+    pub system_metadata: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +90,17 @@ pub struct MultipartUpload {
     pub upload_id: String,
     pub created_at: DateTime<Utc>,
     pub expires_at: Option<DateTime<Utc>>,
+    // None for uploads started before these were recorded
+    pub content_type: Option<String>,
+    pub metadata: Option<String>, // JSON serialized metadata
+    pub system_metadata: Option<String>,
+}
+
+// A part of an object completed from a multipart upload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectPart {
+    pub part_number: i32,
+    pub size: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,15 +109,98 @@ pub struct MultipartPart {
     pub upload_id: Uuid,
     pub part_number: i32,
     pub etag: String,
+    // The binary digest behind the hex ETag, which the multipart ETag is
+    // computed over; None for UUID ETags and parts recorded before it was
+    pub digest: Option<Vec<u8>>,
     pub size: i64,
     pub created_at: DateTime<Utc>,
     pub storage_path: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LifecycleRule {
+    #[serde(rename = "ID")]
+    pub id: String,
+    #[serde(default)]
+    pub prefix: Option<String>,
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+    // A rule expires objects, aborts stale multipart uploads, or both
+    #[serde(default)]
+    pub expiration_days: Option<i64>,
+    #[serde(default)]
+    pub abort_incomplete_upload_days: Option<i64>,
+    #[serde(default = "default_rule_enabled")]
+    pub enabled: bool,
+}
+
+fn default_rule_enabled() -> bool {
+    true
+}
+
+// One CORSRule of a bucket's CORS configuration. Field names follow the S3
+// XML, where each list entry is its own element.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BucketCorsConfig {
+    #[serde(rename = "ID", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "AllowedOrigin", default)]
+    pub allowed_origins: Vec<String>,
+    #[serde(rename = "AllowedMethod", default)]
+    pub allowed_methods: Vec<String>,
+    #[serde(rename = "AllowedHeader", default)]
+    pub allowed_headers: Vec<String>,
+    #[serde(rename = "ExposeHeader", default)]
+    pub expose_headers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_seconds: Option<u32>,
+}
+
+// One S3 or admin request as recorded in the audit log. The id is
+// monotonically increasing and doubles as the pagination cursor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub request_id: String,
+    pub host_id: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+    pub access_key_id: Option<String>,
+    pub action: String,
+    pub bucket: Option<String>,
+    pub key: Option<String>,
+    pub status: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewAuditEntry {
+    pub request_id: String,
+    pub host_id: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+    pub access_key_id: Option<String>,
+    pub action: String,
+    pub bucket: Option<String>,
+    pub key: Option<String>,
+    pub status: u16,
+}
+
+// Every field narrows the search; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditFilter {
+    pub bucket: Option<String>,
+    pub key_prefix: Option<String>,
+    pub access_key_id: Option<String>,
+    pub action: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateBucketRequest {
     pub name: String,
     pub region: String,
+    pub owner_access_key_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,4 +211,10 @@ pub struct CreateObjectRequest {
     pub size: i64,
     pub storage_path: String,
     pub metadata: Option<serde_json::Value>,
+    pub etag_algorithm: String,
+    pub content_encoding: Option<String>,
+    pub checksum_algorithm: Option<String>,
+    pub checksum_value: Option<String>,
+    pub system_metadata: Option<serde_json::Value>,
+    pub expires_at: Option<DateTime<Utc>>,
 }
\ No newline at end of file
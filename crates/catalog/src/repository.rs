@@ -1,9 +1,15 @@
-use anyhow::Result;
-use chrono::Utc;
-use sqlx::{Row, SqlitePool};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{Row, SqliteConnection, SqlitePool};
+use std::collections::HashMap;
+use std::time::Instant;
 use uuid::Uuid;
 
 use crate::models::*;
+use crate::telemetry::record_query;
+
+// Incomplete multipart uploads are abandoned after this many days
+pub const MULTIPART_UPLOAD_EXPIRY_DAYS: i64 = 7;
 
 pub struct BucketRepository {
     pool: SqlitePool,
@@ -14,14 +20,16 @@ impl BucketRepository {
         Self { pool }
     }
 
+    #[tracing::instrument(skip(self, req), fields(bucket = %req.name, db.operation = "INSERT", db.rows = tracing::field::Empty))]
     pub async fn create(&self, req: CreateBucketRequest) -> Result<Bucket> {
+        let started = Instant::now();
         let id = Uuid::new_v4();
         let now = Utc::now();
 
         sqlx::query(
             r#"
-            INSERT INTO buckets (id, name, created_at, updated_at, versioning_enabled, region)
-            VALUES (?, ?, ?, ?, ?, ?)
+            INSERT INTO buckets (id, name, created_at, updated_at, versioning_enabled, region, owner_access_key_id)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(id.to_string())
@@ -30,8 +38,11 @@ impl BucketRepository {
         .bind(now.to_rfc3339())
         .bind(false)
         .bind(&req.region)
+        .bind(&req.owner_access_key_id)
         .execute(&self.pool)
-        .await?;
+        .await
+        .context("BucketRepository::create")?;
+        record_query(started, 1);
 
         let bucket = Bucket {
             id,
@@ -40,18 +51,28 @@ impl BucketRepository {
             updated_at: now,
             versioning_enabled: false,
             region: req.region,
+            decompress_on_upload: false,
+            owner_access_key_id: req.owner_access_key_id,
+            quota_bytes: None,
+            acl: None,
+            public_read: false,
+            public_list: false,
         };
 
         Ok(bucket)
     }
 
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
     pub async fn find_by_name(&self, name: &str) -> Result<Option<Bucket>> {
+        let started = Instant::now();
         let row = sqlx::query(
-            "SELECT id, name, created_at, updated_at, versioning_enabled, region FROM buckets WHERE name = ?"
+            "SELECT id, name, created_at, updated_at, versioning_enabled, region, decompress_on_upload, owner_access_key_id, quota_bytes, acl, public_read, public_list FROM buckets WHERE name = ?"
         )
         .bind(name)
         .fetch_optional(&self.pool)
-        .await?;
+        .await
+        .context("BucketRepository::find_by_name")?;
+        record_query(started, row.is_some() as u64);
 
         if let Some(row) = row {
             let bucket = Bucket {
@@ -61,6 +82,12 @@ impl BucketRepository {
                 updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at"))?.with_timezone(&Utc),
                 versioning_enabled: row.get("versioning_enabled"),
                 region: row.get("region"),
+                decompress_on_upload: row.get("decompress_on_upload"),
+                owner_access_key_id: row.get("owner_access_key_id"),
+                quota_bytes: row.get("quota_bytes"),
+                acl: row.get("acl"),
+                public_read: row.get("public_read"),
+                public_list: row.get("public_list"),
             };
             Ok(Some(bucket))
         } else {
@@ -68,12 +95,16 @@ impl BucketRepository {
         }
     }
 
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
     pub async fn list(&self) -> Result<Vec<Bucket>> {
+        let started = Instant::now();
         let rows = sqlx::query(
-            "SELECT id, name, created_at, updated_at, versioning_enabled, region FROM buckets ORDER BY created_at"
+            "SELECT id, name, created_at, updated_at, versioning_enabled, region, decompress_on_upload, owner_access_key_id, quota_bytes, acl, public_read, public_list FROM buckets ORDER BY created_at"
         )
         .fetch_all(&self.pool)
-        .await?;
+        .await
+        .context("BucketRepository::list")?;
+        record_query(started, rows.len() as u64);
 
         let mut buckets = Vec::new();
         for row in rows {
@@ -84,6 +115,12 @@ impl BucketRepository {
                 updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at"))?.with_timezone(&Utc),
                 versioning_enabled: row.get("versioning_enabled"),
                 region: row.get("region"),
+                decompress_on_upload: row.get("decompress_on_upload"),
+                owner_access_key_id: row.get("owner_access_key_id"),
+                quota_bytes: row.get("quota_bytes"),
+                acl: row.get("acl"),
+                public_read: row.get("public_read"),
+                public_list: row.get("public_list"),
             };
             buckets.push(bucket);
         }
@@ -91,11 +128,125 @@ impl BucketRepository {
         Ok(buckets)
     }
 
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn count(&self) -> Result<u64> {
+        let started = Instant::now();
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM buckets")
+            .fetch_one(&self.pool)
+            .await
+            .context("BucketRepository::count")?;
+        record_query(started, 1);
+
+        Ok(count as u64)
+    }
+
+    // Zero for a bucket that does not exist
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn stats(&self, name: &str) -> Result<BucketStats> {
+        let started = Instant::now();
+        let row = sqlx::query(
+            r#"
+            SELECT COUNT(*) AS objects, COALESCE(SUM(size), 0) AS bytes FROM objects
+            WHERE bucket_id = (SELECT id FROM buckets WHERE name = ?)
+            "#,
+        )
+        .bind(name)
+        .fetch_one(&self.pool)
+        .await
+        .context("BucketRepository::stats")?;
+        record_query(started, 1);
+
+        Ok(BucketStats {
+            object_count: row.get::<i64, _>("objects") as u64,
+            total_bytes: row.get("bytes"),
+        })
+    }
+
+    #[tracing::instrument(skip(self), fields(db.operation = "DELETE", db.rows = tracing::field::Empty))]
     pub async fn delete(&self, name: &str) -> Result<bool> {
+        let started = Instant::now();
         let result = sqlx::query("DELETE FROM buckets WHERE name = ?")
             .bind(name)
             .execute(&self.pool)
-            .await?;
+            .await
+            .context("BucketRepository::delete")?;
+        record_query(started, result.rows_affected());
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(skip(self), fields(db.operation = "UPDATE", db.rows = tracing::field::Empty))]
+    pub async fn set_decompress_on_upload(&self, name: &str, enabled: bool) -> Result<bool> {
+        let started = Instant::now();
+        let result = sqlx::query("UPDATE buckets SET decompress_on_upload = ?, updated_at = ? WHERE name = ?")
+            .bind(enabled)
+            .bind(Utc::now().to_rfc3339())
+            .bind(name)
+            .execute(&self.pool)
+            .await
+            .context("BucketRepository::set_decompress_on_upload")?;
+        record_query(started, result.rows_affected());
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(skip(self, acl), fields(db.operation = "UPDATE", db.rows = tracing::field::Empty))]
+    pub async fn set_acl(&self, name: &str, acl: &str) -> Result<bool> {
+        let started = Instant::now();
+        let result = sqlx::query("UPDATE buckets SET acl = ?, updated_at = ? WHERE name = ?")
+            .bind(acl)
+            .bind(Utc::now().to_rfc3339())
+            .bind(name)
+            .execute(&self.pool)
+            .await
+            .context("BucketRepository::set_acl")?;
+        record_query(started, result.rows_affected());
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(skip(self), fields(db.operation = "UPDATE", db.rows = tracing::field::Empty))]
+    pub async fn set_public_access(&self, name: &str, public_read: bool, public_list: bool) -> Result<bool> {
+        let started = Instant::now();
+        let result = sqlx::query("UPDATE buckets SET public_read = ?, public_list = ?, updated_at = ? WHERE name = ?")
+            .bind(public_read)
+            .bind(public_list)
+            .bind(Utc::now().to_rfc3339())
+            .bind(name)
+            .execute(&self.pool)
+            .await
+            .context("BucketRepository::set_public_access")?;
+        record_query(started, result.rows_affected());
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(skip(self), fields(db.operation = "UPDATE", db.rows = tracing::field::Empty))]
+    pub async fn set_versioning(&self, name: &str, enabled: bool) -> Result<bool> {
+        let started = Instant::now();
+        let result = sqlx::query("UPDATE buckets SET versioning_enabled = ?, updated_at = ? WHERE name = ?")
+            .bind(enabled)
+            .bind(Utc::now().to_rfc3339())
+            .bind(name)
+            .execute(&self.pool)
+            .await
+            .context("BucketRepository::set_versioning")?;
+        record_query(started, result.rows_affected());
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(skip(self), fields(db.operation = "UPDATE", db.rows = tracing::field::Empty))]
+    pub async fn set_quota(&self, name: &str, quota_bytes: Option<i64>) -> Result<bool> {
+        let started = Instant::now();
+        let result = sqlx::query("UPDATE buckets SET quota_bytes = ?, updated_at = ? WHERE name = ?")
+            .bind(quota_bytes)
+            .bind(Utc::now().to_rfc3339())
+            .bind(name)
+            .execute(&self.pool)
+            .await
+            .context("BucketRepository::set_quota")?;
+        record_query(started, result.rows_affected());
 
         Ok(result.rows_affected() > 0)
     }
@@ -110,51 +261,112 @@ impl ObjectRepository {
         Self { pool }
     }
 
+    #[tracing::instrument(skip(self, req, etag), fields(bucket_id = %req.bucket_id, key = %req.key, db.operation = "INSERT", db.rows = tracing::field::Empty))]
     pub async fn create(&self, req: CreateObjectRequest, etag: String) -> Result<Object> {
+        self.insert(req, etag, false).await.context("ObjectRepository::create")
+    }
+
+    // Makes a new version the current object of its key, in place of any
+    // current row. The replaced version stays in object_versions.
+    #[tracing::instrument(skip(self, req, etag), fields(bucket_id = %req.bucket_id, key = %req.key, db.operation = "INSERT", db.rows = tracing::field::Empty))]
+    pub async fn replace(&self, req: CreateObjectRequest, etag: String) -> Result<Object> {
+        self.insert(req, etag, true).await.context("ObjectRepository::replace")
+    }
+
+    async fn insert(&self, req: CreateObjectRequest, etag: String, replace: bool) -> Result<Object> {
+        let started = Instant::now();
         let id = Uuid::new_v4();
         let now = Utc::now();
         let metadata_json = req.metadata.map(|m| serde_json::to_string(&m)).transpose()?;
+        let system_metadata_json = req.system_metadata.map(|m| serde_json::to_string(&m)).transpose()?;
+
+        let mut tx = self.pool.begin().await?;
+
+        if replace {
+            sqlx::query("DELETE FROM objects WHERE bucket_id = ? AND key = ?")
+                .bind(req.bucket_id.to_string())
+                .bind(&req.key)
+                .execute(&mut *tx)
+                .await?;
+        }
 
         sqlx::query(
             r#"
-            INSERT INTO objects (id, bucket_id, key, etag, size, content_type, created_at, updated_at, storage_path, metadata)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO objects (id, bucket_id, key, etag, etag_algorithm, size, content_type, created_at, updated_at, storage_path, metadata, content_encoding, checksum_algorithm, checksum_value, system_metadata, expires_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(id.to_string())
         .bind(req.bucket_id.to_string())
         .bind(&req.key)
         .bind(&etag)
+        .bind(&req.etag_algorithm)
         .bind(req.size)
         .bind(&req.content_type)
         .bind(now.to_rfc3339())
         .bind(now.to_rfc3339())
         .bind(&req.storage_path)
         .bind(&metadata_json)
-        .execute(&self.pool)
+        .bind(&req.content_encoding)
+        .bind(&req.checksum_algorithm)
+        .bind(&req.checksum_value)
+        .bind(&system_metadata_json)
+        .bind(req.expires_at.map(|expires_at| expires_at.to_rfc3339()))
+        .execute(&mut *tx)
         .await?;
 
+        let version = ObjectVersion {
+            id: Uuid::new_v4(),
+            bucket_id: req.bucket_id,
+            key: req.key.clone(),
+            version_id: id,
+            etag: etag.clone(),
+            size: req.size,
+            content_type: req.content_type.clone(),
+            storage_path: req.storage_path.clone(),
+            content_encoding: req.content_encoding.clone(),
+            is_delete_marker: false,
+            created_at: now,
+            etag_algorithm: req.etag_algorithm.clone(),
+            metadata: metadata_json.clone(),
+            checksum_algorithm: req.checksum_algorithm.clone(),
+            checksum_value: req.checksum_value.clone(),
+            system_metadata: system_metadata_json.clone(),
+        };
+        insert_version(&mut tx, &version).await?;
+
+        tx.commit().await?;
+        record_query(started, 1);
+
         let object = Object {
             id,
             bucket_id: req.bucket_id,
             key: req.key,
             version_id: None,
             etag,
+            etag_algorithm: req.etag_algorithm,
             size: req.size,
             content_type: req.content_type,
             created_at: now,
             updated_at: now,
             storage_path: req.storage_path,
             metadata: metadata_json,
+            content_encoding: req.content_encoding,
+            checksum_algorithm: req.checksum_algorithm,
+            checksum_value: req.checksum_value,
+            system_metadata: system_metadata_json,
+            expires_at: req.expires_at,
         };
 
         Ok(object)
     }
 
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
     pub async fn find_by_bucket_and_key(&self, bucket_id: Uuid, key: &str) -> Result<Option<Object>> {
+        let started = Instant::now();
         let row = sqlx::query(
             r#"
-            SELECT id, bucket_id, key, version_id, etag, size, content_type, created_at, updated_at, storage_path, metadata
+            SELECT id, bucket_id, key, version_id, etag, etag_algorithm, size, content_type, created_at, updated_at, storage_path, metadata, content_encoding, checksum_algorithm, checksum_value, system_metadata, expires_at
             FROM objects 
             WHERE bucket_id = ? AND key = ?
             "#,
@@ -162,7 +374,9 @@ impl ObjectRepository {
         .bind(bucket_id.to_string())
         .bind(key)
         .fetch_optional(&self.pool)
-        .await?;
+        .await
+        .context("ObjectRepository::find_by_bucket_and_key")?;
+        record_query(started, row.is_some() as u64);
 
         if let Some(row) = row {
             let object = Object {
@@ -171,12 +385,20 @@ impl ObjectRepository {
                 key: row.get("key"),
                 version_id: row.get::<Option<String>, _>("version_id").map(|v| Uuid::parse_str(&v)).transpose()?,
                 etag: row.get("etag"),
+                etag_algorithm: row.get("etag_algorithm"),
                 size: row.get("size"),
                 content_type: row.get("content_type"),
                 created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
                 updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at"))?.with_timezone(&Utc),
                 storage_path: row.get("storage_path"),
                 metadata: row.get("metadata"),
+                content_encoding: row.get("content_encoding"),
+                checksum_algorithm: row.get("checksum_algorithm"),
+                checksum_value: row.get("checksum_value"),
+                system_metadata: row.get("system_metadata"),
+                expires_at: row.get::<Option<String>, _>("expires_at")
+                    .map(|s| chrono::DateTime::parse_from_rfc3339(&s).map(|dt| dt.with_timezone(&Utc)))
+                    .transpose()?,
             };
             Ok(Some(object))
         } else {
@@ -184,41 +406,62 @@ impl ObjectRepository {
         }
     }
 
-    pub async fn list_by_bucket(&self, bucket_id: Uuid, prefix: Option<&str>, limit: Option<i32>) -> Result<Vec<Object>> {
-        let limit = limit.unwrap_or(1000).min(1000);
+    // One page of a listing in key order, starting after `start_after`.
+    // Returns up to `limit + 1` rows so callers can tell whether the listing
+    // continues past this page without a second query. Expired objects are
+    // left out.
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn list_by_bucket(
+        &self,
+        bucket_id: Uuid,
+        prefix: Option<&str>,
+        start_after: Option<&str>,
+        limit: i32,
+    ) -> Result<Vec<Object>> {
+        let started = Instant::now();
         let bucket_id_str = bucket_id.to_string();
+        let start_after = start_after.unwrap_or("");
+        let now = Utc::now().to_rfc3339();
         
         let rows = if let Some(prefix) = prefix {
-            let like_pattern = format!("{}%", prefix);
+            // An exact, case-sensitive prefix match; LIKE would treat _ and % as wildcards
             sqlx::query(
                 r#"
-                SELECT id, bucket_id, key, version_id, etag, size, content_type, created_at, updated_at, storage_path, metadata
+                SELECT id, bucket_id, key, version_id, etag, etag_algorithm, size, content_type, created_at, updated_at, storage_path, metadata, content_encoding, checksum_algorithm, checksum_value, system_metadata, expires_at
                 FROM objects 
-                WHERE bucket_id = ? AND key LIKE ?
+                WHERE bucket_id = ? AND substr(key, 1, ?) = ? AND key > ? AND (expires_at IS NULL OR expires_at > ?)
                 ORDER BY key
                 LIMIT ?
                 "#,
             )
             .bind(&bucket_id_str)
-            .bind(&like_pattern)
-            .bind(limit)
+            .bind(prefix.chars().count() as i64)
+            .bind(prefix)
+            .bind(start_after)
+            .bind(&now)
+            .bind(limit + 1)
             .fetch_all(&self.pool)
-            .await?
+            .await
+            .context("ObjectRepository::list_by_bucket")?
         } else {
             sqlx::query(
                 r#"
-                SELECT id, bucket_id, key, version_id, etag, size, content_type, created_at, updated_at, storage_path, metadata
+                SELECT id, bucket_id, key, version_id, etag, etag_algorithm, size, content_type, created_at, updated_at, storage_path, metadata, content_encoding, checksum_algorithm, checksum_value, system_metadata, expires_at
                 FROM objects 
-                WHERE bucket_id = ?
+                WHERE bucket_id = ? AND key > ? AND (expires_at IS NULL OR expires_at > ?)
                 ORDER BY key
                 LIMIT ?
                 "#,
             )
             .bind(&bucket_id_str)
-            .bind(limit)
+            .bind(start_after)
+            .bind(&now)
+            .bind(limit + 1)
             .fetch_all(&self.pool)
-            .await?
+            .await
+            .context("ObjectRepository::list_by_bucket")?
         };
+        record_query(started, rows.len() as u64);
 
         let mut objects = Vec::new();
         for row in rows {
@@ -228,12 +471,20 @@ impl ObjectRepository {
                 key: row.get("key"),
                 version_id: row.get::<Option<String>, _>("version_id").map(|v| Uuid::parse_str(&v)).transpose()?,
                 etag: row.get("etag"),
+                etag_algorithm: row.get("etag_algorithm"),
                 size: row.get("size"),
                 content_type: row.get("content_type"),
                 created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
                 updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at"))?.with_timezone(&Utc),
                 storage_path: row.get("storage_path"),
                 metadata: row.get("metadata"),
+                content_encoding: row.get("content_encoding"),
+                checksum_algorithm: row.get("checksum_algorithm"),
+                checksum_value: row.get("checksum_value"),
+                system_metadata: row.get("system_metadata"),
+                expires_at: row.get::<Option<String>, _>("expires_at")
+                    .map(|s| chrono::DateTime::parse_from_rfc3339(&s).map(|dt| dt.with_timezone(&Utc)))
+                    .transpose()?,
             };
             objects.push(object);
         }
@@ -241,12 +492,469 @@ impl ObjectRepository {
         Ok(objects)
     }
 
+    // Keyset pagination over a whole bucket, for background scans
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn list_page(&self, bucket_id: Uuid, start_after: Option<&str>, limit: i32) -> Result<Vec<Object>> {
+        let started = Instant::now();
+        let rows = sqlx::query(
+            r#"
+            SELECT id, bucket_id, key, version_id, etag, etag_algorithm, size, content_type, created_at, updated_at, storage_path, metadata, content_encoding, checksum_algorithm, checksum_value, system_metadata, expires_at
+            FROM objects 
+            WHERE bucket_id = ? AND key > ?
+            ORDER BY key
+            LIMIT ?
+            "#,
+        )
+        .bind(bucket_id.to_string())
+        .bind(start_after.unwrap_or(""))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .context("ObjectRepository::list_page")?;
+        record_query(started, rows.len() as u64);
+
+        let mut objects = Vec::new();
+        for row in rows {
+            let object = Object {
+                id: Uuid::parse_str(&row.get::<String, _>("id"))?,
+                bucket_id: Uuid::parse_str(&row.get::<String, _>("bucket_id"))?,
+                key: row.get("key"),
+                version_id: row.get::<Option<String>, _>("version_id").map(|v| Uuid::parse_str(&v)).transpose()?,
+                etag: row.get("etag"),
+                etag_algorithm: row.get("etag_algorithm"),
+                size: row.get("size"),
+                content_type: row.get("content_type"),
+                created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
+                updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at"))?.with_timezone(&Utc),
+                storage_path: row.get("storage_path"),
+                metadata: row.get("metadata"),
+                content_encoding: row.get("content_encoding"),
+                checksum_algorithm: row.get("checksum_algorithm"),
+                checksum_value: row.get("checksum_value"),
+                system_metadata: row.get("system_metadata"),
+                expires_at: row.get::<Option<String>, _>("expires_at")
+                    .map(|s| chrono::DateTime::parse_from_rfc3339(&s).map(|dt| dt.with_timezone(&Utc)))
+                    .transpose()?,
+            };
+            objects.push(object);
+        }
+
+        Ok(objects)
+    }
+
+    // Object count and total bytes across all buckets
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn totals(&self) -> Result<(u64, u64)> {
+        let started = Instant::now();
+        let row = sqlx::query("SELECT COUNT(*) AS objects, COALESCE(SUM(size), 0) AS bytes FROM objects")
+            .fetch_one(&self.pool)
+            .await
+            .context("ObjectRepository::totals")?;
+        record_query(started, 1);
+
+        Ok((row.get::<i64, _>("objects") as u64, row.get::<i64, _>("bytes") as u64))
+    }
+
+    // Bytes a bucket holds against its quota: its current objects plus the
+    // parts of multipart uploads in progress
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn bucket_usage(&self, bucket_id: Uuid) -> Result<u64> {
+        let started = Instant::now();
+        let bytes: i64 = sqlx::query_scalar(
+            r#"
+            SELECT (SELECT COALESCE(SUM(size), 0) FROM objects WHERE bucket_id = ?)
+                + (SELECT COALESCE(SUM(p.size), 0) FROM multipart_parts p
+                   JOIN multipart_uploads u ON u.id = p.upload_id
+                   WHERE u.bucket_id = ?)
+            "#,
+        )
+        .bind(bucket_id.to_string())
+        .bind(bucket_id.to_string())
+        .fetch_one(&self.pool)
+        .await
+        .context("ObjectRepository::bucket_usage")?;
+        record_query(started, 1);
+
+        Ok(bytes as u64)
+    }
+
+    // Object count and total bytes of every bucket, empty buckets included
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn totals_by_bucket(&self) -> Result<Vec<(String, u64, u64)>> {
+        let started = Instant::now();
+        let rows = sqlx::query(
+            r#"
+            SELECT b.name AS bucket, COUNT(o.id) AS objects, COALESCE(SUM(o.size), 0) AS bytes
+            FROM buckets b
+            LEFT JOIN objects o ON o.bucket_id = b.id
+            GROUP BY b.id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("ObjectRepository::totals_by_bucket")?;
+        record_query(started, rows.len() as u64);
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.get("bucket"), row.get::<i64, _>("objects") as u64, row.get::<i64, _>("bytes") as u64))
+            .collect())
+    }
+
+    // Flags a row whose backing file has gone missing, for fsck to report
+    #[tracing::instrument(skip(self), fields(db.operation = "UPDATE", db.rows = tracing::field::Empty))]
+    pub async fn mark_needs_repair(&self, id: Uuid) -> Result<bool> {
+        let started = Instant::now();
+        let result = sqlx::query("UPDATE objects SET needs_repair = TRUE WHERE id = ?")
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await
+            .context("ObjectRepository::mark_needs_repair")?;
+        record_query(started, result.rows_affected());
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn list_needing_repair(&self) -> Result<Vec<Object>> {
+        let started = Instant::now();
+        let rows = sqlx::query(
+            r#"
+            SELECT id, bucket_id, key, version_id, etag, etag_algorithm, size, content_type, created_at, updated_at, storage_path, metadata, content_encoding, checksum_algorithm, checksum_value, system_metadata, expires_at
+            FROM objects 
+            WHERE needs_repair = TRUE
+            ORDER BY bucket_id, key
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("ObjectRepository::list_needing_repair")?;
+        record_query(started, rows.len() as u64);
+
+        let mut objects = Vec::new();
+        for row in rows {
+            let object = Object {
+                id: Uuid::parse_str(&row.get::<String, _>("id"))?,
+                bucket_id: Uuid::parse_str(&row.get::<String, _>("bucket_id"))?,
+                key: row.get("key"),
+                version_id: row.get::<Option<String>, _>("version_id").map(|v| Uuid::parse_str(&v)).transpose()?,
+                etag: row.get("etag"),
+                etag_algorithm: row.get("etag_algorithm"),
+                size: row.get("size"),
+                content_type: row.get("content_type"),
+                created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
+                updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at"))?.with_timezone(&Utc),
+                storage_path: row.get("storage_path"),
+                metadata: row.get("metadata"),
+                content_encoding: row.get("content_encoding"),
+                checksum_algorithm: row.get("checksum_algorithm"),
+                checksum_value: row.get("checksum_value"),
+                system_metadata: row.get("system_metadata"),
+                expires_at: row.get::<Option<String>, _>("expires_at")
+                    .map(|s| chrono::DateTime::parse_from_rfc3339(&s).map(|dt| dt.with_timezone(&Utc)))
+                    .transpose()?,
+            };
+            objects.push(object);
+        }
+
+        Ok(objects)
+    }
+
+    #[tracing::instrument(skip(self), fields(db.operation = "DELETE", db.rows = tracing::field::Empty))]
     pub async fn delete(&self, bucket_id: Uuid, key: &str) -> Result<bool> {
+        let started = Instant::now();
+        let mut tx = self.pool.begin().await.context("ObjectRepository::delete")?;
+
         let result = sqlx::query("DELETE FROM objects WHERE bucket_id = ? AND key = ?")
             .bind(bucket_id.to_string())
             .bind(key)
+            .execute(&mut *tx)
+            .await
+            .context("ObjectRepository::delete")?;
+
+        let deleted = result.rows_affected() > 0;
+        if deleted {
+            let id = Uuid::new_v4();
+            let marker = ObjectVersion {
+                id,
+                bucket_id,
+                key: key.to_string(),
+                version_id: id,
+                etag: String::new(),
+                size: 0,
+                content_type: String::new(),
+                storage_path: String::new(),
+                content_encoding: None,
+                is_delete_marker: true,
+                created_at: Utc::now(),
+                etag_algorithm: String::new(),
+                metadata: None,
+                checksum_algorithm: None,
+                checksum_value: None,
+                system_metadata: None,
+            };
+            insert_version(&mut tx, &marker).await?;
+        }
+
+        tx.commit().await.context("ObjectRepository::delete")?;
+        record_query(started, result.rows_affected());
+        Ok(deleted)
+    }
+
+    // Deletes up to `limit` objects whose TTL has passed, with their tags,
+    // leaving a delete marker in each key's history as a DELETE would. Returns
+    // the deleted rows so the caller can remove their data.
+    #[tracing::instrument(skip(self), fields(db.operation = "DELETE", db.rows = tracing::field::Empty))]
+    pub async fn delete_expired(&self, now: DateTime<Utc>, limit: i32) -> Result<Vec<Object>> {
+        let started = Instant::now();
+        let mut tx = self.pool.begin().await.context("ObjectRepository::delete_expired")?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT id, bucket_id, key, version_id, etag, etag_algorithm, size, content_type, created_at, updated_at, storage_path, metadata, content_encoding, checksum_algorithm, checksum_value, system_metadata, expires_at
+            FROM objects
+            WHERE expires_at IS NOT NULL AND expires_at <= ?
+            ORDER BY expires_at
+            LIMIT ?
+            "#,
+        )
+        .bind(now.to_rfc3339())
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await
+        .context("ObjectRepository::delete_expired")?;
+
+        let mut objects = Vec::new();
+        for row in rows {
+            let object = Object {
+                id: Uuid::parse_str(&row.get::<String, _>("id"))?,
+                bucket_id: Uuid::parse_str(&row.get::<String, _>("bucket_id"))?,
+                key: row.get("key"),
+                version_id: row.get::<Option<String>, _>("version_id").map(|v| Uuid::parse_str(&v)).transpose()?,
+                etag: row.get("etag"),
+                etag_algorithm: row.get("etag_algorithm"),
+                size: row.get("size"),
+                content_type: row.get("content_type"),
+                created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
+                updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at"))?.with_timezone(&Utc),
+                storage_path: row.get("storage_path"),
+                metadata: row.get("metadata"),
+                content_encoding: row.get("content_encoding"),
+                checksum_algorithm: row.get("checksum_algorithm"),
+                checksum_value: row.get("checksum_value"),
+                system_metadata: row.get("system_metadata"),
+                expires_at: row.get::<Option<String>, _>("expires_at")
+                    .map(|s| chrono::DateTime::parse_from_rfc3339(&s).map(|dt| dt.with_timezone(&Utc)))
+                    .transpose()?,
+            };
+
+            sqlx::query("DELETE FROM objects WHERE id = ?")
+                .bind(object.id.to_string())
+                .execute(&mut *tx)
+                .await
+                .context("ObjectRepository::delete_expired")?;
+            sqlx::query("DELETE FROM object_tags WHERE bucket_id = ? AND key = ?")
+                .bind(object.bucket_id.to_string())
+                .bind(&object.key)
+                .execute(&mut *tx)
+                .await
+                .context("ObjectRepository::delete_expired")?;
+
+            let id = Uuid::new_v4();
+            let marker = ObjectVersion {
+                id,
+                bucket_id: object.bucket_id,
+                key: object.key.clone(),
+                version_id: id,
+                etag: String::new(),
+                size: 0,
+                content_type: String::new(),
+                storage_path: String::new(),
+                content_encoding: None,
+                is_delete_marker: true,
+                created_at: now,
+                etag_algorithm: String::new(),
+                metadata: None,
+                checksum_algorithm: None,
+                checksum_value: None,
+                system_metadata: None,
+            };
+            insert_version(&mut tx, &marker).await?;
+            objects.push(object);
+        }
+
+        tx.commit().await.context("ObjectRepository::delete_expired")?;
+        record_query(started, objects.len() as u64);
+        Ok(objects)
+    }
+}
+
+async fn insert_version(conn: &mut SqliteConnection, version: &ObjectVersion) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO object_versions (id, bucket_id, key, version_id, etag, size, content_type, storage_path, content_encoding, is_delete_marker, created_at, etag_algorithm, metadata, checksum_algorithm, checksum_value, system_metadata)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(version.id.to_string())
+    .bind(version.bucket_id.to_string())
+    .bind(&version.key)
+    .bind(version.version_id.to_string())
+    .bind(&version.etag)
+    .bind(version.size)
+    .bind(&version.content_type)
+    .bind(&version.storage_path)
+    .bind(&version.content_encoding)
+    .bind(version.is_delete_marker)
+    .bind(version.created_at.to_rfc3339())
+    .bind(&version.etag_algorithm)
+    .bind(&version.metadata)
+    .bind(&version.checksum_algorithm)
+    .bind(&version.checksum_value)
+    .bind(&version.system_metadata)
+    .execute(conn)
+    .await
+    .context("insert_version")?;
+
+    Ok(())
+}
+
+pub struct ObjectVersionRepository {
+    pool: SqlitePool,
+}
+
+impl ObjectVersionRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    // Resolves each key to its newest version created at or before `at`,
+    // skipping keys whose newest entry at that time is a delete marker.
+    // Timestamps are all stored as UTC RFC 3339, so they compare as text.
+    // The window walks idx_object_versions_bucket_key_created in order and the
+    // outer query adds no ORDER BY, so SQLite streams rows and stops at LIMIT
+    // instead of ranking the whole bucket first.
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn list_as_of(
+        &self,
+        bucket_id: Uuid,
+        at: DateTime<Utc>,
+        prefix: Option<&str>,
+        start_after: Option<&str>,
+        limit: i32,
+    ) -> Result<Vec<ObjectVersion>> {
+        let started = Instant::now();
+        let prefix = prefix.unwrap_or("");
+
+        let rows = sqlx::query(
+            r#"
+            SELECT id, bucket_id, key, version_id, etag, size, content_type, storage_path, content_encoding, is_delete_marker, created_at, etag_algorithm, metadata, checksum_algorithm, checksum_value, system_metadata
+            FROM (
+                SELECT *, rowid AS version_row,
+                    LAST_VALUE(rowid) OVER (
+                        PARTITION BY key ORDER BY created_at, rowid
+                        ROWS BETWEEN UNBOUNDED PRECEDING AND UNBOUNDED FOLLOWING
+                    ) AS latest_row
+                FROM object_versions
+                WHERE bucket_id = ? AND key > ? AND key >= ? AND substr(key, 1, length(?)) = ? AND created_at <= ?
+                ORDER BY key
+            )
+            WHERE version_row = latest_row AND NOT is_delete_marker
+            LIMIT ?
+            "#,
+        )
+        .bind(bucket_id.to_string())
+        .bind(start_after.unwrap_or(""))
+        .bind(prefix)
+        .bind(prefix)
+        .bind(prefix)
+        .bind(at.to_rfc3339())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .context("ObjectVersionRepository::list_as_of")?;
+        record_query(started, rows.len() as u64);
+
+        let mut versions = Vec::new();
+        for row in rows {
+            let version = ObjectVersion {
+                id: Uuid::parse_str(&row.get::<String, _>("id"))?,
+                bucket_id: Uuid::parse_str(&row.get::<String, _>("bucket_id"))?,
+                key: row.get("key"),
+                version_id: Uuid::parse_str(&row.get::<String, _>("version_id"))?,
+                etag: row.get("etag"),
+                size: row.get("size"),
+                content_type: row.get("content_type"),
+                storage_path: row.get("storage_path"),
+                content_encoding: row.get("content_encoding"),
+                is_delete_marker: row.get("is_delete_marker"),
+                created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
+                etag_algorithm: row.get("etag_algorithm"),
+                metadata: row.get("metadata"),
+                checksum_algorithm: row.get("checksum_algorithm"),
+                checksum_value: row.get("checksum_value"),
+                system_metadata: row.get("system_metadata"),
+            };
+            versions.push(version);
+        }
+
+        Ok(versions)
+    }
+
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn find(&self, bucket_id: Uuid, key: &str, version_id: Uuid) -> Result<Option<ObjectVersion>> {
+        let started = Instant::now();
+        let row = sqlx::query(
+            r#"
+            SELECT id, bucket_id, key, version_id, etag, size, content_type, storage_path, content_encoding, is_delete_marker, created_at, etag_algorithm, metadata, checksum_algorithm, checksum_value, system_metadata
+            FROM object_versions
+            WHERE bucket_id = ? AND key = ? AND version_id = ?
+            "#,
+        )
+        .bind(bucket_id.to_string())
+        .bind(key)
+        .bind(version_id.to_string())
+        .fetch_optional(&self.pool)
+        .await
+        .context("ObjectVersionRepository::find")?;
+        record_query(started, row.is_some() as u64);
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        Ok(Some(ObjectVersion {
+            id: Uuid::parse_str(&row.get::<String, _>("id"))?,
+            bucket_id: Uuid::parse_str(&row.get::<String, _>("bucket_id"))?,
+            key: row.get("key"),
+            version_id: Uuid::parse_str(&row.get::<String, _>("version_id"))?,
+            etag: row.get("etag"),
+            size: row.get("size"),
+            content_type: row.get("content_type"),
+            storage_path: row.get("storage_path"),
+            content_encoding: row.get("content_encoding"),
+            is_delete_marker: row.get("is_delete_marker"),
+            created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
+            etag_algorithm: row.get("etag_algorithm"),
+            metadata: row.get("metadata"),
+            checksum_algorithm: row.get("checksum_algorithm"),
+            checksum_value: row.get("checksum_value"),
+            system_metadata: row.get("system_metadata"),
+        }))
+    }
+
+    // Points a version at the copy of its data kept once it stops being current
+    #[tracing::instrument(skip(self), fields(db.operation = "UPDATE", db.rows = tracing::field::Empty))]
+    pub async fn set_storage_path(&self, bucket_id: Uuid, key: &str, version_id: Uuid, storage_path: &str) -> Result<bool> {
+        let started = Instant::now();
+        let result = sqlx::query("UPDATE object_versions SET storage_path = ? WHERE bucket_id = ? AND key = ? AND version_id = ?")
+            .bind(storage_path)
+            .bind(bucket_id.to_string())
+            .bind(key)
+            .bind(version_id.to_string())
             .execute(&self.pool)
-            .await?;
+            .await
+            .context("ObjectVersionRepository::set_storage_path")?;
+        record_query(started, result.rows_affected());
 
         Ok(result.rows_affected() > 0)
     }
@@ -261,15 +969,27 @@ impl MultipartUploadRepository {
         Self { pool }
     }
 
-    pub async fn create(&self, bucket_id: Uuid, object_key: &str, upload_id: &str) -> Result<MultipartUpload> {
+    #[tracing::instrument(skip(self), fields(db.operation = "INSERT", db.rows = tracing::field::Empty))]
+    pub async fn create(
+        &self,
+        bucket_id: Uuid,
+        object_key: &str,
+        upload_id: &str,
+        content_type: &str,
+        metadata: Option<serde_json::Value>,
+        system_metadata: Option<serde_json::Value>,
+    ) -> Result<MultipartUpload> {
+        let started = Instant::now();
         let id = Uuid::new_v4();
         let now = Utc::now();
-        let expires_at = now + chrono::Duration::days(7); // 7 days default expiration
+        let expires_at = now + chrono::Duration::days(MULTIPART_UPLOAD_EXPIRY_DAYS);
+        let metadata_json = metadata.map(|m| serde_json::to_string(&m)).transpose()?;
+        let system_metadata_json = system_metadata.map(|m| serde_json::to_string(&m)).transpose()?;
 
         sqlx::query(
             r#"
-            INSERT INTO multipart_uploads (id, bucket_id, object_key, upload_id, created_at, expires_at)
-            VALUES (?, ?, ?, ?, ?, ?)
+            INSERT INTO multipart_uploads (id, bucket_id, object_key, upload_id, created_at, expires_at, content_type, metadata, system_metadata)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(id.to_string())
@@ -278,8 +998,13 @@ impl MultipartUploadRepository {
         .bind(upload_id)
         .bind(now.to_rfc3339())
         .bind(expires_at.to_rfc3339())
+        .bind(content_type)
+        .bind(&metadata_json)
+        .bind(&system_metadata_json)
         .execute(&self.pool)
-        .await?;
+        .await
+        .context("MultipartUploadRepository::create")?;
+        record_query(started, 1);
 
         let upload = MultipartUpload {
             id,
@@ -288,22 +1013,29 @@ impl MultipartUploadRepository {
             upload_id: upload_id.to_string(),
             created_at: now,
             expires_at: Some(expires_at),
+            content_type: Some(content_type.to_string()),
+            metadata: metadata_json,
+            system_metadata: system_metadata_json,
         };
 
         Ok(upload)
     }
 
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
     pub async fn find_by_upload_id(&self, upload_id: &str) -> Result<Option<MultipartUpload>> {
+        let started = Instant::now();
         let row = sqlx::query(
             r#"
-            SELECT id, bucket_id, object_key, upload_id, created_at, expires_at
+            SELECT id, bucket_id, object_key, upload_id, created_at, expires_at, content_type, metadata, system_metadata
             FROM multipart_uploads 
             WHERE upload_id = ?
             "#,
         )
         .bind(upload_id)
         .fetch_optional(&self.pool)
-        .await?;
+        .await
+        .context("MultipartUploadRepository::find_by_upload_id")?;
+        record_query(started, row.is_some() as u64);
 
         if let Some(row) = row {
             let upload = MultipartUpload {
@@ -315,6 +1047,9 @@ impl MultipartUploadRepository {
                 expires_at: row.get::<Option<String>, _>("expires_at")
                     .map(|s| chrono::DateTime::parse_from_rfc3339(&s).map(|dt| dt.with_timezone(&Utc)))
                     .transpose()?,
+                content_type: row.get("content_type"),
+                metadata: row.get("metadata"),
+                system_metadata: row.get("system_metadata"),
             };
             Ok(Some(upload))
         } else {
@@ -322,27 +1057,105 @@ impl MultipartUploadRepository {
         }
     }
 
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn count(&self) -> Result<u64> {
+        let started = Instant::now();
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM multipart_uploads")
+            .fetch_one(&self.pool)
+            .await
+            .context("MultipartUploadRepository::count")?;
+        record_query(started, 1);
+
+        Ok(count as u64)
+    }
+
+    // Uploads in progress in a bucket, ordered by key and then upload id. Uploads
+    // after (key_marker, upload_id_marker) are returned; without an upload id
+    // marker, every upload for key_marker itself is skipped as well. Returns up
+    // to limit + 1 rows so the caller can tell whether the listing is truncated.
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn list_by_bucket(
+        &self,
+        bucket_id: Uuid,
+        prefix: &str,
+        key_marker: Option<&str>,
+        upload_id_marker: Option<&str>,
+        limit: i32,
+    ) -> Result<Vec<MultipartUpload>> {
+        let started = Instant::now();
+        let key_marker = key_marker.unwrap_or("");
+        let rows = sqlx::query(
+            r#"
+            SELECT id, bucket_id, object_key, upload_id, created_at, expires_at, content_type, metadata, system_metadata
+            FROM multipart_uploads
+            WHERE bucket_id = ? AND substr(object_key, 1, ?) = ?
+                AND (object_key > ? OR (object_key = ? AND upload_id > ?))
+            ORDER BY object_key, upload_id
+            LIMIT ?
+            "#,
+        )
+        .bind(bucket_id.to_string())
+        .bind(prefix.chars().count() as i64)
+        .bind(prefix)
+        .bind(key_marker)
+        .bind(key_marker)
+        .bind(upload_id_marker)
+        .bind(limit + 1)
+        .fetch_all(&self.pool)
+        .await
+        .context("MultipartUploadRepository::list_by_bucket")?;
+        record_query(started, rows.len() as u64);
+
+        let mut uploads = Vec::new();
+        for row in rows {
+            let upload = MultipartUpload {
+                id: Uuid::parse_str(&row.get::<String, _>("id"))?,
+                bucket_id: Uuid::parse_str(&row.get::<String, _>("bucket_id"))?,
+                object_key: row.get("object_key"),
+                upload_id: row.get("upload_id"),
+                created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
+                expires_at: row.get::<Option<String>, _>("expires_at")
+                    .map(|s| chrono::DateTime::parse_from_rfc3339(&s).map(|dt| dt.with_timezone(&Utc)))
+                    .transpose()?,
+                content_type: row.get("content_type"),
+                metadata: row.get("metadata"),
+                system_metadata: row.get("system_metadata"),
+            };
+            uploads.push(upload);
+        }
+
+        Ok(uploads)
+    }
+
+    #[tracing::instrument(skip(self), fields(db.operation = "DELETE", db.rows = tracing::field::Empty))]
     pub async fn delete(&self, upload_id: &str) -> Result<bool> {
+        let started = Instant::now();
         let result = sqlx::query("DELETE FROM multipart_uploads WHERE upload_id = ?")
             .bind(upload_id)
             .execute(&self.pool)
-            .await?;
+            .await
+            .context("MultipartUploadRepository::delete")?;
+        record_query(started, result.rows_affected());
 
         Ok(result.rows_affected() > 0)
     }
 
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
     pub async fn list_expired(&self) -> Result<Vec<MultipartUpload>> {
+        let started = Instant::now();
         let now = Utc::now();
         let rows = sqlx::query(
             r#"
-            SELECT id, bucket_id, object_key, upload_id, created_at, expires_at
+            SELECT id, bucket_id, object_key, upload_id, created_at, expires_at, content_type, metadata, system_metadata
             FROM multipart_uploads 
             WHERE expires_at < ?
             "#,
         )
         .bind(now.to_rfc3339())
         .fetch_all(&self.pool)
-        .await?;
+        .await
+        .context("MultipartUploadRepository::list_expired")?;
+        record_query(started, rows.len() as u64);
 
         let mut uploads = Vec::new();
         for row in rows {
@@ -355,6 +1168,9 @@ impl MultipartUploadRepository {
                 expires_at: row.get::<Option<String>, _>("expires_at")
                     .map(|s| chrono::DateTime::parse_from_rfc3339(&s).map(|dt| dt.with_timezone(&Utc)))
                     .transpose()?,
+                content_type: row.get("content_type"),
+                metadata: row.get("metadata"),
+                system_metadata: row.get("system_metadata"),
             };
             uploads.push(upload);
         }
@@ -372,31 +1188,52 @@ impl MultipartPartRepository {
         Self { pool }
     }
 
-    pub async fn create(&self, upload_id: Uuid, part_number: i32, etag: String, size: i64, storage_path: String) -> Result<MultipartPart> {
+    #[tracing::instrument(skip(self, etag, storage_path), fields(db.operation = "INSERT", db.rows = tracing::field::Empty))]
+    pub async fn create(
+        &self,
+        upload_id: Uuid,
+        part_number: i32,
+        etag: String,
+        digest: Option<Vec<u8>>,
+        size: i64,
+        storage_path: String,
+    ) -> Result<MultipartPart> {
+        let started = Instant::now();
         let id = Uuid::new_v4();
         let now = Utc::now();
 
         sqlx::query(
             r#"
-            INSERT INTO multipart_parts (id, upload_id, part_number, etag, size, created_at, storage_path)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO multipart_parts (id, upload_id, part_number, etag, digest, size, created_at, storage_path)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (upload_id, part_number) DO UPDATE SET
+                id = excluded.id,
+                etag = excluded.etag,
+                digest = excluded.digest,
+                size = excluded.size,
+                created_at = excluded.created_at,
+                storage_path = excluded.storage_path
             "#,
         )
         .bind(id.to_string())
         .bind(upload_id.to_string())
         .bind(part_number)
         .bind(&etag)
+        .bind(&digest)
         .bind(size)
         .bind(now.to_rfc3339())
         .bind(&storage_path)
         .execute(&self.pool)
-        .await?;
+        .await
+        .context("MultipartPartRepository::create")?;
+        record_query(started, 1);
 
         let part = MultipartPart {
             id,
             upload_id,
             part_number,
             etag,
+            digest,
             size,
             created_at: now,
             storage_path,
@@ -405,10 +1242,12 @@ impl MultipartPartRepository {
         Ok(part)
     }
 
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
     pub async fn find_by_upload_and_part(&self, upload_id: Uuid, part_number: i32) -> Result<Option<MultipartPart>> {
+        let started = Instant::now();
         let row = sqlx::query(
             r#"
-            SELECT id, upload_id, part_number, etag, size, created_at, storage_path
+            SELECT id, upload_id, part_number, etag, digest, size, created_at, storage_path
             FROM multipart_parts 
             WHERE upload_id = ? AND part_number = ?
             "#,
@@ -416,7 +1255,9 @@ impl MultipartPartRepository {
         .bind(upload_id.to_string())
         .bind(part_number)
         .fetch_optional(&self.pool)
-        .await?;
+        .await
+        .context("MultipartPartRepository::find_by_upload_and_part")?;
+        record_query(started, row.is_some() as u64);
 
         if let Some(row) = row {
             let part = MultipartPart {
@@ -424,6 +1265,7 @@ impl MultipartPartRepository {
                 upload_id: Uuid::parse_str(&row.get::<String, _>("upload_id"))?,
                 part_number: row.get("part_number"),
                 etag: row.get("etag"),
+                digest: row.get("digest"),
                 size: row.get("size"),
                 created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
                 storage_path: row.get("storage_path"),
@@ -434,10 +1276,12 @@ impl MultipartPartRepository {
         }
     }
 
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
     pub async fn list_by_upload(&self, upload_id: Uuid) -> Result<Vec<MultipartPart>> {
+        let started = Instant::now();
         let rows = sqlx::query(
             r#"
-            SELECT id, upload_id, part_number, etag, size, created_at, storage_path
+            SELECT id, upload_id, part_number, etag, digest, size, created_at, storage_path
             FROM multipart_parts 
             WHERE upload_id = ?
             ORDER BY part_number
@@ -445,7 +1289,9 @@ impl MultipartPartRepository {
         )
         .bind(upload_id.to_string())
         .fetch_all(&self.pool)
-        .await?;
+        .await
+        .context("MultipartPartRepository::list_by_upload")?;
+        record_query(started, rows.len() as u64);
 
         let mut parts = Vec::new();
         for row in rows {
@@ -454,6 +1300,7 @@ impl MultipartPartRepository {
                 upload_id: Uuid::parse_str(&row.get::<String, _>("upload_id"))?,
                 part_number: row.get("part_number"),
                 etag: row.get("etag"),
+                digest: row.get("digest"),
                 size: row.get("size"),
                 created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
                 storage_path: row.get("storage_path"),
@@ -464,12 +1311,513 @@ impl MultipartPartRepository {
         Ok(parts)
     }
 
+    #[tracing::instrument(skip(self), fields(db.operation = "DELETE", db.rows = tracing::field::Empty))]
     pub async fn delete_by_upload(&self, upload_id: Uuid) -> Result<u64> {
+        let started = Instant::now();
         let result = sqlx::query("DELETE FROM multipart_parts WHERE upload_id = ?")
             .bind(upload_id.to_string())
             .execute(&self.pool)
-            .await?;
+            .await
+            .context("MultipartPartRepository::delete_by_upload")?;
+        record_query(started, result.rows_affected());
+
+        Ok(result.rows_affected())
+    }
+}
+
+pub struct ObjectTagRepository {
+    pool: SqlitePool,
+}
+
+impl ObjectTagRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    // Tags for every key in [first_key, last_key], grouped by key
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn list_in_range(&self, bucket_id: Uuid, first_key: &str, last_key: &str) -> Result<HashMap<String, HashMap<String, String>>> {
+        let started = Instant::now();
+        let rows = sqlx::query(
+            "SELECT key, tag_key, tag_value FROM object_tags WHERE bucket_id = ? AND key >= ? AND key <= ?"
+        )
+        .bind(bucket_id.to_string())
+        .bind(first_key)
+        .bind(last_key)
+        .fetch_all(&self.pool)
+        .await
+        .context("ObjectTagRepository::list_in_range")?;
+        record_query(started, rows.len() as u64);
+
+        let mut tags: HashMap<String, HashMap<String, String>> = HashMap::new();
+        for row in rows {
+            tags.entry(row.get("key"))
+                .or_default()
+                .insert(row.get("tag_key"), row.get("tag_value"));
+        }
+
+        Ok(tags)
+    }
+
+    // Tags of one object, ordered by tag key
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn get(&self, bucket_id: Uuid, key: &str) -> Result<Vec<(String, String)>> {
+        let started = Instant::now();
+        let rows = sqlx::query("SELECT tag_key, tag_value FROM object_tags WHERE bucket_id = ? AND key = ? ORDER BY tag_key")
+            .bind(bucket_id.to_string())
+            .bind(key)
+            .fetch_all(&self.pool)
+            .await
+            .context("ObjectTagRepository::get")?;
+        record_query(started, rows.len() as u64);
+
+        Ok(rows.into_iter().map(|row| (row.get("tag_key"), row.get("tag_value"))).collect())
+    }
+
+    // Replaces the whole tag set of an object; an empty set removes its tags
+    #[tracing::instrument(skip(self, tags), fields(db.operation = "UPSERT", db.rows = tracing::field::Empty))]
+    pub async fn replace(&self, bucket_id: Uuid, key: &str, tags: &[(String, String)]) -> Result<()> {
+        let started = Instant::now();
+        let mut tx = self.pool.begin().await.context("ObjectTagRepository::replace")?;
+
+        sqlx::query("DELETE FROM object_tags WHERE bucket_id = ? AND key = ?")
+            .bind(bucket_id.to_string())
+            .bind(key)
+            .execute(&mut *tx)
+            .await
+            .context("ObjectTagRepository::replace")?;
+
+        for (tag_key, tag_value) in tags {
+            sqlx::query("INSERT INTO object_tags (bucket_id, key, tag_key, tag_value) VALUES (?, ?, ?, ?)")
+                .bind(bucket_id.to_string())
+                .bind(key)
+                .bind(tag_key)
+                .bind(tag_value)
+                .execute(&mut *tx)
+                .await
+                .context("ObjectTagRepository::replace")?;
+        }
+
+        tx.commit().await.context("ObjectTagRepository::replace")?;
+        record_query(started, tags.len() as u64);
+
+        Ok(())
+    }
+}
+
+// Parts of multipart objects, kept per version like object_versions so
+// GetObjectAttributes can list them after the upload's own records are gone
+pub struct ObjectPartRepository {
+    pool: SqlitePool,
+}
+
+impl ObjectPartRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    #[tracing::instrument(skip(self, parts), fields(db.operation = "INSERT", db.rows = tracing::field::Empty))]
+    pub async fn create(&self, bucket_id: Uuid, version_id: Uuid, parts: &[ObjectPart]) -> Result<()> {
+        let started = Instant::now();
+        let mut tx = self.pool.begin().await.context("ObjectPartRepository::create")?;
+
+        for part in parts {
+            sqlx::query("INSERT OR REPLACE INTO object_parts (bucket_id, version_id, part_number, size) VALUES (?, ?, ?, ?)")
+                .bind(bucket_id.to_string())
+                .bind(version_id.to_string())
+                .bind(part.part_number)
+                .bind(part.size)
+                .execute(&mut *tx)
+                .await
+                .context("ObjectPartRepository::create")?;
+        }
+
+        tx.commit().await.context("ObjectPartRepository::create")?;
+        record_query(started, parts.len() as u64);
+
+        Ok(())
+    }
+
+    // Parts of one object version, in part number order; empty for objects
+    // that were not uploaded in parts
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn list(&self, version_id: Uuid) -> Result<Vec<ObjectPart>> {
+        let started = Instant::now();
+        let rows = sqlx::query("SELECT part_number, size FROM object_parts WHERE version_id = ? ORDER BY part_number")
+            .bind(version_id.to_string())
+            .fetch_all(&self.pool)
+            .await
+            .context("ObjectPartRepository::list")?;
+        record_query(started, rows.len() as u64);
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ObjectPart {
+                part_number: row.get("part_number"),
+                size: row.get("size"),
+            })
+            .collect())
+    }
+}
+
+pub struct BucketTagRepository {
+    pool: SqlitePool,
+}
+
+impl BucketTagRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    // Tags of one bucket, ordered by tag key
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn get(&self, bucket_id: Uuid) -> Result<Vec<(String, String)>> {
+        let started = Instant::now();
+        let rows = sqlx::query("SELECT tag_key, tag_value FROM bucket_tags WHERE bucket_id = ? ORDER BY tag_key")
+            .bind(bucket_id.to_string())
+            .fetch_all(&self.pool)
+            .await
+            .context("BucketTagRepository::get")?;
+        record_query(started, rows.len() as u64);
+
+        Ok(rows.into_iter().map(|row| (row.get("tag_key"), row.get("tag_value"))).collect())
+    }
+
+    // Replaces the whole tag set of a bucket; an empty set removes its tags
+    #[tracing::instrument(skip(self, tags), fields(db.operation = "UPSERT", db.rows = tracing::field::Empty))]
+    pub async fn replace(&self, bucket_id: Uuid, tags: &[(String, String)]) -> Result<()> {
+        let started = Instant::now();
+        let mut tx = self.pool.begin().await.context("BucketTagRepository::replace")?;
+
+        sqlx::query("DELETE FROM bucket_tags WHERE bucket_id = ?")
+            .bind(bucket_id.to_string())
+            .execute(&mut *tx)
+            .await
+            .context("BucketTagRepository::replace")?;
+
+        for (tag_key, tag_value) in tags {
+            sqlx::query("INSERT INTO bucket_tags (bucket_id, tag_key, tag_value) VALUES (?, ?, ?)")
+                .bind(bucket_id.to_string())
+                .bind(tag_key)
+                .bind(tag_value)
+                .execute(&mut *tx)
+                .await
+                .context("BucketTagRepository::replace")?;
+        }
+
+        tx.commit().await.context("BucketTagRepository::replace")?;
+        record_query(started, tags.len() as u64);
+
+        Ok(())
+    }
+}
+
+pub struct LifecycleRepository {
+    pool: SqlitePool,
+}
+
+impl LifecycleRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn get_rules(&self, bucket_id: Uuid) -> Result<Vec<LifecycleRule>> {
+        let started = Instant::now();
+        let row = sqlx::query("SELECT rules FROM bucket_lifecycle WHERE bucket_id = ?")
+            .bind(bucket_id.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .context("LifecycleRepository::get_rules")?;
+        record_query(started, row.is_some() as u64);
+
+        match row {
+            Some(row) => Ok(serde_json::from_str(&row.get::<String, _>("rules"))?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    #[tracing::instrument(skip(self, rules), fields(db.operation = "UPSERT", db.rows = tracing::field::Empty))]
+    pub async fn put_rules(&self, bucket_id: Uuid, rules: &[LifecycleRule]) -> Result<()> {
+        let started = Instant::now();
+        sqlx::query(
+            r#"
+            INSERT INTO bucket_lifecycle (bucket_id, rules, updated_at)
+            VALUES (?, ?, ?)
+            ON CONFLICT (bucket_id) DO UPDATE SET
+                rules = excluded.rules,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(bucket_id.to_string())
+        .bind(serde_json::to_string(rules)?)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .context("LifecycleRepository::put_rules")?;
+        record_query(started, 1);
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self), fields(db.operation = "DELETE", db.rows = tracing::field::Empty))]
+    pub async fn delete_rules(&self, bucket_id: Uuid) -> Result<bool> {
+        let started = Instant::now();
+        let result = sqlx::query("DELETE FROM bucket_lifecycle WHERE bucket_id = ?")
+            .bind(bucket_id.to_string())
+            .execute(&self.pool)
+            .await
+            .context("LifecycleRepository::delete_rules")?;
+        record_query(started, result.rows_affected());
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+pub struct BucketCorsRepository {
+    pool: SqlitePool,
+}
+
+impl BucketCorsRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    // None when the bucket has no CORS configuration
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn get_rules(&self, bucket_id: Uuid) -> Result<Option<Vec<BucketCorsConfig>>> {
+        let started = Instant::now();
+        let row = sqlx::query("SELECT rules FROM bucket_cors WHERE bucket_id = ?")
+            .bind(bucket_id.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .context("BucketCorsRepository::get_rules")?;
+        record_query(started, row.is_some() as u64);
+
+        row.map(|row| serde_json::from_str(&row.get::<String, _>("rules")))
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    #[tracing::instrument(skip(self, rules), fields(db.operation = "UPSERT", db.rows = tracing::field::Empty))]
+    pub async fn put_rules(&self, bucket_id: Uuid, rules: &[BucketCorsConfig]) -> Result<()> {
+        let started = Instant::now();
+        sqlx::query(
+            r#"
+            INSERT INTO bucket_cors (bucket_id, rules, updated_at)
+            VALUES (?, ?, ?)
+            ON CONFLICT (bucket_id) DO UPDATE SET
+                rules = excluded.rules,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(bucket_id.to_string())
+        .bind(serde_json::to_string(rules)?)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .context("BucketCorsRepository::put_rules")?;
+        record_query(started, 1);
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self), fields(db.operation = "DELETE", db.rows = tracing::field::Empty))]
+    pub async fn delete_rules(&self, bucket_id: Uuid) -> Result<bool> {
+        let started = Instant::now();
+        let result = sqlx::query("DELETE FROM bucket_cors WHERE bucket_id = ?")
+            .bind(bucket_id.to_string())
+            .execute(&self.pool)
+            .await
+            .context("BucketCorsRepository::delete_rules")?;
+        record_query(started, result.rows_affected());
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+pub struct BucketPolicyRepository {
+    pool: SqlitePool,
+}
+
+impl BucketPolicyRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    // The policy document as it was stored, None when the bucket has none
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn get(&self, bucket_id: Uuid) -> Result<Option<String>> {
+        let started = Instant::now();
+        let row = sqlx::query("SELECT policy FROM bucket_policies WHERE bucket_id = ?")
+            .bind(bucket_id.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .context("BucketPolicyRepository::get")?;
+        record_query(started, row.is_some() as u64);
+
+        Ok(row.map(|row| row.get("policy")))
+    }
+
+    #[tracing::instrument(skip(self, policy), fields(db.operation = "UPSERT", db.rows = tracing::field::Empty))]
+    pub async fn put(&self, bucket_id: Uuid, policy: &str) -> Result<()> {
+        let started = Instant::now();
+        sqlx::query(
+            r#"
+            INSERT INTO bucket_policies (bucket_id, policy, updated_at)
+            VALUES (?, ?, ?)
+            ON CONFLICT (bucket_id) DO UPDATE SET
+                policy = excluded.policy,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(bucket_id.to_string())
+        .bind(policy)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .context("BucketPolicyRepository::put")?;
+        record_query(started, 1);
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self), fields(db.operation = "DELETE", db.rows = tracing::field::Empty))]
+    pub async fn delete(&self, bucket_id: Uuid) -> Result<bool> {
+        let started = Instant::now();
+        let result = sqlx::query("DELETE FROM bucket_policies WHERE bucket_id = ?")
+            .bind(bucket_id.to_string())
+            .execute(&self.pool)
+            .await
+            .context("BucketPolicyRepository::delete")?;
+        record_query(started, result.rows_affected());
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+pub struct AuditRepository {
+    pool: SqlitePool,
+}
+
+// Audit timestamps are stored at a fixed width so they compare correctly as text
+fn audit_timestamp(at: &DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(chrono::SecondsFormat::Micros, true)
+}
+
+impl AuditRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    #[tracing::instrument(skip(self, entry), fields(db.operation = "INSERT", db.rows = tracing::field::Empty))]
+    pub async fn record(&self, entry: &NewAuditEntry) -> Result<()> {
+        let started = Instant::now();
+        sqlx::query(
+            r#"
+            INSERT INTO audit_log (request_id, host_id, occurred_at, access_key_id, action, bucket, key, status)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&entry.request_id)
+        .bind(&entry.host_id)
+        .bind(audit_timestamp(&entry.occurred_at))
+        .bind(&entry.access_key_id)
+        .bind(&entry.action)
+        .bind(&entry.bucket)
+        .bind(&entry.key)
+        .bind(entry.status as i64)
+        .execute(&self.pool)
+        .await
+        .context("AuditRepository::record")?;
+        record_query(started, 1);
+
+        Ok(())
+    }
+
+    // Matching entries in the order they were recorded, starting after the
+    // `after` cursor. Returns up to `limit + 1` rows so callers can tell
+    // whether more follow.
+    #[tracing::instrument(skip(self, filter), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn search(&self, filter: &AuditFilter, after: Option<i64>, limit: i32) -> Result<Vec<AuditEntry>> {
+        let started = Instant::now();
+        let mut query = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
+            "SELECT id, request_id, host_id, occurred_at, access_key_id, action, bucket, key, status FROM audit_log WHERE id > ",
+        );
+        query.push_bind(after.unwrap_or(0));
+        if let Some(bucket) = &filter.bucket {
+            query.push(" AND bucket = ").push_bind(bucket);
+        }
+        if let Some(key_prefix) = &filter.key_prefix {
+            query
+                .push(" AND substr(key, 1, ")
+                .push_bind(key_prefix.chars().count() as i64)
+                .push(") = ")
+                .push_bind(key_prefix);
+        }
+        if let Some(access_key_id) = &filter.access_key_id {
+            query.push(" AND access_key_id = ").push_bind(access_key_id);
+        }
+        if let Some(action) = &filter.action {
+            query.push(" AND action = ").push_bind(action);
+        }
+        if let Some(from) = &filter.from {
+            query.push(" AND occurred_at >= ").push_bind(audit_timestamp(from));
+        }
+        if let Some(to) = &filter.to {
+            query.push(" AND occurred_at < ").push_bind(audit_timestamp(to));
+        }
+        query.push(" ORDER BY id LIMIT ").push_bind(limit + 1);
+
+        let rows = query
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .context("AuditRepository::search")?;
+        record_query(started, rows.len() as u64);
+
+        let mut entries = Vec::new();
+        for row in rows {
+            entries.push(AuditEntry {
+                id: row.get("id"),
+                request_id: row.get("request_id"),
+                host_id: row.get("host_id"),
+                occurred_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("occurred_at"))?.with_timezone(&Utc),
+                access_key_id: row.get("access_key_id"),
+                action: row.get("action"),
+                bucket: row.get("bucket"),
+                key: row.get("key"),
+                status: row.get::<i64, _>("status") as u16,
+            });
+        }
+
+        Ok(entries)
+    }
+
+    // Id of the newest entry, where a follower starts from
+    #[tracing::instrument(skip(self), fields(db.operation = "SELECT", db.rows = tracing::field::Empty))]
+    pub async fn latest_id(&self) -> Result<Option<i64>> {
+        let started = Instant::now();
+        let row = sqlx::query("SELECT MAX(id) AS id FROM audit_log")
+            .fetch_one(&self.pool)
+            .await
+            .context("AuditRepository::latest_id")?;
+        record_query(started, 1);
+
+        Ok(row.get("id"))
+    }
+
+    // Deletes entries recorded before the cutoff; returns how many were removed
+    #[tracing::instrument(skip(self), fields(db.operation = "DELETE", db.rows = tracing::field::Empty))]
+    pub async fn prune_before(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        let started = Instant::now();
+        let result = sqlx::query("DELETE FROM audit_log WHERE occurred_at < ?")
+            .bind(audit_timestamp(&cutoff))
+            .execute(&self.pool)
+            .await
+            .context("AuditRepository::prune_before")?;
+        record_query(started, result.rows_affected());
 
         Ok(result.rows_affected())
     }
-}
\ No newline at end of file
+}
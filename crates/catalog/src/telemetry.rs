@@ -0,0 +1,8 @@
+use std::time::Instant;
+
+// Repository methods declare an empty `db.rows` field on their span; this fills
+// it in and emits the query duration as an event inside that span.
+pub fn record_query(started: Instant, rows: u64) {
+    tracing::Span::current().record("db.rows", rows);
+    tracing::debug!(elapsed_ms = started.elapsed().as_secs_f64() * 1000.0, "query completed");
+}
@@ -1,8 +1,11 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
-use ghostbay_auth::{AuthService, CreateAccessKeyRequest, AccessKeyRepository};
-use ghostbay_catalog::{CatalogService, CreateBucketRequest, BucketRepository};
-use std::path::PathBuf;
+use ghostbay_auth::{apply_provisioning, CreateAccessKeyRequest, AccessKeyRepository, KeyQuota, KeyUsageRepository, ProvisioningFile, UsagePeriod};
+use ghostbay_catalog::{AuditEntry, AuditFilter, AuditRepository, CatalogService, CreateBucketRequest, BucketRepository, BucketTagRepository, LifecycleRepository, LifecycleRule, ObjectRepository, ObjectVersionRepository};
+use ghostbay_catalog::backup;
+use ghostbay_catalog::lifecycle::{LifecycleEvaluator, LifecycleReport};
+use ghostbay_engine::{LocalStorageEngine, LockMode, ProcessLock, StorageConfig, StorageEngine};
+use std::path::{Path, PathBuf};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "GhostBay CLI - Manage your S3-compatible object storage", long_about = None)]
@@ -28,6 +31,25 @@ enum Commands {
         #[command(subcommand)]
         command: BucketCommands,
     },
+    Lifecycle {
+        #[command(subcommand)]
+        command: LifecycleCommands,
+    },
+    Provision {
+        #[command(subcommand)]
+        command: ProvisionCommands,
+    },
+    // Report objects whose data file was found missing on read; exits 1 if any
+    Fsck,
+    // Delete objects past their TTL now rather than at the server's next sweep
+    Expire {
+        #[arg(long, default_value = "./data")]
+        data_dir: PathBuf,
+    },
+    Db {
+        #[command(subcommand)]
+        command: DbCommands,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -36,17 +58,23 @@ enum AdminCommands {
         #[command(subcommand)]
         command: KeyCommands,
     },
+    Audit {
+        #[command(subcommand)]
+        command: AuditCommands,
+    },
 }
 
 #[derive(Subcommand, Debug)]
 enum KeyCommands {
     Create {
-        #[arg(long, default_value = "admin")]
+        #[arg(long, default_value = "admin", help = "admin, read-only, read-write, read:<bucket> or readwrite:<bucket>")]
         policies: Vec<String>,
         #[arg(long)]
         description: Option<String>,
         #[arg(long, help = "Expiration in days from now")]
         expires_days: Option<u64>,
+        #[command(flatten)]
+        quota: QuotaArgs,
     },
     List {
         #[arg(long, help = "Include inactive keys")]
@@ -61,6 +89,82 @@ enum KeyCommands {
     Delete {
         access_key_id: String,
     },
+    SetQuota {
+        access_key_id: String,
+        #[command(flatten)]
+        quota: QuotaArgs,
+    },
+    Usage {
+        access_key_id: String,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+struct QuotaArgs {
+    #[arg(long, help = "Maximum requests per UTC day")]
+    max_requests_per_day: Option<i64>,
+    #[arg(long, help = "Maximum bytes uploaded per calendar month")]
+    max_bytes_in_per_month: Option<i64>,
+    #[arg(long, help = "Maximum bytes downloaded per calendar month")]
+    max_bytes_out_per_month: Option<i64>,
+}
+
+impl QuotaArgs {
+    fn to_quota(&self) -> KeyQuota {
+        KeyQuota {
+            max_requests_per_day: self.max_requests_per_day,
+            max_bytes_in_per_month: self.max_bytes_in_per_month,
+            max_bytes_out_per_month: self.max_bytes_out_per_month,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum AuditCommands {
+    Search {
+        #[command(flatten)]
+        filter: AuditFilterArgs,
+        #[arg(long, default_value_t = 100, help = "Maximum entries to show")]
+        limit: u32,
+        #[arg(long, help = "Only show entries after this entry id")]
+        after: Option<i64>,
+    },
+    // Print entries as they are recorded, like tail -f
+    Tail {
+        #[command(flatten)]
+        filter: AuditFilterArgs,
+        #[arg(long, default_value_t = 2, help = "Seconds between polls")]
+        interval: u64,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+struct AuditFilterArgs {
+    #[arg(long)]
+    bucket: Option<String>,
+    #[arg(long)]
+    key_prefix: Option<String>,
+    #[arg(long)]
+    access_key: Option<String>,
+    #[arg(long, help = "S3 operation, e.g. PutObject")]
+    action: Option<String>,
+    #[arg(long, help = "Only entries at or after this RFC 3339 time")]
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    #[arg(long, help = "Only entries before this RFC 3339 time")]
+    to: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl AuditFilterArgs {
+    fn to_filter(&self) -> AuditFilter {
+        AuditFilter {
+            bucket: self.bucket.clone(),
+            key_prefix: self.key_prefix.clone(),
+            access_key_id: self.access_key.clone(),
+            action: self.action.clone(),
+            from: self.from,
+            to: self.to,
+        }
+    }
 }
 
 #[derive(Subcommand, Debug)]
@@ -74,6 +178,81 @@ enum BucketCommands {
     Delete {
         name: String,
     },
+    Ls {
+        name: String,
+        #[arg(long, help = "Show the bucket as it was at this RFC 3339 time")]
+        at: Option<chrono::DateTime<chrono::Utc>>,
+        #[arg(long)]
+        prefix: Option<String>,
+    },
+    UploadEncoding {
+        name: String,
+        #[arg(long, action = clap::ArgAction::Set, help = "Inflate gzip and zstd encoded uploads instead of storing them encoded")]
+        decompress_on_upload: bool,
+    },
+    SetQuota {
+        name: String,
+        #[arg(help = "Most bytes the bucket may hold; omit to remove the quota")]
+        bytes: Option<u64>,
+    },
+    SetPublic {
+        name: String,
+        #[arg(long, default_value_t = true, action = clap::ArgAction::Set, help = "Allow unsigned GET and HEAD of the bucket's objects")]
+        read: bool,
+        #[arg(long, default_value_t = false, action = clap::ArgAction::Set, help = "Allow unsigned listing of the bucket")]
+        list: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum LifecycleCommands {
+    Set {
+        bucket: String,
+        #[arg(long, help = "JSON file containing an array of lifecycle rules")]
+        file: PathBuf,
+    },
+    Preview {
+        bucket: String,
+        #[arg(long, default_value_t = 10, help = "Sample keys to show per rule")]
+        samples: usize,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DbCommands {
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommands,
+    },
+    // Replace the catalog with a snapshot; refused while a server holds the catalog
+    Restore {
+        snapshot: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum BackupCommands {
+    // Snapshot the catalog now; safe while the server is running
+    Now {
+        #[arg(long, help = "Directory to write the snapshot to")]
+        dir: PathBuf,
+        #[arg(long, help = "Delete all but this many newest snapshots afterwards")]
+        keep: Option<usize>,
+    },
+    List {
+        #[arg(long)]
+        dir: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ProvisionCommands {
+    Apply {
+        #[arg(long, help = "Provisioning TOML file declaring buckets and access keys")]
+        file: PathBuf,
+        #[arg(long, help = "Report drift without changing anything; exits 1 if any is found")]
+        check: bool,
+    },
 }
 
 #[tokio::main]
@@ -94,6 +273,21 @@ async fn main() -> Result<()> {
         Commands::Bucket { command } => {
             handle_bucket_command(command, &cli.database_url).await?;
         }
+        Commands::Lifecycle { command } => {
+            handle_lifecycle_command(command, &cli.database_url).await?;
+        }
+        Commands::Provision { command } => {
+            handle_provision_command(command, &cli.database_url).await?;
+        }
+        Commands::Fsck => {
+            handle_fsck_command(&cli.database_url).await?;
+        }
+        Commands::Expire { data_dir } => {
+            handle_expire_command(&cli.database_url, data_dir).await?;
+        }
+        Commands::Db { command } => {
+            handle_db_command(command, &cli.database_url).await?;
+        }
     }
 
     Ok(())
@@ -104,10 +298,103 @@ async fn handle_admin_command(command: &AdminCommands, database_url: &str) -> Re
         AdminCommands::Key { command } => {
             handle_key_command(command, database_url).await?;
         }
+        AdminCommands::Audit { command } => {
+            handle_audit_command(command, database_url).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn handle_audit_command(command: &AuditCommands, database_url: &str) -> Result<()> {
+    let catalog = CatalogService::new(database_url).await?;
+
+    // Ensure database exists and is migrated
+    ghostbay_catalog::migrations::ensure_database_exists(database_url).await?;
+    ghostbay_catalog::migrations::run_migrations(catalog.pool()).await?;
+
+    let audit_repo = AuditRepository::new(catalog.pool().clone());
+
+    match command {
+        AuditCommands::Search { filter, limit, after } => {
+            let limit = (*limit).clamp(1, 1000);
+            let mut entries = match audit_repo.search(&filter.to_filter(), *after, limit as i32).await {
+                Ok(entries) => entries,
+                Err(e) => {
+                    eprintln!("Failed to search audit log: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            let is_truncated = entries.len() > limit as usize;
+            entries.truncate(limit as usize);
+            if entries.is_empty() {
+                println!("No matching audit entries");
+                return Ok(());
+            }
+
+            for entry in &entries {
+                println!("{}", format_audit_entry(entry));
+            }
+            if is_truncated && let Some(last) = entries.last() {
+                println!("\nMore entries match; continue with --after {}", last.id);
+            }
+        }
+        AuditCommands::Tail { filter, interval } => {
+            let filter = filter.to_filter();
+            let mut cursor = match audit_repo.latest_id().await {
+                Ok(latest) => latest,
+                Err(e) => {
+                    eprintln!("Failed to read audit log: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            loop {
+                let entries = match audit_repo.search(&filter, cursor, 1000).await {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        eprintln!("Failed to read audit log: {}", e);
+                        std::process::exit(1);
+                    }
+                };
+
+                for entry in &entries {
+                    println!("{}", format_audit_entry(entry));
+                }
+                if let Some(last) = entries.last() {
+                    cursor = Some(last.id);
+                }
+
+                // search returns one row past the limit when more are already waiting
+                if entries.len() <= 1000 {
+                    tokio::time::sleep(std::time::Duration::from_secs(*interval)).await;
+                }
+            }
+        }
     }
+
     Ok(())
 }
 
+fn format_audit_entry(entry: &AuditEntry) -> String {
+    let resource = match (&entry.bucket, &entry.key) {
+        (Some(bucket), Some(key)) => format!("{}/{}", bucket, key),
+        (Some(bucket), None) => bucket.clone(),
+        _ => "-".to_string(),
+    };
+    format!(
+        "{:>8}  {}  {}  {:<24}  {}  key={}  request={}  host={}",
+        entry.id,
+        entry.occurred_at.format("%Y-%m-%d %H:%M:%S%.3f"),
+        entry.status,
+        entry.action,
+        resource,
+        entry.access_key_id.as_deref().unwrap_or("-"),
+        entry.request_id,
+        entry.host_id.as_deref().unwrap_or("-")
+    )
+}
+
 async fn handle_key_command(command: &KeyCommands, database_url: &str) -> Result<()> {
     let catalog = CatalogService::new(database_url).await?;
 
@@ -118,7 +405,7 @@ async fn handle_key_command(command: &KeyCommands, database_url: &str) -> Result
     let key_repo = AccessKeyRepository::new(catalog.pool().clone());
 
     match command {
-        KeyCommands::Create { policies, description, expires_days } => {
+        KeyCommands::Create { policies, description, expires_days, quota } => {
             let expires_at = expires_days.map(|days| {
                 chrono::Utc::now() + chrono::Duration::days(days as i64)
             });
@@ -127,6 +414,7 @@ async fn handle_key_command(command: &KeyCommands, database_url: &str) -> Result
                 policies: policies.clone(),
                 description: description.clone(),
                 expires_at,
+                quota: quota.to_quota(),
             };
 
             match key_repo.create(request).await {
@@ -142,6 +430,9 @@ async fn handle_key_command(command: &KeyCommands, database_url: &str) -> Result
                     if let Some(desc) = access_key.description {
                         println!("  Description: {}", desc);
                     }
+                    if !access_key.quota.is_unlimited() {
+                        println!("  Quota: {}", format_quota(&access_key.quota));
+                    }
                     println!("\n⚠️  Warning: Store the secret access key securely - it will not be shown again!");
                 }
                 Err(e) => {
@@ -167,6 +458,9 @@ async fn handle_key_command(command: &KeyCommands, database_url: &str) -> Result
                                 println!("    Description: {}", desc);
                             }
                             println!("    Policies: {:?}", key.policies);
+                            if !key.quota.is_unlimited() {
+                                println!("    Quota: {}", format_quota(&key.quota));
+                            }
                         }
                     }
                 }
@@ -225,10 +519,74 @@ async fn handle_key_command(command: &KeyCommands, database_url: &str) -> Result
                 }
             }
         }
+        KeyCommands::SetQuota { access_key_id, quota } => {
+            let quota = quota.to_quota();
+            match key_repo.set_quota(access_key_id, &quota).await {
+                Ok(true) => {
+                    if quota.is_unlimited() {
+                        println!("Removed quota from access key '{}'", access_key_id);
+                    } else {
+                        println!("Set quota on access key '{}': {}", access_key_id, format_quota(&quota));
+                    }
+                }
+                Ok(false) => {
+                    eprintln!("Access key '{}' not found", access_key_id);
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Failed to set quota: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        KeyCommands::Usage { access_key_id } => {
+            let access_key = match key_repo.find_by_access_key_id(access_key_id).await {
+                Ok(Some(access_key)) => access_key,
+                Ok(None) => {
+                    eprintln!("Access key '{}' not found", access_key_id);
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Failed to load access key: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            let usage_repo = KeyUsageRepository::new(catalog.pool().clone());
+            let now = chrono::Utc::now();
+            let day = usage_repo.get(access_key_id, UsagePeriod::Day, UsagePeriod::Day.start(now)).await?;
+            let month = usage_repo.get(access_key_id, UsagePeriod::Month, UsagePeriod::Month.start(now)).await?;
+            let quota = &access_key.quota;
+
+            println!("Usage for access key '{}':", access_key_id);
+            println!("  Requests today: {}", format_usage(day.requests, quota.max_requests_per_day));
+            println!("  Bytes uploaded this month: {}", format_usage(month.bytes_in, quota.max_bytes_in_per_month));
+            println!("  Bytes downloaded this month: {}", format_usage(month.bytes_out, quota.max_bytes_out_per_month));
+            println!("  Daily period resets: {}", UsagePeriod::Day.end(now).format("%Y-%m-%d %H:%M:%S UTC"));
+            println!("  Monthly period resets: {}", UsagePeriod::Month.end(now).format("%Y-%m-%d %H:%M:%S UTC"));
+            println!("\nNote: a running server flushes usage every 30 seconds, so recent activity may not be shown yet.");
+        }
     }
     Ok(())
 }
 
+fn format_quota(quota: &KeyQuota) -> String {
+    let limit = |value: Option<i64>| value.map(|v| v.to_string()).unwrap_or_else(|| "unlimited".to_string());
+    format!(
+        "{} requests/day, {} bytes in/month, {} bytes out/month",
+        limit(quota.max_requests_per_day),
+        limit(quota.max_bytes_in_per_month),
+        limit(quota.max_bytes_out_per_month)
+    )
+}
+
+fn format_usage(used: i64, limit: Option<i64>) -> String {
+    match limit {
+        Some(limit) => format!("{} / {}", used, limit),
+        None => format!("{} (no limit)", used),
+    }
+}
+
 async fn handle_bucket_command(command: &BucketCommands, database_url: &str) -> Result<()> {
     let catalog = CatalogService::new(database_url).await?;
 
@@ -243,6 +601,7 @@ async fn handle_bucket_command(command: &BucketCommands, database_url: &str) ->
             let request = CreateBucketRequest {
                 name: name.clone(),
                 region: region.clone(),
+                owner_access_key_id: None,
             };
 
             match repo.create(request).await {
@@ -264,8 +623,22 @@ async fn handle_bucket_command(command: &BucketCommands, database_url: &str) ->
                         println!("No buckets found");
                     } else {
                         println!("Buckets:");
+                        let tag_repo = BucketTagRepository::new(catalog.pool().clone());
                         for bucket in buckets {
                             println!("  {} ({})", bucket.name, bucket.created_at.format("%Y-%m-%d %H:%M:%S UTC"));
+                            let stats = repo.stats(&bucket.name).await?;
+                            println!("    Objects: {}, {} bytes", stats.object_count, stats.total_bytes);
+                            if let Some(quota_bytes) = bucket.quota_bytes {
+                                println!("    Quota: {} bytes", quota_bytes);
+                            }
+                            if bucket.public_read || bucket.public_list {
+                                println!("    Public: read={}, list={}", bucket.public_read, bucket.public_list);
+                            }
+                            let tags = tag_repo.get(bucket.id).await?;
+                            if !tags.is_empty() {
+                                let tags: Vec<String> = tags.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+                                println!("    Tags: {}", tags.join(", "));
+                            }
                         }
                     }
                 }
@@ -290,7 +663,397 @@ async fn handle_bucket_command(command: &BucketCommands, database_url: &str) ->
                 }
             }
         }
+        BucketCommands::Ls { name, at, prefix } => {
+            let bucket = match repo.find_by_name(name).await {
+                Ok(Some(bucket)) => bucket,
+                Ok(None) => {
+                    eprintln!("Bucket '{}' not found", name);
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Failed to load bucket: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            let at = at.unwrap_or_else(chrono::Utc::now);
+            let version_repo = ObjectVersionRepository::new(catalog.pool().clone());
+            let mut start_after: Option<String> = None;
+            let mut count = 0;
+
+            println!("Objects in '{}' as of {}:", name, at.format("%Y-%m-%d %H:%M:%S UTC"));
+            loop {
+                let page = match version_repo.list_as_of(bucket.id, at, prefix.as_deref(), start_after.as_deref(), 1000).await {
+                    Ok(page) => page,
+                    Err(e) => {
+                        eprintln!("Failed to list objects: {}", e);
+                        std::process::exit(1);
+                    }
+                };
+
+                for version in &page {
+                    println!("  {}  {:>12}  {}  {}", version.created_at.format("%Y-%m-%d %H:%M:%S"), version.size, version.version_id, version.key);
+                }
+                count += page.len();
+
+                if page.len() < 1000 {
+                    break;
+                }
+                start_after = page.last().map(|v| v.key.clone());
+            }
+
+            if count == 0 {
+                println!("  (no objects)");
+            }
+        }
+        BucketCommands::UploadEncoding { name, decompress_on_upload } => {
+            match repo.set_decompress_on_upload(name, *decompress_on_upload).await {
+                Ok(true) => {
+                    let mode = if *decompress_on_upload { "inflated before storing" } else { "stored as sent" };
+                    println!("Encoded uploads to bucket '{}' are now {}", name, mode);
+                }
+                Ok(false) => {
+                    eprintln!("Bucket '{}' not found", name);
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Failed to update bucket: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        BucketCommands::SetQuota { name, bytes } => {
+            let quota_bytes = match bytes.map(i64::try_from).transpose() {
+                Ok(quota_bytes) => quota_bytes,
+                Err(_) => {
+                    eprintln!("Quota must be at most {} bytes", i64::MAX);
+                    std::process::exit(1);
+                }
+            };
+            match repo.set_quota(name, quota_bytes).await {
+                Ok(true) => match quota_bytes {
+                    Some(quota_bytes) => println!("Bucket '{}' may now hold up to {} bytes", name, quota_bytes),
+                    None => println!("Removed the quota of bucket '{}'", name),
+                },
+                Ok(false) => {
+                    eprintln!("Bucket '{}' not found", name);
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Failed to update bucket: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        BucketCommands::SetPublic { name, read, list } => {
+            match repo.set_public_access(name, *read, *list).await {
+                Ok(true) => {
+                    let access = match (*read, *list) {
+                        (true, true) => "readable and listable by anyone",
+                        (true, false) => "readable by anyone, but not listable",
+                        (false, true) => "listable by anyone, but not readable",
+                        (false, false) => "private",
+                    };
+                    println!("Bucket '{}' is now {}", name, access);
+                }
+                Ok(false) => {
+                    eprintln!("Bucket '{}' not found", name);
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Failed to update bucket: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+async fn handle_lifecycle_command(command: &LifecycleCommands, database_url: &str) -> Result<()> {
+    let catalog = CatalogService::new(database_url).await?;
+
+    // Ensure database exists and is migrated
+    ghostbay_catalog::migrations::ensure_database_exists(database_url).await?;
+    ghostbay_catalog::migrations::run_migrations(catalog.pool()).await?;
+
+    let bucket_name = match command {
+        LifecycleCommands::Set { bucket, .. } | LifecycleCommands::Preview { bucket, .. } => bucket,
+    };
+    let bucket = match BucketRepository::new(catalog.pool().clone()).find_by_name(bucket_name).await {
+        Ok(Some(bucket)) => bucket,
+        Ok(None) => {
+            eprintln!("Bucket '{}' not found", bucket_name);
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Failed to load bucket: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let lifecycle_repo = LifecycleRepository::new(catalog.pool().clone());
+
+    match command {
+        LifecycleCommands::Set { file, .. } => {
+            let content = tokio::fs::read_to_string(file).await?;
+            let rules: Vec<LifecycleRule> = match serde_json::from_str(&content) {
+                Ok(rules) => rules,
+                Err(e) => {
+                    eprintln!("Invalid lifecycle rules: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            match lifecycle_repo.put_rules(bucket.id, &rules).await {
+                Ok(()) => {
+                    println!("Set {} lifecycle rule(s) on bucket '{}'", rules.len(), bucket_name);
+                }
+                Err(e) => {
+                    eprintln!("Failed to set lifecycle rules: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        LifecycleCommands::Preview { samples, .. } => {
+            let rules = match lifecycle_repo.get_rules(bucket.id).await {
+                Ok(rules) => rules,
+                Err(e) => {
+                    eprintln!("Failed to load lifecycle rules: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            if rules.is_empty() {
+                println!("No lifecycle rules configured on bucket '{}'", bucket_name);
+                return Ok(());
+            }
+
+            let now = chrono::Utc::now();
+            let mut evaluator = LifecycleEvaluator::new(catalog.pool().clone(), bucket.id, rules, now);
+            let mut report = LifecycleReport::new(evaluator.rules(), *samples);
+            loop {
+                match evaluator.next_batch().await {
+                    Ok(Some(matches)) => matches.iter().for_each(|matched| report.record(matched)),
+                    Ok(None) => break,
+                    Err(e) => {
+                        eprintln!("Failed to evaluate lifecycle rules: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+
+            println!("Lifecycle preview for '{}' as of {} (dry run, nothing deleted):", bucket_name, now.format("%Y-%m-%d %H:%M:%S UTC"));
+            for (rule, result) in evaluator.rules().iter().zip(&report.rules) {
+                let status = if rule.enabled { "" } else { " [disabled]" };
+                println!("  Rule {}{}: {} object(s), {} bytes", result.id, status, result.matched_objects, result.matched_bytes);
+                for key in &result.sample_keys {
+                    println!("    {}", key);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_provision_command(command: &ProvisionCommands, database_url: &str) -> Result<()> {
+    let catalog = CatalogService::new(database_url).await?;
+
+    // Ensure database exists and is migrated
+    ghostbay_catalog::migrations::ensure_database_exists(database_url).await?;
+    ghostbay_catalog::migrations::run_migrations(catalog.pool()).await?;
+
+    match command {
+        ProvisionCommands::Apply { file, check } => {
+            let provisioning = match ProvisioningFile::load(file) {
+                Ok(provisioning) => provisioning,
+                Err(e) => {
+                    eprintln!("{:#}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            let changes = match apply_provisioning(catalog.pool(), &provisioning, *check).await {
+                Ok(changes) => changes,
+                Err(e) => {
+                    eprintln!("Failed to apply provisioning: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            if changes.is_empty() {
+                println!("Everything matches {}", file.display());
+                return Ok(());
+            }
+
+            println!("{}:", if *check { "Drift found" } else { "Changes" });
+            for change in &changes {
+                let status = if change.applied { "applied" } else { "not applied" };
+                println!("  {} ({})", change, status);
+            }
+            if *check {
+                std::process::exit(1);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_db_command(command: &DbCommands, database_url: &str) -> Result<()> {
+    match command {
+        DbCommands::Backup { command: BackupCommands::Now { dir, keep } } => {
+            let catalog = CatalogService::new(database_url).await?;
+            let backup = match backup::backup_catalog(catalog.pool(), dir).await {
+                Ok(backup) => backup,
+                Err(e) => {
+                    eprintln!("Failed to back up the catalog: {:#}", e);
+                    std::process::exit(1);
+                }
+            };
+            println!("Backed up the catalog to {} ({} bytes)", backup.path.display(), backup.size);
+
+            if let Some(keep) = keep {
+                for pruned in backup::prune_backups(dir, (*keep).max(1))? {
+                    println!("Removed {}", pruned.path.display());
+                }
+            }
+        }
+        DbCommands::Backup { command: BackupCommands::List { dir } } => {
+            let backups = backup::list_backups(dir)?;
+            if backups.is_empty() {
+                println!("No catalog backups in {}", dir.display());
+                return Ok(());
+            }
+            for backup in backups.iter().rev() {
+                println!("{}  {:>12} bytes  {}", backup.created_at.to_rfc3339(), backup.size, backup.path.display());
+            }
+        }
+        DbCommands::Restore { snapshot } => {
+            let Some(database_file) = ghostbay_catalog::database_file(database_url) else {
+                eprintln!("{} is not a file-backed catalog", database_url);
+                std::process::exit(1);
+            };
+
+            // Held until the restore finishes, so a server cannot start on a half-restored catalog
+            let _lock = match ProcessLock::acquire(
+                &ghostbay_catalog::catalog_lock_path(&database_file),
+                LockMode::Exclusive,
+                false,
+            ) {
+                Ok(lock) => lock,
+                Err(e) => {
+                    eprintln!("Refusing to restore while the catalog is in use: {:#}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            match backup::restore_catalog(snapshot, &database_file).await {
+                Ok(previous) => {
+                    println!("Restored {} from {}", database_file.display(), snapshot.display());
+                    if let Some(previous) = previous {
+                        println!("The replaced catalog was kept as {}", previous.display());
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to restore the catalog: {:#}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_fsck_command(database_url: &str) -> Result<()> {
+    let catalog = CatalogService::new(database_url).await?;
+
+    // Ensure database exists and is migrated
+    ghostbay_catalog::migrations::ensure_database_exists(database_url).await?;
+    ghostbay_catalog::migrations::run_migrations(catalog.pool()).await?;
+
+    let objects = match ObjectRepository::new(catalog.pool().clone()).list_needing_repair().await {
+        Ok(objects) => objects,
+        Err(e) => {
+            eprintln!("Failed to list objects needing repair: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if objects.is_empty() {
+        println!("No objects need repair");
+        return Ok(());
+    }
+
+    let buckets = match BucketRepository::new(catalog.pool().clone()).list().await {
+        Ok(buckets) => buckets,
+        Err(e) => {
+            eprintln!("Failed to list buckets: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    println!("Objects with missing data files:");
+    for object in &objects {
+        let bucket_name = buckets
+            .iter()
+            .find(|bucket| bucket.id == object.bucket_id)
+            .map(|bucket| bucket.name.as_str())
+            .unwrap_or("<unknown bucket>");
+        println!("  {}/{} (storage path: {})", bucket_name, object.key, object.storage_path);
+    }
+    std::process::exit(1);
+}
+
+async fn handle_expire_command(database_url: &str, data_dir: &Path) -> Result<()> {
+    let catalog = CatalogService::new(database_url).await?;
+
+    // Ensure database exists and is migrated
+    ghostbay_catalog::migrations::ensure_database_exists(database_url).await?;
+    ghostbay_catalog::migrations::run_migrations(catalog.pool()).await?;
+
+    // Deleting needs no encryption key, so the plain engine opens encrypted
+    // data directories too
+    let config = StorageConfig {
+        data_dir: data_dir.to_path_buf(),
+        ..Default::default()
+    };
+    let storage = match LocalStorageEngine::new(config) {
+        Ok(storage) => storage,
+        Err(e) => {
+            eprintln!("Failed to open data directory {}: {}", data_dir.display(), e);
+            std::process::exit(1);
+        }
+    };
+
+    let object_repo = ObjectRepository::new(catalog.pool().clone());
+    let now = chrono::Utc::now();
+    let (mut objects, mut bytes) = (0u64, 0u64);
+    loop {
+        let expired = match object_repo.delete_expired(now, 1000).await {
+            Ok(expired) => expired,
+            Err(e) => {
+                eprintln!("Failed to delete expired objects: {}", e);
+                std::process::exit(1);
+            }
+        };
+        for object in &expired {
+            objects += 1;
+            bytes += object.size as u64;
+            let Some((bucket, key)) = object.storage_path.split_once('/') else {
+                eprintln!("Malformed storage path {}", object.storage_path);
+                continue;
+            };
+            if let Err(e) = storage.delete_object(bucket, key).await {
+                eprintln!("Failed to delete the data of {}: {}", object.storage_path, e);
+            }
+        }
+        if expired.len() < 1000 {
+            break;
+        }
+    }
+
+    println!("Deleted {} expired objects ({} bytes)", objects, bytes);
+    Ok(())
+}
@@ -0,0 +1,620 @@
+use anyhow::{anyhow, Result};
+use bytes::{Buf, Bytes, BytesMut};
+use futures::{StreamExt, TryStreamExt};
+use aes_gcm::aead::{AeadCore, AeadInPlace, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce, Tag};
+use std::sync::{Arc, Mutex};
+
+use crate::{traits::*, EtagAlgorithm, EtagHasher};
+
+// Layout feature recorded in the data directory once objects are encrypted,
+// so they are never read as plaintext or written without the key
+pub const ENCRYPTION_LAYOUT_FEATURE: &str = "aes-256-gcm";
+
+// On-disk format. Rather than one nonce prepended to a single ciphertext for
+// the whole object, objects are sealed in frames of up to SEGMENT_SIZE
+// plaintext bytes, each with its own 12-byte nonce prepended:
+//
+//   header (u32 BE: plaintext length, high bit set on the last frame)
+//   nonce (12 random bytes) | ciphertext | GCM tag (16 bytes)
+//
+// A single GCM message cannot be verified until its last byte is read, so a
+// whole-object ciphertext would have to be buffered in memory on every GET
+// before any plaintext could be released, and a Range request would have to
+// decrypt the object from the start. Frames are verified as they stream and
+// a range only reads the frames it covers.
+//
+// Each frame authenticates its index and header, so frames cannot be
+// reordered, dropped or cut off without failing to open. Every frame but the
+// last is full, which keeps plaintext sizes and range offsets computable from
+// the stored size alone. An empty object is a single empty final frame.
+const SEGMENT_SIZE: usize = 64 * 1024;
+const HEADER_LEN: usize = 4;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const FRAME_OVERHEAD: usize = HEADER_LEN + NONCE_LEN + TAG_LEN;
+const FULL_FRAME_LEN: u64 = (SEGMENT_SIZE + FRAME_OVERHEAD) as u64;
+const FINAL_FRAME: u32 = 1 << 31;
+
+// Wraps an engine so object data is encrypted with AES-256-GCM before it
+// reaches the inner engine and decrypted on the way back. ETags are hashed
+// over the plaintext, so they match what an unencrypted engine reports.
+pub struct EncryptedStorageEngine<E: StorageEngine> {
+    inner: E,
+    key: Arc<Aes256Gcm>,
+}
+
+impl<E: StorageEngine> EncryptedStorageEngine<E> {
+    pub fn new(inner: E, key: &[u8; 32]) -> Result<Self> {
+        let key = Aes256Gcm::new_from_slice(key).map_err(|_| anyhow!("Invalid AES-256-GCM key"))?;
+        Ok(Self {
+            inner,
+            key: Arc::new(key),
+        })
+    }
+
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    // Encrypts a plaintext stream, feeding the plaintext to `hasher` on the way
+    fn seal(&self, data: ByteStream, hasher: Arc<Mutex<Option<EtagHasher>>>) -> ByteStream {
+        let state = SealState {
+            input: data,
+            buffer: BytesMut::new(),
+            key: self.key.clone(),
+            index: 0,
+            hasher,
+            done: false,
+        };
+        Box::pin(futures::stream::try_unfold(state, |mut state| async move {
+            if state.done {
+                return Ok(None);
+            }
+            // Only cut a full frame once more data is known to follow, so the
+            // final frame is always the one that ends the object
+            while state.buffer.len() <= SEGMENT_SIZE {
+                match state.input.try_next().await? {
+                    Some(chunk) => {
+                        if let Some(hasher) = state.hasher.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+                            hasher.update(&chunk);
+                        }
+                        state.buffer.extend_from_slice(&chunk);
+                    }
+                    None => {
+                        let plaintext = state.buffer.split();
+                        let frame = seal_frame(&state.key, state.index, &plaintext, true)?;
+                        state.done = true;
+                        return Ok(Some((frame, state)));
+                    }
+                }
+            }
+            let plaintext = state.buffer.split_to(SEGMENT_SIZE);
+            let frame = seal_frame(&state.key, state.index, &plaintext, false)?;
+            state.index += 1;
+            Ok(Some((frame, state)))
+        }))
+    }
+
+    fn open(&self, data: ByteStream, frames: FrameSpan) -> ByteStream {
+        let state = OpenState {
+            input: data,
+            buffer: BytesMut::new(),
+            key: self.key.clone(),
+            frames,
+        };
+        Box::pin(futures::stream::try_unfold(state, |mut state| async move {
+            loop {
+                if let Some(frame_len) = next_frame_len(&state.buffer)? {
+                    let frame = state.buffer.split_to(frame_len);
+                    let plaintext = state.frames.open(&state.key, &frame)?;
+                    return Ok(Some((plaintext, state)));
+                }
+                match state.input.try_next().await? {
+                    Some(chunk) => state.buffer.extend_from_slice(&chunk),
+                    None => {
+                        if !state.buffer.is_empty() {
+                            return Err(anyhow!("Encrypted object ends in a partial frame"));
+                        }
+                        state.frames.finish()?;
+                        return Ok(None);
+                    }
+                }
+            }
+        }))
+    }
+
+    // Opens the first frame before a read is answered, so a wrong key or a
+    // damaged object fails the request instead of cutting its body short
+    async fn open_checked(&self, data: ByteStream, frames: FrameSpan) -> Result<ByteStream> {
+        let mut plaintext = self.open(data, frames);
+        let first = plaintext.try_next().await?;
+        Ok(Box::pin(futures::stream::iter(first.map(Ok)).chain(plaintext)))
+    }
+
+    fn etag_hasher(&self) -> Arc<Mutex<Option<EtagHasher>>> {
+        Arc::new(Mutex::new(Some(self.inner.etag_algorithm().hasher())))
+    }
+}
+
+fn finalize_etag(hasher: &Mutex<Option<EtagHasher>>) -> Result<String> {
+    hasher
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take()
+        .map(EtagHasher::finalize)
+        .ok_or_else(|| anyhow!("ETag already computed"))
+}
+
+struct SealState {
+    input: ByteStream,
+    buffer: BytesMut,
+    key: Arc<Aes256Gcm>,
+    index: u64,
+    hasher: Arc<Mutex<Option<EtagHasher>>>,
+    done: bool,
+}
+
+struct OpenState {
+    input: ByteStream,
+    buffer: BytesMut,
+    key: Arc<Aes256Gcm>,
+    frames: FrameSpan,
+}
+
+// Which frames a ciphertext stream is expected to hold
+enum FrameSpan {
+    // Frames next..end of an object with `total` frames
+    Object { next: u64, end: u64, total: u64 },
+    // A completed multipart upload before it is resealed: each part is its
+    // own frame sequence, numbered from 0 and ending in a final frame
+    Parts { next: u64, remaining: usize },
+}
+
+impl FrameSpan {
+    fn open(&mut self, key: &Aes256Gcm, frame: &[u8]) -> Result<Bytes> {
+        let is_final = frame_header(frame) & FINAL_FRAME != 0;
+        match self {
+            FrameSpan::Object { next, end, total } => {
+                if *next >= *end || is_final != (*next + 1 == *total) {
+                    return Err(anyhow!("Encrypted object has an unexpected frame at index {}", next));
+                }
+                let plaintext = open_frame(key, *next, frame)?;
+                *next += 1;
+                Ok(plaintext)
+            }
+            FrameSpan::Parts { next, remaining } => {
+                if *remaining == 0 {
+                    return Err(anyhow!("Encrypted upload has more parts than were completed"));
+                }
+                let plaintext = open_frame(key, *next, frame)?;
+                if is_final {
+                    *next = 0;
+                    *remaining -= 1;
+                } else {
+                    *next += 1;
+                }
+                Ok(plaintext)
+            }
+        }
+    }
+
+    fn finish(&self) -> Result<()> {
+        let complete = match self {
+            FrameSpan::Object { next, end, .. } => next == end,
+            FrameSpan::Parts { next, remaining } => *next == 0 && *remaining == 0,
+        };
+        if complete {
+            Ok(())
+        } else {
+            Err(anyhow!("Encrypted object is truncated"))
+        }
+    }
+}
+
+fn frame_header(frame: &[u8]) -> u32 {
+    u32::from_be_bytes([frame[0], frame[1], frame[2], frame[3]])
+}
+
+// Length of the frame at the start of `buffer`, once all of it is buffered
+fn next_frame_len(buffer: &[u8]) -> Result<Option<usize>> {
+    if buffer.len() < HEADER_LEN {
+        return Ok(None);
+    }
+    let plaintext_len = (frame_header(buffer) & !FINAL_FRAME) as usize;
+    if plaintext_len > SEGMENT_SIZE {
+        return Err(anyhow!("Encrypted frame claims {} bytes", plaintext_len));
+    }
+    let frame_len = plaintext_len + FRAME_OVERHEAD;
+    Ok((buffer.len() >= frame_len).then_some(frame_len))
+}
+
+fn frame_aad(index: u64, header: u32) -> [u8; 12] {
+    let mut aad = [0u8; 12];
+    aad[..8].copy_from_slice(&index.to_be_bytes());
+    aad[8..].copy_from_slice(&header.to_be_bytes());
+    aad
+}
+
+// Nonces are random: at 64 KiB per frame, one key covers petabytes before
+// collisions become a practical concern
+fn seal_frame(key: &Aes256Gcm, index: u64, plaintext: &[u8], is_final: bool) -> Result<Bytes> {
+    let header = plaintext.len() as u32 | if is_final { FINAL_FRAME } else { 0 };
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let mut ciphertext = plaintext.to_vec();
+    let tag = key
+        .encrypt_in_place_detached(&nonce, &frame_aad(index, header), &mut ciphertext)
+        .map_err(|_| anyhow!("Failed to encrypt object data"))?;
+
+    let mut frame = BytesMut::with_capacity(plaintext.len() + FRAME_OVERHEAD);
+    frame.extend_from_slice(&header.to_be_bytes());
+    frame.extend_from_slice(&nonce);
+    frame.extend_from_slice(&ciphertext);
+    frame.extend_from_slice(&tag);
+    Ok(frame.freeze())
+}
+
+fn open_frame(key: &Aes256Gcm, index: u64, frame: &[u8]) -> Result<Bytes> {
+    let header = frame_header(frame);
+    let nonce = Nonce::from_slice(&frame[HEADER_LEN..HEADER_LEN + NONCE_LEN]);
+    let (ciphertext, tag) = frame[HEADER_LEN + NONCE_LEN..].split_at(frame.len() - FRAME_OVERHEAD);
+    let mut plaintext = ciphertext.to_vec();
+    key.decrypt_in_place_detached(nonce, &frame_aad(index, header), &mut plaintext, Tag::from_slice(tag))
+        .map_err(|_| anyhow!("Encrypted frame {} failed authentication; wrong key or corrupted data", index))?;
+    Ok(Bytes::from(plaintext))
+}
+
+fn frame_count(stored_len: u64) -> u64 {
+    stored_len.div_ceil(FULL_FRAME_LEN)
+}
+
+fn plaintext_len(stored_len: u64) -> Result<u64> {
+    let frames = frame_count(stored_len);
+    let last_frame = stored_len - frames.saturating_sub(1) * FULL_FRAME_LEN;
+    if frames == 0 || last_frame < FRAME_OVERHEAD as u64 {
+        return Err(anyhow!("Stored object of {} bytes is not encrypted data", stored_len));
+    }
+    Ok(stored_len - frames * FRAME_OVERHEAD as u64)
+}
+
+fn stored_len(plaintext_len: u64) -> u64 {
+    let frames = plaintext_len.div_ceil(SEGMENT_SIZE as u64).max(1);
+    plaintext_len + frames * FRAME_OVERHEAD as u64
+}
+
+fn plaintext_metadata(mut metadata: ObjectMetadata) -> Result<ObjectMetadata> {
+    metadata.content_length = plaintext_len(metadata.content_length)?;
+    metadata.etag = format!("\"{}\"", metadata.content_length);
+    Ok(metadata)
+}
+
+#[async_trait::async_trait]
+impl<E: StorageEngine> StorageEngine for EncryptedStorageEngine<E> {
+    fn etag_algorithm(&self) -> EtagAlgorithm {
+        self.inner.etag_algorithm()
+    }
+
+    fn available_space(&self) -> Result<u64> {
+        self.inner.available_space()
+    }
+
+    fn is_encrypted(&self) -> bool {
+        true
+    }
+
+    #[tracing::instrument(skip(self, request), fields(bucket = %request.bucket, key = %request.key))]
+    async fn put_object(&self, request: PutObjectRequest) -> Result<String> {
+        let hasher = self.etag_hasher();
+        let sealed = PutObjectRequest {
+            bucket: request.bucket,
+            key: request.key,
+            content_type: request.content_type,
+            content_length: request.content_length.map(stored_len),
+            data: self.seal(request.data, hasher.clone()),
+        };
+        self.inner.put_object(sealed).await?;
+        finalize_etag(&hasher)
+    }
+
+    #[tracing::instrument(skip(self, request), fields(bucket = %request.bucket, key = %request.key, range = ?request.range))]
+    async fn get_object(&self, request: GetObjectRequest) -> Result<Option<GetObjectResponse>> {
+        let Some((start, end)) = request.range else {
+            let Some(response) = self.inner.get_object(request).await? else {
+                return Ok(None);
+            };
+            let total = frame_count(response.metadata.content_length);
+            let metadata = plaintext_metadata(response.metadata)?;
+            let data = self.open_checked(response.data, FrameSpan::Object { next: 0, end: total, total }).await?;
+            return Ok(Some(GetObjectResponse { metadata, data }));
+        };
+
+        // Read only the frames covering the range, then trim to the requested bytes
+        let Some(stored) = self.inner.head_object(&request.bucket, &request.key).await? else {
+            return Ok(None);
+        };
+        let total = frame_count(stored.content_length);
+        let len = plaintext_len(stored.content_length)?;
+        if start >= len {
+            return Err(anyhow!("Invalid range: {}- for object of {} bytes", start, len));
+        }
+        let end = end.unwrap_or(len - 1).min(len - 1);
+        if start > end {
+            return Err(anyhow!("Invalid range: {}-{}", start, end));
+        }
+
+        let first_frame = start / SEGMENT_SIZE as u64;
+        let last_frame = end / SEGMENT_SIZE as u64;
+        let stored_range = (
+            first_frame * FULL_FRAME_LEN,
+            Some(((last_frame + 1) * FULL_FRAME_LEN).min(stored.content_length) - 1),
+        );
+        let Some(response) = self
+            .inner
+            .get_object(GetObjectRequest {
+                bucket: request.bucket,
+                key: request.key,
+                range: Some(stored_range),
+            })
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let metadata = plaintext_metadata(response.metadata)?;
+        let frames = FrameSpan::Object { next: first_frame, end: last_frame + 1, total };
+        let mut skip = (start - first_frame * SEGMENT_SIZE as u64) as usize;
+        let mut remaining = (end - start + 1) as usize;
+        let data = self.open_checked(response.data, frames).await?.map_ok(move |mut chunk| {
+            let skipped = skip.min(chunk.len());
+            chunk.advance(skipped);
+            skip -= skipped;
+            chunk.truncate(remaining);
+            remaining -= chunk.len();
+            chunk
+        });
+
+        Ok(Some(GetObjectResponse {
+            metadata,
+            data: Box::pin(data),
+        }))
+    }
+
+    async fn head_object(&self, bucket: &str, key: &str) -> Result<Option<ObjectMetadata>> {
+        self.inner.head_object(bucket, key).await?.map(plaintext_metadata).transpose()
+    }
+
+    async fn delete_object(&self, bucket: &str, key: &str) -> Result<bool> {
+        self.inner.delete_object(bucket, key).await
+    }
+
+    // Copies are decrypted and sealed again, so no two objects share nonces
+    #[tracing::instrument(skip(self))]
+    async fn copy_object(&self, src_bucket: &str, src_key: &str, dst_bucket: &str, dst_key: &str) -> Result<String> {
+        let source = GetObjectRequest {
+            bucket: src_bucket.to_string(),
+            key: src_key.to_string(),
+            range: None,
+        };
+        let Some(source) = self.get_object(source).await? else {
+            return Err(anyhow!("Source object not found"));
+        };
+
+        self.put_object(PutObjectRequest {
+            bucket: dst_bucket.to_string(),
+            key: dst_key.to_string(),
+            content_type: source.metadata.content_type,
+            content_length: Some(source.metadata.content_length),
+            data: source.data,
+        })
+        .await
+    }
+
+    async fn create_multipart_upload(&self, request: CreateMultipartUploadRequest) -> Result<String> {
+        self.inner.create_multipart_upload(request).await
+    }
+
+    #[tracing::instrument(skip(self, request), fields(upload_id = %request.upload_id, part_number = request.part_number))]
+    async fn upload_part(&self, request: UploadPartRequest) -> Result<String> {
+        let hasher = self.etag_hasher();
+        let sealed = UploadPartRequest {
+            bucket: request.bucket,
+            key: request.key,
+            upload_id: request.upload_id,
+            part_number: request.part_number,
+            data: self.seal(request.data, hasher.clone()),
+        };
+        self.inner.upload_part(sealed).await?;
+        finalize_etag(&hasher)
+    }
+
+    // The inner engine joins the sealed parts; each still numbers its frames
+    // from 0, so the result is resealed as one frame sequence
+    #[tracing::instrument(skip(self, request), fields(upload_id = %request.upload_id, parts = request.parts.len()))]
+    async fn complete_multipart_upload(&self, request: CompleteMultipartUploadRequest) -> Result<String> {
+        let (bucket, key, parts) = (request.bucket.clone(), request.key.clone(), request.parts.len());
+        let etag = self.inner.complete_multipart_upload(request).await?;
+
+        let joined = GetObjectRequest {
+            bucket: bucket.clone(),
+            key: key.clone(),
+            range: None,
+        };
+        let Some(joined) = self.inner.get_object(joined).await? else {
+            return Err(anyhow!("Completed upload {}/{} is missing", bucket, key));
+        };
+        let plaintext = self.open(joined.data, FrameSpan::Parts { next: 0, remaining: parts });
+        let resealed = PutObjectRequest {
+            bucket,
+            key,
+            content_type: joined.metadata.content_type,
+            content_length: None,
+            data: self.seal(plaintext, Arc::new(Mutex::new(None))),
+        };
+        self.inner.put_object(resealed).await?;
+
+        Ok(etag)
+    }
+
+    async fn abort_multipart_upload(&self, bucket: &str, key: &str, upload_id: &str) -> Result<()> {
+        self.inner.abort_multipart_upload(bucket, key, upload_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryStorageEngine;
+
+    const KEY: [u8; 32] = [9; 32];
+
+    fn body(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    async fn put(engine: &impl StorageEngine, data: Vec<u8>) -> String {
+        engine
+            .put_object(PutObjectRequest {
+                bucket: "b".to_string(),
+                key: "k".to_string(),
+                content_type: "application/octet-stream".to_string(),
+                content_length: Some(data.len() as u64),
+                data: Box::pin(futures::stream::iter([Ok(Bytes::from(data))])),
+            })
+            .await
+            .unwrap()
+    }
+
+    async fn get(engine: &impl StorageEngine, range: Option<(u64, Option<u64>)>) -> Result<Vec<u8>> {
+        let request = GetObjectRequest { bucket: "b".to_string(), key: "k".to_string(), range };
+        let response = engine.get_object(request).await?.unwrap();
+        let chunks: Vec<Bytes> = response.data.try_collect().await?;
+        Ok(chunks.concat())
+    }
+
+    #[tokio::test]
+    async fn stores_nonce_prefixed_frames() {
+        let engine = EncryptedStorageEngine::new(MemoryStorageEngine::new(), &KEY).unwrap();
+        let plaintext = body(2 * SEGMENT_SIZE + 100);
+        let etag = put(&engine, plaintext.clone()).await;
+        let mut md5 = EtagAlgorithm::Md5.hasher();
+        md5.update(&plaintext);
+        assert_eq!(etag, md5.finalize());
+
+        let stored = get(engine.inner(), None).await.unwrap();
+        assert_eq!(stored.len() as u64, stored_len(plaintext.len() as u64));
+        let headers: Vec<u32> = (0..3).map(|frame| frame_header(&stored[frame * FULL_FRAME_LEN as usize..])).collect();
+        assert_eq!(headers, [SEGMENT_SIZE as u32, SEGMENT_SIZE as u32, 100 | FINAL_FRAME]);
+        assert!(!stored.windows(64).any(|window| window == &plaintext[..64]));
+
+        // Each frame opens with the AES-GCM primitive alone
+        let cipher = Aes256Gcm::new_from_slice(&KEY).unwrap();
+        let last = &stored[2 * FULL_FRAME_LEN as usize..];
+        let (nonce, sealed) = last[HEADER_LEN..].split_at(NONCE_LEN);
+        let mut opened = sealed[..sealed.len() - TAG_LEN].to_vec();
+        let tag = Tag::from_slice(&sealed[sealed.len() - TAG_LEN..]);
+        cipher
+            .decrypt_in_place_detached(Nonce::from_slice(nonce), &frame_aad(2, 100 | FINAL_FRAME), &mut opened, tag)
+            .unwrap();
+        assert_eq!(opened, plaintext[2 * SEGMENT_SIZE..]);
+    }
+
+    #[tokio::test]
+    async fn reads_whole_objects_and_ranges() {
+        let engine = EncryptedStorageEngine::new(MemoryStorageEngine::new(), &KEY).unwrap();
+        let plaintext = body(3 * SEGMENT_SIZE + 7);
+        put(&engine, plaintext.clone()).await;
+
+        assert_eq!(get(&engine, None).await.unwrap(), plaintext);
+        let start = SEGMENT_SIZE as u64 - 10;
+        let end = 2 * SEGMENT_SIZE as u64 + 10;
+        assert_eq!(get(&engine, Some((start, Some(end)))).await.unwrap(), plaintext[start as usize..=end as usize]);
+        assert_eq!(get(&engine, Some((3 * SEGMENT_SIZE as u64, None))).await.unwrap(), plaintext[3 * SEGMENT_SIZE..]);
+        assert_eq!(engine.head_object("b", "k").await.unwrap().unwrap().content_length, plaintext.len() as u64);
+
+        put(&engine, Vec::new()).await;
+        assert_eq!(get(&engine, None).await.unwrap(), Vec::<u8>::new());
+    }
+
+    #[tokio::test]
+    async fn rejects_a_wrong_key_or_a_cut_object() {
+        let engine = EncryptedStorageEngine::new(MemoryStorageEngine::new(), &KEY).unwrap();
+        put(&engine, body(SEGMENT_SIZE + 1)).await;
+
+        let stored = get(engine.inner(), None).await.unwrap();
+        let other = EncryptedStorageEngine::new(MemoryStorageEngine::new(), &[1; 32]).unwrap();
+        put(other.inner(), stored.clone()).await;
+        assert!(get(&other, None).await.is_err());
+
+        // Dropping the final frame leaves a stream of valid frames that never ends
+        put(engine.inner(), stored[..FULL_FRAME_LEN as usize].to_vec()).await;
+        assert!(get(&engine, None).await.is_err());
+    }
+
+    fn md5_hex(data: &[u8]) -> String {
+        let mut md5 = EtagAlgorithm::Md5.hasher();
+        md5.update(data);
+        md5.finalize()
+    }
+
+    #[tokio::test]
+    async fn md5_etags_are_of_the_plaintext_on_disk_too() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = crate::LocalStorageEngine::new(crate::StorageConfig {
+            data_dir: dir.path().join("data"),
+            temp_dir: dir.path().join("tmp"),
+            etag_algorithm: EtagAlgorithm::Md5,
+            encryption_key: None,
+        })
+        .unwrap();
+        let engine = EncryptedStorageEngine::new(local, &KEY).unwrap();
+        assert_eq!(engine.etag_algorithm(), EtagAlgorithm::Md5);
+
+        let plaintext = body(SEGMENT_SIZE + 100);
+        let etag = put(&engine, plaintext.clone()).await;
+        let ciphertext = get(engine.inner(), None).await.unwrap();
+        assert_eq!(etag, md5_hex(&plaintext));
+        assert_ne!(etag, md5_hex(&ciphertext));
+
+        // Parts, and the multipart ETag built from them
+        let upload_id = engine
+            .create_multipart_upload(CreateMultipartUploadRequest {
+                bucket: "b".to_string(),
+                key: "parts".to_string(),
+                content_type: "application/octet-stream".to_string(),
+                metadata: None,
+            })
+            .await
+            .unwrap();
+        let parts = [body(SEGMENT_SIZE + 1), body(7)];
+        let mut uploaded = Vec::new();
+        for (index, part) in parts.iter().enumerate() {
+            let etag = engine
+                .upload_part(UploadPartRequest {
+                    bucket: "b".to_string(),
+                    key: "parts".to_string(),
+                    upload_id: upload_id.clone(),
+                    part_number: index as i32 + 1,
+                    data: Box::pin(futures::stream::iter([Ok(Bytes::from(part.clone()))])),
+                })
+                .await
+                .unwrap();
+            assert_eq!(etag, md5_hex(part));
+            let digest = EtagAlgorithm::Md5.part_digest(&etag);
+            uploaded.push(MultipartUploadPart { part_number: index as i32 + 1, etag, size: part.len() as u64, digest });
+        }
+        let etag = engine
+            .complete_multipart_upload(CompleteMultipartUploadRequest {
+                bucket: "b".to_string(),
+                key: "parts".to_string(),
+                upload_id,
+                parts: uploaded,
+            })
+            .await
+            .unwrap();
+        assert_eq!(etag, EtagAlgorithm::Md5.combine(&[md5_hex(&parts[0]), md5_hex(&parts[1])]));
+        let request = GetObjectRequest { bucket: "b".to_string(), key: "parts".to_string(), range: None };
+        let stored: Vec<Bytes> = engine.get_object(request).await.unwrap().unwrap().data.try_collect().await.unwrap();
+        assert_eq!(stored.concat(), parts.concat());
+    }
+}
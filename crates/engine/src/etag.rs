@@ -0,0 +1,190 @@
+use anyhow::{anyhow, Result};
+use md5::Digest;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::MultipartUploadPart;
+
+// How object ETags are derived. Hashes are always taken over the bytes the
+// client sent, before any at-rest transform, so `md5` stays S3-compatible.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EtagAlgorithm {
+    #[default]
+    Md5,
+    Sha256Trunc,
+    Uuid,
+}
+
+impl EtagAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EtagAlgorithm::Md5 => "md5",
+            EtagAlgorithm::Sha256Trunc => "sha256-trunc",
+            EtagAlgorithm::Uuid => "uuid",
+        }
+    }
+
+    pub fn hasher(&self) -> EtagHasher {
+        match self {
+            EtagAlgorithm::Md5 => EtagHasher::Md5(md5::Md5::new()),
+            EtagAlgorithm::Sha256Trunc => EtagHasher::Sha256(sha2::Sha256::new()),
+            EtagAlgorithm::Uuid => EtagHasher::Uuid,
+        }
+    }
+
+    // Multipart ETags hash the concatenated binary digests of the parts, not
+    // their hex text, and append the part count, as S3 does. Clients such as
+    // the AWS CLI and rclone check this value after an upload.
+    pub fn combine(&self, part_etags: &[String]) -> String {
+        let digests: Vec<Vec<u8>> = part_etags.iter().map(|etag| etag_digest(etag)).collect();
+        self.combine_digests(&digests)
+    }
+
+    // As combine, over the digests recorded with the parts where there are
+    // any, so the hex ETags are not decoded again
+    pub fn combine_parts(&self, parts: &[MultipartUploadPart]) -> String {
+        let digests: Vec<Vec<u8>> = parts
+            .iter()
+            .map(|part| part.digest.clone().unwrap_or_else(|| etag_digest(&part.etag)))
+            .collect();
+        self.combine_digests(&digests)
+    }
+
+    // The binary digest behind a part's hex ETag, recorded with the part for
+    // combine_parts. UUID ETags are not digests of anything and have none.
+    pub fn part_digest(&self, etag: &str) -> Option<Vec<u8>> {
+        match self {
+            EtagAlgorithm::Uuid => None,
+            _ => decode_hex(etag.trim_matches('"')),
+        }
+    }
+
+    fn combine_digests(&self, digests: &[Vec<u8>]) -> String {
+        let mut hasher = self.hasher();
+        for digest in digests {
+            hasher.update(digest);
+        }
+        format!("{}-{}", hasher.finalize(), digests.len())
+    }
+}
+
+// An ETag that is not hex is hashed as its text
+fn etag_digest(etag: &str) -> Vec<u8> {
+    let etag = etag.trim_matches('"');
+    decode_hex(etag).unwrap_or_else(|| etag.as_bytes().to_vec())
+}
+
+impl FromStr for EtagAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "md5" => Ok(EtagAlgorithm::Md5),
+            "sha256-trunc" => Ok(EtagAlgorithm::Sha256Trunc),
+            "uuid" => Ok(EtagAlgorithm::Uuid),
+            other => Err(anyhow!("Unknown ETag algorithm: {} (expected md5, sha256-trunc or uuid)", other)),
+        }
+    }
+}
+
+pub enum EtagHasher {
+    Md5(md5::Md5),
+    Sha256(sha2::Sha256),
+    Uuid,
+}
+
+impl EtagHasher {
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            EtagHasher::Md5(hasher) => hasher.update(data),
+            EtagHasher::Sha256(hasher) => hasher.update(data),
+            EtagHasher::Uuid => {}
+        }
+    }
+
+    pub fn finalize(self) -> String {
+        match self {
+            EtagHasher::Md5(hasher) => format!("{:x}", hasher.finalize()),
+            // Truncated to 128 bits so it has the same shape as an MD5 ETag
+            EtagHasher::Sha256(hasher) => hex_prefix(&hasher.finalize(), 16),
+            EtagHasher::Uuid => Uuid::new_v4().simple().to_string(),
+        }
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.is_empty() || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect()
+}
+
+fn hex_prefix(bytes: &[u8], len: usize) -> String {
+    bytes.iter().take(len).map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // MD5s of the parts "a", "b" and "c"
+    const PARTS: [&str; 3] = [
+        "0cc175b9c0f1b6a831c399e269772661",
+        "92eb5ffee6ae2fec3ad71c777531578f",
+        "4a8a08f09d37b73795649038408b5f33",
+    ];
+
+    fn etags(parts: &[&str]) -> Vec<String> {
+        parts.iter().map(|part| part.to_string()).collect()
+    }
+
+    #[test]
+    fn combine_matches_s3_multipart_etag() {
+        // md5(md5("a") || md5("b") || md5("c")) over the raw digests, as S3
+        // reports for a three-part upload of those parts
+        assert_eq!(EtagAlgorithm::Md5.combine(&etags(&PARTS)), "4054c6f7787ecb7b323323155b0c0583-3");
+
+        let quoted: Vec<String> = PARTS.iter().map(|part| format!("\"{}\"", part)).collect();
+        assert_eq!(EtagAlgorithm::Md5.combine(&quoted), "4054c6f7787ecb7b323323155b0c0583-3");
+    }
+
+    #[test]
+    fn combine_depends_on_parts_and_their_order() {
+        let reversed = etags(&[PARTS[2], PARTS[1], PARTS[0]]);
+        assert_eq!(EtagAlgorithm::Md5.combine(&reversed), "d70e09892d7c70497f41b3d0059efb32-3");
+        // A single part is still hashed again, not reported as its own MD5
+        assert_eq!(EtagAlgorithm::Md5.combine(&etags(&PARTS[..1])), "b6ff9a06b7e20bcb2858c5b8ff744aea-1");
+    }
+
+    #[test]
+    fn combine_with_other_algorithms() {
+        assert_eq!(EtagAlgorithm::Sha256Trunc.combine(&etags(&PARTS)), "9b76aa0f91303c4baedf3f724e3a6e04-3");
+        let uuid = EtagAlgorithm::Uuid.combine(&etags(&PARTS));
+        assert!(uuid.ends_with("-3") && uuid.len() == 34, "{}", uuid);
+    }
+
+    #[test]
+    fn combine_parts_prefers_recorded_digests() {
+        let part = |part_number: i32, etag: &str, digest: Option<Vec<u8>>| MultipartUploadPart {
+            part_number,
+            etag: etag.to_string(),
+            size: 1,
+            digest,
+        };
+        let digests: Vec<Vec<u8>> = PARTS.iter().map(|etag| EtagAlgorithm::Md5.part_digest(etag).unwrap()).collect();
+        assert_eq!(digests[0], [0x0c, 0xc1, 0x75, 0xb9, 0xc0, 0xf1, 0xb6, 0xa8, 0x31, 0xc3, 0x99, 0xe2, 0x69, 0x77, 0x26, 0x61]);
+        assert_eq!(EtagAlgorithm::Md5.part_digest(&format!("\"{}\"", PARTS[0])), Some(digests[0].clone()));
+        assert_eq!(EtagAlgorithm::Uuid.part_digest(PARTS[0]), None);
+
+        let recorded: Vec<MultipartUploadPart> = (0..3).map(|i| part(i as i32 + 1, "", Some(digests[i].clone()))).collect();
+        assert_eq!(EtagAlgorithm::Md5.combine_parts(&recorded), "4054c6f7787ecb7b323323155b0c0583-3");
+        // Parts recorded before digests were kept fall back to their ETags
+        let mixed = [part(1, PARTS[0], None), part(2, "", Some(digests[1].clone())), part(3, PARTS[2], None)];
+        assert_eq!(EtagAlgorithm::Md5.combine_parts(&mixed), "4054c6f7787ecb7b323323155b0c0583-3");
+    }
+}
@@ -0,0 +1,228 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+pub const LAYOUT_FILE_NAME: &str = "ghostbay-layout.json";
+
+// Layout this build writes. Bump it whenever the on-disk format changes, and
+// ship a migration that ends with LayoutManifest::commit_migration.
+pub const CURRENT_LAYOUT_VERSION: u32 = 1;
+
+// Oldest layout a migration can still bring up to CURRENT_LAYOUT_VERSION
+pub const MIN_MIGRATABLE_LAYOUT_VERSION: u32 = 1;
+
+// Optional layout features this build can read. A data directory using any
+// other feature was written by a newer build and is refused.
+pub const SUPPORTED_LAYOUT_FEATURES: &[&str] = &[crate::ENCRYPTION_LAYOUT_FEATURE];
+
+// Manifest at the root of a data directory recording which on-disk format
+// it uses, so the engine never reads a layout it does not understand
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LayoutManifest {
+    pub layout_version: u32,
+    #[serde(default)]
+    pub features: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub instance_id: Uuid,
+}
+
+impl LayoutManifest {
+    // Reads the data directory's manifest, creating one for directories that
+    // predate it, and fails unless this build can use the layout as it is.
+    pub fn open(data_dir: &Path) -> Result<Self> {
+        let manifest = match Self::load(data_dir)? {
+            Some(manifest) => manifest,
+            None => {
+                // Directories written before the manifest existed use layout 1,
+                // which is also what a fresh directory starts with
+                let manifest = Self {
+                    layout_version: CURRENT_LAYOUT_VERSION,
+                    features: Vec::new(),
+                    created_at: Utc::now(),
+                    instance_id: Uuid::new_v4(),
+                };
+                manifest.write(data_dir)?;
+                tracing::info!(
+                    "Initialized storage layout version {} in {} (instance {})",
+                    manifest.layout_version,
+                    data_dir.display(),
+                    manifest.instance_id
+                );
+                manifest
+            }
+        };
+
+        manifest.check(data_dir)?;
+        Ok(manifest)
+    }
+
+    pub fn load(data_dir: &Path) -> Result<Option<Self>> {
+        let path = manifest_path(data_dir);
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        let manifest = serde_json::from_str(&content).with_context(|| format!("{} is not a valid layout manifest", path.display()))?;
+        Ok(Some(manifest))
+    }
+
+    fn check(&self, data_dir: &Path) -> Result<()> {
+        self.check_versions(data_dir, CURRENT_LAYOUT_VERSION, MIN_MIGRATABLE_LAYOUT_VERSION)
+    }
+
+    // Separate from the build's constants so every path can be exercised
+    // while only one layout version exists
+    fn check_versions(&self, data_dir: &Path, current: u32, min_migratable: u32) -> Result<()> {
+        if self.layout_version > current {
+            return Err(anyhow!(
+                "{} uses storage layout version {}, but this build of GhostBay only understands versions up to {}; \
+                 upgrade GhostBay to use this data directory",
+                data_dir.display(),
+                self.layout_version,
+                current
+            ));
+        }
+
+        if let Some(feature) = self.features.iter().find(|feature| !SUPPORTED_LAYOUT_FEATURES.contains(&feature.as_str())) {
+            return Err(anyhow!(
+                "{} uses the storage layout feature '{}', which this build of GhostBay does not support; \
+                 upgrade GhostBay to use this data directory",
+                data_dir.display(),
+                feature
+            ));
+        }
+
+        if self.layout_version < min_migratable {
+            return Err(anyhow!(
+                "{} uses storage layout version {}, which is too old for this build of GhostBay to migrate \
+                 (oldest supported: {}); migrate it with an older release first",
+                data_dir.display(),
+                self.layout_version,
+                min_migratable
+            ));
+        }
+
+        if self.layout_version < current {
+            return Err(anyhow!(
+                "{} uses storage layout version {}; migrate it to version {} before starting this build of GhostBay",
+                data_dir.display(),
+                self.layout_version,
+                current
+            ));
+        }
+
+        Ok(())
+    }
+
+    // Called by a migration as its last step, once every file is in the new
+    // format. The manifest is replaced atomically, so a migration interrupted
+    // before this point leaves the old version recorded and can be rerun.
+    pub fn commit_migration(&mut self, data_dir: &Path, layout_version: u32, features: Vec<String>) -> Result<()> {
+        let mut migrated = self.clone();
+        migrated.layout_version = layout_version;
+        migrated.features = features;
+        migrated.write(data_dir)?;
+
+        tracing::info!(
+            "Storage layout of {} migrated from version {} to {}",
+            data_dir.display(),
+            self.layout_version,
+            layout_version
+        );
+        *self = migrated;
+        Ok(())
+    }
+
+    // Write-then-rename so readers only ever see a complete manifest
+    fn write(&self, data_dir: &Path) -> Result<()> {
+        let path = manifest_path(data_dir);
+        let temp_path = data_dir.join(format!("{}.tmp", LAYOUT_FILE_NAME));
+
+        let mut file = std::fs::File::create(&temp_path)
+            .with_context(|| format!("Failed to create {}", temp_path.display()))?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&temp_path, &path).with_context(|| format!("Failed to write {}", path.display()))?;
+
+        // Persist the rename itself
+        #[cfg(unix)]
+        std::fs::File::open(data_dir)?.sync_all()?;
+
+        Ok(())
+    }
+}
+
+fn manifest_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(LAYOUT_FILE_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_manifest(data_dir: &Path, layout_version: u32, features: &[&str]) -> LayoutManifest {
+        let manifest = LayoutManifest {
+            layout_version,
+            features: features.iter().map(|feature| feature.to_string()).collect(),
+            created_at: Utc::now(),
+            instance_id: Uuid::new_v4(),
+        };
+        manifest.write(data_dir).unwrap();
+        manifest
+    }
+
+    #[test]
+    fn fresh_directory_is_initialized() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = LayoutManifest::open(dir.path()).unwrap();
+        assert_eq!((manifest.layout_version, manifest.features.len()), (CURRENT_LAYOUT_VERSION, 0));
+        assert_eq!(LayoutManifest::load(dir.path()).unwrap(), Some(manifest));
+        assert!(!dir.path().join(format!("{}.tmp", LAYOUT_FILE_NAME)).exists());
+    }
+
+    #[test]
+    fn matching_version_opens_unchanged() {
+        let dir = tempfile::tempdir().unwrap();
+        let written = write_manifest(dir.path(), CURRENT_LAYOUT_VERSION, &[crate::ENCRYPTION_LAYOUT_FEATURE]);
+        let modified = std::fs::metadata(dir.path().join(LAYOUT_FILE_NAME)).unwrap().modified().unwrap();
+
+        assert_eq!(LayoutManifest::open(dir.path()).unwrap(), written);
+        assert_eq!(LayoutManifest::open(dir.path()).unwrap(), written);
+        assert_eq!(std::fs::metadata(dir.path().join(LAYOUT_FILE_NAME)).unwrap().modified().unwrap(), modified);
+    }
+
+    // Only layout 1 exists so far, so the supported range is widened to 2..=3
+    #[test]
+    fn older_version_must_be_migrated_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let too_old = write_manifest(dir.path(), 1, &[]);
+        let error = too_old.check_versions(dir.path(), 3, 2).unwrap_err().to_string();
+        assert!(error.contains("too old"), "{}", error);
+
+        let mut manifest = write_manifest(dir.path(), 2, &[]);
+        let error = manifest.check_versions(dir.path(), 3, 2).unwrap_err().to_string();
+        assert!(error.contains("migrate it to version 3"), "{}", error);
+
+        manifest.commit_migration(dir.path(), 3, Vec::new()).unwrap();
+        manifest.check_versions(dir.path(), 3, 2).unwrap();
+        assert_eq!(LayoutManifest::load(dir.path()).unwrap(), Some(manifest));
+    }
+
+    #[test]
+    fn unknown_future_version_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let written = write_manifest(dir.path(), CURRENT_LAYOUT_VERSION + 1, &[]);
+        let error = LayoutManifest::open(dir.path()).unwrap_err().to_string();
+        assert!(error.contains("upgrade GhostBay"), "{}", error);
+        // Left as found, so the newer build can still use it
+        assert_eq!(LayoutManifest::load(dir.path()).unwrap(), Some(written));
+
+        write_manifest(dir.path(), CURRENT_LAYOUT_VERSION, &["sharded"]);
+        let error = LayoutManifest::open(dir.path()).unwrap_err().to_string();
+        assert!(error.contains("'sharded'"), "{}", error);
+    }
+}
@@ -1,19 +1,30 @@
-use anyhow::Result;
-use bytes::Bytes;
-use futures::Stream;
+use anyhow::{anyhow, Result};
 use std::path::PathBuf;
-use uuid::Uuid;
+use std::sync::Arc;
 
+pub mod encrypted;
+pub mod etag;
+pub mod layout;
 pub mod local;
+pub mod lock;
+pub mod memory;
 pub mod traits;
 
+pub use encrypted::*;
+pub use etag::*;
+pub use layout::*;
 pub use local::*;
+pub use lock::*;
+pub use memory::*;
 pub use traits::*;
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct StorageConfig {
     pub data_dir: PathBuf,
     pub temp_dir: PathBuf,
+    pub etag_algorithm: EtagAlgorithm,
+    // AES-256-GCM key for object data at rest; None stores plaintext
+    pub encryption_key: Option<[u8; 32]>,
 }
 
 impl Default for StorageConfig {
@@ -21,10 +32,42 @@ impl Default for StorageConfig {
         Self {
             data_dir: PathBuf::from("./data"),
             temp_dir: PathBuf::from("./tmp"),
+            etag_algorithm: EtagAlgorithm::default(),
+            encryption_key: None,
         }
     }
 }
 
-pub fn create_storage_engine(config: StorageConfig) -> Result<LocalStorageEngine> {
-    LocalStorageEngine::new(config)
-}
\ No newline at end of file
+// Written by hand so the key never ends up in logs
+impl std::fmt::Debug for StorageConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StorageConfig")
+            .field("data_dir", &self.data_dir)
+            .field("temp_dir", &self.temp_dir)
+            .field("etag_algorithm", &self.etag_algorithm)
+            .field("encryption_key", &self.encryption_key.map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+// Encryption is a property of the data directory: it is switched on while the
+// directory is still empty and recorded in its layout manifest, after which
+// the directory is only ever opened with a key.
+pub fn create_storage_engine(config: StorageConfig) -> Result<Arc<dyn StorageEngine>> {
+    let encryption_key = config.encryption_key;
+    let mut engine = LocalStorageEngine::new(config)?;
+    let encrypted = engine.layout().features.iter().any(|feature| feature == ENCRYPTION_LAYOUT_FEATURE);
+
+    match encryption_key {
+        Some(key) => {
+            if !encrypted {
+                engine.enable_layout_feature(ENCRYPTION_LAYOUT_FEATURE)?;
+            }
+            Ok(Arc::new(EncryptedStorageEngine::new(engine, &key)?))
+        }
+        None if encrypted => Err(anyhow!(
+            "The data directory holds encrypted objects; configure its encryption key to open it"
+        )),
+        None => Ok(Arc::new(engine)),
+    }
+}
@@ -1,31 +1,94 @@
 use anyhow::{anyhow, Result};
-use bytes::Bytes;
-use futures::{Stream, StreamExt, TryStreamExt};
-use md5::Digest;
+use futures::TryStreamExt;
 use std::path::{Path, PathBuf};
-use tokio::{fs, io::AsyncWriteExt};
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+};
 use uuid::Uuid;
 
 use crate::{
     traits::*,
+    EtagAlgorithm,
+    LayoutManifest,
     StorageConfig,
 };
 
 #[derive(Debug, Clone)]
 pub struct LocalStorageEngine {
     config: StorageConfig,
+    layout: LayoutManifest,
+    // data_dir with symlinks resolved; every object path must lie under it
+    root: PathBuf,
+}
+
+// Bucket names and keys become filesystem paths, so the engine checks them
+// itself rather than trust every caller to
+#[derive(Debug, thiserror::Error)]
+pub enum StoragePathError {
+    #[error("Invalid bucket name for storage: {0}")]
+    InvalidBucketName(String),
+    #[error("Invalid object key for storage: {0}")]
+    InvalidObjectKey(String),
+}
+
+// A key is a relative path that never climbs out of its bucket directory
+pub fn validate_storage_key(key: &str) -> std::result::Result<(), StoragePathError> {
+    if key.is_empty() || key.starts_with('/') || key.contains('\0') || key.split('/').any(|segment| segment == "..") {
+        return Err(StoragePathError::InvalidObjectKey(key.to_string()));
+    }
+    Ok(())
+}
+
+// A bucket is a single directory under the data directory
+pub fn validate_storage_bucket(bucket: &str) -> std::result::Result<(), StoragePathError> {
+    if bucket.is_empty() || bucket == "." || bucket == ".." || bucket.contains(['/', '\\', '\0']) {
+        return Err(StoragePathError::InvalidBucketName(bucket.to_string()));
+    }
+    Ok(())
 }
 
 impl LocalStorageEngine {
     pub fn new(config: StorageConfig) -> Result<Self> {
         std::fs::create_dir_all(&config.data_dir)?;
         std::fs::create_dir_all(&config.temp_dir)?;
+
+        // Refuse data directories in a layout this build cannot read
+        let layout = LayoutManifest::open(&config.data_dir)?;
+        let root = std::fs::canonicalize(&config.data_dir)?;
         
-        Ok(Self { config })
+        Ok(Self { config, layout, root })
     }
 
-    fn object_path(&self, bucket: &str, key: &str) -> PathBuf {
-        self.config.data_dir.join(bucket).join(key)
+    pub fn layout(&self) -> &LayoutManifest {
+        &self.layout
+    }
+
+    // Turns on an optional layout feature. Features change how objects are
+    // stored, so the data directory must not hold any object files yet.
+    pub fn enable_layout_feature(&mut self, feature: &str) -> Result<()> {
+        if contains_object_files(&self.config.data_dir, true)? {
+            return Err(anyhow!(
+                "Cannot enable the '{}' layout feature: {} already holds objects",
+                feature,
+                self.config.data_dir.display()
+            ));
+        }
+
+        let mut features = self.layout.features.clone();
+        features.push(feature.to_string());
+        let layout_version = self.layout.layout_version;
+        self.layout.commit_migration(&self.config.data_dir, layout_version, features)
+    }
+
+    fn object_path(&self, bucket: &str, key: &str) -> Result<PathBuf> {
+        validate_storage_bucket(bucket)?;
+        validate_storage_key(key)?;
+        let path = self.root.join(bucket).join(key);
+        if !path.starts_with(&self.root) {
+            return Err(StoragePathError::InvalidObjectKey(key.to_string()).into());
+        }
+        Ok(path)
     }
 
     fn temp_path(&self) -> PathBuf {
@@ -33,32 +96,68 @@ impl LocalStorageEngine {
     }
 
     async fn ensure_bucket_dir(&self, bucket: &str) -> Result<()> {
-        let bucket_dir = self.config.data_dir.join(bucket);
+        validate_storage_bucket(bucket)?;
+        let bucket_dir = self.root.join(bucket);
         fs::create_dir_all(&bucket_dir).await?;
         Ok(())
     }
 
-    async fn calculate_etag<S>(&self, mut stream: S) -> Result<String>
-    where
-        S: Stream<Item = Result<Bytes>> + Unpin,
-    {
-        use md5::{Digest, Md5};
-        
-        let mut hasher = Md5::new();
-        
-        while let Some(chunk) = stream.try_next().await? {
-            hasher.update(&chunk);
-        }
-        
-        Ok(format!("{:x}", hasher.finalize()))
+    // Locks the upload's metadata file: shared while a part is being written,
+    // exclusive while completing or aborting, so the upload dir is never removed
+    // underneath an in-flight write. Returns None if the upload does not exist.
+    #[tracing::instrument(skip(self))]
+    async fn lock_upload(&self, upload_id: &str, exclusive: bool) -> Result<Option<std::fs::File>> {
+        let metadata_path = self.config.temp_dir.join(upload_id).join("metadata.json");
+
+        tokio::task::spawn_blocking(move || {
+            let file = match std::fs::File::open(&metadata_path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
+
+            if exclusive {
+                file.lock()?;
+            } else {
+                file.lock_shared()?;
+            }
+
+            // The upload may have been removed while we waited for the lock
+            if !metadata_path.exists() {
+                return Ok(None);
+            }
+
+            Ok(Some(file))
+        })
+        .await?
     }
 }
 
+#[async_trait::async_trait]
 impl StorageEngine for LocalStorageEngine {
+    fn etag_algorithm(&self) -> EtagAlgorithm {
+        self.config.etag_algorithm
+    }
+
+    // Bytes available to this process on the filesystem holding the data directory
+    #[cfg(unix)]
+    fn available_space(&self) -> Result<u64> {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = std::ffi::CString::new(self.config.data_dir.as_os_str().as_bytes())?;
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        // SAFETY: `path` is NUL-terminated and `stat` is a valid out-pointer for the call
+        if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+    }
+
+    #[tracing::instrument(skip(self, request), fields(bucket = %request.bucket, key = %request.key, content_length = ?request.content_length))]
     async fn put_object(&self, request: PutObjectRequest) -> Result<String> {
         self.ensure_bucket_dir(&request.bucket).await?;
         
-        let object_path = self.object_path(&request.bucket, &request.key);
+        let object_path = self.object_path(&request.bucket, &request.key)?;
         let temp_path = self.temp_path();
         
         // Ensure parent directories exist
@@ -69,26 +168,33 @@ impl StorageEngine for LocalStorageEngine {
         // Write to temporary file first
         let mut temp_file = fs::File::create(&temp_path).await?;
         let mut stream = request.data;
-        use md5::Digest;
-        let mut hasher = md5::Md5::new();
+        let mut hasher = self.etag_algorithm().hasher();
         
-        while let Some(chunk) = stream.try_next().await? {
-            hasher.update(&chunk);
-            temp_file.write_all(&chunk).await?;
+        let written: Result<()> = async {
+            while let Some(chunk) = stream.try_next().await? {
+                hasher.update(&chunk);
+                temp_file.write_all(&chunk).await?;
+            }
+            temp_file.sync_all().await?;
+            Ok(())
         }
-        
-        temp_file.sync_all().await?;
+        .await;
         drop(temp_file);
+        // A body that fails mid-stream must not leave its partial file behind
+        if let Err(e) = written {
+            let _ = fs::remove_file(&temp_path).await;
+            return Err(e);
+        }
         
         // Atomic move to final location
         fs::rename(&temp_path, &object_path).await?;
         
-        let etag = format!("{:x}", hasher.finalize());
-        Ok(etag)
+        Ok(hasher.finalize())
     }
 
+    #[tracing::instrument(skip(self, request), fields(bucket = %request.bucket, key = %request.key, range = ?request.range))]
     async fn get_object(&self, request: GetObjectRequest) -> Result<Option<GetObjectResponse>> {
-        let object_path = self.object_path(&request.bucket, &request.key);
+        let object_path = self.object_path(&request.bucket, &request.key)?;
         
         if !object_path.exists() {
             return Ok(None);
@@ -98,25 +204,31 @@ impl StorageEngine for LocalStorageEngine {
         let last_modified = metadata.modified()?.into();
         
         let stream: ByteStream = if let Some((start, end)) = request.range {
-            let file = fs::File::open(&object_path).await?;
-            let end = end.unwrap_or(metadata.len() - 1).min(metadata.len() - 1);
+            let mut file = fs::File::open(&object_path).await?;
+            // An empty object has no satisfiable range, and len - 1 would underflow
+            let len = metadata.len();
+            if start >= len {
+                return Err(anyhow!("Invalid range: {}- for object of {} bytes", start, len));
+            }
+            let end = end.unwrap_or(len - 1).min(len - 1);
             
-            if start > end || start >= metadata.len() {
+            if start > end {
                 return Err(anyhow!("Invalid range: {}-{}", start, end));
             }
             
-            let reader = tokio::io::BufReader::new(file);
+            // Seek to the first byte and stop after the last; the chunks of
+            // a ReaderStream are not bytes, so they cannot be skipped or counted
+            file.seek(std::io::SeekFrom::Start(start)).await?;
+            let reader = tokio::io::BufReader::new(file.take(end - start + 1));
             let stream = tokio_util::io::ReaderStream::new(reader)
-                .map_err(|e| anyhow::Error::from(e))
-                .skip(start as usize)
-                .take((end - start + 1) as usize);
+                .map_err(anyhow::Error::from);
             
             Box::pin(stream)
         } else {
             let file = fs::File::open(&object_path).await?;
             let reader = tokio::io::BufReader::new(file);
             let stream = tokio_util::io::ReaderStream::new(reader)
-                .map_err(|e| anyhow::Error::from(e));
+                .map_err(anyhow::Error::from);
             
             Box::pin(stream)
         };
@@ -137,8 +249,9 @@ impl StorageEngine for LocalStorageEngine {
         }))
     }
 
+    #[tracing::instrument(skip(self))]
     async fn head_object(&self, bucket: &str, key: &str) -> Result<Option<ObjectMetadata>> {
-        let object_path = self.object_path(bucket, key);
+        let object_path = self.object_path(bucket, key)?;
         
         if !object_path.exists() {
             return Ok(None);
@@ -156,8 +269,9 @@ impl StorageEngine for LocalStorageEngine {
         }))
     }
 
+    #[tracing::instrument(skip(self))]
     async fn delete_object(&self, bucket: &str, key: &str) -> Result<bool> {
-        let object_path = self.object_path(bucket, key);
+        let object_path = self.object_path(bucket, key)?;
         
         if !object_path.exists() {
             return Ok(false);
@@ -167,9 +281,10 @@ impl StorageEngine for LocalStorageEngine {
         Ok(true)
     }
 
+    #[tracing::instrument(skip(self))]
     async fn copy_object(&self, src_bucket: &str, src_key: &str, dst_bucket: &str, dst_key: &str) -> Result<String> {
-        let src_path = self.object_path(src_bucket, src_key);
-        let dst_path = self.object_path(dst_bucket, dst_key);
+        let src_path = self.object_path(src_bucket, src_key)?;
+        let dst_path = self.object_path(dst_bucket, dst_key)?;
         
         if !src_path.exists() {
             return Err(anyhow!("Source object not found"));
@@ -181,15 +296,27 @@ impl StorageEngine for LocalStorageEngine {
             fs::create_dir_all(parent).await?;
         }
         
-        fs::copy(&src_path, &dst_path).await?;
+        // Stream through the ETag hasher so copies follow the configured algorithm
+        let temp_path = self.temp_path();
+        let mut reader = tokio_util::io::ReaderStream::new(fs::File::open(&src_path).await?);
+        let mut temp_file = fs::File::create(&temp_path).await?;
+        let mut hasher = self.etag_algorithm().hasher();
         
-        let metadata = fs::metadata(&dst_path).await?;
-        let etag = format!("\"{}\"", metadata.len());
+        while let Some(chunk) = reader.try_next().await? {
+            hasher.update(&chunk);
+            temp_file.write_all(&chunk).await?;
+        }
+        
+        temp_file.sync_all().await?;
+        drop(temp_file);
         
-        Ok(etag)
+        fs::rename(&temp_path, &dst_path).await?;
+        
+        Ok(hasher.finalize())
     }
     
     // Multipart upload operations
+    #[tracing::instrument(skip(self, request), fields(bucket = %request.bucket, key = %request.key))]
     async fn create_multipart_upload(&self, request: CreateMultipartUploadRequest) -> Result<String> {
         self.ensure_bucket_dir(&request.bucket).await?;
         
@@ -215,39 +342,52 @@ impl StorageEngine for LocalStorageEngine {
         Ok(upload_id)
     }
     
+    #[tracing::instrument(skip(self, request), fields(bucket = %request.bucket, key = %request.key, upload_id = %request.upload_id, part_number = request.part_number))]
     async fn upload_part(&self, request: UploadPartRequest) -> Result<String> {
         let upload_dir = self.config.temp_dir.join(&request.upload_id);
         
-        if !upload_dir.exists() {
+        let Some(_lock) = self.lock_upload(&request.upload_id, false).await? else {
             return Err(anyhow!("Multipart upload not found: {}", request.upload_id));
-        }
+        };
         
-        // Write part to a numbered file
+        // Write to a unique temp name so concurrent uploads of the same part
+        // never share a file, then atomically move it to the numbered name
         let part_path = upload_dir.join(format!("part_{:05}", request.part_number));
-        let mut part_file = fs::File::create(&part_path).await?;
-        let mut stream = request.data;
+        let temp_path = upload_dir.join(format!("part_{:05}.{}.tmp", request.part_number, Uuid::new_v4()));
         
-        use md5::Digest;
-        let mut hasher = md5::Md5::new();
-        
-        while let Some(chunk) = stream.try_next().await? {
-            hasher.update(&chunk);
-            part_file.write_all(&chunk).await?;
+        let result = async {
+            let mut part_file = fs::File::create(&temp_path).await?;
+            let mut stream = request.data;
+            let mut hasher = self.etag_algorithm().hasher();
+            
+            while let Some(chunk) = stream.try_next().await? {
+                hasher.update(&chunk);
+                part_file.write_all(&chunk).await?;
+            }
+            
+            part_file.sync_all().await?;
+            drop(part_file);
+            
+            fs::rename(&temp_path, &part_path).await?;
+            Ok(hasher.finalize())
         }
+        .await;
         
-        part_file.sync_all().await?;
-        drop(part_file);
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path).await;
+        }
         
-        let etag = format!("{:x}", hasher.finalize());
-        Ok(etag)
+        result
     }
     
+    #[tracing::instrument(skip(self, request), fields(bucket = %request.bucket, key = %request.key, upload_id = %request.upload_id, parts = request.parts.len()))]
     async fn complete_multipart_upload(&self, request: CompleteMultipartUploadRequest) -> Result<String> {
         let upload_dir = self.config.temp_dir.join(&request.upload_id);
         
-        if !upload_dir.exists() {
+        // Waits for in-flight part writes, so only fully written parts are read
+        let Some(_lock) = self.lock_upload(&request.upload_id, true).await? else {
             return Err(anyhow!("Multipart upload not found: {}", request.upload_id));
-        }
+        };
         
         // Read upload metadata
         let metadata_path = upload_dir.join("metadata.json");
@@ -275,18 +415,16 @@ impl StorageEngine for LocalStorageEngine {
         }
         
         // Create the final object by concatenating parts
-        let final_path = self.object_path(&request.bucket, &request.key);
+        let final_path = self.object_path(&request.bucket, &request.key)?;
         if let Some(parent) = final_path.parent() {
             fs::create_dir_all(parent).await?;
         }
         
         let mut final_file = fs::File::create(&final_path).await?;
-        let mut combined_hasher = md5::Md5::new();
         
         for part in &sorted_parts {
             let part_path = upload_dir.join(format!("part_{:05}", part.part_number));
             let part_data = fs::read(&part_path).await?;
-            combined_hasher.update(&part_data);
             final_file.write_all(&part_data).await?;
         }
         
@@ -296,20 +434,15 @@ impl StorageEngine for LocalStorageEngine {
         // Clean up temp directory
         fs::remove_dir_all(&upload_dir).await?;
         
-        // Calculate final ETag (for multipart, it's different from simple MD5)
-        // AWS uses: MD5 of concatenated MD5s + "-" + part count
-        let mut etag_parts = Vec::new();
-        for part in &sorted_parts {
-            etag_parts.extend_from_slice(part.etag.as_bytes());
-        }
-        let final_etag = format!("{:x}-{}", md5::Md5::digest(&etag_parts), sorted_parts.len());
-        
-        Ok(final_etag)
+        // Calculate final ETag (for multipart, it's different from a single-part hash)
+        Ok(self.etag_algorithm().combine_parts(&sorted_parts))
     }
     
+    #[tracing::instrument(skip(self))]
     async fn abort_multipart_upload(&self, _bucket: &str, _key: &str, upload_id: &str) -> Result<()> {
         let upload_dir = self.config.temp_dir.join(upload_id);
         
+        let _lock = self.lock_upload(upload_id, true).await?;
         if upload_dir.exists() {
             fs::remove_dir_all(&upload_dir).await?;
         }
@@ -318,6 +451,24 @@ impl StorageEngine for LocalStorageEngine {
     }
 }
 
+// Any file under the data directory other than its manifest and lock file
+fn contains_object_files(dir: &Path, top_level: bool) -> Result<bool> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            if contains_object_files(&entry.path(), false)? {
+                return Ok(true);
+            }
+            continue;
+        }
+        let reserved = matches!(entry.file_name().to_str(), Some(crate::LAYOUT_FILE_NAME | crate::LOCK_FILE_NAME));
+        if !(top_level && reserved) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
 impl LocalStorageEngine {
     fn guess_content_type(&self, key: &str) -> String {
         let extension = Path::new(key)
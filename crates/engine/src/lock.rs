@@ -0,0 +1,154 @@
+use anyhow::{anyhow, Context, Result};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+pub const LOCK_FILE_NAME: &str = "ghostbay.lock";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    // Held by a running server; excludes everything else
+    Exclusive,
+    // Held by offline tools that only read, so several can run together
+    Shared,
+}
+
+// Advisory flock guarding a data directory or catalog file against a second
+// process. The lock is released by the OS when the holder exits, however it
+// exits; the pid/start-time/version record inside names the holder in errors
+// and flags a free lock whose recorded process is somehow still alive.
+#[derive(Debug)]
+pub struct ProcessLock {
+    file: File,
+    path: PathBuf,
+    mode: LockMode,
+}
+
+impl ProcessLock {
+    // `force` takes over a lock file whose flock is free but whose recorded
+    // process still appears to be running, e.g. after a reused pid.
+    pub fn acquire(path: &Path, mode: LockMode, force: bool) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("Failed to open lock file {}", path.display()))?;
+
+        let locked = match mode {
+            LockMode::Exclusive => file.try_lock(),
+            LockMode::Shared => file.try_lock_shared(),
+        };
+        match locked {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let holder = read_record(&mut file);
+                return Err(anyhow!(
+                    "{} is locked by another GhostBay process ({}); stop it before starting this one",
+                    path.display(),
+                    describe(&holder)
+                ));
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("Failed to lock {}", path.display()));
+            }
+        }
+
+        if mode == LockMode::Exclusive {
+            let previous = read_record(&mut file);
+            if !previous.trim().is_empty() {
+                let pid = record_field(&previous, "pid").and_then(|pid| pid.parse::<u32>().ok());
+                if let Some(pid) = pid
+                    && pid != std::process::id()
+                    && process_running(pid)
+                    && !force
+                {
+                    return Err(anyhow!(
+                        "{} was left by process {} which still appears to be running ({}); \
+                         if that is not a GhostBay process using this path, restart with --force-unlock",
+                        path.display(),
+                        pid,
+                        describe(&previous)
+                    ));
+                }
+                tracing::warn!("Taking over stale lock {} ({})", path.display(), describe(&previous));
+            }
+
+            let record = format!(
+                "pid={}\nstarted_at={}\nversion={}\n",
+                std::process::id(),
+                chrono::Utc::now().to_rfc3339(),
+                env!("CARGO_PKG_VERSION")
+            );
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(record.as_bytes())?;
+            file.sync_all()?;
+        }
+
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+            mode,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ProcessLock {
+    fn drop(&mut self) {
+        // Clear the record on a clean shutdown; the flock goes with the file handle
+        if self.mode == LockMode::Exclusive {
+            let _ = self.file.set_len(0);
+        }
+    }
+}
+
+fn read_record(file: &mut File) -> String {
+    let mut record = String::new();
+    let _ = file.seek(SeekFrom::Start(0));
+    let _ = file.read_to_string(&mut record);
+    record
+}
+
+fn record_field<'a>(record: &'a str, name: &str) -> Option<&'a str> {
+    record
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.trim())
+}
+
+fn describe(record: &str) -> String {
+    match (record_field(record, "pid"), record_field(record, "started_at"), record_field(record, "version")) {
+        (Some(pid), Some(started_at), Some(version)) => {
+            format!("pid {}, started {}, version {}", pid, started_at, version)
+        }
+        _ => "holder unknown".to_string(),
+    }
+}
+
+// Zombies keep their /proc entry until reaped but no longer hold anything
+#[cfg(target_os = "linux")]
+fn process_running(pid: u32) -> bool {
+    match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        Ok(stat) => stat
+            .rsplit_once(')')
+            .is_some_and(|(_, rest)| !rest.trim_start().starts_with('Z')),
+        Err(_) => false,
+    }
+}
+
+// Without a cheap liveness check, trust the free flock
+#[cfg(not(target_os = "linux"))]
+fn process_running(_pid: u32) -> bool {
+    false
+}
@@ -0,0 +1,217 @@
+use anyhow::{anyhow, Result};
+use bytes::{Bytes, BytesMut};
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use std::collections::{BTreeMap, HashMap};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::{
+    ByteStream, CompleteMultipartUploadRequest, CreateMultipartUploadRequest, EtagAlgorithm, GetObjectRequest,
+    GetObjectResponse, ObjectMetadata, PutObjectRequest, StorageEngine, UploadPartRequest,
+};
+
+// Keeps every object in memory, for tests that exercise the API without a
+// data directory. Nothing survives the process.
+pub struct MemoryStorageEngine {
+    etag_algorithm: EtagAlgorithm,
+    objects: RwLock<HashMap<(String, String), MemoryObject>>,
+    uploads: RwLock<HashMap<String, MemoryUpload>>,
+}
+
+struct MemoryObject {
+    data: Bytes,
+    content_type: String,
+    etag: String,
+    last_modified: DateTime<Utc>,
+}
+
+struct MemoryUpload {
+    bucket: String,
+    key: String,
+    content_type: String,
+    parts: BTreeMap<i32, Bytes>,
+}
+
+impl MemoryStorageEngine {
+    pub fn new() -> Self {
+        Self::with_etag_algorithm(EtagAlgorithm::default())
+    }
+
+    pub fn with_etag_algorithm(etag_algorithm: EtagAlgorithm) -> Self {
+        Self {
+            etag_algorithm,
+            objects: RwLock::new(HashMap::new()),
+            uploads: RwLock::new(HashMap::new()),
+        }
+    }
+
+    // Drains a request body, hashing it for the ETag on the way
+    async fn collect(&self, mut data: ByteStream) -> Result<(Bytes, String)> {
+        let mut buffer = BytesMut::new();
+        let mut hasher = self.etag_algorithm.hasher();
+        while let Some(chunk) = data.try_next().await? {
+            hasher.update(&chunk);
+            buffer.extend_from_slice(&chunk);
+        }
+        Ok((buffer.freeze(), hasher.finalize()))
+    }
+
+    async fn insert(&self, bucket: &str, key: &str, data: Bytes, content_type: String, etag: String) {
+        let object = MemoryObject {
+            data,
+            content_type,
+            etag,
+            last_modified: Utc::now(),
+        };
+        self.objects.write().await.insert((bucket.to_string(), key.to_string()), object);
+    }
+}
+
+impl Default for MemoryStorageEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryObject {
+    fn metadata(&self) -> ObjectMetadata {
+        ObjectMetadata {
+            content_type: self.content_type.clone(),
+            content_length: self.data.len() as u64,
+            etag: format!("\"{}\"", self.etag),
+            last_modified: self.last_modified,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl StorageEngine for MemoryStorageEngine {
+    fn etag_algorithm(&self) -> EtagAlgorithm {
+        self.etag_algorithm
+    }
+
+    async fn put_object(&self, request: PutObjectRequest) -> Result<String> {
+        let (data, etag) = self.collect(request.data).await?;
+        self.insert(&request.bucket, &request.key, data, request.content_type, etag.clone()).await;
+        Ok(etag)
+    }
+
+    async fn get_object(&self, request: GetObjectRequest) -> Result<Option<GetObjectResponse>> {
+        let objects = self.objects.read().await;
+        let Some(object) = objects.get(&(request.bucket, request.key)) else {
+            return Ok(None);
+        };
+
+        // Same range rules as the local engine: the end is clamped to the
+        // object, a start past it is an error
+        let len = object.data.len() as u64;
+        let data = match request.range {
+            Some((start, end)) => {
+                if start >= len {
+                    return Err(anyhow!("Invalid range: {}- for object of {} bytes", start, len));
+                }
+                let end = end.unwrap_or(len - 1).min(len - 1);
+                if start > end {
+                    return Err(anyhow!("Invalid range: {}-{}", start, end));
+                }
+                object.data.slice(start as usize..=end as usize)
+            }
+            None => object.data.clone(),
+        };
+
+        Ok(Some(GetObjectResponse {
+            metadata: object.metadata(),
+            data: Box::pin(futures::stream::once(async move { Ok(data) })),
+        }))
+    }
+
+    async fn head_object(&self, bucket: &str, key: &str) -> Result<Option<ObjectMetadata>> {
+        let objects = self.objects.read().await;
+        Ok(objects.get(&(bucket.to_string(), key.to_string())).map(MemoryObject::metadata))
+    }
+
+    async fn delete_object(&self, bucket: &str, key: &str) -> Result<bool> {
+        Ok(self.objects.write().await.remove(&(bucket.to_string(), key.to_string())).is_some())
+    }
+
+    async fn copy_object(&self, src_bucket: &str, src_key: &str, dst_bucket: &str, dst_key: &str) -> Result<String> {
+        let (data, content_type) = {
+            let objects = self.objects.read().await;
+            let source = objects
+                .get(&(src_bucket.to_string(), src_key.to_string()))
+                .ok_or_else(|| anyhow!("Source object not found"))?;
+            (source.data.clone(), source.content_type.clone())
+        };
+
+        // Rehashed so copies follow the configured algorithm, as on disk
+        let mut hasher = self.etag_algorithm.hasher();
+        hasher.update(&data);
+        let etag = hasher.finalize();
+        self.insert(dst_bucket, dst_key, data, content_type, etag.clone()).await;
+        Ok(etag)
+    }
+
+    async fn create_multipart_upload(&self, request: CreateMultipartUploadRequest) -> Result<String> {
+        let upload_id = format!("mpu_{}", Uuid::new_v4());
+        let upload = MemoryUpload {
+            bucket: request.bucket,
+            key: request.key,
+            content_type: request.content_type,
+            parts: BTreeMap::new(),
+        };
+        self.uploads.write().await.insert(upload_id.clone(), upload);
+        Ok(upload_id)
+    }
+
+    async fn upload_part(&self, request: UploadPartRequest) -> Result<String> {
+        if !self.uploads.read().await.contains_key(&request.upload_id) {
+            return Err(anyhow!("Multipart upload not found: {}", request.upload_id));
+        }
+        let (data, etag) = self.collect(request.data).await?;
+
+        // The upload may have been completed or aborted while the body arrived
+        let mut uploads = self.uploads.write().await;
+        let upload = uploads
+            .get_mut(&request.upload_id)
+            .ok_or_else(|| anyhow!("Multipart upload not found: {}", request.upload_id))?;
+        upload.parts.insert(request.part_number, data);
+        Ok(etag)
+    }
+
+    async fn complete_multipart_upload(&self, request: CompleteMultipartUploadRequest) -> Result<String> {
+        let mut uploads = self.uploads.write().await;
+        let upload = uploads
+            .get(&request.upload_id)
+            .ok_or_else(|| anyhow!("Multipart upload not found: {}", request.upload_id))?;
+        if upload.bucket != request.bucket || upload.key != request.key {
+            return Err(anyhow!("Bucket/key mismatch in multipart upload"));
+        }
+
+        let mut sorted_parts = request.parts.clone();
+        sorted_parts.sort_by_key(|part| part.part_number);
+
+        let mut data = BytesMut::new();
+        for part in &sorted_parts {
+            let part_data = upload
+                .parts
+                .get(&part.part_number)
+                .ok_or_else(|| anyhow!("Part {} not found", part.part_number))?;
+            data.extend_from_slice(part_data);
+        }
+
+        let Some(upload) = uploads.remove(&request.upload_id) else {
+            return Err(anyhow!("Multipart upload not found: {}", request.upload_id));
+        };
+        drop(uploads);
+
+        let etag = self.etag_algorithm.combine_parts(&sorted_parts);
+        self.insert(&request.bucket, &request.key, data.freeze(), upload.content_type, etag.clone()).await;
+        Ok(etag)
+    }
+
+    async fn abort_multipart_upload(&self, _bucket: &str, _key: &str, upload_id: &str) -> Result<()> {
+        self.uploads.write().await.remove(upload_id);
+        Ok(())
+    }
+}
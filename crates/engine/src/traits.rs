@@ -2,7 +2,8 @@ use anyhow::Result;
 use bytes::Bytes;
 use futures::Stream;
 use std::pin::Pin;
-use uuid::Uuid;
+
+use crate::EtagAlgorithm;
 
 pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes>> + Send>>;
 
@@ -39,6 +40,9 @@ pub struct MultipartUploadPart {
     pub part_number: i32,
     pub etag: String,
     pub size: u64,
+    // The binary digest the hex ETag was made from, as recorded with the
+    // part; None to have it decoded from the ETag
+    pub digest: Option<Vec<u8>>,
 }
 
 pub struct CreateMultipartUploadRequest {
@@ -63,7 +67,23 @@ pub struct CompleteMultipartUploadRequest {
     pub parts: Vec<MultipartUploadPart>,
 }
 
+// Object safe, so servers hold whichever engine they were configured with
+// as an Arc<dyn StorageEngine>
+#[async_trait::async_trait]
 pub trait StorageEngine: Send + Sync {
+    // Algorithm used for every ETag this engine computes
+    fn etag_algorithm(&self) -> EtagAlgorithm;
+
+    // Bytes still available for object data, where the engine can tell
+    fn available_space(&self) -> Result<u64> {
+        Err(anyhow::anyhow!("free space is not reported by this engine"))
+    }
+
+    // Whether object data is encrypted at rest
+    fn is_encrypted(&self) -> bool {
+        false
+    }
+    
     async fn put_object(&self, request: PutObjectRequest) -> Result<String>;
     
     async fn get_object(&self, request: GetObjectRequest) -> Result<Option<GetObjectResponse>>;
@@ -1,20 +1,32 @@
-use anyhow::Result;
-use ghostbay_api::{create_router, AppState};
-use ghostbay_auth::{AuthService, CreateAccessKeyRequest};
-use ghostbay_catalog::CatalogService;
-use ghostbay_engine::{create_storage_engine, StorageConfig};
+use anyhow::{Context, Result};
+use ghostbay_api::health::{refresh_health_stats, HealthState};
+use ghostbay_admin_ui::{console_router, ConsoleSessions};
+use ghostbay_api::{create_router, middleware::RateLimiter, AppState, MultipartLimits, RegionRouting};
+use ghostbay_auth::{apply_provisioning, AccessKeyRepository, AuthService, CreateAccessKeyRequest, ProvisioningFile};
+use ghostbay_catalog::{AuditRepository, CatalogService};
+use ghostbay_engine::{create_storage_engine, EtagAlgorithm, LockMode, ProcessLock, StorageConfig, LOCK_FILE_NAME};
 use serde::{Deserialize, Serialize};
-use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+use std::{collections::HashMap, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 use tokio::net::TcpListener;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::WithExportConfig;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use axum::{
     extract::Request,
-    http::Uri,
+    http::{HeaderValue, Uri},
     middleware::{self, Next},
     response::{IntoResponse, Redirect, Response},
     Router,
 };
 use axum_server::tls_rustls::RustlsConfig;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tower_http::catch_panic::CatchPanicLayer;
+
+const USAGE_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+const HEALTH_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+const AUDIT_PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+const RATE_LIMIT_PRUNE_INTERVAL: Duration = Duration::from_secs(60);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
@@ -25,6 +37,119 @@ pub struct ServerConfig {
     pub temp_dir: PathBuf,
     pub log_level: String,
     pub tls: Option<TlsConfig>,
+    // Region this node serves, and the endpoints of nodes serving other regions
+    #[serde(default = "default_region")]
+    pub region: String,
+    #[serde(default)]
+    pub region_endpoints: HashMap<String, String>,
+    #[serde(default)]
+    pub etag_algorithm: EtagAlgorithm,
+    // Buckets and access keys to create or correct on every boot
+    #[serde(default)]
+    pub provisioning_file: Option<PathBuf>,
+    // Highest accepted multipart part number; raise it for objects too large for 10,000 parts
+    #[serde(default = "default_max_part_count")]
+    pub max_part_count: i32,
+    // Smallest multipart part other than the last; unset uses S3's 5 MiB
+    #[serde(default)]
+    pub min_part_size: Option<u64>,
+    // Days of audit log kept; older entries are pruned hourly. 0 keeps everything.
+    #[serde(default = "default_audit_retention_days")]
+    pub audit_retention_days: u32,
+    // Directory for scheduled catalog snapshots; unset disables them
+    #[serde(default)]
+    pub backup_dir: Option<PathBuf>,
+    #[serde(default = "default_backup_interval_hours")]
+    pub backup_interval_hours: u64,
+    // Snapshots kept in backup_dir; older ones are deleted after each backup
+    #[serde(default = "default_backup_retention")]
+    pub backup_retention: usize,
+    // Minutes between lifecycle sweeps, which expire objects and abort stale
+    // multipart uploads under each bucket's rules. 0 disables the sweep.
+    #[serde(default = "default_lifecycle_interval_minutes")]
+    pub lifecycle_interval_minutes: u64,
+    // Seconds between sweeps aborting multipart uploads past their
+    // x-amz-abort-date. 0 disables the sweep.
+    #[serde(default = "default_multipart_cleanup_interval_seconds")]
+    pub multipart_cleanup_interval_seconds: u64,
+    // Seconds between sweeps deleting objects past the TTL they were
+    // uploaded with. 0 disables the sweep; expired objects stay hidden.
+    #[serde(default = "default_object_expiry_interval_seconds")]
+    pub object_expiry_interval_seconds: u64,
+    // Seconds in-flight requests get to finish after SIGINT/SIGTERM before the process exits anyway
+    #[serde(default = "default_shutdown_timeout_seconds")]
+    pub shutdown_timeout_seconds: u64,
+    // File holding the 32-byte AES-256-GCM key for object data, as 64 hex
+    // digits. Only takes effect on an empty data directory, which then always
+    // needs the key.
+    #[serde(default)]
+    pub encryption_key_file: Option<PathBuf>,
+    // Embedded in every x-amz-id-2 and metric label to tell replicas apart;
+    // defaults to the host name
+    #[serde(default)]
+    pub instance_id: Option<String>,
+    // Serve the built-in web console under /console; admin keys sign in to it
+    #[serde(default)]
+    pub console_enabled: bool,
+    // OTLP/gRPC collector endpoint, e.g. http://localhost:4317, that request
+    // traces are exported to; unset keeps them in the logs only
+    #[serde(default)]
+    pub otel_endpoint: Option<String>,
+    // Create an admin access key on a boot that finds no active keys, and log
+    // its secret. Turn off in production and create keys with the CLI instead.
+    #[serde(default = "default_auto_create_admin_key")]
+    pub auto_create_admin_key: bool,
+    // Minutes a signed request's X-Amz-Date may differ from this server's clock
+    #[serde(default = "default_clock_skew_minutes")]
+    pub clock_skew_minutes: u64,
+    // Requests per second allowed per client address and per access key;
+    // over it requests get 429 SlowDown. Off by default.
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_max_part_count() -> i32 {
+    MultipartLimits::default().max_part_count
+}
+
+fn default_audit_retention_days() -> u32 {
+    90
+}
+
+fn default_backup_interval_hours() -> u64 {
+    24
+}
+
+fn default_backup_retention() -> usize {
+    7
+}
+
+fn default_lifecycle_interval_minutes() -> u64 {
+    60
+}
+
+fn default_multipart_cleanup_interval_seconds() -> u64 {
+    60 * 60
+}
+
+fn default_object_expiry_interval_seconds() -> u64 {
+    5 * 60
+}
+
+fn default_shutdown_timeout_seconds() -> u64 {
+    30
+}
+
+fn default_auto_create_admin_key() -> bool {
+    true
+}
+
+fn default_clock_skew_minutes() -> u64 {
+    ghostbay_auth::DEFAULT_CLOCK_SKEW_MINUTES
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +160,18 @@ pub struct TlsConfig {
     pub redirect_http_to_https: bool,
 }
 
+// A rate of 0 leaves that limit off; a burst of 0 allows one second's worth
+// of requests at once
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    #[serde(default)]
+    pub per_ip: u32,
+    #[serde(default)]
+    pub per_key: u32,
+    #[serde(default)]
+    pub burst: u32,
+}
+
 impl Default for ServerConfig {
     fn default() -> Self {
         Self {
@@ -45,24 +182,109 @@ impl Default for ServerConfig {
             temp_dir: PathBuf::from("./tmp"),
             log_level: "info".to_string(),
             tls: None,
+            region: default_region(),
+            region_endpoints: HashMap::new(),
+            etag_algorithm: EtagAlgorithm::default(),
+            provisioning_file: None,
+            max_part_count: default_max_part_count(),
+            min_part_size: None,
+            audit_retention_days: default_audit_retention_days(),
+            backup_dir: None,
+            backup_interval_hours: default_backup_interval_hours(),
+            backup_retention: default_backup_retention(),
+            lifecycle_interval_minutes: default_lifecycle_interval_minutes(),
+            multipart_cleanup_interval_seconds: default_multipart_cleanup_interval_seconds(),
+            object_expiry_interval_seconds: default_object_expiry_interval_seconds(),
+            shutdown_timeout_seconds: default_shutdown_timeout_seconds(),
+            encryption_key_file: None,
+            instance_id: None,
+            console_enabled: false,
+            otel_endpoint: None,
+            auto_create_admin_key: default_auto_create_admin_key(),
+            clock_skew_minutes: default_clock_skew_minutes(),
+            rate_limit: RateLimitConfig::default(),
+        }
+    }
+}
+
+impl ServerConfig {
+    // The defaults with any GHOSTBAY_* variables set in the environment
+    // applied on top. TLS needs both GHOSTBAY_TLS_CERT and GHOSTBAY_TLS_KEY.
+    pub fn from_env() -> Result<Self> {
+        let mut config = Self::default();
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+
+        if let Some(bind_address) = var("GHOSTBAY_BIND_ADDRESS") {
+            config.bind_address = bind_address;
+        }
+        if let Some(port) = var("GHOSTBAY_PORT") {
+            config.port = port.parse().with_context(|| format!("Invalid GHOSTBAY_PORT: {}", port))?;
+        }
+        if let Some(database_url) = var("GHOSTBAY_DATABASE_URL") {
+            config.database_url = database_url;
+        }
+        if let Some(data_dir) = var("GHOSTBAY_DATA_DIR") {
+            config.data_dir = PathBuf::from(data_dir);
         }
+        if let Some(temp_dir) = var("GHOSTBAY_TEMP_DIR") {
+            config.temp_dir = PathBuf::from(temp_dir);
+        }
+        if let Some(log_level) = var("GHOSTBAY_LOG_LEVEL") {
+            config.log_level = log_level;
+        }
+        match (var("GHOSTBAY_TLS_CERT"), var("GHOSTBAY_TLS_KEY")) {
+            (Some(cert_path), Some(key_path)) => {
+                config.tls = Some(TlsConfig {
+                    cert_path: PathBuf::from(cert_path),
+                    key_path: PathBuf::from(key_path),
+                    https_port: None,
+                    redirect_http_to_https: false,
+                });
+            }
+            (None, None) => {}
+            _ => anyhow::bail!("GHOSTBAY_TLS_CERT and GHOSTBAY_TLS_KEY must be set together"),
+        }
+
+        Ok(config)
     }
 }
 
 pub struct GhostBayServer {
     config: ServerConfig,
+    force_unlock: bool,
 }
 
 impl GhostBayServer {
     pub fn new(config: ServerConfig) -> Self {
-        Self { config }
+        Self { config, force_unlock: false }
+    }
+
+    // Take over lock files left by a crashed process even if their recorded pid looks alive
+    pub fn force_unlock(mut self, force_unlock: bool) -> Self {
+        self.force_unlock = force_unlock;
+        self
     }
 
     pub async fn run(self) -> Result<()> {
-        self.setup_tracing()?;
+        let tracer_provider = self.setup_tracing()?;
+        ghostbay_api::middleware::install_panic_hook();
 
         tracing::info!("Starting GhostBay server...");
         tracing::info!("Configuration: {:?}", self.config);
+        anyhow::ensure!(self.config.max_part_count >= 1, "max_part_count must be at least 1");
+        anyhow::ensure!(self.config.backup_interval_hours >= 1, "backup_interval_hours must be at least 1");
+        anyhow::ensure!(self.config.backup_retention >= 1, "backup_retention must be at least 1");
+
+        // Refuse to share the data directory or catalog with another server
+        let mut _locks = vec![ProcessLock::acquire(
+            &self.config.data_dir.join(LOCK_FILE_NAME),
+            LockMode::Exclusive,
+            self.force_unlock,
+        )?];
+        if let Some(database_file) = ghostbay_catalog::database_file(&self.config.database_url) {
+            let lock_path = ghostbay_catalog::catalog_lock_path(&database_file);
+            _locks.push(ProcessLock::acquire(&lock_path, LockMode::Exclusive, self.force_unlock)?);
+        }
 
         // Initialize catalog service
         let catalog = CatalogService::new(&self.config.database_url).await?;
@@ -71,68 +293,344 @@ impl GhostBayServer {
         ghostbay_catalog::migrations::ensure_database_exists(&self.config.database_url).await?;
         ghostbay_catalog::migrations::run_migrations(catalog.pool()).await?;
 
+        // Apply declared buckets and keys before any listener binds
+        if let Some(path) = &self.config.provisioning_file {
+            let file = ProvisioningFile::load(path)?;
+            let changes = apply_provisioning(catalog.pool(), &file, false).await?;
+            for change in &changes {
+                if change.applied {
+                    tracing::info!("Provisioning: {}", change);
+                } else {
+                    tracing::warn!("Provisioning: {}", change);
+                }
+            }
+            tracing::info!("Provisioning applied from {} ({} change(s))", path.display(), changes.len());
+        }
+
         // Initialize storage engine
         let storage_config = StorageConfig {
             data_dir: self.config.data_dir.clone(),
             temp_dir: self.config.temp_dir.clone(),
+            etag_algorithm: self.config.etag_algorithm,
+            encryption_key: self.config.encryption_key_file.as_deref().map(load_encryption_key).transpose()?,
         };
-        let storage = Arc::new(create_storage_engine(storage_config)?);
+        let storage = create_storage_engine(storage_config)?;
+        if storage.is_encrypted() {
+            tracing::info!("Object data is encrypted at rest with AES-256-GCM");
+        }
 
         // Initialize auth service with database connection
-        let auth_service = AuthService::new(catalog.pool().clone());
+        let auth_service = AuthService::new(catalog.pool().clone()).clock_skew_minutes(self.config.clock_skew_minutes);
         
-        // Create a default access key for testing if none exist
-        let request = CreateAccessKeyRequest {
-            policies: vec!["admin".to_string()],
-            description: Some("Default admin access key for testing".to_string()),
-            expires_at: None,
-        };
-        let default_key = auth_service.create_access_key(request).await?;
-        tracing::info!(
-            "Created default access key: {} (secret: {})",
-            default_key.access_key_id,
-            default_key.secret_access_key
-        );
+        // Create a default access key for testing on a first boot, unless keys
+        // are provisioned declaratively
+        let active_keys = AccessKeyRepository::new(catalog.pool().clone()).list(false).await?;
+        if self.config.auto_create_admin_key && self.config.provisioning_file.is_none() && active_keys.is_empty() {
+            let request = CreateAccessKeyRequest {
+                policies: vec!["admin".to_string()],
+                description: Some("Default admin access key for testing".to_string()),
+                expires_at: None,
+                quota: Default::default(),
+            };
+            let default_key = auth_service.create_access_key(request).await?;
+            tracing::info!(
+                "Created default access key: {} (secret: {})",
+                default_key.access_key_id,
+                default_key.secret_access_key
+            );
+        }
 
         let auth = Arc::new(auth_service);
+        let instance_id = match &self.config.instance_id {
+            Some(instance_id) => instance_id.clone(),
+            None => whoami::fallible::hostname().unwrap_or_else(|e| {
+                tracing::warn!("Could not read the host name for the instance id: {}", e);
+                "ghostbay".to_string()
+            }),
+        };
+        anyhow::ensure!(!instance_id.is_empty(), "instance_id must not be empty");
+        tracing::info!("Instance id: {}", instance_id);
+        let health = Arc::new(HealthState::new(ghostbay_catalog::database_file(&self.config.database_url)));
+
+        // Cancelled on SIGINT/SIGTERM: the listeners stop accepting and drain,
+        // and the background jobs below finish their current run and exit
+        let shutdown = CancellationToken::new();
+        tokio::spawn(watch_for_shutdown(
+            shutdown.clone(),
+            Duration::from_secs(self.config.shutdown_timeout_seconds),
+        ));
+        let mut jobs: Vec<JoinHandle<()>> = Vec::new();
+
+        // Persist per-key usage counters periodically so quotas survive restarts
+        let usage = auth.usage().clone();
+        let usage_health = health.clone();
+        let usage_shutdown = shutdown.clone();
+        jobs.push(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(USAGE_FLUSH_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    // One last flush so the final requests are not lost
+                    _ = usage_shutdown.cancelled() => {
+                        if let Err(e) = usage.flush().await {
+                            tracing::error!("Failed to flush key usage on shutdown: {}", e);
+                        }
+                        break;
+                    }
+                }
+                let result = usage.flush().await;
+                if let Err(e) = &result {
+                    tracing::error!("Failed to flush key usage: {}", e);
+                }
+                usage_health.record_job("usage_flush", &result);
+            }
+        }));
 
         // Create application state
         let app_state = AppState {
             catalog,
             storage,
             auth,
+            regions: Arc::new(RegionRouting {
+                region: self.config.region.clone(),
+                endpoints: self.config.region_endpoints.clone(),
+            }),
+            multipart: MultipartLimits {
+                max_part_count: self.config.max_part_count,
+                min_part_size: self.config.min_part_size.unwrap_or(MultipartLimits::default().min_part_size),
+                ..Default::default()
+            },
+            health,
+            instance_id: Arc::from(instance_id.as_str()),
+            rate_limiter: Arc::new(RateLimiter::new(
+                self.config.rate_limit.per_ip,
+                self.config.rate_limit.per_key,
+                self.config.rate_limit.burst,
+            )),
+            part_locks: Default::default(),
         };
 
-        // Create router with security headers
-        let app = create_router()
-            .with_state(app_state)
-            .layer(middleware::from_fn(security_headers_middleware));
+        // Forget clients and keys whose rate limit has fully refilled
+        if app_state.rate_limiter.is_enabled() {
+            let rate_limiter = app_state.rate_limiter.clone();
+            let rate_limit_shutdown = shutdown.clone();
+            jobs.push(tokio::spawn(async move {
+                let mut interval = tokio::time::interval(RATE_LIMIT_PRUNE_INTERVAL);
+                loop {
+                    tokio::select! {
+                        _ = interval.tick() => {}
+                        _ = rate_limit_shutdown.cancelled() => break,
+                    }
+                    rate_limiter.retain_recent();
+                }
+            }));
+        }
+
+        // Keep the counters behind /health current without querying per request
+        let refresh_state = app_state.clone();
+        let refresh_shutdown = shutdown.clone();
+        jobs.push(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(HEALTH_REFRESH_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = refresh_shutdown.cancelled() => break,
+                }
+                let result = refresh_health_stats(&refresh_state).await;
+                if let Err(e) = &result {
+                    tracing::error!("Failed to refresh health counters: {}", e);
+                }
+                refresh_state.health.record_job("health_refresh", &result);
+            }
+        }));
+
+        // Drop audit entries older than the retention window
+        if self.config.audit_retention_days > 0 {
+            let audit = AuditRepository::new(app_state.catalog.pool().clone());
+            let retention = chrono::Duration::days(self.config.audit_retention_days as i64);
+            let audit_health = app_state.health.clone();
+            let audit_shutdown = shutdown.clone();
+            jobs.push(tokio::spawn(async move {
+                let mut interval = tokio::time::interval(AUDIT_PRUNE_INTERVAL);
+                loop {
+                    tokio::select! {
+                        _ = interval.tick() => {}
+                        _ = audit_shutdown.cancelled() => break,
+                    }
+                    let result = audit.prune_before(chrono::Utc::now() - retention).await;
+                    match &result {
+                        Ok(0) => {}
+                        Ok(pruned) => tracing::info!("Pruned {} audit log entries", pruned),
+                        Err(e) => tracing::error!("Failed to prune audit log: {}", e),
+                    }
+                    audit_health.record_job("audit_prune", &result.map(|_| ()));
+                }
+            }));
+        }
+
+        // Snapshot the catalog on a schedule. The first run is timed from the
+        // newest existing snapshot, so restarts neither skip nor repeat backups.
+        if let Some(backup_dir) = self.config.backup_dir.clone() {
+            let catalog = app_state.catalog.clone();
+            let period = Duration::from_secs(self.config.backup_interval_hours * 3600);
+            let retention = self.config.backup_retention;
+            let backup_health = app_state.health.clone();
+            let first_run = match ghostbay_catalog::backup::list_backups(&backup_dir)?.last() {
+                Some(latest) => {
+                    let age = (chrono::Utc::now() - latest.created_at).to_std().unwrap_or_default();
+                    ghostbay_api::metrics::CATALOG_BACKUP_LAST_SUCCESS.set(latest.created_at.timestamp());
+                    period.saturating_sub(age)
+                }
+                None => Duration::ZERO,
+            };
+            let backup_shutdown = shutdown.clone();
+            jobs.push(tokio::spawn(async move {
+                let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + first_run, period);
+                loop {
+                    tokio::select! {
+                        _ = interval.tick() => {}
+                        _ = backup_shutdown.cancelled() => break,
+                    }
+                    let result = run_catalog_backup(&catalog, &backup_dir, retention).await;
+                    if let Err(e) = &result {
+                        tracing::error!("Failed to back up the catalog: {}", e);
+                    }
+                    backup_health.record_job("catalog_backup", &result);
+                }
+            }));
+        }
+
+        // Apply bucket lifecycle rules in the background
+        if self.config.lifecycle_interval_minutes > 0 {
+            let lifecycle_state = app_state.clone();
+            let period = Duration::from_secs(self.config.lifecycle_interval_minutes * 60);
+            let lifecycle_shutdown = shutdown.clone();
+            jobs.push(tokio::spawn(async move {
+                let mut interval = tokio::time::interval(period);
+                loop {
+                    tokio::select! {
+                        _ = interval.tick() => {}
+                        _ = lifecycle_shutdown.cancelled() => break,
+                    }
+                    let result = ghostbay_api::handlers::sweep_lifecycle(&lifecycle_state).await;
+                    match &result {
+                        Ok(sweep) => tracing::info!(
+                            "Lifecycle sweep: {} buckets with rules, {} objects ({} bytes) expired, {} multipart uploads aborted",
+                            sweep.buckets,
+                            sweep.expired_objects,
+                            sweep.expired_bytes,
+                            sweep.aborted_uploads
+                        ),
+                        Err(e) => tracing::error!("Lifecycle sweep failed: {}", e),
+                    }
+                    lifecycle_state
+                        .health
+                        .record_job("lifecycle_sweep", &result.map(|_| ()).map_err(anyhow::Error::from));
+                }
+            }));
+        }
 
+        // Abort multipart uploads left past their expiry
+        if self.config.multipart_cleanup_interval_seconds > 0 {
+            let cleanup_state = app_state.clone();
+            let period = Duration::from_secs(self.config.multipart_cleanup_interval_seconds);
+            let cleanup_shutdown = shutdown.clone();
+            jobs.push(tokio::spawn(async move {
+                let mut interval = tokio::time::interval(period);
+                loop {
+                    tokio::select! {
+                        _ = interval.tick() => {}
+                        _ = cleanup_shutdown.cancelled() => break,
+                    }
+                    let result = ghostbay_api::handlers::abort_expired_uploads(&cleanup_state).await;
+                    match &result {
+                        Ok(0) => {}
+                        Ok(aborted) => tracing::info!("Aborted {} expired multipart uploads", aborted),
+                        Err(e) => tracing::error!("Multipart upload cleanup failed: {}", e),
+                    }
+                    cleanup_state
+                        .health
+                        .record_job("multipart_cleanup", &result.map(|_| ()).map_err(anyhow::Error::from));
+                }
+            }));
+        }
+
+        // Delete objects past their TTL
+        if self.config.object_expiry_interval_seconds > 0 {
+            let expiry_state = app_state.clone();
+            let period = Duration::from_secs(self.config.object_expiry_interval_seconds);
+            let expiry_shutdown = shutdown.clone();
+            jobs.push(tokio::spawn(async move {
+                let mut interval = tokio::time::interval(period);
+                loop {
+                    tokio::select! {
+                        _ = interval.tick() => {}
+                        _ = expiry_shutdown.cancelled() => break,
+                    }
+                    let result = ghostbay_api::handlers::sweep_expired_objects(&expiry_state).await;
+                    match &result {
+                        Ok(sweep) if sweep.expired_objects == 0 => {}
+                        Ok(sweep) => tracing::info!(
+                            "Deleted {} expired objects ({} bytes)",
+                            sweep.expired_objects,
+                            sweep.expired_bytes
+                        ),
+                        Err(e) => tracing::error!("Object expiry sweep failed: {}", e),
+                    }
+                    expiry_state
+                        .health
+                        .record_job("object_expiry", &result.map(|_| ()).map_err(anyhow::Error::from));
+                }
+            }));
+        }
+
+        if self.config.console_enabled {
+            tracing::info!("Web console enabled at /console");
+        }
+        let app = build_app(app_state, self.config.console_enabled);
         let tls_config = self.config.tls.clone();
         
-        if let Some(tls_config) = tls_config {
+        let served = if let Some(tls_config) = tls_config {
             // TLS enabled
-            self.run_with_tls(app, tls_config).await
+            self.run_with_tls(app, tls_config, shutdown.clone()).await
         } else {
             // HTTP only
-            self.run_http_only(app).await
+            self.run_http_only(app, shutdown.clone()).await
+        };
+
+        // The listeners are drained; let the background jobs wind down
+        shutdown.cancel();
+        for job in jobs {
+            if let Err(e) = job.await {
+                tracing::error!("Background job failed during shutdown: {}", e);
+            }
+        }
+        tracing::info!("GhostBay server stopped");
+        // Flushes spans still waiting in the batch exporter
+        if let Some(Err(e)) = tracer_provider.map(|provider| provider.shutdown()) {
+            tracing::error!("Failed to shut down the OpenTelemetry exporter: {}", e);
         }
+        served
     }
 
-    async fn run_http_only(self, app: Router) -> Result<()> {
+    async fn run_http_only(self, app: Router, shutdown: CancellationToken) -> Result<()> {
         let addr: SocketAddr = format!("{}:{}", self.config.bind_address, self.config.port).parse()?;
         let listener = TcpListener::bind(addr).await?;
 
         tracing::info!("GhostBay server listening on http://{}", addr);
         tracing::info!("Health check available at: http://{}/health", addr);
+        tracing::info!("Prometheus metrics available at: http://{}/metrics", addr);
         tracing::info!("S3 API available at: http://{}/", addr);
         tracing::warn!("⚠️  TLS is disabled. Consider enabling HTTPS in production!");
 
-        axum::serve(listener, app).await?;
+        // Connection info gives the rate limiter each client's address
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .with_graceful_shutdown(shutdown.cancelled_owned())
+            .await?;
         Ok(())
     }
 
-    async fn run_with_tls(self, app: Router, tls_config: TlsConfig) -> Result<()> {
+    async fn run_with_tls(self, app: Router, tls_config: TlsConfig, shutdown: CancellationToken) -> Result<()> {
         // Load TLS certificates
         let rustls_config = self.load_tls_config(&tls_config).await?;
         
@@ -156,16 +654,26 @@ impl GhostBayServer {
             tracing::info!("HTTP redirect server listening on http://{}", http_addr);
             
             // Start HTTP redirect server in background
+            let redirect_shutdown = shutdown.clone();
             tokio::spawn(async move {
-                if let Err(e) = axum::serve(http_listener, redirect_app).await {
+                let server = axum::serve(http_listener, redirect_app)
+                    .with_graceful_shutdown(redirect_shutdown.cancelled_owned());
+                if let Err(e) = server.await {
                     tracing::error!("HTTP redirect server error: {}", e);
                 }
             });
         }
 
-        // Start HTTPS server
+        // Start HTTPS server; the watchdog in watch_for_shutdown bounds the drain
+        let handle = axum_server::Handle::new();
+        let shutdown_handle = handle.clone();
+        tokio::spawn(async move {
+            shutdown.cancelled().await;
+            shutdown_handle.graceful_shutdown(None);
+        });
         axum_server::bind_rustls(https_addr, rustls_config)
-            .serve(app.into_make_service())
+            .handle(handle)
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
             .await?;
 
         Ok(())
@@ -180,19 +688,148 @@ impl GhostBayServer {
         Ok(config)
     }
 
-    fn setup_tracing(&self) -> Result<()> {
+    fn setup_tracing(&self) -> Result<Option<opentelemetry_sdk::trace::TracerProvider>> {
         let filter = tracing_subscriber::EnvFilter::try_from_default_env()
             .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(&self.config.log_level));
 
+        let tracer_provider = self.config.otel_endpoint.as_deref().map(otlp_tracer_provider).transpose()?;
+        let otel_layer = tracer_provider
+            .as_ref()
+            .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer("ghostbay")));
+
         tracing_subscriber::registry()
             .with(filter)
             .with(tracing_subscriber::fmt::layer())
+            .with(otel_layer)
             .init();
 
-        Ok(())
+        Ok(tracer_provider)
     }
 }
 
+// Batches spans to an OTLP collector over gRPC
+fn otlp_tracer_provider(endpoint: &str) -> Result<opentelemetry_sdk::trace::TracerProvider> {
+    let exporter = opentelemetry_otlp::new_exporter().tonic().with_endpoint(endpoint);
+    let resource = opentelemetry_sdk::Resource::new(vec![opentelemetry::KeyValue::new("service.name", "ghostbay")]);
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(exporter)
+        .with_trace_config(opentelemetry_sdk::trace::Config::default().with_resource(resource))
+        .install_batch(opentelemetry_sdk::runtime::Tokio)
+        .with_context(|| format!("Failed to set up OpenTelemetry export to {}", endpoint))
+}
+
+fn load_encryption_key(path: &std::path::Path) -> Result<[u8; 32]> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read encryption key file {}", path.display()))?;
+    let bytes = hex::decode(content.trim())
+        .with_context(|| format!("{} must contain the key as 64 hex digits", path.display()))?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("{} must contain a 32-byte key (64 hex digits)", path.display()))
+}
+
+// Waits for SIGINT or SIGTERM and starts a graceful shutdown. Requests still
+// running when the grace period ends are abandoned and the process exits.
+async fn watch_for_shutdown(shutdown: CancellationToken, grace_period: Duration) {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+
+    tracing::info!(
+        "Shutdown requested; draining in-flight requests for up to {}s",
+        grace_period.as_secs()
+    );
+    shutdown.cancel();
+
+    tokio::time::sleep(grace_period).await;
+    tracing::error!("Requests still running after {}s; exiting without waiting for them", grace_period.as_secs());
+    std::process::exit(1);
+}
+
+async fn run_catalog_backup(catalog: &CatalogService, backup_dir: &std::path::Path, retention: usize) -> Result<()> {
+    let backup = ghostbay_catalog::backup::backup_catalog(catalog.pool(), backup_dir).await?;
+    ghostbay_api::metrics::CATALOG_BACKUP_LAST_SUCCESS.set(backup.created_at.timestamp());
+    tracing::info!("Backed up the catalog to {} ({} bytes)", backup.path.display(), backup.size);
+
+    for pruned in ghostbay_catalog::backup::prune_backups(backup_dir, retention)? {
+        tracing::info!("Removed old catalog backup {}", pruned.path.display());
+    }
+    Ok(())
+}
+
+// The router and middleware stack the server runs. Layers added later wrap
+// the earlier ones, so authentication runs before the audit, quota and rate
+// checks that read its AuthContext. The console claims /console, shadowing
+// any bucket of that name.
+pub fn build_app(app_state: AppState, console_enabled: bool) -> Router {
+    let mut router = create_router();
+    if console_enabled {
+        router = router.merge(console_router(Arc::new(ConsoleSessions::default())));
+    }
+
+    router
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            ghostbay_api::middleware::enforce_key_quota,
+        ))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            ghostbay_api::middleware::limit_key_rate,
+        ))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            ghostbay_api::middleware::redirect_foreign_buckets,
+        ))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            ghostbay_api::middleware::record_audit,
+        ))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            ghostbay_api::middleware::authenticate,
+        ))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            ghostbay_api::middleware::limit_client_rate,
+        ))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            ghostbay_api::middleware::apply_bucket_cors,
+        ))
+        .layer(middleware::from_fn(ghostbay_api::middleware::record_metrics))
+        .with_state(app_state.clone())
+        .layer(CatchPanicLayer::custom(ghostbay_api::middleware::handle_panic))
+        .layer(middleware::from_fn_with_state(
+            app_state,
+            ghostbay_api::middleware::request_context,
+        ))
+        .layer(middleware::from_fn(security_headers_middleware))
+}
+
 // Security headers middleware
 async fn security_headers_middleware(request: Request, next: Next) -> Response {
     let mut response = next.run(request).await;
@@ -202,28 +839,28 @@ async fn security_headers_middleware(request: Request, next: Next) -> Response {
     // HSTS (HTTP Strict Transport Security)
     headers.insert(
         "Strict-Transport-Security",
-        "max-age=31536000; includeSubDomains; preload".parse().unwrap(),
+        HeaderValue::from_static("max-age=31536000; includeSubDomains; preload"),
     );
     
     // Content Security Policy
     headers.insert(
         "Content-Security-Policy",
-        "default-src 'self'; object-src 'none'; frame-ancestors 'none'".parse().unwrap(),
+        HeaderValue::from_static("default-src 'self'; object-src 'none'; frame-ancestors 'none'"),
     );
     
     // X-Frame-Options
-    headers.insert("X-Frame-Options", "DENY".parse().unwrap());
+    headers.insert("X-Frame-Options", HeaderValue::from_static("DENY"));
     
     // X-Content-Type-Options
-    headers.insert("X-Content-Type-Options", "nosniff".parse().unwrap());
+    headers.insert("X-Content-Type-Options", HeaderValue::from_static("nosniff"));
     
     // Referrer Policy
-    headers.insert("Referrer-Policy", "strict-origin-when-cross-origin".parse().unwrap());
+    headers.insert("Referrer-Policy", HeaderValue::from_static("strict-origin-when-cross-origin"));
     
     // Permissions Policy
     headers.insert(
         "Permissions-Policy",
-        "geolocation=(), microphone=(), camera=()".parse().unwrap(),
+        HeaderValue::from_static("geolocation=(), microphone=(), camera=()"),
     );
 
     response
@@ -1,28 +1,110 @@
 use anyhow::Result;
 use clap::Parser;
-use ghostbay_gateway::{GhostBayServer, ServerConfig, TlsConfig};
+use ghostbay_engine::EtagAlgorithm;
+use ghostbay_gateway::{GhostBayServer, RateLimitConfig, ServerConfig, TlsConfig};
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    #[arg(long, default_value = "127.0.0.1")]
-    bind_address: String,
+    // Flags left out here and for TLS fall back to GHOSTBAY_BIND_ADDRESS,
+    // GHOSTBAY_PORT and so on, then to the defaults
+    #[arg(long)]
+    bind_address: Option<String>,
+
+    #[arg(short, long)]
+    port: Option<u16>,
+
+    #[arg(long)]
+    database_url: Option<String>,
+
+    #[arg(long)]
+    data_dir: Option<PathBuf>,
+
+    #[arg(long)]
+    temp_dir: Option<PathBuf>,
+
+    #[arg(long)]
+    log_level: Option<String>,
+
+    // ETag algorithm for new objects: md5, sha256-trunc or uuid
+    #[arg(long, default_value = "md5")]
+    etag_algorithm: EtagAlgorithm,
+
+    // Highest multipart part number accepted
+    #[arg(long, default_value_t = 10_000, value_parser = clap::value_parser!(i32).range(1..))]
+    max_part_count: i32,
+
+    // Smallest multipart part other than the last, in bytes; defaults to 5 MiB
+    #[arg(long)]
+    min_part_size: Option<u64>,
+
+    // Days of audit log to keep; 0 keeps everything
+    #[arg(long, default_value_t = 90)]
+    audit_retention_days: u32,
+
+    // Directory for scheduled catalog snapshots; omit to disable them
+    #[arg(long)]
+    backup_dir: Option<PathBuf>,
+
+    #[arg(long, default_value_t = 24, value_parser = clap::value_parser!(u64).range(1..))]
+    backup_interval_hours: u64,
+
+    // Snapshots kept in the backup directory
+    #[arg(long, default_value_t = 7, value_parser = clap::value_parser!(u64).range(1..))]
+    backup_retention: u64,
+
+    // Minutes between lifecycle sweeps; 0 disables them
+    #[arg(long, default_value_t = 60)]
+    lifecycle_interval_minutes: u64,
 
-    #[arg(short, long, default_value_t = 3000)]
-    port: u16,
+    // Seconds between sweeps for expired multipart uploads; 0 disables them
+    #[arg(long, default_value_t = 3600)]
+    multipart_cleanup_interval_seconds: u64,
 
-    #[arg(long, default_value = "sqlite:./ghostbay.db")]
-    database_url: String,
+    // Seconds between sweeps deleting objects past their TTL; 0 disables them
+    #[arg(long, default_value_t = 300)]
+    object_expiry_interval_seconds: u64,
 
-    #[arg(long, default_value = "./data")]
-    data_dir: PathBuf,
+    // Seconds in-flight requests get to finish on shutdown
+    #[arg(long, default_value_t = 30)]
+    shutdown_timeout_seconds: u64,
 
-    #[arg(long, default_value = "./tmp")]
-    temp_dir: PathBuf,
+    // File holding the 32-byte object encryption key as 64 hex digits
+    #[arg(long)]
+    encryption_key_file: Option<PathBuf>,
+
+    // Instance id embedded in x-amz-id-2 and metric labels (default: host name)
+    #[arg(long)]
+    instance_id: Option<String>,
+
+    // Serve the built-in web console under /console
+    #[arg(long)]
+    console_enabled: bool,
+
+    // OTLP/gRPC endpoint to export request traces to, e.g. http://localhost:4317
+    #[arg(long)]
+    otel_endpoint: Option<String>,
+
+    // Create an admin access key when the server starts without any
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    auto_create_admin_key: bool,
+
+    // Minutes a signed request's date may differ from the server's clock
+    #[arg(long, default_value_t = 15)]
+    clock_skew_minutes: u64,
+
+    // Requests per second allowed from each client address; 0 disables the limit
+    #[arg(long, default_value_t = 0)]
+    rate_limit_per_ip: u32,
+
+    // Requests per second allowed for each access key; 0 disables the limit
+    #[arg(long, default_value_t = 0)]
+    rate_limit_per_key: u32,
 
-    #[arg(long, default_value = "info")]
-    log_level: String,
+    // Requests allowed at once above those rates; 0 allows one second's worth
+    #[arg(long, default_value_t = 0)]
+    rate_limit_burst: u32,
 
     #[arg(short, long)]
     config: Option<PathBuf>,
@@ -39,6 +121,28 @@ struct Args {
 
     #[arg(long)]
     redirect_http_to_https: bool,
+
+    // Multi-region options
+    #[arg(long, default_value = "us-east-1")]
+    region: String,
+
+    #[arg(long = "region-endpoint", value_name = "REGION=ENDPOINT", value_parser = parse_region_endpoint)]
+    region_endpoints: Vec<(String, String)>,
+
+    // Declared buckets and access keys applied at startup
+    #[arg(long)]
+    provisioning_file: Option<PathBuf>,
+
+    // Take over lock files left behind by a crashed server
+    #[arg(long)]
+    force_unlock: bool,
+}
+
+fn parse_region_endpoint(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(region, endpoint)| (region.to_string(), endpoint.to_string()))
+        .ok_or_else(|| format!("expected REGION=ENDPOINT, got '{}'", value))
 }
 
 #[tokio::main]
@@ -51,29 +155,61 @@ async fn main() -> Result<()> {
         let config_content = tokio::fs::read_to_string(&config_path).await?;
         toml::from_str(&config_content)?
     } else {
-        // Build TLS config if cert and key are provided
-        let tls = if let (Some(cert_path), Some(key_path)) = (args.tls_cert, args.tls_key) {
+        let env = ServerConfig::from_env()?;
+
+        // Build TLS config if cert and key are provided, otherwise keep any from
+        // the environment
+        let mut tls = if let (Some(cert_path), Some(key_path)) = (args.tls_cert, args.tls_key) {
             Some(TlsConfig {
                 cert_path,
                 key_path,
-                https_port: args.https_port,
-                redirect_http_to_https: args.redirect_http_to_https,
+                https_port: None,
+                redirect_http_to_https: false,
             })
         } else {
-            None
+            env.tls
         };
+        if let Some(tls) = tls.as_mut() {
+            tls.https_port = args.https_port.or(tls.https_port);
+            tls.redirect_http_to_https |= args.redirect_http_to_https;
+        }
 
         ServerConfig {
-            bind_address: args.bind_address,
-            port: args.port,
-            database_url: args.database_url,
-            data_dir: args.data_dir,
-            temp_dir: args.temp_dir,
-            log_level: args.log_level,
+            bind_address: args.bind_address.unwrap_or(env.bind_address),
+            port: args.port.unwrap_or(env.port),
+            database_url: args.database_url.unwrap_or(env.database_url),
+            data_dir: args.data_dir.unwrap_or(env.data_dir),
+            temp_dir: args.temp_dir.unwrap_or(env.temp_dir),
+            log_level: args.log_level.unwrap_or(env.log_level),
             tls,
+            region: args.region,
+            region_endpoints: args.region_endpoints.into_iter().collect(),
+            etag_algorithm: args.etag_algorithm,
+            provisioning_file: args.provisioning_file,
+            max_part_count: args.max_part_count,
+            min_part_size: args.min_part_size,
+            audit_retention_days: args.audit_retention_days,
+            backup_dir: args.backup_dir,
+            backup_interval_hours: args.backup_interval_hours,
+            backup_retention: args.backup_retention as usize,
+            lifecycle_interval_minutes: args.lifecycle_interval_minutes,
+            multipart_cleanup_interval_seconds: args.multipart_cleanup_interval_seconds,
+            object_expiry_interval_seconds: args.object_expiry_interval_seconds,
+            shutdown_timeout_seconds: args.shutdown_timeout_seconds,
+            encryption_key_file: args.encryption_key_file,
+            instance_id: args.instance_id,
+            console_enabled: args.console_enabled,
+            otel_endpoint: args.otel_endpoint,
+            auto_create_admin_key: args.auto_create_admin_key,
+            clock_skew_minutes: args.clock_skew_minutes,
+            rate_limit: RateLimitConfig {
+                per_ip: args.rate_limit_per_ip,
+                per_key: args.rate_limit_per_key,
+                burst: args.rate_limit_burst,
+            },
         }
     };
 
-    let server = GhostBayServer::new(config);
+    let server = GhostBayServer::new(config).force_unlock(args.force_unlock);
     server.run().await
 }
\ No newline at end of file
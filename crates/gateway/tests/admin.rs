@@ -0,0 +1,210 @@
+mod common;
+
+use aws_sdk_s3::primitives::ByteStream;
+use common::{TestServer, ADMIN_KEY, USER_KEY};
+
+const JSON: &[(&str, &str)] = &[("content-type", "application/json")];
+
+fn anonymous(method: &str, path: &str, body: &str) -> Vec<u8> {
+    format!(
+        "{} {} HTTP/1.1\r\nhost: localhost\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    )
+    .into_bytes()
+}
+
+#[tokio::test]
+async fn lifecycle_rules_need_an_admin_key() {
+    let server = TestServer::start().await;
+    let client = server.admin();
+    client.create_bucket().bucket("logs").send().await.unwrap();
+    client
+        .put_object()
+        .bucket("logs")
+        .key("tmp/a.log")
+        .body(ByteStream::from_static(b"a"))
+        .send()
+        .await
+        .unwrap();
+    let wipe = r#"{"Rules":[{"ID":"wipe","ExpirationDays":0}]}"#;
+    let path = "/admin/v1/buckets/logs/lifecycle";
+
+    let response = server.raw(&anonymous("PUT", path, wipe)).await;
+    assert_eq!((response.status, response.error_code()), (403, Some("AccessDenied")));
+    let response = server.raw(&server.signed_with(USER_KEY, "PUT", path, JSON, wipe.as_bytes())).await;
+    assert_eq!((response.status, response.error_code()), (403, Some("AccessDenied")));
+    for action in ["preview", "run"] {
+        let response = server.raw(&anonymous("POST", &format!("{}/{}", path, action), "")).await;
+        assert_eq!(response.status, 403, "{}", action);
+    }
+    client.head_object().bucket("logs").key("tmp/a.log").send().await.unwrap();
+
+    let response = server.raw(&server.signed_with(ADMIN_KEY, "PUT", path, JSON, wipe.as_bytes())).await;
+    assert_eq!(response.status, 204, "{}", response.body);
+    let response = server.raw(&server.signed(ADMIN_KEY, "POST", &format!("{}/run", path), b"")).await;
+    assert_eq!(response.status, 200, "{}", response.body);
+    assert!(client.head_object().bucket("logs").key("tmp/a.log").send().await.is_err());
+}
+
+#[tokio::test]
+async fn audit_search_needs_an_admin_key() {
+    let server = TestServer::start().await;
+    server.admin().create_bucket().bucket("audited").send().await.unwrap();
+    let path = "/admin/v1/audit?bucket=audited";
+
+    let response = server.raw(&anonymous("GET", path, "")).await;
+    assert_eq!((response.status, response.error_code()), (403, Some("AccessDenied")));
+    let response = server.raw(&server.signed(USER_KEY, "GET", path, b"")).await;
+    assert_eq!((response.status, response.error_code()), (403, Some("AccessDenied")));
+
+    // Entries are written in the background, after the response
+    for _ in 0..50 {
+        let response = server.raw(&server.signed(ADMIN_KEY, "GET", path, b"")).await;
+        assert_eq!(response.status, 200, "{}", response.body);
+        if response.body.contains(r#""Bucket":"audited""#) {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    panic!("CreateBucket never showed up in the audit log");
+}
+
+// gzip of "hello, gzip"
+const GZIPPED: &[u8] = &[
+    0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0xd7, 0x51, 0x48, 0xaf, 0xca,
+    0x2c, 0x00, 0x00, 0x4a, 0x9b, 0xb1, 0x5c, 0x0b, 0x00, 0x00, 0x00,
+];
+
+#[tokio::test]
+async fn encoded_uploads_are_stored_as_sent_or_inflated() {
+    let server = TestServer::start().await;
+    let client = server.admin();
+    client.create_bucket().bucket("encoded").send().await.unwrap();
+    let upload = |key: &'static str| {
+        client
+            .put_object()
+            .bucket("encoded")
+            .key(key)
+            .content_encoding("gzip")
+            .body(ByteStream::from_static(GZIPPED))
+            .send()
+    };
+
+    // By default the object keeps its encoding
+    upload("as-sent").await.unwrap();
+    let object = client.get_object().bucket("encoded").key("as-sent").send().await.unwrap();
+    assert_eq!(object.content_encoding(), Some("gzip"));
+    assert_eq!(object.body.collect().await.unwrap().into_bytes().as_ref(), GZIPPED);
+
+    let path = "/admin/v1/buckets/encoded/upload-encoding";
+    let inflate = br#"{"DecompressOnUpload":true}"#;
+    let response = server.raw(&anonymous("PUT", path, r#"{"DecompressOnUpload":true}"#)).await;
+    assert_eq!((response.status, response.error_code()), (403, Some("AccessDenied")));
+    let response = server.raw(&server.signed_with(USER_KEY, "PUT", path, JSON, inflate)).await;
+    assert_eq!((response.status, response.error_code()), (403, Some("AccessDenied")));
+    let response = server.raw(&server.signed_with(ADMIN_KEY, "PUT", path, JSON, inflate)).await;
+    assert_eq!(response.status, 204, "{}", response.body);
+
+    upload("inflated").await.unwrap();
+    let object = client.get_object().bucket("encoded").key("inflated").send().await.unwrap();
+    assert_eq!(object.content_encoding(), None);
+    assert_eq!(object.content_length(), Some(11));
+    assert_eq!(object.body.collect().await.unwrap().into_bytes().as_ref(), b"hello, gzip");
+
+    // Objects uploaded before the change keep their encoding
+    let object = client.get_object().bucket("encoded").key("as-sent").send().await.unwrap();
+    assert_eq!(object.content_encoding(), Some("gzip"));
+}
+
+#[tokio::test]
+async fn snapshots_pick_the_version_current_at_each_time() {
+    use aws_sdk_s3::types::{BucketVersioningStatus, VersioningConfiguration};
+
+    let server = TestServer::start().await;
+    let client = server.admin();
+    client.create_bucket().bucket("history").send().await.unwrap();
+    client
+        .put_bucket_versioning()
+        .bucket("history")
+        .versioning_configuration(VersioningConfiguration::builder().status(BucketVersioningStatus::Enabled).build())
+        .send()
+        .await
+        .unwrap();
+
+    // After each round, the ETag every key should have in a snapshot taken then
+    let mut checkpoints = Vec::new();
+    for round in 0..12 {
+        let mut etags = serde_json::Map::new();
+        for key in ["a.txt", "b.txt", "c.txt"] {
+            let put = client
+                .put_object()
+                .bucket("history")
+                .key(key)
+                .body(ByteStream::from(format!("{} v{}", key, round).into_bytes()))
+                .send()
+                .await
+                .unwrap();
+            etags.insert(key.to_string(), put.e_tag().unwrap().trim_matches('"').into());
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        checkpoints.push((chrono::Utc::now(), etags));
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+
+    let snapshot = |at: chrono::DateTime<chrono::Utc>, extra: &str| {
+        let at = at.to_rfc3339_opts(chrono::SecondsFormat::Micros, true).replace(':', "%3A");
+        server.signed(ADMIN_KEY, "GET", &format!("/admin/v1/buckets/history/snapshot?at={}{}", at, extra), b"")
+    };
+    for (at, etags) in &checkpoints {
+        let response = server.raw(&snapshot(*at, "")).await;
+        assert_eq!(response.status, 200, "{}", response.body);
+        let listing = response.json();
+        let contents = listing["Contents"].as_array().unwrap();
+        assert_eq!(contents.len(), 3);
+        for object in contents {
+            let key = object["Key"].as_str().unwrap();
+            assert_eq!(object["ETag"].as_str().unwrap().trim_matches('"'), etags[key], "{} at {}", key, at);
+        }
+    }
+
+    // Paging one key at a time still yields each key once
+    let (at, _) = &checkpoints[4];
+    let mut keys = Vec::new();
+    let mut start_after = String::new();
+    loop {
+        let response = server.raw(&snapshot(*at, &format!("&max-keys=1&start-after={}", start_after))).await;
+        let listing = response.json();
+        keys.extend(listing["Contents"].as_array().unwrap().iter().map(|object| object["Key"].as_str().unwrap().to_string()));
+        match listing["NextStartAfter"].as_str() {
+            Some(next) => start_after = next.to_string(),
+            None => break,
+        }
+    }
+    assert_eq!(keys, ["a.txt", "b.txt", "c.txt"]);
+
+    let response = server.raw(&anonymous("GET", "/admin/v1/buckets/history/snapshot?at=2020-01-01T00%3A00%3A00Z", "")).await;
+    assert_eq!((response.status, response.error_code()), (403, Some("AccessDenied")));
+}
+
+#[tokio::test]
+async fn host_id_lookup_needs_an_admin_key() {
+    let server = TestServer::start().await;
+    let response = server.raw(&server.signed(USER_KEY, "GET", "/", b"")).await;
+    assert_eq!(response.status, 200);
+    let host_id = response.header("x-amz-id-2").unwrap().to_string();
+    let path = format!("/admin/v1/host-ids/{}", host_id);
+
+    let response = server.raw(&anonymous("GET", &path, "")).await;
+    assert_eq!((response.status, response.error_code()), (403, Some("AccessDenied")));
+    let response = server.raw(&server.signed(USER_KEY, "GET", &path, b"")).await;
+    assert_eq!((response.status, response.error_code()), (403, Some("AccessDenied")));
+
+    let response = server.raw(&server.signed(ADMIN_KEY, "GET", &path, b"")).await;
+    assert_eq!(response.status, 200, "{}", response.body);
+    let decoded = response.json();
+    assert_eq!(decoded["InstanceId"], "test");
+    assert_eq!(decoded["ServedByThisInstance"], true);
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use aws_sdk_s3::primitives::ByteStream;
+use common::{TestServer, ADMIN_KEY};
+use ghostbay_catalog::backup::{backup_catalog, restore_catalog};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::task::JoinSet;
+
+const WRITERS: usize = 4;
+const OBJECTS_PER_WRITER: usize = 60;
+
+fn key(writer: usize, index: usize) -> String {
+    format!("writer{}/{:03}", writer, index)
+}
+
+fn body(writer: usize, index: usize) -> String {
+    format!("object {} of writer {}", index, writer)
+}
+
+fn copy_dir(from: &Path, to: &Path) {
+    std::fs::create_dir_all(to).unwrap();
+    for entry in std::fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        if entry.file_type().unwrap().is_dir() {
+            copy_dir(&entry.path(), &to.join(entry.file_name()));
+        } else {
+            std::fs::copy(entry.path(), to.join(entry.file_name())).unwrap();
+        }
+    }
+}
+
+// A snapshot taken while writers are busy restores into a fresh server that
+// reads back every object the snapshot holds
+#[tokio::test(flavor = "multi_thread")]
+async fn a_backup_under_concurrent_writes_restores_consistently() {
+    let server = TestServer::start().await;
+    let client = server.admin();
+    client.create_bucket().bucket("busy").send().await.unwrap();
+
+    let written = Arc::new(AtomicUsize::new(0));
+    let mut writers = JoinSet::new();
+    for writer in 0..WRITERS {
+        let client = client.clone();
+        let written = written.clone();
+        writers.spawn(async move {
+            for index in 0..OBJECTS_PER_WRITER {
+                client
+                    .put_object()
+                    .bucket("busy")
+                    .key(key(writer, index))
+                    .body(ByteStream::from(body(writer, index).into_bytes()))
+                    .send()
+                    .await
+                    .unwrap();
+                written.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+    }
+    while written.load(Ordering::SeqCst) < WRITERS * 5 {
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    let backup = backup_catalog(server.state.catalog.pool(), &server.dir().join("backups")).await.unwrap();
+    while let Some(writer) = writers.join_next().await {
+        writer.unwrap();
+    }
+
+    // Keys only ever get added, so the data files of everything in the
+    // snapshot are among the original server's
+    let restored = tempfile::tempdir().unwrap();
+    copy_dir(&server.dir().join("data"), &restored.path().join("data"));
+    assert_eq!(restore_catalog(&backup.path, &restored.path().join("catalog.db")).await.unwrap(), None);
+    let restored = TestServer::start_in(restored).await;
+
+    let mut listed = Vec::new();
+    let mut pages = restored.admin().list_objects_v2().bucket("busy").into_paginator().send();
+    while let Some(page) = pages.next().await {
+        listed.extend(page.unwrap().contents().iter().map(|object| object.key().unwrap().to_string()));
+    }
+    assert!(listed.len() >= WRITERS * 5, "{} objects restored", listed.len());
+
+    // Each writer puts its keys one after another, so a point-in-time
+    // snapshot holds an unbroken run of each writer's keys from the first
+    for writer in 0..WRITERS {
+        let prefix = format!("writer{}/", writer);
+        let keys: Vec<&String> = listed.iter().filter(|key| key.starts_with(&prefix)).collect();
+        let expected: Vec<String> = (0..keys.len()).map(|index| key(writer, index)).collect();
+        assert_eq!(keys, expected.iter().collect::<Vec<_>>());
+        for (index, key) in keys.iter().enumerate() {
+            let response = restored.raw(&restored.signed(ADMIN_KEY, "GET", &format!("/busy/{}", key), b"")).await;
+            assert_eq!((response.status, response.body), (200, body(writer, index)), "{}", key);
+        }
+    }
+
+    // And the restored server takes new writes
+    restored.admin().put_object().bucket("busy").key("after").body(ByteStream::from_static(b"new")).send().await.unwrap();
+    let response = restored.raw(&restored.signed(ADMIN_KEY, "GET", "/busy/after", b"")).await;
+    assert_eq!((response.status, response.body.as_str()), (200, "new"));
+}
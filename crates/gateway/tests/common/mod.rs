@@ -0,0 +1,259 @@
+// Runs the gateway's router and middleware stack in-process on a loopback
+// port, over a throwaway catalog and data directory.
+#![allow(dead_code)]
+
+use aws_credential_types::Credentials;
+use aws_sdk_s3::config::{BehaviorVersion, Region};
+use aws_sigv4::http_request::{
+    sign, PayloadChecksumKind, PercentEncodingMode, SignableBody, SignableRequest, SigningSettings, UriPathNormalizationMode,
+};
+use aws_sigv4::sign::v4;
+use aws_smithy_runtime_api::client::identity::Identity;
+use ghostbay_api::{health::HealthState, middleware::RateLimiter, AppState, MultipartLimits, RegionRouting};
+use ghostbay_auth::{apply_provisioning, AuthService, ProvisionedKey, ProvisioningFile};
+use ghostbay_catalog::CatalogService;
+use ghostbay_engine::{create_storage_engine, StorageConfig};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+pub const ADMIN_KEY: (&str, &str) = ("GBTESTADMIN000000001", "admin-secret-0000000000000000000000000000");
+pub const USER_KEY: (&str, &str) = ("GBTESTUSER0000000001", "user-secret-00000000000000000000000000000");
+
+pub struct TestServer {
+    pub addr: SocketAddr,
+    pub state: AppState,
+    dir: TempDir,
+}
+
+impl TestServer {
+    pub async fn start() -> Self {
+        Self::start_with(MultipartLimits::default()).await
+    }
+
+    pub async fn start_with(multipart: MultipartLimits) -> Self {
+        Self::launch(tempfile::tempdir().unwrap(), multipart, RegionRouting { region: "us-east-1".to_string(), ..Default::default() }).await
+    }
+
+    // Serves a directory laid out like dir(), such as a restored catalog
+    // next to a copy of another server's data
+    pub async fn start_in(dir: TempDir) -> Self {
+        Self::launch(dir, MultipartLimits::default(), RegionRouting { region: "us-east-1".to_string(), ..Default::default() }).await
+    }
+
+    // Serves us-east-1, with the nodes of other regions at `endpoints`
+    pub async fn start_with_regions(endpoints: &[(&str, &str)]) -> Self {
+        let endpoints = endpoints.iter().map(|(region, endpoint)| (region.to_string(), endpoint.to_string())).collect();
+        Self::launch(tempfile::tempdir().unwrap(), MultipartLimits::default(), RegionRouting { region: "us-east-1".to_string(), endpoints }).await
+    }
+
+    async fn launch(dir: TempDir, multipart: MultipartLimits, regions: RegionRouting) -> Self {
+        let database_url = format!("sqlite:{}?mode=rwc", dir.path().join("catalog.db").display());
+        ghostbay_catalog::migrations::ensure_database_exists(&database_url).await.unwrap();
+        let catalog = CatalogService::new(&database_url).await.unwrap();
+        ghostbay_catalog::migrations::run_migrations(catalog.pool()).await.unwrap();
+
+        let key = |(access_key_id, secret_access_key): (&str, &str), policy: &str| ProvisionedKey {
+            access_key_id: access_key_id.to_string(),
+            secret_access_key: secret_access_key.to_string(),
+            policies: vec![policy.to_string()],
+            description: None,
+            quota: Default::default(),
+        };
+        let provisioning = ProvisioningFile {
+            keys: vec![key(ADMIN_KEY, "admin"), key(USER_KEY, "read-write")],
+            buckets: Vec::new(),
+        };
+        apply_provisioning(catalog.pool(), &provisioning, false).await.unwrap();
+
+        let storage = create_storage_engine(StorageConfig {
+            data_dir: dir.path().join("data"),
+            temp_dir: dir.path().join("tmp"),
+            ..Default::default()
+        })
+        .unwrap();
+        let state = AppState {
+            auth: Arc::new(AuthService::new(catalog.pool().clone())),
+            catalog,
+            storage,
+            regions: Arc::new(regions),
+            multipart,
+            health: Arc::new(HealthState::new(ghostbay_catalog::database_file(&database_url))),
+            instance_id: Arc::from("test"),
+            rate_limiter: Arc::new(RateLimiter::default()),
+            part_locks: Default::default(),
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = ghostbay_gateway::build_app(state.clone(), false);
+        tokio::spawn(async move {
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+        });
+
+        Self { addr, state, dir }
+    }
+
+    // Holds the catalog, and the engine's data/ and tmp/ directories
+    pub fn dir(&self) -> &std::path::Path {
+        self.dir.path()
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn client(&self, (access_key_id, secret_access_key): (&str, &str)) -> aws_sdk_s3::Client {
+        let config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .endpoint_url(self.url())
+            .force_path_style(true)
+            .credentials_provider(Credentials::new(access_key_id, secret_access_key, None, None, "test"))
+            .build();
+        aws_sdk_s3::Client::from_conf(config)
+    }
+
+    pub fn admin(&self) -> aws_sdk_s3::Client {
+        self.client(ADMIN_KEY)
+    }
+
+    // A complete HTTP/1.1 request for `path_and_query`, signed in its
+    // Authorization header like an SDK would, that closes the connection
+    pub fn signed(&self, key: (&str, &str), method: &str, path_and_query: &str, body: &[u8]) -> Vec<u8> {
+        self.signed_with(key, method, path_and_query, &[], body)
+    }
+
+    pub fn signed_with(
+        &self,
+        (access_key_id, secret_access_key): (&str, &str),
+        method: &str,
+        path_and_query: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> Vec<u8> {
+        let host = self.addr.to_string();
+        // A caller's x-amz-content-sha256, such as a STREAMING-* value, is
+        // signed in place of the body's hash
+        let payload_hash = headers.iter().find(|(name, _)| *name == "x-amz-content-sha256").map(|(_, value)| *value);
+        let mut headers: Vec<(String, String)> = headers
+            .iter()
+            .filter(|(name, _)| *name != "x-amz-content-sha256")
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        headers.push(("host".to_string(), host.clone()));
+
+        let identity: Identity = Credentials::new(access_key_id, secret_access_key, None, None, "test").into();
+        // Signed the way S3 clients sign: the path as sent, encoded once
+        let mut settings = SigningSettings::default();
+        settings.payload_checksum_kind = PayloadChecksumKind::XAmzSha256;
+        settings.percent_encoding_mode = PercentEncodingMode::Single;
+        settings.uri_path_normalization_mode = UriPathNormalizationMode::Disabled;
+        let params = v4::SigningParams::builder()
+            .identity(&identity)
+            .region("us-east-1")
+            .name("s3")
+            .time(SystemTime::now())
+            .settings(settings)
+            .build()
+            .unwrap()
+            .into();
+        let url = format!("http://{}{}", host, path_and_query);
+        let signable = SignableRequest::new(
+            method,
+            url,
+            headers.iter().map(|(name, value)| (name.as_str(), value.as_str())),
+            payload_hash.map_or(SignableBody::Bytes(body), |hash| SignableBody::Precomputed(hash.to_string())),
+        )
+        .unwrap();
+        let (instructions, _) = sign(signable, &params).unwrap().into_parts();
+        headers.extend(instructions.headers().map(|(name, value)| (name.to_string(), value.to_string())));
+
+        let mut request = format!("{} {} HTTP/1.1\r\n", method, path_and_query);
+        for (name, value) in &headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str(&format!("content-length: {}\r\nconnection: close\r\n\r\n", body.len()));
+        let mut request = request.into_bytes();
+        request.extend_from_slice(body);
+        request
+    }
+
+    // A signed request with its content-length line swapped for `framing`,
+    // which may be empty, and `body` sent as-is. Neither is signed.
+    pub fn framed(&self, key: (&str, &str), method: &str, path: &str, headers: &[(&str, &str)], framing: &str, body: &[u8]) -> Vec<u8> {
+        let signed = self.signed_with(key, method, path, headers, b"");
+        let head = String::from_utf8(signed).unwrap().replace("content-length: 0\r\n", framing);
+        let mut request = head.into_bytes();
+        request.extend_from_slice(body);
+        request
+    }
+
+    // Writes `request` as-is and reads until the server closes the
+    // connection, so callers should send Connection: close
+    pub async fn raw(&self, request: &[u8]) -> RawResponse {
+        raw_to(self.addr, request).await
+    }
+}
+
+// Like TestServer::raw, for a router served by the test itself
+pub async fn raw_to(addr: SocketAddr, request: &[u8]) -> RawResponse {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(request).await.unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await.unwrap();
+    RawResponse::parse(&response)
+}
+
+pub struct RawResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl RawResponse {
+    pub fn parse(response: &[u8]) -> Self {
+        let response = String::from_utf8_lossy(response);
+        let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+        let mut lines = head.lines();
+        let status = lines.next().and_then(|line| line.split(' ').nth(1)).and_then(|code| code.parse().ok()).unwrap_or(0);
+        let headers: Vec<(String, String)> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .collect();
+        let chunked = headers.iter().any(|(name, value)| name == "transfer-encoding" && value == "chunked");
+        let body = if chunked { dechunk(body) } else { body.to_string() };
+        Self { status, headers, body }
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).unwrap_or_else(|e| panic!("{}: {}", e, self.body))
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(header, _)| header == name).map(|(_, value)| value.as_str())
+    }
+
+    // The <Code> of an S3 error body
+    pub fn error_code(&self) -> Option<&str> {
+        let start = self.body.find("<Code>")? + "<Code>".len();
+        let end = self.body[start..].find("</Code>")?;
+        Some(&self.body[start..start + end])
+    }
+}
+
+fn dechunk(mut body: &str) -> String {
+    let mut decoded = String::new();
+    while let Some((size, rest)) = body.split_once("\r\n") {
+        let size = usize::from_str_radix(size.split(';').next().unwrap_or_default().trim(), 16).unwrap_or(0);
+        if size == 0 || rest.len() < size {
+            break;
+        }
+        decoded.push_str(&rest[..size]);
+        body = rest[size..].strip_prefix("\r\n").unwrap_or(&rest[size..]);
+    }
+    decoded
+}
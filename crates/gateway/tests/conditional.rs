@@ -0,0 +1,102 @@
+mod common;
+
+use common::{RawResponse, TestServer, ADMIN_KEY};
+
+const PAST: &str = "Mon, 01 Jan 2001 00:00:00 GMT";
+const FUTURE: &str = "Fri, 01 Jan 2100 00:00:00 GMT";
+
+struct Object {
+    server: TestServer,
+    etag: String,
+}
+
+async fn object() -> Object {
+    let server = TestServer::start().await;
+    server.admin().create_bucket().bucket("cond").send().await.unwrap();
+    let response = server.raw(&server.signed(ADMIN_KEY, "PUT", "/cond/page.html", b"<p>hello</p>")).await;
+    assert_eq!(response.status, 200);
+    let etag = response.header("etag").unwrap().to_string();
+    Object { server, etag }
+}
+
+impl Object {
+    async fn request(&self, method: &str, headers: &[(&str, &str)]) -> RawResponse {
+        self.server.raw(&self.server.signed_with(ADMIN_KEY, method, "/cond/page.html", headers, b"")).await
+    }
+}
+
+fn assert_not_modified(response: &RawResponse, etag: &str) {
+    assert_eq!(response.status, 304, "{}", response.body);
+    assert_eq!(response.header("etag"), Some(etag));
+    assert_eq!(response.header("content-length"), Some("12"));
+    assert!(response.header("last-modified").is_some());
+    assert_eq!(response.body, "");
+}
+
+#[tokio::test]
+async fn not_modified() {
+    let object = object().await;
+    let weak = format!("W/{}", object.etag);
+    let listed = format!("\"0000\", {}", object.etag);
+    for method in ["GET", "HEAD"] {
+        for headers in [
+            vec![("if-none-match", object.etag.as_str())],
+            vec![("if-none-match", listed.as_str())],
+            vec![("if-none-match", "*")],
+            vec![("if-modified-since", FUTURE)],
+            // If-None-Match is evaluated in place of If-Modified-Since
+            vec![("if-none-match", object.etag.as_str()), ("if-modified-since", PAST)],
+        ] {
+            let response = object.request(method, &headers).await;
+            assert_not_modified(&response, &object.etag);
+        }
+
+        // Weak tags never match strongly, and a stale date does not count
+        // once If-None-Match is present
+        for headers in [
+            vec![("if-none-match", "\"0000\"")],
+            vec![("if-none-match", weak.as_str())],
+            vec![("if-modified-since", PAST)],
+            vec![("if-none-match", "\"0000\""), ("if-modified-since", FUTURE)],
+        ] {
+            let response = object.request(method, &headers).await;
+            assert_eq!(response.status, 200, "{} {:?}", method, headers);
+            assert_eq!(response.header("etag"), Some(object.etag.as_str()));
+        }
+    }
+    let response = object.request("GET", &[("if-none-match", "\"0000\"")]).await;
+    assert_eq!(response.body, "<p>hello</p>");
+}
+
+#[tokio::test]
+async fn precondition_failed() {
+    let object = object().await;
+    let weak = format!("W/{}", object.etag);
+    for headers in [
+        vec![("if-match", "\"0000\"")],
+        vec![("if-match", weak.as_str())],
+        vec![("if-unmodified-since", PAST)],
+        // A failed If-Match wins over a 304 from If-None-Match
+        vec![("if-match", "\"0000\""), ("if-none-match", object.etag.as_str())],
+    ] {
+        let response = object.request("GET", &headers).await;
+        assert_eq!((response.status, response.error_code()), (412, Some("PreconditionFailed")), "{:?}", headers);
+        let response = object.request("HEAD", &headers).await;
+        assert_eq!((response.status, response.body.as_str()), (412, ""), "{:?}", headers);
+    }
+
+    // If-Match is evaluated in place of If-Unmodified-Since
+    for headers in [
+        vec![("if-match", object.etag.as_str())],
+        vec![("if-match", "*")],
+        vec![("if-match", object.etag.as_str()), ("if-unmodified-since", PAST)],
+        vec![("if-unmodified-since", FUTURE)],
+    ] {
+        let response = object.request("GET", &headers).await;
+        assert_eq!((response.status, response.body.as_str()), (200, "<p>hello</p>"), "{:?}", headers);
+    }
+
+    // Both pass, but the object is unchanged
+    let response = object.request("GET", &[("if-match", object.etag.as_str()), ("if-none-match", object.etag.as_str())]).await;
+    assert_not_modified(&response, &object.etag);
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use axum::routing::get;
+use axum::Router;
+use common::{raw_to, RawResponse, TestServer, ADMIN_KEY};
+use std::net::SocketAddr;
+use tower_http::catch_panic::CatchPanicLayer;
+use quick_xml::events::Event;
+
+// The root element's name and its children in document order, as an XML
+// parser reads them
+fn parse(body: &str) -> (String, Vec<(String, String)>) {
+    let mut reader = quick_xml::Reader::from_str(body);
+    let mut path: Vec<String> = Vec::new();
+    let mut root = String::new();
+    let mut children = Vec::new();
+    loop {
+        match reader.read_event().unwrap() {
+            Event::Start(start) => {
+                let name = String::from_utf8(start.name().as_ref().to_vec()).unwrap();
+                if path.is_empty() {
+                    root = name.clone();
+                } else {
+                    assert_eq!(path.len(), 1, "{} is nested below {:?}", name, path);
+                    children.push((name.clone(), String::new()));
+                }
+                path.push(name);
+            }
+            Event::Text(text) if path.len() == 2 => {
+                children.last_mut().unwrap().1 = text.unescape().unwrap().into_owned();
+            }
+            Event::End(_) => {
+                path.pop();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    (root, children)
+}
+
+// Checks the error document and returns its elements by name
+fn error_document(response: &RawResponse, status: u16, code: &str, resource: &str) -> Vec<(String, String)> {
+    assert_eq!(response.status, status, "{}", response.body);
+    assert_eq!(response.header("content-type"), Some("application/xml"));
+    let (root, children) = parse(&response.body);
+    assert_eq!(root, "Error");
+    let value = |name: &str| children.iter().find(|(child, _)| child == name).map(|(_, value)| value.as_str());
+    assert_eq!(value("Code"), Some(code));
+    assert!(value("Message").is_some_and(|message| !message.is_empty()));
+    assert_eq!(value("Resource"), Some(resource));
+    assert_eq!(value("RequestId"), response.header("x-amz-request-id"));
+    let request_id = response.header("x-amz-request-id").unwrap();
+    assert!(request_id.len() == 16 && request_id.bytes().all(|byte| byte.is_ascii_digit() || byte.is_ascii_uppercase()), "{}", request_id);
+    assert_eq!(value("HostId"), response.header("x-amz-id-2"));
+    children
+}
+
+fn names(children: &[(String, String)]) -> Vec<&str> {
+    children.iter().map(|(name, _)| name.as_str()).collect()
+}
+
+#[tokio::test]
+async fn no_such_bucket() {
+    let server = TestServer::start().await;
+    let response = server.raw(&server.signed(ADMIN_KEY, "GET", "/missing/key.txt", b"")).await;
+    let children = error_document(&response, 404, "NoSuchBucket", "/missing/key.txt");
+    assert_eq!(names(&children), ["Code", "Message", "BucketName", "Resource", "RequestId", "HostId"]);
+    assert_eq!(children[2].1, "missing");
+
+    let error = server.admin().list_objects_v2().bucket("missing").send().await.unwrap_err();
+    assert!(error.into_service_error().is_no_such_bucket());
+}
+
+#[tokio::test]
+async fn no_such_key() {
+    let server = TestServer::start().await;
+    server.admin().create_bucket().bucket("present").send().await.unwrap();
+    let response = server.raw(&server.signed(ADMIN_KEY, "GET", "/present/dir/gone.txt", b"")).await;
+    let children = error_document(&response, 404, "NoSuchKey", "/present/dir/gone.txt");
+    assert_eq!(names(&children), ["Code", "Message", "Key", "Resource", "RequestId", "HostId"]);
+    assert_eq!(children[2].1, "dir/gone.txt");
+
+    let error = server.admin().get_object().bucket("present").key("dir/gone.txt").send().await.unwrap_err();
+    assert!(error.into_service_error().is_no_such_key());
+}
+
+#[tokio::test]
+async fn invalid_bucket_name() {
+    let server = TestServer::start().await;
+    let response = server.raw(&server.signed(ADMIN_KEY, "PUT", "/Not_Valid", b"")).await;
+    let children = error_document(&response, 400, "InvalidBucketName", "/Not_Valid");
+    assert_eq!(names(&children), ["Code", "Message", "BucketName", "Resource", "RequestId", "HostId"]);
+    assert_eq!(children[2].1, "Not_Valid");
+
+    let error = server.admin().create_bucket().bucket("ab").send().await.unwrap_err();
+    assert_eq!(error.code(), Some("InvalidBucketName"));
+}
+
+async fn explode() -> &'static str {
+    panic!("handler exploded")
+}
+
+// No real handler panics on purpose, so this serves a router of its own
+// with the panic handling and request context layers of build_app
+#[tokio::test]
+async fn panicking_handler() {
+    let server = TestServer::start().await;
+    ghostbay_api::middleware::install_panic_hook();
+    let app = Router::new()
+        .route("/boom/key.txt", get(explode))
+        .route("/fine", get(|| async { "fine" }))
+        .layer(CatchPanicLayer::custom(ghostbay_api::middleware::handle_panic))
+        .layer(axum::middleware::from_fn_with_state(
+            server.state.clone(),
+            ghostbay_api::middleware::request_context,
+        ));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+    });
+
+    let request = |path: &str| format!("GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", path, addr);
+    let response = raw_to(addr, request("/boom/key.txt").as_bytes()).await;
+    let children = error_document(&response, 500, "InternalError", "/boom/key.txt");
+    assert_eq!(names(&children), ["Code", "Message", "Resource", "RequestId", "HostId"]);
+    // The panic message stays in the log
+    assert!(!response.body.contains("exploded"), "{}", response.body);
+
+    // And the server keeps serving
+    let response = raw_to(addr, request("/fine").as_bytes()).await;
+    assert_eq!((response.status, response.body.as_str()), (200, "fine"));
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use common::{RawResponse, TestServer, ADMIN_KEY};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+const BODY: &[u8] = b"only sent once the server asks for it";
+
+// Sends the head of a signed request with Expect: 100-continue, holding
+// back the body
+async fn send_head(server: &TestServer, path: &str) -> TcpStream {
+    let request = server.signed_with(ADMIN_KEY, "PUT", path, &[("expect", "100-continue")], BODY);
+    let head_len = request.windows(4).position(|window| window == b"\r\n\r\n").unwrap() + 4;
+    let mut stream = TcpStream::connect(server.addr).await.unwrap();
+    stream.write_all(&request[..head_len]).await.unwrap();
+    stream
+}
+
+// Everything the server writes until it closes the connection
+async fn read_all(stream: &mut TcpStream) -> String {
+    let mut response = Vec::new();
+    tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut response)).await.unwrap().unwrap();
+    String::from_utf8(response).unwrap()
+}
+
+// A request that fails its checks is answered without the body: no 100
+// Continue, just the error
+#[tokio::test]
+async fn rejected_before_the_body_is_sent() {
+    let server = TestServer::start().await;
+    server.admin().create_bucket().bucket("present").send().await.unwrap();
+
+    for (path, status, code) in [
+        ("/missing/key.txt", 404, "NoSuchBucket"),
+        ("/present/key.txt?partNumber=1&uploadId=no-such-upload", 404, "NoSuchUpload"),
+    ] {
+        let mut stream = send_head(&server, path).await;
+        let response = read_all(&mut stream).await;
+        assert!(!response.contains("100 Continue"), "{}", response);
+        let response = RawResponse::parse(response.as_bytes());
+        assert_eq!((response.status, response.error_code()), (status, Some(code)), "{}: {}", path, response.body);
+    }
+    let listing = server.admin().list_objects_v2().bucket("present").send().await.unwrap();
+    assert_eq!(listing.key_count(), Some(0));
+}
+
+#[tokio::test]
+async fn accepted_with_100_continue() {
+    let server = TestServer::start().await;
+    server.admin().create_bucket().bucket("present").send().await.unwrap();
+
+    let mut stream = send_head(&server, "/present/key.txt").await;
+    // Nothing but the interim response arrives until the body is sent
+    let mut interim = Vec::new();
+    while !interim.ends_with(b"\r\n\r\n") {
+        let mut byte = [0u8; 1];
+        let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut byte)).await.unwrap().unwrap();
+        assert_eq!(read, 1, "closed after {:?}", String::from_utf8_lossy(&interim));
+        interim.push(byte[0]);
+    }
+    assert_eq!(String::from_utf8(interim).unwrap().to_lowercase(), "http/1.1 100 continue\r\n\r\n");
+
+    stream.write_all(BODY).await.unwrap();
+    let response = RawResponse::parse(read_all(&mut stream).await.as_bytes());
+    assert_eq!(response.status, 200, "{}", response.body);
+
+    let response = server.raw(&server.signed(ADMIN_KEY, "GET", "/present/key.txt", b"")).await;
+    assert_eq!((response.status, response.body.as_bytes()), (200, BODY));
+}
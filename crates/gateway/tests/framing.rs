@@ -0,0 +1,132 @@
+mod common;
+
+use common::{RawResponse, TestServer, ADMIN_KEY};
+
+const UNSIGNED: &[(&str, &str)] = &[("x-amz-content-sha256", "UNSIGNED-PAYLOAD")];
+const AWS_CHUNKED: &[(&str, &str)] = &[
+    ("content-encoding", "aws-chunked"),
+    ("x-amz-content-sha256", "STREAMING-UNSIGNED-PAYLOAD-TRAILER"),
+    ("x-amz-decoded-content-length", "5"),
+];
+
+async fn server_with_bucket() -> TestServer {
+    let server = TestServer::start().await;
+    server.admin().create_bucket().bucket("framing").send().await.unwrap();
+    server
+}
+
+async fn stored(server: &TestServer, key: &str) -> Option<Vec<u8>> {
+    let object = server.admin().get_object().bucket("framing").key(key).send().await.ok()?;
+    Some(object.body.collect().await.unwrap().into_bytes().to_vec())
+}
+
+fn assert_error(response: &RawResponse, status: u16, code: &str) {
+    assert_eq!((response.status, response.error_code()), (status, Some(code)), "{}", response.body);
+}
+
+#[tokio::test]
+async fn put_without_a_length_is_length_required() {
+    let server = server_with_bucket().await;
+    let response = server.raw(&server.framed(ADMIN_KEY, "PUT", "/framing/unframed", &[], "", b"")).await;
+    assert_error(&response, 411, "MissingContentLength");
+    assert_eq!(stored(&server, "unframed").await, None);
+
+    let upload = server.admin().create_multipart_upload().bucket("framing").key("parts").send().await.unwrap();
+    let path = format!("/framing/parts?partNumber=1&uploadId={}", upload.upload_id().unwrap());
+    let response = server.raw(&server.framed(ADMIN_KEY, "PUT", &path, &[], "", b"")).await;
+    assert_error(&response, 411, "MissingContentLength");
+}
+
+#[tokio::test]
+async fn malformed_content_length_is_refused_before_the_body() {
+    let server = server_with_bucket().await;
+    // hyper refuses these while parsing the request head, before it reaches
+    // the router, so there is no S3 error body
+    for length in ["-1", "ten", "5, 6"] {
+        let framing = format!("content-length: {}\r\n", length);
+        let response = server.raw(&server.framed(ADMIN_KEY, "PUT", "/framing/bad", UNSIGNED, &framing, b"hello")).await;
+        assert_eq!((response.status, response.error_code()), (400, None), "{}", length);
+    }
+    assert_eq!(stored(&server, "bad").await, None);
+}
+
+#[tokio::test]
+async fn chunked_transfer_encoding_frames_the_body() {
+    let server = server_with_bucket().await;
+    let framing = "transfer-encoding: chunked\r\n";
+    let body = b"5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n";
+    let response = server.raw(&server.framed(ADMIN_KEY, "PUT", "/framing/chunked", UNSIGNED, framing, body)).await;
+    assert_eq!(response.status, 200, "{}", response.body);
+    assert_eq!(stored(&server, "chunked").await.as_deref(), Some(&b"hello world"[..]));
+}
+
+#[tokio::test]
+async fn bodies_on_reads_and_deletes_are_ignored() {
+    let server = server_with_bucket().await;
+    let response = server.raw(&server.signed(ADMIN_KEY, "PUT", "/framing/kept", b"kept")).await;
+    assert_eq!(response.status, 200);
+
+    let framing = "content-length: 7\r\n";
+    let response = server.raw(&server.framed(ADMIN_KEY, "GET", "/framing/kept", UNSIGNED, framing, b"ignored")).await;
+    assert_eq!((response.status, response.body.as_str()), (200, "kept"));
+    let response = server.raw(&server.framed(ADMIN_KEY, "HEAD", "/framing/kept", UNSIGNED, framing, b"ignored")).await;
+    assert_eq!((response.status, response.header("content-length")), (200, Some("4")));
+    let response = server.raw(&server.framed(ADMIN_KEY, "DELETE", "/framing/kept", UNSIGNED, framing, b"ignored")).await;
+    assert_eq!(response.status, 204);
+    assert_eq!(stored(&server, "kept").await, None);
+}
+
+#[tokio::test]
+async fn aws_chunked_upload_is_decoded() {
+    let server = server_with_bucket().await;
+    let body = b"5\r\nhello\r\n0\r\n\r\n";
+    let framing = format!("content-length: {}\r\n", body.len());
+    let response = server.raw(&server.framed(ADMIN_KEY, "PUT", "/framing/decoded", AWS_CHUNKED, &framing, body)).await;
+    assert_eq!(response.status, 200, "{}", response.body);
+    assert_eq!(stored(&server, "decoded").await.as_deref(), Some(&b"hello"[..]));
+}
+
+#[tokio::test]
+async fn malformed_aws_chunked_bodies_are_invalid_requests() {
+    let server = server_with_bucket().await;
+    let long_header = format!("5;{}\r\nhello\r\n0\r\n\r\n", "x".repeat(5000));
+    let cases: [(&str, &[u8]); 5] = [
+        ("data longer than declared", b"5\r\nhello!\r\n0\r\n\r\n"),
+        ("body ends inside a chunk", b"5\r\nhel"),
+        ("size is not hexadecimal", b"five\r\nhello\r\n0\r\n\r\n"),
+        ("header is too long", long_header.as_bytes()),
+        ("trailer is not a header", b"5\r\nhello\r\n0\r\nnot a trailer\r\n\r\n"),
+    ];
+    for (case, body) in cases {
+        let framing = format!("content-length: {}\r\n", body.len());
+        let response = server.raw(&server.framed(ADMIN_KEY, "PUT", "/framing/malformed", AWS_CHUNKED, &framing, body)).await;
+        assert_eq!((response.status, response.error_code()), (400, Some("InvalidRequest")), "{}: {}", case, response.body);
+    }
+    assert_eq!(stored(&server, "malformed").await, None);
+}
+
+#[tokio::test]
+async fn aws_chunked_needs_a_decoded_length() {
+    let server = server_with_bucket().await;
+    let headers = &AWS_CHUNKED[..2];
+    let body = b"5\r\nhello\r\n0\r\n\r\n";
+    let framing = format!("content-length: {}\r\n", body.len());
+    let response = server.raw(&server.framed(ADMIN_KEY, "PUT", "/framing/undecoded", headers, &framing, body)).await;
+    assert_error(&response, 411, "MissingContentLength");
+}
+
+#[tokio::test]
+async fn forged_chunk_signatures_are_denied() {
+    let server = server_with_bucket().await;
+    let headers = &[
+        ("content-encoding", "aws-chunked"),
+        ("x-amz-content-sha256", "STREAMING-AWS4-HMAC-SHA256-PAYLOAD"),
+        ("x-amz-decoded-content-length", "5"),
+    ];
+    let forged = "0".repeat(64);
+    let body = format!("5;chunk-signature={}\r\nhello\r\n0;chunk-signature={}\r\n\r\n", forged, forged);
+    let framing = format!("content-length: {}\r\n", body.len());
+    let response = server.raw(&server.framed(ADMIN_KEY, "PUT", "/framing/forged", headers, &framing, body.as_bytes())).await;
+    assert_error(&response, 403, "AccessDenied");
+    assert_eq!(stored(&server, "forged").await, None);
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use aws_sdk_s3::primitives::ByteStream;
+use common::TestServer;
+use ghostbay_api::health::refresh_health_stats;
+
+async fn health(server: &TestServer) -> serde_json::Value {
+    let response = server.raw(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await;
+    assert_eq!(response.status, 200, "{}", response.body);
+    response.json()
+}
+
+// What the gateway's refresh job does on each tick
+async fn refresh(server: &TestServer) {
+    let result = refresh_health_stats(&server.state).await;
+    server.state.health.record_job("health_refresh", &result);
+    result.unwrap();
+}
+
+#[tokio::test]
+async fn counters_change_only_when_refreshed() {
+    let server = TestServer::start().await;
+    let client = server.admin();
+    let body = health(&server).await;
+    assert!(body["stats"].is_null(), "{}", body);
+    assert_eq!(body["jobs"], serde_json::json!({}));
+    assert_eq!(body["jobs_failing"], false);
+
+    for bucket in ["first", "second"] {
+        client.create_bucket().bucket(bucket).send().await.unwrap();
+    }
+    client.put_object().bucket("first").key("a").body(ByteStream::from_static(b"hello")).send().await.unwrap();
+    client.put_object().bucket("second").key("b").body(ByteStream::from_static(b"0123456789")).send().await.unwrap();
+    // Served from the cache, never counted per request
+    assert!(health(&server).await["stats"].is_null());
+
+    refresh(&server).await;
+    let body = health(&server).await;
+    let stats = &body["stats"];
+    assert_eq!((&stats["buckets"], &stats["objects"], &stats["bytes"]), (&2.into(), &2.into(), &15.into()), "{}", body);
+    assert!(stats["data_dir_free_bytes"].as_u64().is_some_and(|free| free > 0), "{}", body);
+    let catalog_file = std::fs::metadata(server.dir().join("catalog.db")).unwrap().len();
+    assert!(stats["catalog_file_bytes"].as_u64().is_some_and(|bytes| bytes > 0 && bytes <= catalog_file), "{}", body);
+    let first_refresh = chrono::DateTime::parse_from_rfc3339(stats["refreshed_at"].as_str().unwrap()).unwrap();
+    assert_eq!(body["jobs"]["health_refresh"]["failing"], false);
+    assert!(body["jobs"]["health_refresh"]["last_error"].is_null());
+
+    client.delete_object().bucket("first").key("a").send().await.unwrap();
+    client.delete_bucket().bucket("first").send().await.unwrap();
+    assert_eq!(health(&server).await["stats"]["objects"], 2);
+    refresh(&server).await;
+    let body = health(&server).await;
+    let stats = &body["stats"];
+    assert_eq!((&stats["buckets"], &stats["objects"], &stats["bytes"]), (&1.into(), &1.into(), &10.into()), "{}", body);
+    let second_refresh = chrono::DateTime::parse_from_rfc3339(stats["refreshed_at"].as_str().unwrap()).unwrap();
+    assert!(second_refresh >= first_refresh);
+
+    // A failing job is reported without touching the counters
+    server.state.health.record_job("catalog_backup", &Err(anyhow::anyhow!("disk full")));
+    let body = health(&server).await;
+    assert_eq!(body["jobs_failing"], true);
+    assert_eq!(body["jobs"]["catalog_backup"]["last_error"], "disk full");
+    assert_eq!(body["jobs"]["health_refresh"]["failing"], false);
+    assert_eq!(body["stats"]["objects"], 1);
+}
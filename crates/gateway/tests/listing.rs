@@ -0,0 +1,129 @@
+mod common;
+
+use aws_sdk_s3::primitives::ByteStream;
+use common::TestServer;
+use tokio::task::JoinSet;
+
+const OBJECTS: usize = 2500;
+
+// Filled through the API once, since writing 2500 objects dominates the run
+async fn bucket_with_objects() -> (TestServer, Vec<String>) {
+    let server = TestServer::start().await;
+    let client = server.admin();
+    client.create_bucket().bucket("many").send().await.unwrap();
+
+    // Keys sort differently from their numbers, and are written out of order
+    let mut keys: Vec<String> = (0..OBJECTS).map(|i| format!("dir{}/obj-{}", i % 7, i)).collect();
+    let mut uploads = JoinSet::new();
+    for (worker, chunk) in keys.chunks(OBJECTS / 10).enumerate() {
+        let client = client.clone();
+        let mut chunk = chunk.to_vec();
+        if worker % 2 == 1 {
+            chunk.reverse();
+        }
+        uploads.spawn(async move {
+            for key in chunk {
+                client.put_object().bucket("many").key(&key).body(ByteStream::from_static(b"x")).send().await.unwrap();
+            }
+        });
+    }
+    while let Some(upload) = uploads.join_next().await {
+        upload.unwrap();
+    }
+
+    keys.sort();
+    (server, keys)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pages_stitch_together_without_gaps_or_duplicates() {
+    let (server, expected) = bucket_with_objects().await;
+    let client = server.admin();
+
+    let mut listed = Vec::new();
+    let mut pages = Vec::new();
+    let mut token = None;
+    loop {
+        let page = client.list_objects_v2().bucket("many").set_continuation_token(token.clone()).send().await.unwrap();
+        assert_eq!(page.continuation_token(), token.as_deref());
+        let keys: Vec<String> = page.contents().iter().map(|object| object.key().unwrap().to_string()).collect();
+        assert_eq!(page.key_count(), Some(keys.len() as i32));
+        pages.push((keys.len(), page.is_truncated()));
+        listed.extend(keys);
+        token = page.next_continuation_token().map(str::to_string);
+        if token.is_none() {
+            break;
+        }
+    }
+    assert_eq!(pages, [(1000, Some(true)), (1000, Some(true)), (500, Some(false))]);
+    // Sorted and equal to every key once: no gaps, duplicates or reordering
+    assert_eq!(listed, expected);
+
+    let start_after = &expected[1499];
+    let page = client.list_objects_v2().bucket("many").start_after(start_after).send().await.unwrap();
+    let keys: Vec<&str> = page.contents().iter().map(|object| object.key().unwrap()).collect();
+    assert_eq!(keys, expected[1500..]);
+    assert_eq!(page.is_truncated(), Some(false));
+
+    // A continuation token takes over from start-after
+    let first = client.list_objects_v2().bucket("many").max_keys(700).send().await.unwrap();
+    let token = first.next_continuation_token().unwrap();
+    let next = client
+        .list_objects_v2()
+        .bucket("many")
+        .max_keys(700)
+        .start_after(&expected[2000])
+        .continuation_token(token)
+        .send()
+        .await
+        .unwrap();
+    let keys: Vec<&str> = next.contents().iter().map(|object| object.key().unwrap()).collect();
+    assert_eq!(keys, expected[700..1400]);
+    assert_eq!(next.start_after(), Some(expected[2000].as_str()));
+
+    // The SDK's paginator walks every page the same way
+    let mut paginated = Vec::new();
+    let mut pages = client.list_objects_v2().bucket("many").max_keys(999).into_paginator().send();
+    while let Some(page) = pages.next().await {
+        paginated.extend(page.unwrap().contents().iter().map(|object| object.key().unwrap().to_string()));
+    }
+    assert_eq!(paginated, expected);
+}
+
+// ListObjects v1 pages by marker: NextMarker only comes with a delimiter,
+// otherwise the client resumes from the last key it was sent
+#[tokio::test]
+async fn version_1_pages_by_marker() {
+    let server = TestServer::start().await;
+    let client = server.admin();
+    client.create_bucket().bucket("marked").send().await.unwrap();
+    for key in ["a.txt", "b.txt", "dogs/rex", "dogs/spot", "e.txt"] {
+        client.put_object().bucket("marked").key(key).body(ByteStream::from_static(b"x")).send().await.unwrap();
+    }
+
+    let mut listed = Vec::new();
+    let mut marker = None;
+    loop {
+        let page = client.list_objects().bucket("marked").max_keys(2).set_marker(marker.clone()).send().await.unwrap();
+        assert_eq!(page.marker(), Some(marker.as_deref().unwrap_or("")));
+        assert_eq!(page.next_marker(), None);
+        let keys: Vec<String> = page.contents().iter().map(|object| object.key().unwrap().to_string()).collect();
+        marker = keys.last().cloned().filter(|_| page.is_truncated() == Some(true));
+        listed.extend(keys);
+        if marker.is_none() {
+            break;
+        }
+    }
+    assert_eq!(listed, ["a.txt", "b.txt", "dogs/rex", "dogs/spot", "e.txt"]);
+
+    let first = client.list_objects().bucket("marked").delimiter("/").max_keys(3).send().await.unwrap();
+    let prefixes: Vec<&str> = first.common_prefixes().iter().map(|prefix| prefix.prefix().unwrap()).collect();
+    assert_eq!((first.contents().len(), prefixes), (2, vec!["dogs/"]));
+    assert_eq!(first.is_truncated(), Some(true));
+    assert_eq!(first.next_marker(), Some("dogs/"));
+    // Resuming from a common prefix skips the keys rolled up into it
+    let next = client.list_objects().bucket("marked").delimiter("/").marker("dogs/").send().await.unwrap();
+    let keys: Vec<&str> = next.contents().iter().map(|object| object.key().unwrap()).collect();
+    assert_eq!(keys, ["e.txt"]);
+    assert_eq!((next.is_truncated(), next.next_marker()), (Some(false), None));
+}
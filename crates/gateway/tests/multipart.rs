@@ -0,0 +1,238 @@
+mod common;
+
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use common::{TestServer, ADMIN_KEY};
+use ghostbay_api::MultipartLimits;
+use ghostbay_catalog::{MultipartPartRepository, MultipartUploadRepository};
+use md5::{Digest, Md5};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const PART_SIZE: usize = 64 * 1024;
+
+fn limits() -> MultipartLimits {
+    MultipartLimits { min_part_size: PART_SIZE as u64, ..Default::default() }
+}
+
+fn part_body(part_number: i32, len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i as u8).wrapping_add(part_number as u8 * 31)).collect()
+}
+
+#[tokio::test]
+async fn sdk_multipart_upload_completes() {
+    let server = TestServer::start_with(limits()).await;
+    let client = server.admin();
+    client.create_bucket().bucket("big").send().await.unwrap();
+
+    let upload = client
+        .create_multipart_upload()
+        .bucket("big")
+        .key("video/raw.bin")
+        .content_type("video/mp4")
+        .send()
+        .await
+        .unwrap();
+    let upload_id = upload.upload_id().unwrap();
+
+    let sizes = [PART_SIZE, PART_SIZE, 1000];
+    let mut expected = Vec::new();
+    let mut completed = Vec::new();
+    let mut digests = Vec::new();
+    for (index, size) in sizes.into_iter().enumerate() {
+        let part_number = index as i32 + 1;
+        let body = part_body(part_number, size);
+        expected.extend_from_slice(&body);
+        digests.push(Md5::digest(&body).to_vec());
+        let part = client
+            .upload_part()
+            .bucket("big")
+            .key("video/raw.bin")
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .body(ByteStream::from(body))
+            .send()
+            .await
+            .unwrap();
+        completed.push(CompletedPart::builder().part_number(part_number).e_tag(part.e_tag().unwrap()).build());
+    }
+
+    let parts = client.list_parts().bucket("big").key("video/raw.bin").upload_id(upload_id).send().await.unwrap();
+    let listed: Vec<(i32, i64)> = parts.parts().iter().map(|part| (part.part_number().unwrap(), part.size().unwrap())).collect();
+    assert_eq!(listed, [(1, PART_SIZE as i64), (2, PART_SIZE as i64), (3, 1000)]);
+    // Each part is recorded with the binary digest its hex ETag spells out
+    let pool = server.state.catalog.pool().clone();
+    let recorded = MultipartUploadRepository::new(pool.clone()).find_by_upload_id(upload_id).await.unwrap().unwrap();
+    let recorded = MultipartPartRepository::new(pool).list_by_upload(recorded.id).await.unwrap();
+    let recorded: Vec<Option<Vec<u8>>> = recorded.into_iter().map(|part| part.digest).collect();
+    assert_eq!(recorded, digests.iter().cloned().map(Some).collect::<Vec<_>>());
+
+    let result = client
+        .complete_multipart_upload()
+        .bucket("big")
+        .key("video/raw.bin")
+        .upload_id(upload_id)
+        .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(completed)).build())
+        .send()
+        .await
+        .unwrap();
+    let etag = result.e_tag().unwrap().to_string();
+    // MD5 over the concatenated part digests, then the part count, as S3 reports
+    assert_eq!(etag, format!("\"{}-3\"", hex::encode(Md5::digest(digests.concat()))));
+    assert_eq!(result.key(), Some("video/raw.bin"));
+
+    let object = client.get_object().bucket("big").key("video/raw.bin").send().await.unwrap();
+    assert_eq!(object.e_tag(), Some(etag.as_str()));
+    assert_eq!(object.content_type(), Some("video/mp4"));
+    assert_eq!(object.content_length(), Some(expected.len() as i64));
+    assert!(object.body.collect().await.unwrap().into_bytes() == expected);
+
+    // The upload is gone once completed
+    let error = client.list_parts().bucket("big").key("video/raw.bin").upload_id(upload_id).send().await.unwrap_err();
+    assert_eq!(error.code(), Some("NoSuchUpload"));
+}
+
+#[tokio::test]
+async fn sdk_multipart_upload_rejects_small_parts_and_aborts() {
+    let server = TestServer::start_with(limits()).await;
+    let client = server.admin();
+    client.create_bucket().bucket("big").send().await.unwrap();
+    let upload = client.create_multipart_upload().bucket("big").key("small").send().await.unwrap();
+    let upload_id = upload.upload_id().unwrap();
+
+    let mut completed = Vec::new();
+    for part_number in 1..=2 {
+        let part = client
+            .upload_part()
+            .bucket("big")
+            .key("small")
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .body(ByteStream::from(part_body(part_number, 100)))
+            .send()
+            .await
+            .unwrap();
+        completed.push(CompletedPart::builder().part_number(part_number).e_tag(part.e_tag().unwrap()).build());
+    }
+
+    // Only the last part may be below the minimum
+    let error = client
+        .complete_multipart_upload()
+        .bucket("big")
+        .key("small")
+        .upload_id(upload_id)
+        .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(completed)).build())
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Some("EntityTooSmall"));
+
+    client.abort_multipart_upload().bucket("big").key("small").upload_id(upload_id).send().await.unwrap();
+    let uploads = client.list_multipart_uploads().bucket("big").send().await.unwrap();
+    assert!(uploads.uploads().is_empty());
+    assert!(client.head_object().bucket("big").key("small").send().await.is_err());
+}
+
+fn md5_etag(body: &[u8]) -> String {
+    format!("\"{}\"", hex::encode(Md5::digest(body)))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn concurrent_uploads_of_the_same_part_leave_one_whole_part() {
+    let server = TestServer::start_with(limits()).await;
+    let client = server.admin();
+    client.create_bucket().bucket("big").send().await.unwrap();
+    let bodies = [part_body(1, 4 * PART_SIZE), part_body(2, 3 * PART_SIZE)];
+
+    for round in 0..10 {
+        let key = format!("race-{}", round);
+        let upload = client.create_multipart_upload().bucket("big").key(&key).send().await.unwrap();
+        let upload_id = upload.upload_id().unwrap().to_string();
+
+        let uploads = bodies.clone().map(|body| {
+            let (client, key, upload_id) = (client.clone(), key.clone(), upload_id.clone());
+            tokio::spawn(async move {
+                let part = client
+                    .upload_part()
+                    .bucket("big")
+                    .key(&key)
+                    .upload_id(&upload_id)
+                    .part_number(1)
+                    .body(ByteStream::from(body))
+                    .send()
+                    .await
+                    .unwrap();
+                part.e_tag().unwrap().to_string()
+            })
+        });
+        for (upload, body) in uploads.into_iter().zip(&bodies) {
+            assert_eq!(upload.await.unwrap(), md5_etag(body));
+        }
+
+        // Whichever write landed last, the listing, the stored bytes and the
+        // completed object all agree on it
+        let parts = client.list_parts().bucket("big").key(&key).upload_id(&upload_id).send().await.unwrap();
+        let [part] = parts.parts() else { panic!("{:?}", parts.parts()) };
+        let etag = part.e_tag().unwrap();
+        let body = bodies.iter().find(|body| md5_etag(body) == etag).expect("listed ETag is one of the uploads");
+        assert_eq!(part.size(), Some(body.len() as i64));
+
+        client
+            .complete_multipart_upload()
+            .bucket("big")
+            .key(&key)
+            .upload_id(&upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .parts(CompletedPart::builder().part_number(1).e_tag(etag).build())
+                    .build(),
+            )
+            .send()
+            .await
+            .unwrap();
+        let object = client.get_object().bucket("big").key(&key).send().await.unwrap();
+        assert!(object.body.collect().await.unwrap().into_bytes() == *body, "round {}", round);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_dir_outlives_a_part_write_in_flight() {
+    let server = TestServer::start_with(limits()).await;
+    let client = server.admin();
+    client.create_bucket().bucket("big").send().await.unwrap();
+    let upload = client.create_multipart_upload().bucket("big").key("slow").send().await.unwrap();
+    let upload_id = upload.upload_id().unwrap().to_string();
+    let upload_dir = server.dir().join("tmp").join(&upload_id);
+
+    // Send half of a part and hold the rest back
+    let body = part_body(1, PART_SIZE);
+    let path = format!("/big/slow?partNumber=1&uploadId={}", upload_id);
+    let framing = format!("content-length: {}\r\n", body.len());
+    let head = server.framed(ADMIN_KEY, "PUT", &path, &[("x-amz-content-sha256", "UNSIGNED-PAYLOAD")], &framing, b"");
+    let mut stream = tokio::net::TcpStream::connect(server.addr).await.unwrap();
+    stream.write_all(&head).await.unwrap();
+    stream.write_all(&body[..PART_SIZE / 2]).await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    // Aborting waits for the write rather than removing the dir under it
+    let abort = tokio::spawn({
+        let client = client.clone();
+        let upload_id = upload_id.clone();
+        async move { client.abort_multipart_upload().bucket("big").key("slow").upload_id(&upload_id).send().await }
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    assert!(!abort.is_finished());
+    assert!(upload_dir.join("metadata.json").exists());
+
+    stream.write_all(&body[PART_SIZE / 2..]).await.unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await.unwrap();
+    let response = String::from_utf8_lossy(&response);
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.to_lowercase().contains(&format!("etag: {}", md5_etag(&body))), "{}", response);
+
+    abort.await.unwrap().unwrap();
+    assert!(!upload_dir.exists());
+    let uploads = client.list_multipart_uploads().bucket("big").send().await.unwrap();
+    assert!(uploads.uploads().is_empty());
+}
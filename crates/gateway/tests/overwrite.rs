@@ -0,0 +1,157 @@
+mod common;
+
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{
+    BucketVersioningStatus, ChecksumAlgorithm, ChecksumMode, CompletedMultipartUpload, CompletedPart, VersioningConfiguration,
+};
+use common::{TestServer, ADMIN_KEY};
+
+// MD5s of "first version" and "second, longer version"
+const FIRST_ETAG: &str = "\"e9e2371570daec2e7b70faa4f0f1eab8\"";
+const SECOND_ETAG: &str = "\"2b2bdbe8123720a59b58123591110315\"";
+
+#[tokio::test]
+async fn put_replaces_an_existing_key() {
+    let server = TestServer::start().await;
+    server.admin().create_bucket().bucket("docs").send().await.unwrap();
+
+    let first = server.raw(&server.signed(ADMIN_KEY, "PUT", "/docs/readme.txt", b"first version")).await;
+    assert_eq!((first.status, first.header("etag")), (200, Some(FIRST_ETAG)), "{}", first.body);
+    let second = server.raw(&server.signed(ADMIN_KEY, "PUT", "/docs/readme.txt", b"second, longer version")).await;
+    assert_eq!((second.status, second.header("etag")), (200, Some(SECOND_ETAG)), "{}", second.body);
+
+    let object = server.admin().get_object().bucket("docs").key("readme.txt").send().await.unwrap();
+    assert_eq!(object.e_tag(), Some(SECOND_ETAG));
+    assert_eq!(object.content_length(), Some(22));
+    assert_eq!(object.body.collect().await.unwrap().into_bytes().as_ref(), b"second, longer version");
+
+    let listing = server.admin().list_objects_v2().bucket("docs").send().await.unwrap();
+    let listed: Vec<(&str, &str, i64)> = listing
+        .contents()
+        .iter()
+        .map(|object| (object.key().unwrap(), object.e_tag().unwrap(), object.size().unwrap()))
+        .collect();
+    assert_eq!(listed, [("readme.txt", SECOND_ETAG, 22)]);
+}
+
+#[tokio::test]
+async fn multipart_upload_replaces_an_existing_key() {
+    let server = TestServer::start().await;
+    let client = server.admin();
+    client.create_bucket().bucket("docs").send().await.unwrap();
+    client.put_object().bucket("docs").key("big.bin").body(ByteStream::from_static(b"small")).send().await.unwrap();
+
+    let upload = client.create_multipart_upload().bucket("docs").key("big.bin").send().await.unwrap();
+    let upload_id = upload.upload_id().unwrap();
+    let part = client
+        .upload_part()
+        .bucket("docs")
+        .key("big.bin")
+        .upload_id(upload_id)
+        .part_number(1)
+        .body(ByteStream::from_static(b"replaced by multipart"))
+        .send()
+        .await
+        .unwrap();
+    let completed = client
+        .complete_multipart_upload()
+        .bucket("docs")
+        .key("big.bin")
+        .upload_id(upload_id)
+        .multipart_upload(
+            CompletedMultipartUpload::builder()
+                .parts(CompletedPart::builder().part_number(1).e_tag(part.e_tag().unwrap()).build())
+                .build(),
+        )
+        .send()
+        .await
+        .unwrap();
+
+    let object = client.get_object().bucket("docs").key("big.bin").send().await.unwrap();
+    assert_eq!(object.e_tag(), completed.e_tag());
+    assert_eq!(object.body.collect().await.unwrap().into_bytes().as_ref(), b"replaced by multipart");
+    let listing = client.list_objects_v2().bucket("docs").send().await.unwrap();
+    assert_eq!(listing.key_count(), Some(1));
+}
+
+#[tokio::test]
+async fn versioned_put_keeps_the_previous_version() {
+    let server = TestServer::start().await;
+    let client = server.admin();
+    client.create_bucket().bucket("docs").send().await.unwrap();
+    client
+        .put_bucket_versioning()
+        .bucket("docs")
+        .versioning_configuration(VersioningConfiguration::builder().status(BucketVersioningStatus::Enabled).build())
+        .send()
+        .await
+        .unwrap();
+
+    let first = server.raw(&server.signed(ADMIN_KEY, "PUT", "/docs/readme.txt", b"first version")).await;
+    let second = server.raw(&server.signed(ADMIN_KEY, "PUT", "/docs/readme.txt", b"second, longer version")).await;
+    assert_eq!((second.status, second.header("etag")), (200, Some(SECOND_ETAG)));
+    let first_version = first.header("x-amz-version-id").unwrap();
+    assert_ne!(Some(first_version), second.header("x-amz-version-id"));
+
+    let latest = client.get_object().bucket("docs").key("readme.txt").send().await.unwrap();
+    assert_eq!(latest.e_tag(), Some(SECOND_ETAG));
+    let previous = client.get_object().bucket("docs").key("readme.txt").version_id(first_version).send().await.unwrap();
+    assert_eq!(previous.e_tag(), Some(FIRST_ETAG));
+    assert_eq!(previous.body.collect().await.unwrap().into_bytes().as_ref(), b"first version");
+}
+
+#[tokio::test]
+async fn previous_version_keeps_its_metadata() {
+    let server = TestServer::start().await;
+    let client = server.admin();
+    client.create_bucket().bucket("docs").send().await.unwrap();
+    client
+        .put_bucket_versioning()
+        .bucket("docs")
+        .versioning_configuration(VersioningConfiguration::builder().status(BucketVersioningStatus::Enabled).build())
+        .send()
+        .await
+        .unwrap();
+
+    let first = client
+        .put_object()
+        .bucket("docs")
+        .key("readme.txt")
+        .body(ByteStream::from_static(b"first version"))
+        .content_type("text/plain")
+        .metadata("author", "alice")
+        .cache_control("max-age=60")
+        .checksum_algorithm(ChecksumAlgorithm::Sha256)
+        .send()
+        .await
+        .unwrap();
+    client
+        .put_object()
+        .bucket("docs")
+        .key("readme.txt")
+        .body(ByteStream::from_static(b"second, longer version"))
+        .metadata("author", "bob")
+        .send()
+        .await
+        .unwrap();
+
+    let previous = client
+        .get_object()
+        .bucket("docs")
+        .key("readme.txt")
+        .version_id(first.version_id().unwrap())
+        .checksum_mode(ChecksumMode::Enabled)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(previous.e_tag(), Some(FIRST_ETAG));
+    assert_eq!(previous.content_type(), Some("text/plain"));
+    assert_eq!(previous.metadata().and_then(|metadata| metadata.get("author")).map(String::as_str), Some("alice"));
+    assert_eq!(previous.cache_control(), Some("max-age=60"));
+    assert_eq!(previous.checksum_sha256(), first.checksum_sha256());
+    assert!(previous.checksum_sha256().is_some());
+
+    let latest = client.head_object().bucket("docs").key("readme.txt").send().await.unwrap();
+    assert_eq!(latest.metadata().and_then(|metadata| metadata.get("author")).map(String::as_str), Some("bob"));
+    assert_eq!(latest.cache_control(), None);
+}
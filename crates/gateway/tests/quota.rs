@@ -0,0 +1,232 @@
+mod common;
+
+use aws_sdk_s3::primitives::ByteStream;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use common::{TestServer, ADMIN_KEY};
+use ghostbay_auth::{apply_provisioning, KeyQuota, ProvisionedKey, ProvisioningFile};
+use ghostbay_catalog::BucketRepository;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const METERED_KEY: (&str, &str) = ("GBTESTMETERED0000001", "metered-secret-000000000000000000000000000");
+
+#[tokio::test]
+async fn download_that_crosses_the_quota_finishes() {
+    let server = TestServer::start().await;
+    let provisioning = ProvisioningFile {
+        keys: vec![ProvisionedKey {
+            access_key_id: METERED_KEY.0.to_string(),
+            secret_access_key: METERED_KEY.1.to_string(),
+            policies: vec!["read-only".to_string()],
+            description: None,
+            quota: KeyQuota { max_bytes_out_per_month: Some(100_000), ..Default::default() },
+        }],
+        buckets: Vec::new(),
+    };
+    apply_provisioning(server.state.catalog.pool(), &provisioning, false).await.unwrap();
+
+    let body = vec![7u8; 60_000];
+    server.admin().create_bucket().bucket("media").send().await.unwrap();
+    server
+        .admin()
+        .put_object()
+        .bucket("media")
+        .key("clip.bin")
+        .body(ByteStream::from(body.clone()))
+        .send()
+        .await
+        .unwrap();
+
+    let client = server.client(METERED_KEY);
+    let usage = server.state.auth.usage().clone();
+    let download = || async {
+        let object = client.get_object().bucket("media").key("clip.bin").send().await?;
+        Ok::<_, Box<dyn std::error::Error>>(object.body.collect().await?.into_bytes())
+    };
+
+    assert_eq!(download().await.unwrap(), body);
+    // The second download starts under the limit and crosses it halfway,
+    // with the usage flushed while it streams
+    let (second, flushed) = tokio::join!(download(), usage.flush());
+    flushed.unwrap();
+    assert_eq!(second.unwrap(), body);
+
+    let refused = server.raw(&server.signed(METERED_KEY, "GET", "/media/clip.bin", b"")).await;
+    assert_eq!((refused.status, refused.error_code()), (403, Some("AccessDenied")));
+    assert!(refused.header("retry-after").is_some());
+    usage.flush().await.unwrap();
+    let refused = server.raw(&server.signed(METERED_KEY, "GET", "/media/clip.bin", b"")).await;
+    assert_eq!(refused.status, 403);
+
+    let month = usage.current(METERED_KEY.0, ghostbay_auth::UsagePeriod::Month).await.unwrap();
+    assert_eq!(month.bytes_out, 120_000);
+}
+
+const UNSIGNED: &[(&str, &str)] = &[("x-amz-content-sha256", "UNSIGNED-PAYLOAD")];
+const QUOTA: i64 = 2000;
+
+async fn capped_bucket() -> TestServer {
+    let server = TestServer::start().await;
+    server.admin().create_bucket().bucket("capped").send().await.unwrap();
+    BucketRepository::new(server.state.catalog.pool().clone()).set_quota("capped", Some(QUOTA)).await.unwrap();
+    let response = server.raw(&server.signed(ADMIN_KEY, "PUT", "/capped/existing", &[b'e'; 500])).await;
+    assert_eq!(response.status, 200);
+    server
+}
+
+// HTTP chunked framing of `body` in 100-byte chunks
+fn chunked(body: &[u8]) -> Vec<u8> {
+    let mut framed = Vec::new();
+    for chunk in body.chunks(100) {
+        framed.extend_from_slice(format!("{:x}\r\n", chunk.len()).as_bytes());
+        framed.extend_from_slice(chunk);
+        framed.extend_from_slice(b"\r\n");
+    }
+    framed.extend_from_slice(b"0\r\n\r\n");
+    framed
+}
+
+async fn assert_nothing_left(server: &TestServer, key: &str) {
+    assert!(server.admin().head_object().bucket("capped").key(key).send().await.is_err());
+    let temp_files: Vec<_> = std::fs::read_dir(server.dir().join("tmp")).map(|dir| dir.collect()).unwrap_or_default();
+    assert!(temp_files.is_empty(), "{:?}", temp_files);
+}
+
+#[tokio::test]
+async fn chunked_upload_without_a_length_is_held_to_the_bucket_quota() {
+    let server = capped_bucket().await;
+    let framing = "transfer-encoding: chunked\r\n";
+
+    // 500 bytes stored, so 1500 still fit
+    let body = chunked(&[b'a'; 1500]);
+    let response = server.raw(&server.framed(ADMIN_KEY, "PUT", "/capped/fits", UNSIGNED, framing, &body)).await;
+    assert_eq!(response.status, 200, "{}", response.body);
+
+    let body = chunked(&[b'b'; 5000]);
+    let response = server.raw(&server.framed(ADMIN_KEY, "PUT", "/capped/over", UNSIGNED, framing, &body)).await;
+    assert_eq!((response.status, response.error_code()), (507, Some("QuotaExceeded")), "{}", response.body);
+    assert_nothing_left(&server, "over").await;
+
+    // Replacing an object frees its bytes first
+    let body = chunked(&[b'c'; 500]);
+    let response = server.raw(&server.framed(ADMIN_KEY, "PUT", "/capped/fits", UNSIGNED, framing, &body)).await;
+    assert_eq!(response.status, 200, "{}", response.body);
+}
+
+#[tokio::test]
+async fn aws_chunked_upload_is_checked_by_its_decoded_length() {
+    let server = capped_bucket().await;
+    let headers = &[
+        ("content-encoding", "aws-chunked"),
+        ("x-amz-content-sha256", "STREAMING-UNSIGNED-PAYLOAD-TRAILER"),
+        ("x-amz-decoded-content-length", "5000"),
+    ];
+    // Refused from the declared length, before any of the body is read
+    let response = server.raw(&server.framed(ADMIN_KEY, "PUT", "/capped/declared", headers, "content-length: 5000\r\n", b"")).await;
+    assert_eq!((response.status, response.error_code()), (507, Some("QuotaExceeded")), "{}", response.body);
+    assert!(response.body.contains("<ProposedSize>5000</ProposedSize>"), "{}", response.body);
+    assert_nothing_left(&server, "declared").await;
+}
+
+#[tokio::test]
+async fn upload_part_without_a_length_is_held_to_the_bucket_quota() {
+    let server = capped_bucket().await;
+    let upload = server.admin().create_multipart_upload().bucket("capped").key("parts").send().await.unwrap();
+    let path = format!("/capped/parts?partNumber=1&uploadId={}", upload.upload_id().unwrap());
+    let body = chunked(&[b'p'; 5000]);
+    let response = server.raw(&server.framed(ADMIN_KEY, "PUT", &path, UNSIGNED, "transfer-encoding: chunked\r\n", &body)).await;
+    assert_eq!((response.status, response.error_code()), (507, Some("QuotaExceeded")), "{}", response.body);
+}
+
+#[tokio::test]
+async fn post_upload_is_held_to_the_bucket_quota() {
+    let server = capped_bucket().await;
+    let now = std::time::SystemTime::now();
+    let date = chrono::DateTime::<chrono::Utc>::from(now);
+    let amz_date = date.format("%Y%m%dT%H%M%SZ").to_string();
+    let credential = format!("{}/{}/us-east-1/s3/aws4_request", ADMIN_KEY.0, date.format("%Y%m%d"));
+    let policy = serde_json::json!({
+        "expiration": "2100-01-01T00:00:00Z",
+        "conditions": [
+            {"bucket": "capped"},
+            ["starts-with", "$key", ""],
+            {"x-amz-algorithm": "AWS4-HMAC-SHA256"},
+            {"x-amz-credential": credential},
+            {"x-amz-date": amz_date},
+        ],
+    });
+    let policy = BASE64.encode(policy.to_string());
+    let signing_key = aws_sigv4::sign::v4::generate_signing_key(ADMIN_KEY.1, now, "us-east-1", "s3");
+    let signature = aws_sigv4::sign::v4::calculate_signature(signing_key, policy.as_bytes());
+
+    let post = |key: &str, size: usize| {
+        let mut body = Vec::new();
+        for (name, value) in [
+            ("key", key),
+            ("x-amz-algorithm", "AWS4-HMAC-SHA256"),
+            ("x-amz-credential", &credential),
+            ("x-amz-date", &amz_date),
+            ("policy", &policy),
+            ("x-amz-signature", &signature),
+        ] {
+            body.extend_from_slice(format!("--BOUNDARY\r\ncontent-disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n", name, value).as_bytes());
+        }
+        body.extend_from_slice(b"--BOUNDARY\r\ncontent-disposition: form-data; name=\"file\"; filename=\"f.bin\"\r\n\r\n");
+        body.extend(std::iter::repeat_n(b'f', size));
+        body.extend_from_slice(b"\r\n--BOUNDARY--\r\n");
+        let mut request = format!(
+            "POST /capped HTTP/1.1\r\nhost: {}\r\ncontent-type: multipart/form-data; boundary=BOUNDARY\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+            server.addr,
+            body.len()
+        )
+        .into_bytes();
+        request.extend_from_slice(&body);
+        request
+    };
+
+    let response = server.raw(&post("small", 1000)).await;
+    assert_eq!(response.status, 204, "{}", response.body);
+    let response = server.raw(&post("large", 5000)).await;
+    assert_eq!((response.status, response.error_code()), (507, Some("QuotaExceeded")), "{}", response.body);
+    assert_nothing_left(&server, "large").await;
+}
+
+// Peak resident memory of this process, in bytes
+fn peak_rss() -> u64 {
+    let status = std::fs::read_to_string("/proc/self/status").unwrap();
+    let line = status.lines().find(|line| line.starts_with("VmHWM:")).unwrap();
+    line.split_whitespace().nth(1).unwrap().parse::<u64>().unwrap() * 1024
+}
+
+// Streams a 256 MiB body in HTTP chunks; the server must write it through to
+// disk rather than buffer it
+#[tokio::test]
+#[cfg(target_os = "linux")]
+async fn large_streamed_upload_keeps_memory_bounded() {
+    const CHUNK: usize = 1 << 20;
+    const CHUNKS: usize = 256;
+    let server = TestServer::start().await;
+    server.admin().create_bucket().bucket("large").send().await.unwrap();
+
+    let head = server.framed(ADMIN_KEY, "PUT", "/large/blob", UNSIGNED, "transfer-encoding: chunked\r\n", b"");
+    // Resets the peak to the current usage
+    std::fs::write("/proc/self/clear_refs", "5").unwrap();
+    let before = peak_rss();
+
+    let mut stream = tokio::net::TcpStream::connect(server.addr).await.unwrap();
+    stream.write_all(&head).await.unwrap();
+    let chunk = vec![b'z'; CHUNK];
+    for _ in 0..CHUNKS {
+        stream.write_all(format!("{:x}\r\n", CHUNK).as_bytes()).await.unwrap();
+        stream.write_all(&chunk).await.unwrap();
+        stream.write_all(b"\r\n").await.unwrap();
+    }
+    stream.write_all(b"0\r\n\r\n").await.unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await.unwrap();
+    assert!(response.starts_with(b"HTTP/1.1 200"), "{}", String::from_utf8_lossy(&response));
+
+    let grown = peak_rss().saturating_sub(before);
+    assert!(grown < 64 << 20, "peak memory grew by {} MiB", grown >> 20);
+    let object = server.admin().head_object().bucket("large").key("blob").send().await.unwrap();
+    assert_eq!(object.content_length(), Some((CHUNK * CHUNKS) as i64));
+}
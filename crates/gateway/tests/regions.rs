@@ -0,0 +1,49 @@
+mod common;
+
+use common::{TestServer, ADMIN_KEY};
+use ghostbay_catalog::{BucketRepository, CreateBucketRequest};
+
+const EU_ENDPOINT: &str = "https://eu-west-1.ghostbay.example";
+
+async fn server_with_buckets() -> TestServer {
+    let server = TestServer::start_with_regions(&[("eu-west-1", EU_ENDPOINT)]).await;
+    server.admin().create_bucket().bucket("local").send().await.unwrap();
+    // Homed elsewhere: one in a region with a known node, one without
+    let repo = BucketRepository::new(server.state.catalog.pool().clone());
+    for (name, region) in [("abroad", "eu-west-1"), ("unrouted", "ap-south-1")] {
+        repo.create(CreateBucketRequest { name: name.to_string(), region: region.to_string(), owner_access_key_id: None })
+            .await
+            .unwrap();
+    }
+    server
+}
+
+#[tokio::test]
+async fn foreign_bucket_is_a_permanent_redirect() {
+    let server = server_with_buckets().await;
+    for (method, path) in [("GET", "/abroad"), ("GET", "/abroad/photo.jpg"), ("PUT", "/abroad/photo.jpg"), ("DELETE", "/abroad")] {
+        let response = server.raw(&server.signed(ADMIN_KEY, method, path, b"")).await;
+        assert_eq!((response.status, response.error_code()), (301, Some("PermanentRedirect")), "{} {}", method, path);
+        assert_eq!(response.header("x-amz-bucket-region"), Some("eu-west-1"));
+        assert!(response.body.contains("<Bucket>abroad</Bucket>"), "{}", response.body);
+        assert!(response.body.contains(&format!("<Endpoint>{}</Endpoint>", EU_ENDPOINT)), "{}", response.body);
+    }
+    // Nothing was written on this node
+    assert!(server.admin().head_object().bucket("abroad").key("photo.jpg").send().await.is_err());
+}
+
+#[tokio::test]
+async fn local_bucket_passes_through() {
+    let server = server_with_buckets().await;
+    let response = server.raw(&server.signed(ADMIN_KEY, "PUT", "/local/photo.jpg", b"jpeg")).await;
+    assert_eq!(response.status, 200, "{}", response.body);
+    assert_eq!(response.header("x-amz-bucket-region"), None);
+    let response = server.raw(&server.signed(ADMIN_KEY, "GET", "/local/photo.jpg", b"")).await;
+    assert_eq!((response.status, response.body.as_str()), (200, "jpeg"));
+
+    // As do names no bucket has, and buckets whose region has no known node
+    let response = server.raw(&server.signed(ADMIN_KEY, "GET", "/missing/photo.jpg", b"")).await;
+    assert_eq!((response.status, response.error_code()), (404, Some("NoSuchBucket")));
+    let response = server.raw(&server.signed(ADMIN_KEY, "GET", "/unrouted", b"")).await;
+    assert_eq!(response.status, 200, "{}", response.body);
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use aws_sdk_s3::primitives::ByteStream;
+use common::{TestServer, ADMIN_KEY};
+use ghostbay_catalog::{BucketRepository, ObjectRepository};
+
+// An object whose data file is gone reads as a 500, not a 404, and is left
+// flagged for fsck to report
+#[tokio::test]
+async fn a_missing_data_file_flags_the_object_for_repair() {
+    let server = TestServer::start().await;
+    let client = server.admin();
+    client.create_bucket().bucket("fragile").send().await.unwrap();
+    for key in ["gone.txt", "kept.txt"] {
+        client.put_object().bucket("fragile").key(key).body(ByteStream::from_static(b"hello")).send().await.unwrap();
+    }
+
+    let pool = server.state.catalog.pool().clone();
+    let bucket = BucketRepository::new(pool.clone()).find_by_name("fragile").await.unwrap().unwrap();
+    let objects = ObjectRepository::new(pool.clone());
+    let gone = objects.find_by_bucket_and_key(bucket.id, "gone.txt").await.unwrap().unwrap();
+    assert!(objects.list_needing_repair().await.unwrap().is_empty());
+    // Behind the catalog's back
+    assert!(server.state.storage.delete_object("fragile", "gone.txt").await.unwrap());
+
+    let response = server.raw(&server.signed(ADMIN_KEY, "GET", "/fragile/gone.txt", b"")).await;
+    assert_eq!((response.status, response.error_code()), (500, Some("InternalError")), "{}", response.body);
+
+    let needs_repair = |key: &'static str| {
+        let pool = pool.clone();
+        async move {
+            sqlx::query_scalar::<_, bool>("SELECT needs_repair FROM objects WHERE key = ?")
+                .bind(key)
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+        }
+    };
+    assert!(needs_repair("gone.txt").await);
+    assert!(!needs_repair("kept.txt").await);
+    let flagged = objects.list_needing_repair().await.unwrap();
+    assert_eq!(flagged.iter().map(|object| (object.id, object.key.as_str())).collect::<Vec<_>>(), [(gone.id, "gone.txt")]);
+
+    // The object stays listed and the rest of the bucket keeps reading
+    let response = server.raw(&server.signed(ADMIN_KEY, "GET", "/fragile/kept.txt", b"")).await;
+    assert_eq!((response.status, response.body.as_str()), (200, "hello"));
+    let listing = client.list_objects_v2().bucket("fragile").send().await.unwrap();
+    assert_eq!(listing.key_count(), Some(2));
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use common::{TestServer, ADMIN_KEY};
+use std::sync::{Arc, Mutex};
+use tracing::span::{Attributes, Id};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+// Spans by target and name
+const REQUEST: &str = "ghostbay_api::middleware::request";
+const HTTP_TRACE: &str = "tower_http::trace::make_span::request";
+const HANDLER: &str = "ghostbay_api::handlers::subresource::handler";
+
+// A span as it was opened: its target and name, whether it carries
+// db.operation, and the spans it was opened under, nearest first
+#[derive(Debug, Clone)]
+struct Opened {
+    name: String,
+    db_operation: bool,
+    ancestors: Vec<String>,
+}
+
+fn path(metadata: &tracing::Metadata<'_>) -> String {
+    format!("{}::{}", metadata.target(), metadata.name())
+}
+
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<Opened>>>);
+
+impl<S: tracing::Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Recorder {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).unwrap();
+        let ancestors = span.scope().skip(1).map(|ancestor| path(ancestor.metadata())).collect();
+        self.0.lock().unwrap().push(Opened {
+            name: path(attrs.metadata()),
+            db_operation: attrs.metadata().fields().field("db.operation").is_some(),
+            ancestors,
+        });
+    }
+}
+
+#[tokio::test]
+async fn repository_spans_nest_under_the_handler_and_request() {
+    let recorder = Recorder::default();
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(recorder.clone())).unwrap();
+
+    let server = TestServer::start().await;
+    server.admin().create_bucket().bucket("traced").send().await.unwrap();
+    let response = server.raw(&server.signed(ADMIN_KEY, "PUT", "/traced/note.txt", b"hello")).await;
+    assert_eq!(response.status, 200);
+    recorder.0.lock().unwrap().clear();
+
+    let response = server.raw(&server.signed(ADMIN_KEY, "GET", "/traced/note.txt", b"")).await;
+    assert_eq!((response.status, response.body.as_str()), (200, "hello"));
+
+    let opened = recorder.0.lock().unwrap().clone();
+    let handlers: Vec<&Opened> = opened.iter().filter(|span| span.name == HANDLER).collect();
+    assert_eq!(handlers.len(), 1, "{:#?}", opened);
+    // tower-http's HTTP span sits between the request span and the handler
+    assert_eq!(handlers[0].ancestors, [HTTP_TRACE, REQUEST], "{:#?}", opened);
+
+    // Every query is opened within the request; the handler's own, such as
+    // the object lookup, directly under the handler
+    let queries: Vec<&Opened> = opened.iter().filter(|span| span.db_operation).collect();
+    assert!(queries.iter().all(|query| query.ancestors.last().map(String::as_str) == Some(REQUEST)), "{:#?}", queries);
+    let lookup = queries.iter().find(|query| query.name == "ghostbay_catalog::repository::find_by_bucket_and_key").unwrap();
+    assert_eq!(lookup.ancestors, [HANDLER, HTTP_TRACE, REQUEST]);
+    // Authentication runs before routing, outside any handler
+    let authentication = queries.iter().find(|query| query.name == "ghostbay_auth::keys::find_by_access_key_id").unwrap();
+    assert_eq!(authentication.ancestors, [REQUEST]);
+}